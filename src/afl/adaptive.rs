@@ -0,0 +1,484 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::afl::cmd::split_flags;
+use crate::afl::strategies::{MutationMode, PowerSchedule};
+use crate::afl::version::{Capabilities, Capability};
+
+/// Decision log of the adaptive mode in the AFL++ output directory, one JSON round per line
+pub const ADAPTIVE_DECISIONS: &str = "aflr_adaptive.jsonl";
+/// Options the adaptive mode reallocates
+pub const ARM_OPTIONS: [&str; 3] = ["-p", "-P", "-L"];
+/// Weight of a newly found edge relative to a new queue entry
+const EDGE_WEIGHT: f64 = 2.0;
+/// Exploration weight of the UCB1 bonus
+const EXPLORATION: f64 = std::f64::consts::SQRT_2;
+/// Probability of trying a neighbouring configuration instead of the selected one
+const MUTATE_PROBABILITY: f64 = 0.2;
+/// Instances scoring at least this fraction of the best instance are never restarted
+const RESTART_THRESHOLD: f64 = 0.5;
+
+const SCHEDULES: [PowerSchedule; 7] = [
+    PowerSchedule::Fast,
+    PowerSchedule::Explore,
+    PowerSchedule::Coe,
+    PowerSchedule::Lin,
+    PowerSchedule::Quad,
+    PowerSchedule::Exploit,
+    PowerSchedule::Rare,
+];
+const MUTATIONS: [Option<MutationMode>; 3] = [
+    None,
+    Some(MutationMode::Explore),
+    Some(MutationMode::Exploit),
+];
+
+/// One configuration of the bandit: power schedule, mutation mode and MOpt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Arm {
+    pub schedule: PowerSchedule,
+    pub mutation: Option<MutationMode>,
+    pub mopt: bool,
+}
+
+impl Default for Arm {
+    /// The AFL++ defaults
+    fn default() -> Self {
+        Self {
+            schedule: PowerSchedule::Fast,
+            mutation: None,
+            mopt: false,
+        }
+    }
+}
+
+impl Arm {
+    /// All configurations the bandit chooses from
+    pub fn all() -> Vec<Self> {
+        SCHEDULES
+            .iter()
+            .flat_map(|&schedule| {
+                MUTATIONS.iter().flat_map(move |&mutation| {
+                    [false, true].map(|mopt| Self {
+                        schedule,
+                        mutation,
+                        mopt,
+                    })
+                })
+            })
+            .collect()
+    }
+
+    /// Reads the configuration from the miscellaneous flags of a command
+    pub fn from_flags(flags: &[String]) -> Self {
+        let mut arm = Self::default();
+        for flag in flags {
+            match flag.split_once(' ') {
+                Some(("-p", schedule)) => {
+                    if let Ok(schedule) = schedule.parse() {
+                        arm.schedule = schedule;
+                    }
+                }
+                Some(("-P", mode)) => arm.mutation = mode.parse().ok(),
+                Some(("-L", _)) => arm.mopt = true,
+                _ => {}
+            }
+        }
+        arm
+    }
+
+    pub fn flags(&self) -> Vec<String> {
+        let mut flags = vec![self.schedule.to_string()];
+        flags.extend(self.mutation.map(|mode| mode.to_string()));
+        if self.mopt {
+            flags.push("-L 0".to_string());
+        }
+        flags
+    }
+
+    /// Changes one of the three knobs at random
+    fn mutate<R: Rng>(self, rng: &mut R) -> Self {
+        let mut arm = self;
+        match rng.gen_range(0..3) {
+            0 => arm.schedule = *SCHEDULES.choose(rng).unwrap_or(&self.schedule),
+            1 => arm.mutation = *MUTATIONS.choose(rng).unwrap_or(&self.mutation),
+            _ => arm.mopt = !arm.mopt,
+        }
+        arm
+    }
+}
+
+impl fmt::Display for Arm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.flags().join(" "))
+    }
+}
+
+impl FromStr for Arm {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::from_flags(&split_flags(s)))
+    }
+}
+
+/// Progress counters of one instance read from its `fuzzer_stats`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct InstanceStats {
+    pub pid: u32,
+    pub run_time: u64,
+    pub corpus_found: u64,
+    pub edges_found: u64,
+    pub execs_per_sec: f64,
+}
+
+impl InstanceStats {
+    pub fn parse(content: &str) -> Self {
+        let mut stats = Self::default();
+        for (key, value) in content.lines().filter_map(|line| line.split_once(':')) {
+            let value = value.trim();
+            match key.trim() {
+                "fuzzer_pid" => stats.pid = value.parse().unwrap_or_default(),
+                "run_time" => stats.run_time = value.parse().unwrap_or_default(),
+                // `paths_found` in AFL++ versions before 4.00
+                "corpus_found" | "paths_found" => {
+                    stats.corpus_found = value.parse().unwrap_or_default();
+                }
+                "edges_found" => stats.edges_found = value.parse().unwrap_or_default(),
+                "execs_per_sec" => stats.execs_per_sec = value.parse().unwrap_or_default(),
+                _ => {}
+            }
+        }
+        stats
+    }
+
+    /// Reads the stats of an instance directory, `None` if none have been written yet
+    pub fn read(instance_dir: &Path) -> Option<Self> {
+        fs::read_to_string(instance_dir.join("fuzzer_stats"))
+            .ok()
+            .map(|content| Self::parse(&content))
+    }
+
+    /// New queue entries and edges per hour since `prev`, with the execution speed as
+    /// tie breaker between instances that found nothing
+    #[allow(clippy::cast_precision_loss)]
+    pub fn score(&self, prev: &Self) -> f64 {
+        let gain = self.corpus_found.saturating_sub(prev.corpus_found) as f64
+            + EDGE_WEIGHT * self.edges_found.saturating_sub(prev.edges_found) as f64;
+        let hours = self.run_time.saturating_sub(prev.run_time).max(1) as f64 / 3600.0;
+        gain / hours + self.execs_per_sec / 1e6
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+struct ArmRecord {
+    pulls: u64,
+    reward: f64,
+}
+
+/// UCB1 bandit over the configurations, keyed by their flags
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Bandit {
+    arms: BTreeMap<String, ArmRecord>,
+}
+
+impl Bandit {
+    /// Records one round of an instance running `arm`, `reward` lies within 0..=1
+    pub fn record(&mut self, arm: Arm, reward: f64) {
+        let record = self.arms.entry(arm.to_string()).or_default();
+        record.pulls += 1;
+        record.reward += reward;
+    }
+
+    /// Mean reward of `arm`, `None` if it never ran
+    #[allow(clippy::cast_precision_loss)]
+    pub fn mean(&self, arm: Arm) -> Option<f64> {
+        self.arms
+            .get(&arm.to_string())
+            .filter(|record| record.pulls > 0)
+            .map(|record| record.reward / record.pulls as f64)
+    }
+
+    /// Draws a configuration for a restarted instance
+    ///
+    /// Picks the configuration with the highest upper confidence bound among those that
+    /// already ran, and with a small probability a neighbouring one of it, so untried
+    /// configurations are explored starting from the well performing ones.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn select<R: Rng>(&self, allowed: impl Fn(&Arm) -> bool, rng: &mut R) -> Option<Arm> {
+        let total: u64 = self.arms.values().map(|record| record.pulls).sum();
+        let ln_total = ((total + 1) as f64).ln();
+        let best = Arm::all()
+            .into_iter()
+            .filter_map(|arm| {
+                let record = self.arms.get(&arm.to_string())?;
+                let pulls = record.pulls.max(1) as f64;
+                Some((
+                    arm,
+                    record.reward / pulls + EXPLORATION * (ln_total / pulls).sqrt(),
+                ))
+            })
+            .filter(|(arm, _)| allowed(arm))
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(arm, _)| arm);
+
+        match best {
+            Some(arm) if !rng.gen_bool(MUTATE_PROBABILITY) => Some(arm),
+            Some(arm) => Some(Some(arm.mutate(rng)).filter(&allowed).unwrap_or(arm)),
+            None => Arm::all()
+                .into_iter()
+                .filter(allowed)
+                .collect::<Vec<_>>()
+                .choose(rng)
+                .copied(),
+        }
+    }
+}
+
+/// Instance scored in one adaptation round
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub name: String,
+    pub arm: Arm,
+    pub score: f64,
+    /// Whether the instance may be restarted, i.e. it is not the `-M` instance
+    pub restartable: bool,
+    /// MOpt is not used together with custom mutators
+    pub custom_mutator: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScoreEntry {
+    pub instance: String,
+    pub arm: String,
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Restart {
+    pub instance: String,
+    pub from: String,
+    pub to: String,
+}
+
+/// Scores and restarts of one adaptation round
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Round {
+    pub timestamp: i64,
+    pub scores: Vec<ScoreEntry>,
+    pub restarts: Vec<Restart>,
+}
+
+impl Round {
+    /// Records the scores of all candidates and picks up to `max_restarts` of the worst
+    /// restartable ones along with their new configuration
+    ///
+    /// Rewards are the scores relative to the best instance of the round. Instances
+    /// scoring at least half of the best one are kept.
+    pub fn plan<R: Rng>(
+        bandit: &mut Bandit,
+        candidates: &[Candidate],
+        max_restarts: usize,
+        capabilities: &Capabilities,
+        rng: &mut R,
+    ) -> Self {
+        let best = candidates.iter().map(|c| c.score).fold(0.0_f64, f64::max);
+        let reward = |score: f64| if best > 0.0 { score / best } else { 0.0 };
+        for candidate in candidates {
+            bandit.record(candidate.arm, reward(candidate.score));
+        }
+
+        let mut worst: Vec<&Candidate> = candidates
+            .iter()
+            .filter(|c| c.restartable && reward(c.score) < RESTART_THRESHOLD)
+            .collect();
+        worst.sort_by(|a, b| a.score.total_cmp(&b.score));
+        let restarts = worst
+            .into_iter()
+            .take(max_restarts)
+            .filter_map(|c| {
+                let to = bandit.select(
+                    |arm| {
+                        *arm != c.arm
+                            && !(c.custom_mutator && arm.mopt)
+                            && (arm.mutation.is_none()
+                                || capabilities.allows(Capability::MutationModes))
+                    },
+                    rng,
+                )?;
+                Some(Restart {
+                    instance: c.name.clone(),
+                    from: c.arm.to_string(),
+                    to: to.to_string(),
+                })
+            })
+            .collect();
+
+        Self {
+            timestamp: chrono::Utc::now().timestamp(),
+            scores: candidates
+                .iter()
+                .map(|c| ScoreEntry {
+                    instance: c.name.clone(),
+                    arm: c.arm.to_string(),
+                    score: c.score,
+                })
+                .collect(),
+            restarts,
+        }
+    }
+
+    /// Appends the round to the decision log of the campaign
+    ///
+    /// # Errors
+    /// * If the log cannot be written
+    pub fn append(&self, afl_out: &Path) -> Result<()> {
+        let path = afl_out.join(ADAPTIVE_DECISIONS);
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        writeln!(file, "{}", serde_json::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::afl::version::AflVersion;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn candidate(name: &str, arm: &str, score: f64, restartable: bool) -> Candidate {
+        Candidate {
+            name: name.to_string(),
+            arm: arm.parse().unwrap(),
+            score,
+            restartable,
+            custom_mutator: false,
+        }
+    }
+
+    #[test]
+    fn test_arm() {
+        assert_eq!(Arm::all().len(), 42);
+        let flags = split_flags("-S s1_target -p rare -P exploit -L 0 -Z");
+        let arm = Arm::from_flags(&flags);
+        assert_eq!(arm.schedule, PowerSchedule::Rare);
+        assert_eq!(arm.mutation, Some(MutationMode::Exploit));
+        assert!(arm.mopt);
+        assert_eq!(arm.to_string(), "-p rare -P exploit -L 0");
+        assert_eq!(Arm::from_flags(&[]).to_string(), "-p fast");
+        assert_eq!(
+            "-p coe".parse::<Arm>().unwrap().schedule,
+            PowerSchedule::Coe
+        );
+    }
+
+    #[test]
+    fn test_stats_score() {
+        let prev = InstanceStats::parse(
+            "fuzzer_pid        : 42\nrun_time          : 3600\ncorpus_found      : 10\nedges_found       : 100\nexecs_per_sec     : 1000.00\n",
+        );
+        assert_eq!(prev.pid, 42);
+        assert_eq!(prev.corpus_found, 10);
+        let cur = InstanceStats {
+            run_time: 7200,
+            corpus_found: 14,
+            edges_found: 103,
+            ..prev
+        };
+        assert!((cur.score(&prev) - 10.001).abs() < 1e-9);
+        assert!(cur.score(&cur) < 0.01);
+    }
+
+    #[test]
+    fn test_plan() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut bandit = Bandit::default();
+        let candidates = [
+            candidate("m_target", "-p fast", 1.0, false),
+            candidate("s1_target", "-p explore -P explore", 10.0, true),
+            candidate("s2_target", "-p rare", 0.5, true),
+            candidate("s3_target", "-p coe -L 0", 6.0, true),
+        ];
+        let round = Round::plan(
+            &mut bandit,
+            &candidates,
+            4,
+            &Capabilities::default(),
+            &mut rng,
+        );
+        assert_eq!(round.scores.len(), 4);
+        assert!(
+            (bandit
+                .mean("-p explore -P explore".parse().unwrap())
+                .unwrap()
+                - 1.0)
+                .abs()
+                < 1e-9
+        );
+        assert!(bandit.mean("-p lin".parse().unwrap()).is_none());
+
+        // s3 performs well enough, the main instance is never restarted
+        let restarted: Vec<_> = round.restarts.iter().map(|r| r.instance.as_str()).collect();
+        assert_eq!(restarted, vec!["s2_target"]);
+        assert_ne!(round.restarts[0].to, "-p rare");
+
+        let dir = tempfile::tempdir().unwrap();
+        round.append(dir.path()).unwrap();
+        round.append(dir.path()).unwrap();
+        let log = fs::read_to_string(dir.path().join(ADAPTIVE_DECISIONS)).unwrap();
+        assert_eq!(log.lines().count(), 2);
+    }
+
+    #[test]
+    fn test_plan_capabilities() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut bandit = Bandit::default();
+        for _ in 0..20 {
+            bandit.record("-p explore -P explore".parse().unwrap(), 1.0);
+        }
+        let candidates: Vec<_> = (0..8)
+            .map(|i| {
+                let score = if i == 0 { 10.0 } else { 0.1 };
+                candidate(&format!("s{i}_target"), "-p rare", score, true)
+            })
+            .collect();
+        let old = Capabilities::new(Some(AflVersion::new(4, 0, 'c')));
+        let round = Round::plan(&mut bandit, &candidates, 7, &old, &mut rng);
+        assert_eq!(round.restarts.len(), 7);
+        assert!(round.restarts.iter().all(|r| !r.to.contains("-P")));
+    }
+
+    #[test]
+    fn test_select() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut bandit = Bandit::default();
+        assert!(bandit.select(|_| true, &mut rng).is_some());
+        assert!(bandit.select(|_| false, &mut rng).is_none());
+
+        let good: Arm = "-p explore -P explore".parse().unwrap();
+        let bad: Arm = "-p rare".parse().unwrap();
+        for _ in 0..20 {
+            bandit.record(good, 1.0);
+            bandit.record(bad, 0.0);
+        }
+        let picks = (0..50)
+            .filter_map(|_| bandit.select(|arm| !arm.mopt, &mut rng))
+            .collect::<Vec<_>>();
+        assert!(picks.iter().all(|arm| !arm.mopt));
+        assert!(picks.iter().filter(|arm| **arm == good).count() > 25);
+    }
+}
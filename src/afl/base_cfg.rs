@@ -1,6 +1,14 @@
 use std::path::PathBuf;
 
+use anyhow::{bail, Result};
+
+use crate::afl::dictionary::DictionaryConfig;
+use crate::afl::env_vars::AFLEnvSetting;
+use crate::afl::foreign::ForeignDirs;
+use crate::afl::overrides::InstanceOverride;
+use crate::afl::phases::Phase;
 use crate::utils::system::create_ramdisk;
+use crate::utils::topology::CoreBinding;
 
 #[derive(Clone, Debug, Default)]
 pub struct Bcfg {
@@ -8,14 +16,30 @@ pub struct Bcfg {
     pub input_dir: PathBuf,
     /// Output directory for AFL
     pub output_dir: PathBuf,
-    /// Path to the dictionary file/directory
-    pub dictionary: Option<String>,
+    /// Dictionaries and how they are distributed
+    pub dictionary: DictionaryConfig,
     /// Raw AFL++ flags
     pub raw_afl_flags: Option<String>,
     /// Path to the AFL++ binary
     pub afl_binary: Option<String>,
     /// Path to the `RAMDisk`
     pub ramdisk: Option<String>,
+    /// CPU cores to bind instances to via `-b`
+    pub core_binding: Option<CoreBinding>,
+    /// User-provided AFL++ environment variables
+    pub env_settings: Vec<AFLEnvSetting>,
+    /// Settings pinned to specific instances
+    pub instance_overrides: Vec<InstanceOverride>,
+    /// Foreign corpus directories imported via `-F`
+    pub foreign_dirs: ForeignDirs,
+    /// Phase of a phased campaign the commands are generated for
+    pub phase: Option<Phase>,
+    /// Maximum number of instances per sync group, a flat campaign if unset
+    pub group_size: Option<usize>,
+    /// Whether the commands run on other hosts, whose memory is unknown here
+    pub is_remote: bool,
+    /// Directory generated dictionaries are written to, `<output_dir>/aflr_dicts` if unset
+    pub dict_dir: Option<PathBuf>,
 }
 
 impl Bcfg {
@@ -27,11 +51,29 @@ impl Bcfg {
         }
     }
 
-    pub fn with_dictionary(mut self, dictionary: Option<PathBuf>) -> Self {
-        self.dictionary =
-            dictionary.and_then(|d| d.exists().then(|| d.to_string_lossy().into_owned()));
+    /// Sets the dictionary configuration
+    ///
+    /// # Errors
+    /// * If a dictionary or `libtokencap` does not exist
+    pub fn with_dictionary(mut self, dictionary: DictionaryConfig) -> Result<Self> {
+        if let Some(missing) = dictionary.paths.iter().find(|d| !d.exists()) {
+            bail!("Dictionary not found: {}", missing.display());
+        }
+        if let Some(lib) = dictionary.tokencap.as_ref().filter(|l| !l.is_file()) {
+            bail!("libtokencap not found: {}", lib.display());
+        }
+        self.dictionary = dictionary;
+        Ok(self)
+    }
 
-        self
+    /// Sets the foreign corpus directories, creating missing ones
+    ///
+    /// # Errors
+    /// * If a directory is invalid or cannot be created
+    pub fn with_foreign_dirs(mut self, mut foreign_dirs: ForeignDirs) -> Result<Self> {
+        foreign_dirs.prepare(&self.output_dir)?;
+        self.foreign_dirs = foreign_dirs;
+        Ok(self)
     }
 
     pub fn with_raw_afl_flags(mut self, raw_afl_flags: Option<&String>) -> Self {
@@ -44,6 +86,38 @@ impl Bcfg {
         self
     }
 
+    pub fn with_core_binding(mut self, core_binding: Option<CoreBinding>) -> Self {
+        self.core_binding = core_binding;
+        self
+    }
+
+    pub fn with_env_settings(mut self, env_settings: Vec<AFLEnvSetting>) -> Self {
+        self.env_settings = env_settings;
+        self
+    }
+
+    pub fn with_instance_overrides(mut self, instance_overrides: Vec<InstanceOverride>) -> Self {
+        self.instance_overrides = instance_overrides;
+        self
+    }
+
+    pub fn with_phase(mut self, phase: Option<Phase>) -> Self {
+        self.phase = phase;
+        self
+    }
+
+    /// Sets the maximum number of instances per sync group
+    ///
+    /// # Errors
+    /// * If a group would hold fewer than two instances
+    pub fn with_group_size(mut self, group_size: Option<usize>) -> Result<Self> {
+        if group_size.is_some_and(|s| s < 2) {
+            bail!("Sync groups need room for at least 2 instances");
+        }
+        self.group_size = group_size;
+        Ok(self)
+    }
+
     pub fn with_ramdisk(mut self, is_ramdisk: bool) -> Self {
         let rdisk = is_ramdisk
             .then(|| create_ramdisk().map_err(|e| println!("[!] Failed to create RAMDisk: {e}")))
@@ -58,4 +132,9 @@ impl Bcfg {
         self.ramdisk = rdisk;
         self
     }
+
+    pub fn with_remote(mut self, is_remote: bool) -> Self {
+        self.is_remote = is_remote;
+        self
+    }
 }
@@ -0,0 +1,423 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+
+use crate::afl::cmd::AFLCmd;
+use crate::afl::preflight::list_seeds;
+use crate::afl::target_exec::{ExecOutcome, ExecResult, TargetRunner, DEFAULT_EXEC_TIMEOUT_MS};
+
+/// Only this many seeds are executed to keep the startup time bounded
+const MAX_SEEDS: usize = 1024;
+/// Lower bound for the recommended `-t` in ms
+const MIN_TIMEOUT_MS: u64 = 20;
+/// Multiplier on top of the 95th percentile execution time
+const TIMEOUT_FACTOR: u32 = 5;
+/// Multiplier on top of the peak RSS, as `-m` limits virtual memory rather than RSS
+const MEM_LIMIT_FACTOR: u64 = 4;
+/// Lower bound for the recommended `-m` in MB
+const MIN_MEM_LIMIT_MB: u64 = 128;
+/// File the calibration results are written to inside the AFL++ output directory
+pub const CALIBRATION_REPORT: &str = "aflr_calibration.toml";
+
+/// Timing and memory profile of the target over the seed corpus
+#[derive(Debug, Clone)]
+pub struct Calibration {
+    /// Result of every executed seed
+    pub results: Vec<ExecResult>,
+    /// Peak resident set size of the target in MB
+    pub peak_rss_mb: u64,
+    /// Address space limit in MB the seeds were verified to behave the same under,
+    /// `None` if the limit changed the outcome of any seed
+    pub mem_limit_mb: Option<u64>,
+}
+
+/// Serialized form of the calibration written for review
+#[derive(Serialize)]
+struct CalibrationReport {
+    seeds: usize,
+    exec_time_min_ms: f64,
+    exec_time_median_ms: f64,
+    exec_time_p95_ms: f64,
+    exec_time_max_ms: f64,
+    peak_rss_mb: u64,
+    timeout_flag: String,
+    mem_limit_flag: Option<String>,
+    crashing_seeds: Vec<PathBuf>,
+    hanging_seeds: Vec<PathBuf>,
+}
+
+impl Calibration {
+    /// Executes the target once per seed and records execution times and memory usage
+    ///
+    /// # Errors
+    /// * If the seed directory cannot be read or is empty
+    /// * If the target cannot be executed
+    pub fn run(target: &Path, target_args: Vec<String>, seed_dir: &Path) -> Result<Self> {
+        let mut seeds = list_seeds(seed_dir)?;
+        if seeds.is_empty() {
+            bail!("No seeds to calibrate in {}", seed_dir.display());
+        }
+        if seeds.len() > MAX_SEEDS {
+            println!(
+                "[*] Calibrating on the first {MAX_SEEDS} of {} seeds",
+                seeds.len()
+            );
+            seeds.truncate(MAX_SEEDS);
+        }
+
+        println!(
+            "[*] Calibrating timeout and memory limit on {} seeds...",
+            seeds.len()
+        );
+        let runner = TargetRunner::new(
+            target,
+            target_args,
+            Duration::from_millis(DEFAULT_EXEC_TIMEOUT_MS),
+        );
+        let results = seeds
+            .iter()
+            .map(|seed| runner.run(seed))
+            .collect::<Result<Vec<_>>>()?;
+        let peak_rss_mb = results.iter().map(|r| r.peak_rss_mb).max().unwrap_or(0);
+
+        // `-m` sets RLIMIT_AS, which RSS says little about, so the candidate is only
+        // recommended if the seeds behave the same when re-executed under it
+        let candidate = Self::mem_limit_candidate(peak_rss_mb);
+        let limited = runner.with_mem_limit(candidate);
+        let mut mem_limit_mb = Some(candidate);
+        for result in results
+            .iter()
+            .filter(|r| matches!(r.outcome, ExecOutcome::Exited(_)))
+        {
+            if limited.run(&result.input)?.outcome != result.outcome {
+                mem_limit_mb = None;
+                break;
+            }
+        }
+
+        Ok(Self {
+            results,
+            peak_rss_mb,
+            mem_limit_mb,
+        })
+    }
+
+    /// Sorted execution times of all seeds that neither crashed nor hung
+    fn exec_times(&self) -> Vec<Duration> {
+        let mut times: Vec<Duration> = self
+            .results
+            .iter()
+            .filter(|r| matches!(r.outcome, ExecOutcome::Exited(_)))
+            .map(|r| r.exec_time)
+            .collect();
+        times.sort_unstable();
+        times
+    }
+
+    fn percentile(times: &[Duration], pct: usize) -> Duration {
+        if times.is_empty() {
+            return Duration::ZERO;
+        }
+        times[((times.len() - 1) * pct).div_ceil(100)]
+    }
+
+    fn seeds_with(&self, pred: fn(&ExecOutcome) -> bool) -> Vec<PathBuf> {
+        self.results
+            .iter()
+            .filter(|r| pred(&r.outcome))
+            .map(|r| r.input.clone())
+            .collect()
+    }
+
+    /// Seeds that crashed the target
+    pub fn crashing_seeds(&self) -> Vec<PathBuf> {
+        self.seeds_with(|o| matches!(o, ExecOutcome::Crashed(_)))
+    }
+
+    /// Seeds that exceeded the calibration timeout
+    pub fn hanging_seeds(&self) -> Vec<PathBuf> {
+        self.seeds_with(|o| matches!(o, ExecOutcome::TimedOut))
+    }
+
+    /// Recommended `-t` flag
+    ///
+    /// The timeout leaves headroom over the 95th percentile and at least twice the slowest
+    /// regular seed, rounded up to 10ms. Without any regular seed it falls back to the
+    /// calibration timeout, which every seed exceeded or crashed within.
+    /// The `+` suffix makes AFL++ skip timing out seeds instead of aborting, which is used
+    /// when seeds hung during calibration.
+    pub fn timeout_flag(&self) -> String {
+        let times = self.exec_times();
+        let timeout = match times.last() {
+            Some(&slowest) => (Self::percentile(&times, 95) * TIMEOUT_FACTOR).max(slowest * 2),
+            None => Duration::from_millis(DEFAULT_EXEC_TIMEOUT_MS),
+        };
+        let timeout = u64::try_from(timeout.as_millis())
+            .unwrap_or(u64::MAX)
+            .max(MIN_TIMEOUT_MS)
+            .div_ceil(10)
+            * 10;
+        let suffix = if self.hanging_seeds().is_empty() {
+            ""
+        } else {
+            "+"
+        };
+        format!("-t {timeout}{suffix}")
+    }
+
+    /// Address space limit derived from the peak RSS, rounded up to 64 MB
+    fn mem_limit_candidate(peak_rss_mb: u64) -> u64 {
+        (peak_rss_mb * MEM_LIMIT_FACTOR)
+            .max(MIN_MEM_LIMIT_MB)
+            .div_ceil(64)
+            * 64
+    }
+
+    /// Recommended `-m` flag, `None` if no limit was verified
+    pub fn mem_limit_flag(&self) -> Option<String> {
+        self.mem_limit_mb.map(|limit| format!("-m {limit}"))
+    }
+
+    /// Adds the recommended `-t` and the verified `-m` to commands that do not set them
+    /// already
+    ///
+    /// Sanitizer instances never get a memory limit as ASAN reserves large amounts of
+    /// virtual memory.
+    pub fn apply(&self, cmds: &mut [AFLCmd], sanitizer_bin: Option<&Path>) {
+        let has_option = |cmd: &AFLCmd, option: &str| {
+            cmd.misc_afl_flags
+                .iter()
+                .any(|f| f.split_whitespace().any(|tok| tok == option))
+        };
+        let timeout = self.timeout_flag();
+        let mem_limit = self.mem_limit_flag();
+        for cmd in cmds {
+            if !has_option(cmd, "-t") {
+                cmd.add_flag(timeout.clone());
+            }
+            let is_sanitizer = sanitizer_bin.is_some_and(|s| s == cmd.target_binary);
+            if let Some(mem_limit) = mem_limit.as_ref() {
+                if !is_sanitizer && !has_option(cmd, "-m") {
+                    cmd.add_flag(mem_limit.clone());
+                }
+            }
+        }
+    }
+
+    /// Prints the calibration summary and any problematic seeds
+    pub fn print(&self) {
+        let times = self.exec_times();
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        println!("[+] Calibration results:");
+        println!(
+            "    Exec time (min/median/p95/max): {:.1}/{:.1}/{:.1}/{:.1} ms",
+            ms(times.first().copied().unwrap_or_default()),
+            ms(Self::percentile(&times, 50)),
+            ms(Self::percentile(&times, 95)),
+            ms(times.last().copied().unwrap_or_default())
+        );
+        println!(
+            "    Peak RSS:                       {} MB",
+            self.peak_rss_mb
+        );
+        println!(
+            "    Recommended flags:              {}",
+            [Some(self.timeout_flag()), self.mem_limit_flag()]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>()
+                .join(" ")
+        );
+        if self.mem_limit_mb.is_none() {
+            println!(
+                "[!] Warning: Seeds behave differently under -m {}, leaving the memory limit unset",
+                Self::mem_limit_candidate(self.peak_rss_mb)
+            );
+        }
+        for seed in self.crashing_seeds() {
+            println!("[!] Seed crashes the target: {}", seed.display());
+        }
+        for seed in self.hanging_seeds() {
+            println!(
+                "[!] Seed exceeds {DEFAULT_EXEC_TIMEOUT_MS}ms and is treated as a hang: {}",
+                seed.display()
+            );
+        }
+    }
+
+    /// Writes the calibration results to `dir` for later review
+    ///
+    /// # Errors
+    /// * If the report cannot be serialized or written
+    pub fn write_report(&self, dir: &Path) -> Result<PathBuf> {
+        let times = self.exec_times();
+        let ms = |d: Duration| (d.as_secs_f64() * 1_000_000.0).round() / 1000.0;
+        let report = CalibrationReport {
+            seeds: self.results.len(),
+            exec_time_min_ms: ms(times.first().copied().unwrap_or_default()),
+            exec_time_median_ms: ms(Self::percentile(&times, 50)),
+            exec_time_p95_ms: ms(Self::percentile(&times, 95)),
+            exec_time_max_ms: ms(times.last().copied().unwrap_or_default()),
+            peak_rss_mb: self.peak_rss_mb,
+            timeout_flag: self.timeout_flag(),
+            mem_limit_flag: self.mem_limit_flag(),
+            crashing_seeds: self.crashing_seeds(),
+            hanging_seeds: self.hanging_seeds(),
+        };
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create directory: {}", dir.display()))?;
+        let path = dir.join(CALIBRATION_REPORT);
+        fs::write(&path, toml::to_string_pretty(&report)?)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn result(name: &str, outcome: ExecOutcome, ms: u64) -> ExecResult {
+        ExecResult {
+            input: PathBuf::from(name),
+            outcome,
+            exec_time: Duration::from_millis(ms),
+            peak_rss_mb: 0,
+        }
+    }
+
+    #[test]
+    fn test_recommended_flags() {
+        let mut cal = Calibration {
+            results: (1..=20)
+                .map(|i| result(&format!("seed{i}"), ExecOutcome::Exited(0), i))
+                .collect(),
+            peak_rss_mb: 40,
+            mem_limit_mb: None,
+        };
+        assert_eq!(cal.timeout_flag(), "-t 100");
+        assert_eq!(cal.mem_limit_flag(), None);
+        assert_eq!(Calibration::mem_limit_candidate(40), 192);
+
+        cal.results.push(result(
+            "hang",
+            ExecOutcome::TimedOut,
+            DEFAULT_EXEC_TIMEOUT_MS,
+        ));
+        cal.results
+            .push(result("crash", ExecOutcome::Crashed(11), 1));
+        assert_eq!(cal.timeout_flag(), "-t 100+");
+        assert_eq!(cal.hanging_seeds(), vec![PathBuf::from("hang")]);
+        assert_eq!(cal.crashing_seeds(), vec![PathBuf::from("crash")]);
+
+        let fast = Calibration {
+            results: vec![result("a", ExecOutcome::Exited(0), 0)],
+            peak_rss_mb: 1,
+            mem_limit_mb: Some(MIN_MEM_LIMIT_MB),
+        };
+        assert_eq!(fast.timeout_flag(), format!("-t {MIN_TIMEOUT_MS}"));
+
+        let hanging = Calibration {
+            results: vec![result(
+                "hang",
+                ExecOutcome::TimedOut,
+                DEFAULT_EXEC_TIMEOUT_MS,
+            )],
+            peak_rss_mb: 1,
+            mem_limit_mb: None,
+        };
+        assert_eq!(
+            hanging.timeout_flag(),
+            format!("-t {DEFAULT_EXEC_TIMEOUT_MS}+")
+        );
+        assert_eq!(Calibration::mem_limit_candidate(1), MIN_MEM_LIMIT_MB);
+        assert_eq!(
+            fast.mem_limit_flag(),
+            Some(format!("-m {MIN_MEM_LIMIT_MB}"))
+        );
+    }
+
+    #[test]
+    fn test_apply_keeps_user_flags() {
+        let mut cal = Calibration {
+            results: vec![result("a", ExecOutcome::Exited(0), 10)],
+            peak_rss_mb: 100,
+            mem_limit_mb: Some(448),
+        };
+        let mut cmds = vec![
+            AFLCmd::new(PathBuf::from("afl-fuzz"), PathBuf::from("/bin/san")),
+            AFLCmd::new(PathBuf::from("afl-fuzz"), PathBuf::from("/bin/target")),
+        ];
+        cmds[1].add_flag("-t 5000+".into());
+
+        cal.apply(&mut cmds, Some(Path::new("/bin/san")));
+        assert_eq!(cmds[0].misc_afl_flags, vec!["-t 50"]);
+        assert_eq!(cmds[1].misc_afl_flags, vec!["-t 5000+", "-m 448"]);
+
+        cal.mem_limit_mb = None;
+        let mut cmds = vec![AFLCmd::new(
+            PathBuf::from("afl-fuzz"),
+            PathBuf::from("/bin/target"),
+        )];
+        cal.apply(&mut cmds, None);
+        assert_eq!(cmds[0].misc_afl_flags, vec!["-t 50"]);
+    }
+
+    #[test]
+    fn test_calibration_run() {
+        let dir = tempdir().unwrap();
+        let seeds = dir.path().join("seeds");
+        fs::create_dir(&seeds).unwrap();
+        fs::write(seeds.join("ok"), "fine").unwrap();
+        fs::write(seeds.join("bad"), "crash").unwrap();
+        fs::write(seeds.join(".hidden"), "crash").unwrap();
+
+        let cal = Calibration::run(
+            Path::new("/bin/sh"),
+            vec![
+                "-c".into(),
+                "grep -q crash \"$1\" && kill -SEGV $$; exit 0".into(),
+                "sh".into(),
+                "@@".into(),
+            ],
+            &seeds,
+        )
+        .unwrap();
+        assert_eq!(cal.results.len(), 2);
+        assert_eq!(cal.crashing_seeds(), vec![seeds.join("bad")]);
+        assert!(cal.mem_limit_mb.is_some());
+
+        let report = cal.write_report(&dir.path().join("out")).unwrap();
+        let content = fs::read_to_string(report).unwrap();
+        assert!(content.contains("timeout_flag"));
+        assert!(content.contains("bad"));
+
+        // A target that only works without an address space limit never gets `-m`
+        let unlimited = Calibration::run(
+            Path::new("/bin/sh"),
+            vec![
+                "-c".into(),
+                "test \"$(ulimit -v)\" = unlimited".into(),
+                "sh".into(),
+                "@@".into(),
+            ],
+            &seeds,
+        )
+        .unwrap();
+        assert!(unlimited.mem_limit_mb.is_none());
+        assert_eq!(unlimited.mem_limit_flag(), None);
+
+        assert!(Calibration::run(
+            Path::new("/bin/sh"),
+            vec![],
+            dir.path().join("none").as_path()
+        )
+        .is_err());
+    }
+}
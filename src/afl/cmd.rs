@@ -1,5 +1,20 @@
 use std::path::PathBuf;
 
+/// Splits a raw flag string like `-t 1000+ -Z` into one entry per option and its values
+pub fn split_flags(raw_flags: &str) -> Vec<String> {
+    let mut flags: Vec<String> = Vec::new();
+    for tok in raw_flags.split_whitespace() {
+        match flags.last_mut() {
+            Some(last) if !tok.starts_with('-') => {
+                last.push(' ');
+                last.push_str(tok);
+            }
+            _ => flags.push(tok.to_string()),
+        }
+    }
+    flags
+}
+
 /// Represents an AFL++ command configuration
 #[derive(Debug, Clone)]
 pub struct AFLCmd {
@@ -42,6 +57,15 @@ impl AFLCmd {
         self
     }
 
+    /// Sets a single environment variable, replacing any previous value of the same key
+    pub fn set_env(&mut self, entry: String) -> &mut Self {
+        let key = entry.split('=').next().unwrap_or_default().to_string();
+        self.env
+            .retain(|e| e.split('=').next().unwrap_or_default() != key);
+        self.env.push(entry);
+        self
+    }
+
     /// Sets the input directory for AFL
     pub fn with_input_dir(&mut self, input_dir: PathBuf) -> &mut Self {
         self.input_dir = input_dir;
@@ -71,6 +95,19 @@ impl AFLCmd {
         self.misc_afl_flags.push(flag);
     }
 
+    /// Removes every miscellaneous flag that contains one of the given options
+    pub fn remove_flags(&mut self, options: &[&str]) {
+        self.misc_afl_flags
+            .retain(|f| !f.split_whitespace().any(|tok| options.contains(&tok)));
+    }
+
+    /// Returns the `-M`/`-S` name of the instance once its role has been assigned
+    pub fn instance_name(&self) -> Option<&str> {
+        self.misc_afl_flags
+            .iter()
+            .find_map(|f| f.strip_prefix("-M ").or_else(|| f.strip_prefix("-S ")))
+    }
+
     /// Assembles the AFL++ command into a string
     pub fn assemble(&self) -> String {
         let mut cmd_parts = Vec::new();
@@ -123,7 +160,7 @@ pub trait ToStringVec {
     fn to_string_vec(&self) -> Vec<String>;
 }
 
-impl ToStringVec for Vec<AFLCmd> {
+impl ToStringVec for [AFLCmd] {
     fn to_string_vec(&self) -> Vec<String> {
         self.iter().map(std::string::ToString::to_string).collect()
     }
@@ -1,15 +1,25 @@
-use std::collections::HashSet;
+use std::cell::{OnceCell, RefCell};
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use crate::afl::env::AFLEnv;
+use crate::afl::dictionary::{Dictionary, DICT_DIR};
+use crate::afl::env::{AFLEnv, AFLFlag};
+use crate::afl::env_vars::{self, AFLEnvSetting};
 use crate::afl::harness::Harness;
 use crate::afl::mode::Mode;
+use crate::afl::resources::DEFAULT_TESTCACHE_MB;
 use crate::afl::strategies::{AFLStrategy, CmpcovConfig, CmplogConfig};
-use crate::afl::{base_cfg::Bcfg, cmd::AFLCmd};
+use crate::afl::sync_groups;
+use crate::afl::version::Capabilities;
+use crate::afl::{
+    base_cfg::Bcfg,
+    cmd::{split_flags, AFLCmd},
+};
 use crate::utils::seed::Xorshift64;
 use crate::utils::system::find_binary_in_path;
-use anyhow::{Context, Result};
+use crate::utils::topology::{CoreBinding, CpuTopology};
+use anyhow::{bail, Context, Result};
 use rand::rngs::StdRng;
 use rand::SeedableRng;
 
@@ -27,13 +37,18 @@ pub struct AFLCmdGenerator {
     pub mode: Mode,
     /// Seed for AFL++
     pub seed: Option<u64>,
+    /// Resolved `afl-fuzz` binary and its capabilities, probed once on first use
+    afl_fuzz: OnceCell<(PathBuf, Capabilities)>,
+    /// Generated dictionaries the commands refer to, written by `write_dictionaries`
+    generated_dicts: RefCell<Vec<(PathBuf, Dictionary)>>,
 }
 
 impl AFLCmdGenerator {
     /// Creates a new `AFLCmdGenerator` instance
     pub fn new(harness: Harness, runners: u32, meta: &Bcfg, mode: Mode, seed: Option<u64>) -> Self {
-        if runners > RUNNER_THRESH {
-            println!("[!] Warning: Performance degradation may occur with more than 32 runners. Observe campaign results carefully.");
+        let group_size = meta.group_size.map_or(runners, |s| runners.min(s as u32));
+        if group_size > RUNNER_THRESH {
+            println!("[!] Warning: Performance degradation may occur with more than 32 runners per sync directory. Consider splitting them with --group-size.");
         }
 
         Self {
@@ -42,9 +57,57 @@ impl AFLCmdGenerator {
             runners,
             mode,
             seed,
+            afl_fuzz: OnceCell::new(),
+            generated_dicts: RefCell::default(),
         }
     }
 
+    /// Directory the generated dictionaries are written to
+    pub fn dict_dir(&self) -> PathBuf {
+        self.base_cfg
+            .dict_dir
+            .clone()
+            .unwrap_or_else(|| self.base_cfg.output_dir.join(DICT_DIR))
+    }
+
+    /// Writes the dictionaries generated by the last `run` and returns their number
+    ///
+    /// Generation itself does not touch the output directory, so this has to be called once
+    /// the commands are actually started.
+    ///
+    /// # Errors
+    /// * If the dictionary directory or a dictionary cannot be written
+    pub fn write_dictionaries(&self) -> Result<usize> {
+        let dicts = self.generated_dicts.borrow();
+        if dicts.is_empty() {
+            return Ok(0);
+        }
+        let dict_dir = self.dict_dir();
+        fs::create_dir_all(&dict_dir).context("Failed to create dictionary directory")?;
+        for (path, dict) in dicts.iter() {
+            dict.write(path)?;
+        }
+        Ok(dicts.len())
+    }
+
+    /// Resolves the `afl-fuzz` binary and probes its version on the first call
+    fn afl_fuzz(&self) -> Result<&(PathBuf, Capabilities)> {
+        if let Some(afl_fuzz) = self.afl_fuzz.get() {
+            return Ok(afl_fuzz);
+        }
+        let afl_binary = find_binary_in_path(self.base_cfg.afl_binary.clone())?;
+        let capabilities = Capabilities::probe(&afl_binary);
+        Ok(self.afl_fuzz.get_or_init(|| (afl_binary, capabilities)))
+    }
+
+    /// Options supported by the used `afl-fuzz`
+    ///
+    /// # Errors
+    /// * If no `afl-fuzz` binary can be found
+    pub fn capabilities(&self) -> Result<&Capabilities> {
+        Ok(&self.afl_fuzz()?.1)
+    }
+
     /// Retrieves AFL++ environment variables
     fn get_afl_env_vars() -> Vec<String> {
         let gl_afl_env = std::env::vars()
@@ -62,29 +125,65 @@ impl AFLCmdGenerator {
     /// # Errors
     /// * If the set of intial commands cannot be constructed
     /// * If dictionary path cannot be resolved
+    /// * If the AFL++ environment variables are invalid or conflict with each other
+    /// * If an `[[instance]]` override cannot be applied
     pub fn run(&self) -> Result<Vec<AFLCmd>> {
         let seed = Xorshift64::new(self.seed.unwrap_or(0)).rand();
         let mut rng = StdRng::seed_from_u64(seed);
 
-        let afl_envs = AFLEnv::new(
+        let (afl_binary, capabilities) = self.afl_fuzz()?;
+
+        let mut afl_envs = AFLEnv::new(
             self.mode,
             self.runners,
             self.base_cfg.ramdisk.as_ref(),
+            capabilities,
             &mut rng,
         );
+        // The local free memory says nothing about the hosts of a multi-host campaign
+        if self.base_cfg.is_remote {
+            for env in &mut afl_envs {
+                env.set_testcache_size(DEFAULT_TESTCACHE_MB);
+            }
+        }
+
+        let mut cmds = self.create_initial_cmds(afl_binary, &afl_envs)?;
 
-        let mut cmds = self.create_initial_cmds(&afl_envs)?;
+        // Apply user-provided environment variables on top of the generated ones
+        self.apply_env_settings(&mut cmds)?;
 
         let afl_env_vars: Vec<String> = Self::get_afl_env_vars();
+        for warning in env_vars::validate_exported(&afl_env_vars) {
+            println!("[!] Warning: {warning}");
+        }
         let is_using_custom_mutator = afl_env_vars
             .iter()
-            .any(|e| e.starts_with("AFL_CUSTOM_MUTATOR_LIBRARY"));
+            .any(|e| e.starts_with("AFL_CUSTOM_MUTATOR_LIBRARY"))
+            || self.base_cfg.env_settings.iter().any(|s| {
+                s.to_env_string().is_some()
+                    && matches!(
+                        s.flag,
+                        AFLFlag::CustomMutatorLibrary | AFLFlag::PythonModule
+                    )
+            });
 
         let mut afl_strategy_builder = AFLStrategy::builder(self.mode);
 
+        // Apply the strategy of the current phase
+        if let Some(phase) = &self.base_cfg.phase {
+            afl_strategy_builder = phase.apply(afl_strategy_builder);
+        }
+
+        // Drop or substitute options the used afl-fuzz does not support
+        afl_strategy_builder = afl_strategy_builder.with_capabilities(capabilities.clone());
+
         // Enable CMPLOG if requested
         if let Some(ref cmplog_bin) = self.harness.cmplog_bin {
-            afl_strategy_builder.with_cmplog(CmplogConfig::new(cmplog_bin.clone()));
+            let mut cmplog = CmplogConfig::new(cmplog_bin.clone());
+            if let Some(ratio) = self.base_cfg.phase.as_ref().and_then(|p| p.cmplog_ratio) {
+                cmplog.runner_ratio = ratio;
+            }
+            afl_strategy_builder.with_cmplog(cmplog);
         }
 
         // Enable CMPCOV if requested
@@ -106,7 +205,7 @@ impl AFLCmdGenerator {
         // Apply -i and -o
         self.apply_directory(&mut cmds);
         // Apply -x
-        self.apply_dictionary(&mut cmds)?;
+        self.apply_dictionary(&mut cmds, &mut rng)?;
         // Apply sanitizer binary to first command if present
         self.apply_sanitizer_or_target_binary(&mut cmds);
 
@@ -117,12 +216,239 @@ impl AFLCmdGenerator {
         // NOTE: Needs to called last as it relies on cmpcov/cmplog being already set
         self.apply_fuzzer_roles(&mut cmds, afl_strategy.get_cmpcov_indices(), self.mode);
 
+        // Split into sync groups with their own -M and -o
+        self.apply_sync_groups(&mut cmds);
+
+        // Apply -F
+        self.apply_foreign_dirs(&mut cmds)?;
+
+        // Apply [[instance]] overrides on top of the generated commands
+        self.apply_instance_overrides(&mut cmds)?;
+
+        // Apply -b
+        if let Some(binding) = &self.base_cfg.core_binding {
+            Self::apply_core_binding(&mut cmds, binding, &CpuTopology::detect()?)?;
+        }
+
         // Apply global environment variables that are not yet part of the commands
         Self::apply_global_env_vars(&mut cmds, &afl_env_vars);
 
+        Self::check_env_conflicts(&cmds)?;
+
+        capabilities.report();
+
         Ok(cmds)
     }
 
+    /// Applies the user-provided AFL++ environment variables to the selected instances
+    fn apply_env_settings(&self, cmds: &mut [AFLCmd]) -> Result<()> {
+        for setting in &self.base_cfg.env_settings {
+            if let Some(idx) = setting
+                .instances
+                .iter()
+                .flatten()
+                .find(|&&i| i >= cmds.len())
+            {
+                bail!(
+                    "{} is scoped to instance {idx} but only {} instances are generated",
+                    setting.flag,
+                    cmds.len()
+                );
+            }
+            for (idx, cmd) in cmds.iter_mut().enumerate() {
+                if !setting.applies_to(idx) {
+                    continue;
+                }
+                Self::apply_env_setting(cmd, setting);
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets or removes a single user-provided environment variable
+    fn apply_env_setting(cmd: &mut AFLCmd, setting: &AFLEnvSetting) {
+        match setting.to_env_string() {
+            Some(entry) => {
+                cmd.set_env(entry);
+            }
+            None => Self::unset_env(cmd, setting),
+        }
+    }
+
+    /// Applies the `[[instance]]` overrides on top of the generated commands
+    ///
+    /// Overrides are resolved against the assigned `-M`/`-S` names, so this has to run after
+    /// the roles are applied. A setting may only be pinned once per instance.
+    fn apply_instance_overrides(&self, cmds: &mut [AFLCmd]) -> Result<()> {
+        let names: Vec<Option<String>> = cmds
+            .iter()
+            .map(|c| c.instance_name().map(String::from))
+            .collect();
+        let names: Vec<Option<&str>> = names.iter().map(Option::as_deref).collect();
+
+        let mut pinned: HashMap<(usize, String), usize> = HashMap::new();
+        for (ovr_idx, ovr) in self.base_cfg.instance_overrides.iter().enumerate() {
+            let indices = ovr.resolve(&names)?;
+            for &idx in &indices {
+                for field in ovr.pinned_fields() {
+                    if let Some(prev) = pinned.insert((idx, field.clone()), ovr_idx) {
+                        bail!(
+                            "Instance {idx} ({}): '{field}' is pinned by both [[instance]] entry {prev} and {ovr_idx}",
+                            names[idx].unwrap_or("-")
+                        );
+                    }
+                }
+            }
+
+            let dictionary = ovr
+                .dictionary
+                .as_ref()
+                .map(|d| {
+                    fs::canonicalize(d).with_context(|| {
+                        format!("Failed to resolve dictionary path: {}", d.display())
+                    })
+                })
+                .transpose()?;
+            let custom_mutator = ovr
+                .custom_mutator
+                .as_ref()
+                .map(|m| {
+                    fs::canonicalize(m).with_context(|| {
+                        format!("Failed to resolve custom mutator path: {}", m.display())
+                    })
+                })
+                .transpose()?;
+
+            for idx in indices {
+                let is_main = idx == 0 && self.mode != Mode::CIFuzzing;
+                if is_main && ovr.binary.is_some() && self.harness.sanitizer_bin.is_some() {
+                    println!(
+                        "[!] Warning: [[instance]] entry {ovr_idx} replaces the sanitizer binary of the -M instance"
+                    );
+                }
+
+                let cmd = &mut cmds[idx];
+                cmd.remove_flags(&ovr.pinned_options());
+                if let Some(binary) = &ovr.binary {
+                    cmd.target_binary.clone_from(binary);
+                }
+                if let Some(schedule) = ovr.schedule {
+                    cmd.add_flag(schedule.to_string());
+                }
+                for flag in &ovr.flags {
+                    cmd.add_flag(flag.clone());
+                }
+                if let Some(dict) = &dictionary {
+                    cmd.add_flag(format!("-x {}", dict.display()));
+                }
+                if let Some(mutator) = &custom_mutator {
+                    // MOpt is not applied together with custom mutators, see AFLStrategy
+                    cmd.misc_afl_flags.retain(|f| f != "-L 0");
+                    cmd.set_env(format!("AFL_CUSTOM_MUTATOR_LIBRARY={}", mutator.display()));
+                }
+                for setting in &ovr.env {
+                    Self::apply_env_setting(cmd, setting);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Splits the instances into sync groups, each with its own `-M` instance and sync
+    /// directory below the output directory
+    ///
+    /// The `-M` instance of every group imports the entries of the other groups via `-F`,
+    /// which `aflr group-sync` copies over. Has to run after the roles are applied.
+    fn apply_sync_groups(&self, cmds: &mut [AFLCmd]) {
+        let Some(size) = self.base_cfg.group_size else {
+            return;
+        };
+        if cmds.len() <= size {
+            return;
+        }
+        if self.mode == Mode::CIFuzzing {
+            println!(
+                "[!] Warning: CI fuzzing runs without -M instances, not splitting into sync groups"
+            );
+            return;
+        }
+        let groups = sync_groups::split(cmds.len(), size);
+        println!(
+            "[*] Splitting {} instances into {} sync groups",
+            cmds.len(),
+            groups.len()
+        );
+        for (group, range) in groups.into_iter().enumerate() {
+            let group_dir = sync_groups::group_dir(&self.base_cfg.output_dir, group);
+            let head = &mut cmds[range.start];
+            if group > 0 {
+                let name = head.instance_name().unwrap_or_default();
+                let suffix = name.find('_').map_or("", |i| &name[i..]).to_string();
+                head.remove_flags(&["-S"]);
+                head.add_flag(format!("-M m{group}{suffix}"));
+            }
+            head.add_flag(format!(
+                "-F {}",
+                group_dir.join(sync_groups::GROUP_SYNC_DIR).display()
+            ));
+            for cmd in &mut cmds[range] {
+                cmd.with_output_dir(group_dir.clone());
+            }
+        }
+    }
+
+    /// Lets the selected instances, by default the `-M` one, import the foreign directories
+    ///
+    /// AFL++ parses `-F` only after `-M`, so this has to run after the roles are applied.
+    fn apply_foreign_dirs(&self, cmds: &mut [AFLCmd]) -> Result<()> {
+        let foreign = &self.base_cfg.foreign_dirs;
+        if foreign.is_empty() {
+            return Ok(());
+        }
+        let names: Vec<Option<String>> = cmds
+            .iter()
+            .map(|c| c.instance_name().map(String::from))
+            .collect();
+        let names: Vec<Option<&str>> = names.iter().map(Option::as_deref).collect();
+        let main = (self.mode != Mode::CIFuzzing && !cmds.is_empty()).then_some(0);
+
+        let indices = foreign.resolve(&names, main)?;
+        if indices.is_empty() {
+            println!("[!] Warning: No -M instance to import the foreign directories, select instances explicitly");
+        }
+        for idx in indices {
+            if Some(idx) != main {
+                println!(
+                    "[!] Warning: {} imports foreign directories without -M, which older AFL++ versions reject",
+                    names[idx].unwrap_or("-")
+                );
+            }
+            for dir in &foreign.dirs {
+                cmds[idx].add_flag(format!("-F {}", dir.display()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes a variable that has been explicitly disabled by the user
+    fn unset_env(cmd: &mut AFLCmd, setting: &AFLEnvSetting) {
+        cmd.env
+            .retain(|e| e.split('=').next().unwrap_or_default() != setting.flag.as_str());
+    }
+
+    /// Checks every instance for conflicting environment variables and flags
+    fn check_env_conflicts(cmds: &[AFLCmd]) -> Result<()> {
+        for (idx, cmd) in cmds.iter().enumerate() {
+            env_vars::check_conflicts(&cmd.env)
+                .with_context(|| format!("Conflicting environment for instance {idx}"))?;
+            let no_affinity = cmd.env.iter().any(|e| e.starts_with("AFL_NO_AFFINITY=1"));
+            if no_affinity && cmd.misc_afl_flags.iter().any(|f| f.starts_with("-b ")) {
+                bail!("Instance {idx}: AFL_NO_AFFINITY cannot be combined with core binding (-b)");
+            }
+        }
+        Ok(())
+    }
+
     // Inherit global AFL++ environment variables that are not already set
     fn apply_global_env_vars(cmds: &mut [AFLCmd], afl_env_vars: &[String]) {
         for cmd in cmds {
@@ -139,16 +465,15 @@ impl AFLCmdGenerator {
     }
 
     /// Creates initial AFL++ commands
-    fn create_initial_cmds(&self, afl_envs: &[AFLEnv]) -> Result<Vec<AFLCmd>> {
-        let afl_binary = find_binary_in_path(self.base_cfg.afl_binary.clone())?;
+    fn create_initial_cmds(&self, afl_binary: &Path, afl_envs: &[AFLEnv]) -> Result<Vec<AFLCmd>> {
         let target_binary = &self.harness.target_bin;
         Ok(afl_envs
             .iter()
             .map(|afl_env_cfg| {
-                let mut cmd = AFLCmd::new(afl_binary.clone(), target_binary.clone());
+                let mut cmd = AFLCmd::new(afl_binary.to_path_buf(), target_binary.clone());
                 cmd.with_env(afl_env_cfg.generate(), false);
                 if let Some(flags) = &self.base_cfg.raw_afl_flags {
-                    cmd.with_misc_flags(flags.split_whitespace().map(String::from).collect());
+                    cmd.with_misc_flags(split_flags(flags));
                 }
 
                 cmd
@@ -208,12 +533,115 @@ impl AFLCmdGenerator {
         }
     }
 
-    /// Applies dictionary to AFL++ commands
-    fn apply_dictionary(&self, cmds: &mut [AFLCmd]) -> Result<()> {
-        if let Some(dict) = &self.base_cfg.dictionary {
-            let dict_path = fs::canonicalize(dict).context("Failed to resolve dictionary path")?;
-            for cmd in cmds {
-                cmd.add_flag(format!("-x {}", dict_path.display()));
+    /// Applies dictionaries to AFL++ commands
+    ///
+    /// User dictionaries are passed through unmodified unless tokens are extracted or the
+    /// dictionary is split. In that case all tokens are merged into a generated dictionary
+    /// which the `-M` instance gets in full while the secondaries get disjoint subsets.
+    fn apply_dictionary(&self, cmds: &mut [AFLCmd], rng: &mut StdRng) -> Result<()> {
+        self.generated_dicts.borrow_mut().clear();
+        let cfg = &self.base_cfg.dictionary;
+        if !cfg.is_enabled() {
+            return Ok(());
+        }
+
+        let mut merged = Dictionary::default();
+        for path in &cfg.paths {
+            merged.extend(Dictionary::load(path)?);
+        }
+        if cfg.is_passthrough() {
+            for path in &cfg.paths {
+                let dict_path = fs::canonicalize(path).with_context(|| {
+                    format!("Failed to resolve dictionary path: {}", path.display())
+                })?;
+                for cmd in cmds.iter_mut() {
+                    cmd.add_flag(format!("-x {}", dict_path.display()));
+                }
+            }
+            return Ok(());
+        }
+
+        self.extract_tokens(&mut merged)?;
+        if merged.is_empty() {
+            println!("[!] Dictionary is empty, not using -x");
+            return Ok(());
+        }
+
+        let dict_dir = self.dict_dir();
+        let full_path = dict_dir.join("full.dict");
+        println!(
+            "[+] Using dictionary with {} tokens: {}",
+            merged.len(),
+            full_path.display()
+        );
+
+        let secondaries = cmds.len().saturating_sub(1);
+        if !cfg.split || secondaries == 0 {
+            for cmd in cmds.iter_mut() {
+                cmd.add_flag(format!("-x {}", full_path.display()));
+            }
+            self.generated_dicts.borrow_mut().push((full_path, merged));
+            return Ok(());
+        }
+
+        let subsets = merged.split(merged.subset_count(secondaries), rng);
+        let mut generated = self.generated_dicts.borrow_mut();
+        let mut subset_paths = Vec::with_capacity(subsets.len());
+        for (i, subset) in subsets.into_iter().enumerate() {
+            let path = dict_dir.join(format!("subset_{i}.dict"));
+            subset_paths.push(path.clone());
+            generated.push((path, subset));
+        }
+        println!(
+            "[+] Split dictionary into {} subsets for {secondaries} secondaries",
+            subset_paths.len()
+        );
+        cmds[0].add_flag(format!("-x {}", full_path.display()));
+        generated.push((full_path, merged));
+        for (i, cmd) in cmds.iter_mut().skip(1).enumerate() {
+            cmd.add_flag(format!(
+                "-x {}",
+                subset_paths[i % subset_paths.len()].display()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Adds tokens from the target binary, the seeds and `libtokencap` to `dict`
+    fn extract_tokens(&self, dict: &mut Dictionary) -> Result<()> {
+        let cfg = &self.base_cfg.dictionary;
+        let seed_dir = &self.base_cfg.input_dir;
+        if cfg.auto_extract {
+            let from_binary = Dictionary::from_binary(&self.harness.target_bin)?;
+            println!(
+                "[*] Extracted {} tokens from {}",
+                from_binary.len(),
+                self.harness.target_bin.display()
+            );
+            dict.extend(from_binary);
+            if seed_dir.is_dir() {
+                let from_seeds = Dictionary::from_seeds(seed_dir)?;
+                println!(
+                    "[*] Extracted {} tokens from the seed corpus",
+                    from_seeds.len()
+                );
+                dict.extend(from_seeds);
+            }
+        }
+        if let Some(lib) = &cfg.tokencap {
+            if seed_dir.is_dir() {
+                let args = self
+                    .harness
+                    .target_args
+                    .as_deref()
+                    .map(|a| a.split_whitespace().map(String::from).collect())
+                    .unwrap_or_default();
+                let captured =
+                    Dictionary::from_tokencap(lib, &self.harness.target_bin, args, seed_dir)?;
+                println!("[*] Captured {} tokens with libtokencap", captured.len());
+                dict.extend(captured);
+            } else {
+                println!("[!] Seed directory does not exist, skipping libtokencap");
             }
         }
         Ok(())
@@ -240,6 +668,51 @@ impl AFLCmdGenerator {
         }
     }
 
+    /// Binds each AFL++ command to a dedicated CPU core
+    /// Binds every instance without a pinned `-b` to a core not taken by a pinned one
+    fn apply_core_binding(
+        cmds: &mut [AFLCmd],
+        binding: &CoreBinding,
+        topology: &CpuTopology,
+    ) -> Result<()> {
+        let pinned_core = |cmd: &AFLCmd| {
+            cmd.misc_afl_flags
+                .iter()
+                .find_map(|f| f.strip_prefix("-b "))
+                .map(str::trim)
+                .map(String::from)
+        };
+        let pinned: Vec<usize> = cmds
+            .iter()
+            .filter_map(|cmd| pinned_core(cmd)?.parse().ok())
+            .collect();
+        let mut binding = binding.clone();
+        if !pinned.is_empty() {
+            let allowed = binding
+                .allowed
+                .take()
+                .unwrap_or_else(|| topology.cpus.iter().map(|c| c.id).collect());
+            binding.allowed = Some(
+                allowed
+                    .into_iter()
+                    .filter(|id| !pinned.contains(id))
+                    .collect(),
+            );
+        }
+
+        let unbound: Vec<&mut AFLCmd> = cmds
+            .iter_mut()
+            .filter(|cmd| pinned_core(cmd).is_none())
+            .collect();
+        let cores = topology
+            .place(&binding, unbound.len())
+            .context("Failed to place instances on CPU cores")?;
+        for (cmd, core) in unbound.into_iter().zip(cores) {
+            cmd.add_flag(format!("-b {core}"));
+        }
+        Ok(())
+    }
+
     fn apply_afl_seed(cmds: &mut [AFLCmd], seed: u64) {
         for cmd in cmds {
             cmd.add_flag(format!("-s {seed}"));
@@ -250,6 +723,8 @@ impl AFLCmdGenerator {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::afl::dictionary::DictionaryConfig;
+    use crate::afl::foreign::ForeignDirs;
     use tempfile::TempDir;
 
     fn create_test_harness() -> Harness {
@@ -292,7 +767,7 @@ mod tests {
         let (_temp, generator) = setup_test_generator();
         assert_eq!(generator.runners, 2);
         assert_eq!(generator.seed, Some(42));
-        assert!(generator.base_cfg.dictionary.is_none());
+        assert!(!generator.base_cfg.dictionary.is_enabled());
         assert!(generator.base_cfg.ramdisk.is_none());
     }
 
@@ -314,9 +789,11 @@ mod tests {
     fn test_generator_with_dictionary() {
         let temp_dir = TempDir::new().unwrap();
         let dict_path = temp_dir.path().join("dict.txt");
-        fs::write(&dict_path, "test:test").unwrap();
+        fs::write(&dict_path, "test=\"test\"").unwrap();
 
-        let afl_base = create_afl_base_cfg().with_dictionary(Some(dict_path));
+        let afl_base = create_afl_base_cfg()
+            .with_dictionary(DictionaryConfig::new(vec![dict_path]))
+            .unwrap();
 
         let generator = AFLCmdGenerator::new(
             create_test_harness(),
@@ -326,17 +803,89 @@ mod tests {
             None,
         );
 
-        assert!(generator.base_cfg.dictionary.is_some());
+        assert!(generator.base_cfg.dictionary.is_enabled());
 
         let cmds = generator.run().unwrap();
         assert!(cmds.iter().all(|cmd| cmd.to_string().contains("-x")));
     }
 
+    #[test]
+    fn test_dictionary_validation_and_split() {
+        let temp_dir = TempDir::new().unwrap();
+        let missing = temp_dir.path().join("missing.dict");
+        assert!(create_afl_base_cfg()
+            .with_dictionary(DictionaryConfig::new(vec![missing]))
+            .is_err());
+
+        let invalid = temp_dir.path().join("invalid.dict");
+        fs::write(&invalid, "test:test").unwrap();
+        let afl_base = create_afl_base_cfg()
+            .with_dictionary(DictionaryConfig::new(vec![invalid]))
+            .unwrap();
+        let generator = AFLCmdGenerator::new(
+            create_test_harness(),
+            2,
+            &afl_base,
+            Mode::MultipleCores,
+            None,
+        );
+        assert!(generator.run().is_err());
+
+        let first = temp_dir.path().join("first.dict");
+        let second = temp_dir.path().join("second.dict");
+        let tokens: Vec<String> = (0..40).map(|i| format!("t{i}=\"token{i}\"")).collect();
+        fs::write(&first, tokens[..30].join("\n")).unwrap();
+        fs::write(&second, tokens[20..].join("\n")).unwrap();
+        let output_dir = temp_dir.path().join("output");
+        let afl_base = Bcfg::new(PathBuf::from("/input"), output_dir.clone())
+            .with_dictionary(DictionaryConfig {
+                split: true,
+                ..DictionaryConfig::new(vec![first, second])
+            })
+            .unwrap();
+        let generator = AFLCmdGenerator::new(
+            create_test_harness(),
+            4,
+            &afl_base,
+            Mode::MultipleCores,
+            Some(7),
+        );
+        let cmds = generator.run().unwrap();
+        let dicts: Vec<&String> = cmds
+            .iter()
+            .map(|c| {
+                let flags: Vec<&String> = c
+                    .misc_afl_flags
+                    .iter()
+                    .filter(|f| f.starts_with("-x "))
+                    .collect();
+                assert_eq!(flags.len(), 1);
+                flags[0]
+            })
+            .collect();
+        assert_eq!(
+            *dicts[0],
+            format!(
+                "-x {}",
+                output_dir.join(DICT_DIR).join("full.dict").display()
+            )
+        );
+        assert!(dicts[1].ends_with("subset_0.dict"));
+        assert!(dicts[2].ends_with("subset_1.dict"));
+        assert!(dicts[3].ends_with("subset_0.dict"));
+
+        // Nothing is written before the commands are started
+        assert!(!output_dir.exists());
+        assert_eq!(generator.write_dictionaries().unwrap(), 3);
+        let full = Dictionary::load(&PathBuf::from(&dicts[0][3..])).unwrap();
+        assert_eq!(full.len(), 40);
+    }
+
     #[test]
     fn test_generator_with_raw_flags() {
         let (_temp, generator) = setup_test_generator();
 
-        let result = generator.create_initial_cmds(&[AFLEnv::default()]);
+        let result = generator.create_initial_cmds(Path::new("afl-fuzz"), &[AFLEnv::default()]);
         assert!(result.is_ok());
     }
 
@@ -485,6 +1034,186 @@ mod tests {
         assert!(cmds_with_defaults[0].to_string().len() < cmds_no_defaults[0].to_string().len());
     }
 
+    #[test]
+    fn test_core_binding() {
+        let topology = CpuTopology {
+            cpus: (0..4)
+                .map(|id| crate::utils::topology::LogicalCpu {
+                    id,
+                    core_id: id % 2,
+                    package_id: 0,
+                    node: 0,
+                })
+                .collect(),
+        };
+        let mut cmds =
+            vec![AFLCmd::new(PathBuf::from("afl-fuzz"), PathBuf::from("/bin/test-target")); 2];
+        let binding = CoreBinding::new("all", true).unwrap();
+
+        AFLCmdGenerator::apply_core_binding(&mut cmds, &binding, &topology).unwrap();
+        assert!(cmds[0].misc_afl_flags.contains(&"-b 0".to_string()));
+        assert!(cmds[1].misc_afl_flags.contains(&"-b 1".to_string()));
+
+        let mut too_many =
+            vec![AFLCmd::new(PathBuf::from("afl-fuzz"), PathBuf::from("/bin/test-target")); 3];
+        assert!(AFLCmdGenerator::apply_core_binding(&mut too_many, &binding, &topology).is_err());
+    }
+
+    #[test]
+    fn test_core_binding_with_pinned_core() {
+        use crate::afl::overrides::{InstanceOverride, InstanceSelector};
+
+        let (_temp, mut generator) = setup_test_generator();
+        generator.runners = 3;
+        generator.base_cfg.instance_overrides =
+            vec![InstanceOverride::new(InstanceSelector::Indices(vec![1]))
+                .with_flags("-b 3")
+                .unwrap()];
+        let mut cmds = generator.run().unwrap();
+        let topology = CpuTopology {
+            cpus: (0..4)
+                .map(|id| crate::utils::topology::LogicalCpu {
+                    id,
+                    core_id: id,
+                    package_id: 0,
+                    node: 0,
+                })
+                .collect(),
+        };
+
+        let bound = |cmd: &AFLCmd| {
+            cmd.misc_afl_flags
+                .iter()
+                .filter(|f| f.starts_with("-b "))
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        let mut unbindable = cmds.clone();
+        AFLCmdGenerator::apply_core_binding(
+            &mut cmds,
+            &CoreBinding::new("1-3", false).unwrap(),
+            &topology,
+        )
+        .unwrap();
+        assert_eq!(bound(&cmds[0]), vec!["-b 1"]);
+        assert_eq!(bound(&cmds[1]), vec!["-b 3"]);
+        assert_eq!(bound(&cmds[2]), vec!["-b 2"]);
+
+        // The pinned core is not available to the other instances
+        let binding = CoreBinding::new("2-3", false).unwrap();
+        assert!(AFLCmdGenerator::apply_core_binding(&mut unbindable, &binding, &topology).is_err());
+    }
+
+    #[test]
+    fn test_env_settings() {
+        let (_temp, mut generator) = setup_test_generator();
+        generator.base_cfg.env_settings = vec![
+            AFLEnvSetting::parse("AFL_MAP_SIZE", "65536").unwrap(),
+            AFLEnvSetting::parse("AFL_TESTCACHE_SIZE", "100").unwrap(),
+            AFLEnvSetting::parse("AFL_NO_UI", "1")
+                .unwrap()
+                .for_instances(vec![1]),
+        ];
+
+        let cmds = generator.run().unwrap();
+        for cmd in &cmds {
+            assert!(cmd.env.contains(&"AFL_MAP_SIZE=65536".to_string()));
+            assert!(cmd.env.contains(&"AFL_TESTCACHE_SIZE=100".to_string()));
+            assert_eq!(
+                cmd.env
+                    .iter()
+                    .filter(|e| e.starts_with("AFL_TESTCACHE_SIZE"))
+                    .count(),
+                1
+            );
+        }
+        assert!(!cmds[0].env.contains(&"AFL_NO_UI=1".to_string()));
+        assert!(cmds[1].env.contains(&"AFL_NO_UI=1".to_string()));
+    }
+
+    #[test]
+    fn test_env_settings_invalid_scope_and_conflicts() {
+        let (_temp, mut generator) = setup_test_generator();
+        generator.base_cfg.env_settings = vec![AFLEnvSetting::parse("AFL_NO_UI", "1")
+            .unwrap()
+            .for_instances(vec![5])];
+        assert!(generator.run().is_err());
+
+        generator.base_cfg.env_settings = vec![
+            AFLEnvSetting::parse("AFL_NO_UI", "1").unwrap(),
+            AFLEnvSetting::parse("AFL_FORCE_UI", "1").unwrap(),
+        ];
+        assert!(generator.run().is_err());
+    }
+
+    #[test]
+    fn test_instance_overrides() {
+        use crate::afl::overrides::{InstanceOverride, InstanceSelector};
+
+        let (temp, mut generator) = setup_test_generator();
+        let dict_path = temp.path().join("dict.txt");
+        fs::write(&dict_path, "kw=\"test\"").unwrap();
+        generator.runners = 3;
+
+        let mut by_idx = InstanceOverride::new(InstanceSelector::Indices(vec![1]))
+            .with_flags("-t 500+")
+            .unwrap();
+        by_idx.schedule = Some("rare".parse().unwrap());
+        by_idx.binary = Some(PathBuf::from("/bin/other-target"));
+        by_idx.env = vec![AFLEnvSetting::parse("AFL_SYNC_TIME", "5").unwrap()];
+        let mut by_name =
+            InstanceOverride::new(InstanceSelector::Name(glob::Pattern::new("m_*").unwrap()));
+        by_name.dictionary = Some(dict_path.clone());
+        generator.base_cfg.instance_overrides = vec![by_idx, by_name];
+
+        let cmds = generator.run().unwrap();
+        let schedules = |cmd: &AFLCmd| {
+            cmd.misc_afl_flags
+                .iter()
+                .filter(|f| f.starts_with("-p "))
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(schedules(&cmds[1]), vec!["-p rare"]);
+        assert!(cmds[1].misc_afl_flags.contains(&"-t 500+".to_string()));
+        assert!(cmds[1].env.contains(&"AFL_SYNC_TIME=5".to_string()));
+        assert_eq!(cmds[1].target_binary, PathBuf::from("/bin/other-target"));
+        assert!(cmds[1].instance_name().unwrap().starts_with("s0_"));
+        assert!(cmds[0].to_string().contains("-x "));
+        assert!(!cmds[2].to_string().contains("-x "));
+        assert!(!cmds[2].env.contains(&"AFL_SYNC_TIME=5".to_string()));
+    }
+
+    #[test]
+    fn test_instance_overrides_conflicts() {
+        use crate::afl::overrides::{InstanceOverride, InstanceSelector};
+
+        let (_temp, mut generator) = setup_test_generator();
+        let pin_schedule = |selector: InstanceSelector| {
+            let mut ovr = InstanceOverride::new(selector);
+            ovr.schedule = Some("fast".parse().unwrap());
+            ovr
+        };
+
+        // Same setting pinned twice for the same instance
+        generator.base_cfg.instance_overrides = vec![
+            pin_schedule(InstanceSelector::Indices(vec![1])),
+            pin_schedule(InstanceSelector::Name(glob::Pattern::new("s0_*").unwrap())),
+        ];
+        assert!(generator.run().is_err());
+
+        // There is no -M instance in CI mode
+        generator.mode = Mode::CIFuzzing;
+        generator.base_cfg.instance_overrides = vec![pin_schedule(InstanceSelector::Name(
+            glob::Pattern::new("m_*").unwrap(),
+        ))];
+        assert!(generator.run().is_err());
+
+        generator.base_cfg.instance_overrides =
+            vec![pin_schedule(InstanceSelector::Indices(vec![2]))];
+        assert!(generator.run().is_err());
+    }
+
     #[test]
     fn test_afl_relay_seed() {
         let (_temp_dir, generator) = setup_test_generator();
@@ -494,4 +1223,72 @@ mod tests {
         assert!(cmds[0].to_string().contains("-s"));
         assert!(cmds[0].to_string().contains(&format!("{}", expected_seed)));
     }
+
+    #[test]
+    fn test_foreign_dirs() {
+        let (temp_dir, mut generator) = setup_test_generator();
+        let libfuzzer = temp_dir.path().join("libfuzzer");
+        generator.base_cfg = generator
+            .base_cfg
+            .clone()
+            .with_foreign_dirs(ForeignDirs::new(vec![libfuzzer.clone()]))
+            .unwrap();
+        let flag = format!("-F {}", fs::canonicalize(&libfuzzer).unwrap().display());
+
+        let cmds = generator.run().unwrap();
+        let main_flags = &cmds[0].misc_afl_flags;
+        let m_pos = main_flags
+            .iter()
+            .position(|f| f.starts_with("-M "))
+            .unwrap();
+        assert!(main_flags.iter().position(|f| *f == flag).unwrap() > m_pos);
+        assert!(!cmds[1].misc_afl_flags.contains(&flag));
+
+        generator.base_cfg.foreign_dirs.selector = Some("1".parse().unwrap());
+        let cmds = generator.run().unwrap();
+        assert!(!cmds[0].misc_afl_flags.contains(&flag));
+        assert!(cmds[1].misc_afl_flags.contains(&flag));
+
+        generator.base_cfg.foreign_dirs.selector = Some("5".parse().unwrap());
+        assert!(generator.run().is_err());
+    }
+
+    #[test]
+    fn test_sync_groups() {
+        let (temp_dir, mut generator) = setup_test_generator();
+        let output_dir = temp_dir.path().join("output");
+        assert!(generator.base_cfg.clone().with_group_size(Some(1)).is_err());
+        generator.base_cfg = generator.base_cfg.clone().with_group_size(Some(3)).unwrap();
+        assert!(generator
+            .run()
+            .unwrap()
+            .iter()
+            .all(|c| c.output_dir == output_dir));
+
+        generator.runners = 5;
+        let cmds = generator.run().unwrap();
+        let names: Vec<_> = cmds.iter().map(|c| c.instance_name().unwrap()).collect();
+        assert_eq!(names[0], "m_test-target");
+        assert_eq!(names[2], "m1_test-target");
+        assert!(names[1].starts_with("s0_") && names[4].starts_with("s3_"));
+        assert_eq!(cmds[0].output_dir, output_dir.join("group-0"));
+        assert_eq!(cmds[1].output_dir, output_dir.join("group-0"));
+        assert_eq!(cmds[4].output_dir, output_dir.join("group-1"));
+
+        let flag = format!(
+            "-F {}",
+            output_dir
+                .join("group-1")
+                .join(sync_groups::GROUP_SYNC_DIR)
+                .display()
+        );
+        let head_flags = &cmds[2].misc_afl_flags;
+        assert!(head_flags.iter().all(|f| !f.starts_with("-S ")));
+        assert!(head_flags.contains(&flag));
+        assert!(!cmds[3].misc_afl_flags.contains(&flag));
+        assert_eq!(
+            sync_groups::group_dirs(&output_dir, &cmds),
+            vec![output_dir.join("group-0"), output_dir.join("group-1")]
+        );
+    }
 }
@@ -0,0 +1,547 @@
+use std::{
+    collections::{BTreeSet, HashSet},
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, BufWriter, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::afl::sync_groups::instance_dirs;
+use crate::utils::hash::content_hash;
+
+/// Directory in the AFL++ output directory imported entries are written to, read via `-F`
+pub const SYNC_DIR: &str = "aflr_sync";
+/// Sync log in the AFL++ output directory and the server store
+pub const SYNC_LOG: &str = "aflr_sync.log";
+/// Port the sync server listens on by default
+pub const DEFAULT_SYNC_PORT: u16 = 7411;
+/// State of the sync client in the AFL++ output directory
+const SYNC_STATE: &str = "aflr_sync_state.json";
+/// Largest entry exchanged, matching the AFL++ maximum input size
+const MAX_ENTRY_SIZE: usize = 1024 * 1024;
+/// Maximum number of entries per `GET` response and hashes per `QUERY`
+const MAX_BATCH: usize = 512;
+/// Longest protocol line accepted, including the newline
+const MAX_LINE: u64 = 128;
+/// Time a peer may stall before the connection is dropped
+const IO_TIMEOUT: Duration = Duration::from_secs(60);
+
+fn hex_hash(data: &[u8]) -> String {
+    format!("{:016x}", content_hash(data))
+}
+
+fn log_line(log: &mut File, line: &str) {
+    let _ = writeln!(log, "{} {line}", chrono::Utc::now().to_rfc3339());
+}
+
+/// Reads a protocol line without the trailing newline, `None` at the end of the stream
+fn read_line(reader: &mut impl BufRead) -> Result<Option<String>> {
+    let mut line = String::new();
+    if reader.take(MAX_LINE).read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    if !line.ends_with('\n') && line.len() as u64 == MAX_LINE {
+        bail!("Protocol line exceeds {MAX_LINE} bytes");
+    }
+    Ok(Some(line.trim_end().to_string()))
+}
+
+/// Parses the number of lines following a `QUERY` or `MISSING`
+fn batch_count(count: &str) -> Result<usize> {
+    let count: usize = count.parse().context("Invalid batch size")?;
+    if count > MAX_BATCH {
+        bail!("Batch of {count} hashes exceeds the maximum of {MAX_BATCH}");
+    }
+    Ok(count)
+}
+
+/// Drops connections whose peer stalls for longer than `IO_TIMEOUT`
+fn set_timeouts(stream: &TcpStream) -> Result<()> {
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+    Ok(())
+}
+
+/// Reads an entry of `len` bytes after a protocol line
+fn read_entry(reader: &mut impl Read, len: &str) -> Result<Vec<u8>> {
+    let len: usize = len.parse().context("Invalid entry length")?;
+    if len > MAX_ENTRY_SIZE {
+        bail!("Entry of {len} bytes exceeds the maximum of {MAX_ENTRY_SIZE}");
+    }
+    let mut data = vec![0; len];
+    reader.read_exact(&mut data)?;
+    Ok(data)
+}
+
+/// Content-addressed corpus of the sync server with an append-only index
+#[derive(Debug)]
+struct Store {
+    dir: PathBuf,
+    index: Mutex<(Vec<String>, HashSet<String>)>,
+    log: Mutex<File>,
+}
+
+impl Store {
+    fn open(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir.join("entries"))
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+        let order: Vec<String> = fs::read_to_string(dir.join("index"))
+            .unwrap_or_default()
+            .lines()
+            .map(String::from)
+            .collect();
+        let known = order.iter().cloned().collect();
+        let log = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(SYNC_LOG))?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            index: Mutex::new((order, known)),
+            log: Mutex::new(log),
+        })
+    }
+
+    fn len(&self) -> usize {
+        self.index.lock().unwrap().0.len()
+    }
+
+    fn missing(&self, hashes: &[String]) -> Vec<String> {
+        let index = self.index.lock().unwrap();
+        hashes
+            .iter()
+            .filter(|h| !index.1.contains(*h))
+            .cloned()
+            .collect()
+    }
+
+    /// Stores an entry unless its content is known already
+    fn insert(&self, data: &[u8]) -> Result<bool> {
+        let hash = hex_hash(data);
+        let mut index = self.index.lock().unwrap();
+        if index.1.contains(&hash) {
+            return Ok(false);
+        }
+        fs::write(self.dir.join("entries").join(&hash), data)?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.dir.join("index"))?;
+        writeln!(file, "{hash}")?;
+        index.0.push(hash.clone());
+        index.1.insert(hash);
+        Ok(true)
+    }
+
+    /// Hashes of the entries stored after position `since` and the next position
+    fn since(&self, since: usize) -> (Vec<String>, usize) {
+        let index = self.index.lock().unwrap();
+        let batch: Vec<_> = index
+            .0
+            .iter()
+            .skip(since)
+            .take(MAX_BATCH)
+            .cloned()
+            .collect();
+        let next = since.min(index.0.len()) + batch.len();
+        (batch, next)
+    }
+
+    fn read(&self, hash: &str) -> Result<Vec<u8>> {
+        Ok(fs::read(self.dir.join("entries").join(hash))?)
+    }
+
+    fn log(&self, line: &str) {
+        log_line(&mut self.log.lock().unwrap(), line);
+    }
+}
+
+/// TCP server exchanging queue entries between the campaigns of `aflr sync-client`s
+#[derive(Debug)]
+pub struct SyncServer {
+    listener: TcpListener,
+    store: Arc<Store>,
+}
+
+impl SyncServer {
+    /// Listens on `addr` and stores the corpus in `store_dir`
+    ///
+    /// # Errors
+    /// * If the address cannot be bound or the store cannot be opened
+    pub fn bind(addr: &str, store_dir: &Path) -> Result<Self> {
+        let listener = TcpListener::bind(addr).with_context(|| format!("Failed to bind {addr}"))?;
+        Ok(Self {
+            listener,
+            store: Arc::new(Store::open(store_dir)?),
+        })
+    }
+
+    /// Address the server listens on
+    ///
+    /// # Errors
+    /// * If the address of the socket cannot be retrieved
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Number of stored entries
+    pub fn entries(&self) -> usize {
+        self.store.len()
+    }
+
+    /// Serves clients until the process is stopped, one thread per connection
+    pub fn serve(&self) {
+        for stream in self.listener.incoming() {
+            let Ok(stream) = stream else { continue };
+            let store = Arc::clone(&self.store);
+            thread::spawn(move || {
+                let peer = stream
+                    .peer_addr()
+                    .map_or_else(|_| "unknown".to_string(), |a| a.to_string());
+                match Self::handle(stream, &store) {
+                    Ok((received, sent)) => store.log(&format!(
+                        "{peer}: received {received}, sent {sent}, {} stored",
+                        store.len()
+                    )),
+                    Err(e) => store.log(&format!("{peer}: {e:#}")),
+                }
+            });
+        }
+    }
+
+    /// Handles the requests of one client and returns the entries received and sent
+    fn handle(stream: TcpStream, store: &Store) -> Result<(usize, usize)> {
+        set_timeouts(&stream)?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut writer = BufWriter::new(stream);
+        let (mut received, mut sent) = (0, 0);
+
+        while let Some(line) = read_line(&mut reader)? {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            match parts.as_slice() {
+                ["QUERY", count] => {
+                    let hashes = (0..batch_count(count)?)
+                        .map(|_| read_line(&mut reader)?.context("Incomplete QUERY"))
+                        .collect::<Result<Vec<_>>>()?;
+                    let missing = store.missing(&hashes);
+                    writeln!(writer, "MISSING {}", missing.len())?;
+                    for hash in missing {
+                        writeln!(writer, "{hash}")?;
+                    }
+                }
+                ["PUT", len] => {
+                    let data = read_entry(&mut reader, len)?;
+                    if store.insert(&data)? {
+                        received += 1;
+                    }
+                    writeln!(writer, "OK")?;
+                }
+                ["GET", since] => {
+                    let (hashes, next) = store.since(since.parse()?);
+                    writeln!(writer, "ENTRIES {} {next}", hashes.len())?;
+                    for hash in hashes {
+                        let data = store.read(&hash)?;
+                        writeln!(writer, "{}", data.len())?;
+                        writer.write_all(&data)?;
+                        sent += 1;
+                    }
+                }
+                ["BYE"] => break,
+                _ => bail!("Invalid request '{line}'"),
+            }
+            writer.flush()?;
+        }
+        Ok((received, sent))
+    }
+}
+
+/// Entries exchanged in one sync round
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SyncRound {
+    /// Local queue entries the server did not know yet
+    pub pushed: usize,
+    /// Foreign entries imported into the sync directory
+    pub pulled: usize,
+}
+
+/// Persistent state of the sync client of a campaign
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ClientState {
+    /// Position in the server index up to which entries were fetched
+    cursor: usize,
+    /// Queue entries already offered to the server, relative to the output directory
+    offered: BTreeSet<String>,
+    /// Content hashes of all offered and imported entries
+    known: BTreeSet<String>,
+}
+
+/// Client exchanging the queue entries of a campaign with an `aflr sync-server`
+///
+/// New entries of all local instances are offered to the server, foreign ones are imported
+/// into `aflr_sync/` in the AFL++ output directory, which the `-M` instance reads via `-F`.
+#[derive(Debug, Clone)]
+pub struct SyncClient {
+    server: String,
+    afl_out: PathBuf,
+}
+
+impl SyncClient {
+    pub fn new(server: &str, afl_out: &Path) -> Self {
+        Self {
+            server: server.to_string(),
+            afl_out: afl_out.to_path_buf(),
+        }
+    }
+
+    /// Directory foreign entries are imported into
+    pub fn foreign_dir(&self) -> PathBuf {
+        self.afl_out.join(SYNC_DIR)
+    }
+
+    /// Queue entries of the local instances as paths relative to the output directory
+    fn local_entries(&self) -> Vec<String> {
+        let mut entries = Vec::new();
+        for instance in instance_dirs(&self.afl_out) {
+            for entry in fs::read_dir(instance.join("queue"))
+                .into_iter()
+                .flatten()
+                .flatten()
+            {
+                let file = entry.file_name().to_string_lossy().into_owned();
+                let path = entry.path();
+                if file.starts_with("id:") && path.is_file() {
+                    if let Ok(rel) = path.strip_prefix(&self.afl_out) {
+                        entries.push(rel.to_string_lossy().into_owned());
+                    }
+                }
+            }
+        }
+        entries
+    }
+
+    /// Exchanges new entries with the server once
+    ///
+    /// # Errors
+    /// * If the server cannot be reached or answers with an invalid response
+    /// * If the state or imported entries cannot be written
+    pub fn sync(&self) -> Result<SyncRound> {
+        let state_path = self.afl_out.join(SYNC_STATE);
+        let mut state: ClientState = fs::read_to_string(&state_path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        let foreign_dir = self.foreign_dir();
+        fs::create_dir_all(&foreign_dir)?;
+
+        let addr = self
+            .server
+            .to_socket_addrs()
+            .with_context(|| format!("Failed to resolve {}", self.server))?
+            .next()
+            .with_context(|| format!("No address found for {}", self.server))?;
+        let stream = TcpStream::connect_timeout(&addr, IO_TIMEOUT)
+            .with_context(|| format!("Failed to connect to {}", self.server))?;
+        set_timeouts(&stream)?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut writer = BufWriter::new(stream);
+        let mut round = SyncRound::default();
+
+        // Offer the new local entries and upload the ones the server misses
+        let mut offered = Vec::new();
+        for rel in self.local_entries() {
+            if state.offered.contains(&rel) {
+                continue;
+            }
+            let data = fs::read(self.afl_out.join(&rel))?;
+            if data.len() <= MAX_ENTRY_SIZE {
+                offered.push((hex_hash(&data), data));
+            }
+            state.offered.insert(rel);
+        }
+        offered.retain(|(hash, _)| state.known.insert(hash.clone()));
+        for batch in offered.chunks(MAX_BATCH) {
+            writeln!(writer, "QUERY {}", batch.len())?;
+            for (hash, _) in batch {
+                writeln!(writer, "{hash}")?;
+            }
+            writer.flush()?;
+            let missing: HashSet<String> = match read_line(&mut reader)?.as_deref() {
+                Some(line) if line.starts_with("MISSING ") => (0..batch_count(&line[8..])?)
+                    .map(|_| read_line(&mut reader)?.context("Incomplete MISSING response"))
+                    .collect::<Result<_>>()?,
+                other => bail!("Invalid response {other:?}"),
+            };
+            for (_, data) in batch.iter().filter(|(hash, _)| missing.contains(hash)) {
+                writeln!(writer, "PUT {}", data.len())?;
+                writer.write_all(data)?;
+                writer.flush()?;
+                if read_line(&mut reader)?.as_deref() != Some("OK") {
+                    bail!("Server rejected an entry");
+                }
+                round.pushed += 1;
+            }
+        }
+
+        // Import the entries of other campaigns
+        loop {
+            writeln!(writer, "GET {}", state.cursor)?;
+            writer.flush()?;
+            let line = read_line(&mut reader)?.context("Server closed the connection")?;
+            let (count, next) = match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+                ["ENTRIES", count, next] => (count.parse::<usize>()?, next.parse::<usize>()?),
+                _ => bail!("Invalid response '{line}'"),
+            };
+            for _ in 0..count {
+                let len = read_line(&mut reader)?.context("Incomplete ENTRIES response")?;
+                let data = read_entry(&mut reader, &len)?;
+                let hash = hex_hash(&data);
+                if state.known.insert(hash.clone()) {
+                    fs::write(foreign_dir.join(format!("id:{hash}")), &data)?;
+                    round.pulled += 1;
+                }
+            }
+            state.cursor = next;
+            if count < MAX_BATCH {
+                break;
+            }
+        }
+        writeln!(writer, "BYE")?;
+        writer.flush()?;
+
+        fs::write(&state_path, serde_json::to_string(&state)?)
+            .with_context(|| format!("Failed to write {}", state_path.display()))?;
+        let mut log = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.afl_out.join(SYNC_LOG))?;
+        log_line(
+            &mut log,
+            &format!(
+                "{}: pushed {}, pulled {}",
+                self.server, round.pushed, round.pulled
+            ),
+        );
+        Ok(round)
+    }
+
+    /// Syncs every `interval` until the process is stopped, failed rounds are retried
+    pub fn run(&self, interval: Duration) {
+        println!(
+            "[*] Syncing {} with {} every {}s",
+            self.afl_out.display(),
+            self.server,
+            interval.as_secs()
+        );
+        loop {
+            match self.sync() {
+                Ok(round) => println!(
+                    "[+] Pushed {} and imported {} entries",
+                    round.pushed, round.pulled
+                ),
+                Err(e) => eprintln!("[-] Sync failed: {e:#}"),
+            }
+            thread::sleep(interval);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn campaign(dir: &Path, name: &str, entries: &[&str]) -> PathBuf {
+        let out = dir.join(name);
+        let queue = out.join("m_target/queue");
+        fs::create_dir_all(&queue).unwrap();
+        for (i, data) in entries.iter().enumerate() {
+            fs::write(queue.join(format!("id:{i:06},time:0")), data).unwrap();
+        }
+        fs::write(queue.join(".state"), "ignored").unwrap();
+        out
+    }
+
+    fn imported(out: &Path) -> Vec<String> {
+        let mut data: Vec<_> = fs::read_dir(out.join(SYNC_DIR))
+            .unwrap()
+            .map(|e| fs::read_to_string(e.unwrap().path()).unwrap())
+            .collect();
+        data.sort();
+        data
+    }
+
+    #[test]
+    fn test_corpus_sync() {
+        let dir = tempfile::tempdir().unwrap();
+        let server = SyncServer::bind("127.0.0.1:0", &dir.path().join("store")).unwrap();
+        let addr = server.local_addr().unwrap().to_string();
+        let server = Arc::new(server);
+        thread::spawn({
+            let server = Arc::clone(&server);
+            move || server.serve()
+        });
+
+        let first = SyncClient::new(&addr, &campaign(dir.path(), "a", &["shared", "a1"]));
+        let second = SyncClient::new(&addr, &campaign(dir.path(), "b", &["shared", "b1"]));
+
+        assert_eq!(
+            first.sync().unwrap(),
+            SyncRound {
+                pushed: 2,
+                pulled: 0
+            }
+        );
+        assert_eq!(
+            second.sync().unwrap(),
+            SyncRound {
+                pushed: 1,
+                pulled: 1
+            }
+        );
+        assert_eq!(
+            first.sync().unwrap(),
+            SyncRound {
+                pushed: 0,
+                pulled: 1
+            }
+        );
+        assert_eq!(second.sync().unwrap(), SyncRound::default());
+
+        assert_eq!(server.entries(), 3);
+        assert_eq!(imported(&dir.path().join("a")), vec!["b1"]);
+        assert_eq!(imported(&dir.path().join("b")), vec!["a1"]);
+        assert!(fs::read_to_string(dir.path().join("a").join(SYNC_LOG))
+            .unwrap()
+            .contains("pushed 2, pulled 0"));
+
+        // Entries survive a server restart
+        let restarted = SyncServer::bind("127.0.0.1:0", &dir.path().join("store")).unwrap();
+        assert_eq!(restarted.entries(), 3);
+    }
+
+    #[test]
+    fn test_protocol_limits() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::open(&dir.path().join("store")).unwrap();
+        let (mut client, server) = {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+            (client, listener.accept().unwrap().0)
+        };
+        writeln!(client, "QUERY {}", MAX_BATCH + 1).unwrap();
+        let err = SyncServer::handle(server, &store).unwrap_err();
+        assert!(err.to_string().contains("exceeds the maximum"));
+
+        let long = "A".repeat(MAX_LINE as usize * 4);
+        assert!(read_line(&mut long.as_bytes()).is_err());
+        assert_eq!(
+            read_line(&mut "GET 0\nBYE\n".as_bytes()).unwrap(),
+            Some("GET 0".to_string())
+        );
+    }
+}
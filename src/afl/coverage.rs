@@ -1,17 +1,38 @@
 use anyhow::{bail, Context, Result};
-use glob::glob;
 use rayon::prelude::*;
 use std::{
     ffi::OsString,
     fs,
     path::{Path, PathBuf},
-    process::{Command, Stdio},
-    time::Instant,
+    process::Command,
+    sync::Arc,
+    time::{Duration, Instant},
 };
 use uuid::Uuid;
 
+use crate::afl::coverage_backend::{self, BackendKind, CoverageBackend, GcovTool};
+use crate::afl::coverage_cache::{CacheState, CoverageCache};
+use crate::afl::coverage_contrib::{ContributionReport, InstanceConfig, InstanceCoverage};
+use crate::afl::coverage_diff::CoverageDiff;
+use crate::afl::coverage_export::{self, CoverageTotals, ReportFormat};
+use crate::afl::coverage_filter::SourceFilter;
+use crate::afl::coverage_observer::{ConsoleObserver, CoverageObserver};
+use crate::afl::coverage_replay::{ReplaySummary, DEFAULT_REPLAY_TIMEOUT_MS};
+use crate::afl::coverage_result::{Artifact, ArtifactKind, CollectionResult, ScopeResult};
+use crate::afl::coverage_timeline::{self, Timeline, TimelinePoint};
+use crate::afl::sync_groups::instance_dirs;
+use crate::afl::target_exec::TargetRunner;
+use crate::tui::Tui;
 use crate::utils::system::get_user_input;
 
+/// Cache scope of the unified report
+const UNIFIED_SCOPE: &str = "all";
+/// Cache scopes of the crash-only view
+const CRASH_SCOPE: &str = "crashes";
+const CRASH_BASELINE_SCOPE: &str = "crash_baseline";
+/// Output directory of the crash-only view
+const CRASH_VIEW_DIR: &str = "coverage_crash_only";
+
 #[derive(Debug)]
 struct QueueDirectory {
     path: PathBuf,
@@ -25,23 +46,56 @@ enum ReportType {
         instance: Option<usize>,
     },
     Text,
+    Export {
+        format: ReportFormat,
+        output: PathBuf,
+    },
+}
+
+/// What to do if the HTML report directory exists already
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverwritePolicy {
+    /// Replace the existing report
+    #[default]
+    Overwrite,
+    /// Fail without touching the existing report
+    Fail,
+    /// Ask on stdin, for interactive use only
+    Prompt,
 }
 
 #[derive(Clone, Debug)]
 pub struct CoverageCollector {
     target: PathBuf,
     afl_out: PathBuf,
+    kind: BackendKind,
+    backend: Arc<dyn CoverageBackend>,
+    observer: Arc<dyn CoverageObserver>,
     config: CollectorConfig,
     merged_profdata: Option<PathBuf>,
+    cache: Option<CoverageCache>,
+    result: CollectionResult,
 }
 
 #[derive(Clone, Debug)]
 struct CollectorConfig {
     target_args: Vec<String>,
     split_reporting: bool,
-    is_html: bool,
+    format: ReportFormat,
     show_args: Vec<String>,
     report_args: Vec<String>,
+    incremental: bool,
+    timeline: Option<Option<Duration>>,
+    sanitizer_bin: Option<PathBuf>,
+    cmpcov_bin: Option<PathBuf>,
+    exec_timeout: Duration,
+    mem_limit_mb: Option<u64>,
+    jobs: usize,
+    overwrite: OverwritePolicy,
+    filter: SourceFilter,
+    replay_crashes: bool,
+    replay_hangs: bool,
+    crash_view: bool,
 }
 
 impl Default for CollectorConfig {
@@ -49,9 +103,21 @@ impl Default for CollectorConfig {
         Self {
             target_args: Vec::new(),
             split_reporting: false,
-            is_html: true,
+            format: ReportFormat::Html,
             show_args: Vec::new(),
             report_args: Vec::new(),
+            incremental: true,
+            timeline: None,
+            sanitizer_bin: None,
+            cmpcov_bin: None,
+            exec_timeout: Duration::from_millis(DEFAULT_REPLAY_TIMEOUT_MS),
+            mem_limit_mb: None,
+            jobs: std::thread::available_parallelism().map_or(1, std::num::NonZero::get),
+            overwrite: OverwritePolicy::default(),
+            filter: SourceFilter::default(),
+            replay_crashes: false,
+            replay_hangs: false,
+            crash_view: false,
         }
     }
 }
@@ -69,42 +135,48 @@ impl CoverageCollector {
     /// # Errors
     /// Returns an error if:
     /// - Any of the system requirements are not met
-    /// - The target binary is not compiled with LLVM coverage instrumentation
+    /// - The target binary is not compiled with LLVM coverage instrumentation or GCC --coverage
     /// - The readelf command fails to execute
     pub fn new<P: AsRef<Path>>(target: P, afl_out: P) -> Result<Self> {
-        Self::is_target_cov_compiled(&target)?;
-        let progs = vec!["llvm-profdata", "llvm-cov", "genhtml", "lcov"];
-        Self::are_reqs_met(&progs)?;
+        let kind = BackendKind::detect(target.as_ref())?;
+        Self::new_with_backend(target, afl_out, kind, GcovTool::default())
+    }
+
+    /// Creates a new coverage collector that uses the given coverage backend
+    ///
+    /// # Arguments
+    /// * `target` - Path to the instrumented binary
+    /// * `afl_out` - Path to the AFL++ output directory containing queue folders
+    /// * `kind` - Toolchain the target was instrumented with
+    /// * `gcov_tool` - Report generator of the gcov backend
+    ///
+    /// # Errors
+    /// Returns an error if:
+    /// - Any of the tools of the backend are not installed
+    /// - The target binary is not instrumented for the backend
+    pub fn new_with_backend<P: AsRef<Path>>(
+        target: P,
+        afl_out: P,
+        kind: BackendKind,
+        gcov_tool: GcovTool,
+    ) -> Result<Self> {
+        kind.check_target(target.as_ref())?;
+        let backend = coverage_backend::create_backend(kind, gcov_tool);
+        Self::are_reqs_met(backend.required_tools())?;
 
         Ok(Self {
             target: target.as_ref().to_path_buf(),
             afl_out: afl_out.as_ref().to_path_buf(),
+            kind,
+            backend,
+            observer: Arc::new(ConsoleObserver),
             config: CollectorConfig::default(),
             merged_profdata: None,
+            cache: None,
+            result: CollectionResult::default(),
         })
     }
 
-    fn is_target_cov_compiled<P: AsRef<Path>>(path: P) -> Result<bool> {
-        let output = Command::new("readelf")
-            .arg("-s")
-            .arg(path.as_ref())
-            .output()?;
-
-        if !output.status.success() {
-            bail!("readelf command failed to execute",);
-        }
-
-        // Convert output bytes to string, ignoring invalid UTF-8 sequences
-        let output_str = String::from_utf8_lossy(&output.stdout);
-
-        // Check if any line contains "covrec"
-        if output_str.lines().any(|line| line.contains("covrec")) {
-            Ok(true)
-        } else {
-            bail!("Target binary is not compiled with LLVM coverage instrumentation");
-        }
-    }
-
     fn are_reqs_met(progs: &[&str]) -> Result<()> {
         for prog in progs {
             let output = Command::new(prog)
@@ -151,7 +223,20 @@ impl CoverageCollector {
     /// # Arguments
     /// * `enabled` - If true, generates HTML reports; if false, generates text reports
     pub fn with_html(&mut self, enabled: bool) -> &mut Self {
-        self.config.is_html = enabled;
+        self.config.format = if enabled {
+            ReportFormat::Html
+        } else {
+            ReportFormat::Text
+        };
+        self
+    }
+
+    /// Sets the output format of the coverage report
+    ///
+    /// # Arguments
+    /// * `format` - HTML/text report or an lcov, Cobertura or JSON export
+    pub fn with_report_format(&mut self, format: ReportFormat) -> &mut Self {
+        self.config.format = format;
         self
     }
 
@@ -173,107 +258,582 @@ impl CoverageCollector {
         self
     }
 
+    /// Sets whether only queue entries that were not replayed before are executed
+    ///
+    /// # Arguments
+    /// * `enabled` - If true, new coverage is merged into the profile of the previous run
+    pub fn with_incremental(&mut self, enabled: bool) -> &mut Self {
+        self.config.incremental = enabled;
+        self
+    }
+
+    /// Enables the coverage timeline, i.e. cumulative coverage by queue entry discovery time
+    ///
+    /// # Arguments
+    /// * `interval` - Distance between two points, derived from the campaign length if `None`
+    pub fn with_timeline(&mut self, interval: Option<Duration>) -> &mut Self {
+        self.config.timeline = Some(interval);
+        self
+    }
+
+    /// Sets the sanitizer and CMPCOV binaries used to label instances in the contribution report
+    ///
+    /// # Arguments
+    /// * `sanitizer_bin` - Binary instrumented with *SAN
+    /// * `cmpcov_bin` - Binary instrumented with CMPCOV/laf-intel
+    pub fn with_instance_binaries(
+        &mut self,
+        sanitizer_bin: Option<PathBuf>,
+        cmpcov_bin: Option<PathBuf>,
+    ) -> &mut Self {
+        self.config.sanitizer_bin = sanitizer_bin;
+        self.config.cmpcov_bin = cmpcov_bin;
+        self
+    }
+
+    /// Sets the timeout after which a single replay is killed
+    ///
+    /// # Arguments
+    /// * `timeout` - Maximum execution time per queue entry
+    pub fn with_exec_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.config.exec_timeout = timeout;
+        self
+    }
+
+    /// Limits the address space of every replay
+    ///
+    /// # Arguments
+    /// * `mb` - Memory limit in MB, `None` for no limit
+    pub fn with_mem_limit(&mut self, mb: Option<u64>) -> &mut Self {
+        self.config.mem_limit_mb = mb;
+        self
+    }
+
+    /// Sets the number of queue entries replayed in parallel
+    ///
+    /// # Arguments
+    /// * `jobs` - Number of parallel executions, at least 1
+    pub fn with_jobs(&mut self, jobs: usize) -> &mut Self {
+        self.config.jobs = jobs.max(1);
+        self
+    }
+
+    /// Sets what happens if an HTML report exists already
+    ///
+    /// # Arguments
+    /// * `policy` - Overwrite policy, `Overwrite` by default
+    pub fn with_overwrite_policy(&mut self, policy: OverwritePolicy) -> &mut Self {
+        self.config.overwrite = policy;
+        self
+    }
+
+    /// Restricts reports and exports to the source files matching `filter`
+    ///
+    /// # Arguments
+    /// * `filter` - Include/exclude path regexes
+    pub fn with_source_filter(&mut self, filter: SourceFilter) -> &mut Self {
+        self.config.filter = filter;
+        self
+    }
+
+    /// Also replays the inputs in the `crashes/` and `hangs/` directories of each instance
+    ///
+    /// # Arguments
+    /// * `crashes` - Whether to replay saved crashes
+    /// * `hangs` - Whether to replay saved hangs
+    pub fn with_crash_inputs(&mut self, crashes: bool, hangs: bool) -> &mut Self {
+        self.config.replay_crashes = crashes;
+        self.config.replay_hangs = hangs;
+        self
+    }
+
+    /// Enables the crash-only view of lines that only crashing inputs reach
+    ///
+    /// # Arguments
+    /// * `enabled` - Whether to write the crash-only view
+    pub fn with_crash_view(&mut self, enabled: bool) -> &mut Self {
+        self.config.crash_view = enabled;
+        self
+    }
+
+    /// Sets the observer that receives progress events
+    ///
+    /// # Arguments
+    /// * `observer` - Observer, `ConsoleObserver` by default
+    pub fn with_observer(&mut self, observer: Arc<dyn CoverageObserver>) -> &mut Self {
+        self.observer = observer;
+        self
+    }
+
     /// Collects coverage information for the target binary
     ///
     /// This function processes all queue files, generates raw coverage data,
     /// and creates either a unified report or separate reports for each fuzzer instance
-    /// based on the configuration.
+    /// based on the configuration. In incremental mode only queue entries that are not
+    /// in the coverage cache yet are executed.
+    ///
+    /// Returns the merged profiles, written reports and replay summary.
     ///
     /// # Errors
     /// * If the AFL++ output directory cannot be read
-    pub fn collect(&mut self) -> Result<()> {
+    /// * If the coverage cache cannot be read or written
+    /// * If an HTML report exists and the overwrite policy does not allow replacing it
+    pub fn collect(&mut self) -> Result<CollectionResult> {
         let queue_dirs = self.find_queue_directories()?;
+        let timeline_files: Vec<PathBuf> = if self.config.timeline.is_some() {
+            queue_dirs
+                .iter()
+                .flat_map(|dir| self.instance_inputs(dir))
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        self.load_cache()?;
 
+        self.observer.info(&format!(
+            "Replaying queue entries via {} with {} jobs and a {}ms timeout",
+            if self.is_file_based_harness() {
+                "@@"
+            } else {
+                "stdin"
+            },
+            self.config.jobs,
+            self.config.exec_timeout.as_millis()
+        ));
+        self.result = CollectionResult::default();
+        let crash_inputs = self
+            .config
+            .crash_view
+            .then(|| self.crash_view_inputs(&queue_dirs));
         if self.config.split_reporting {
-            self.process_split_reports(queue_dirs)
+            self.process_split_reports(queue_dirs)?;
         } else {
-            self.process_unified_report(queue_dirs)
+            self.process_unified_report(queue_dirs)?;
         }
+        if let Some((baseline, crashes)) = crash_inputs {
+            self.process_crash_view(&baseline, &crashes)
+                .context("Failed to compute crash-only view")?;
+        }
+        self.write_replay_summary()?;
+
+        if let Some(cache) = &self.cache {
+            cache.save()?;
+        }
+
+        if let Some(interval) = self.config.timeline {
+            self.process_timeline(&timeline_files, interval)
+                .context("Failed to compute coverage timeline")?;
+        }
+
+        let result = std::mem::take(&mut self.result);
+        self.observer.finished(&result);
+        Ok(result)
     }
 
-    fn process_split_reports(&mut self, queue_dirs: Vec<QueueDirectory>) -> Result<()> {
-        for (idx, dir) in queue_dirs.into_iter().enumerate() {
-            let tmp_dir = self.process_queue_directory(&dir)?;
-            let output_file = self.afl_out.join(format!("merged_{idx}.profdata"));
+    /// Replays all queue entries into the merged profile without generating a report
+    ///
+    /// Returns the path of the merged profile, which is reused as long as no new queue
+    /// entries were found.
+    ///
+    /// # Errors
+    /// * If the AFL++ output directory cannot be read
+    /// * If the queue entries cannot be replayed or merged
+    pub fn collect_profile(&mut self) -> Result<PathBuf> {
+        let queue_dirs = self.find_queue_directories()?;
+        self.load_cache()?;
+        self.result = CollectionResult::default();
+        let scope = self.merge_unified(queue_dirs)?;
+        self.finish_scope(scope);
+        if let Some(cache) = &self.cache {
+            cache.save()?;
+        }
+        if !self.result.replay.is_clean() {
+            self.observer.replay_summary(&self.result.replay);
+        }
+        self.get_merged_profdata().map(Path::to_path_buf)
+    }
 
-            Self::merge_raw_coverage(&tmp_dir, &output_file)?;
-            self.merged_profdata = Some(output_file);
+    /// Reports the backend and loads or removes the coverage cache
+    fn load_cache(&mut self) -> Result<()> {
+        if self.kind != BackendKind::Llvm {
+            self.observer
+                .info(&format!("Using {} coverage backend", self.kind));
+        }
+        self.cache = if self.config.incremental {
+            let (cache, state) =
+                CoverageCache::load(&self.afl_out, &self.target, &self.config.target_args)?;
+            match state {
+                CacheState::Empty => {}
+                CacheState::Reused(replayed) => self.observer.info(&format!(
+                    "Using coverage cache with {replayed} replayed entries"
+                )),
+                CacheState::Discarded => self
+                    .observer
+                    .warning("Target binary or arguments changed, discarding coverage cache"),
+            }
+            Some(cache)
+        } else {
+            CoverageCache::remove(&self.afl_out)?;
+            None
+        };
+        Ok(())
+    }
 
-            let report_type = if self.config.is_html {
-                ReportType::Html {
-                    base_dir: self.afl_out.join("coverage_html"),
-                    instance: Some(idx),
-                }
-            } else {
-                ReportType::Text
-            };
+    /// Reports the replay summary and writes it next to the report
+    fn write_replay_summary(&mut self) -> Result<()> {
+        if self.result.replay.executed == 0 {
+            return Ok(());
+        }
+        self.observer.replay_summary(&self.result.replay);
+        let dir = match self.report_type(None) {
+            ReportType::Html { base_dir, .. } => base_dir,
+            _ => self.afl_out.clone(),
+        };
+        fs::create_dir_all(&dir)?;
+        let path = self.result.replay.write(&dir)?;
+        self.record_artifact(ArtifactKind::ReplaySummary, path);
+        Ok(())
+    }
 
-            self.generate_report(report_type)?;
-            fs::remove_dir_all(&tmp_dir).with_context(|| {
-                format!(
-                    "Failed to remove temporary directory: {}",
-                    tmp_dir.display()
-                )
-            })?;
+    /// Adds a written file to the result and notifies the observer
+    fn record_artifact(&mut self, kind: ArtifactKind, path: PathBuf) {
+        let artifact = Artifact { kind, path };
+        self.observer.artifact_written(&artifact);
+        self.result.artifacts.push(artifact);
+    }
+
+    /// Adds a replayed scope to the result and notifies the observer
+    fn finish_scope(&mut self, scope: ScopeResult) {
+        self.observer.replay_finished(&scope);
+        self.result.scopes.push(scope);
+    }
+
+    /// Replays the queue entries in order of discovery and records cumulative coverage
+    fn process_timeline(
+        &mut self,
+        queue_files: &[PathBuf],
+        interval: Option<Duration>,
+    ) -> Result<()> {
+        let buckets = coverage_timeline::bucketize(queue_files, interval);
+        self.observer.info(&format!(
+            "Computing coverage timeline over {} intervals",
+            buckets.len()
+        ));
+
+        let work_dir = Self::create_persistent_tmpdir()?;
+        let profdata = work_dir.join(self.backend.profile_name("timeline"));
+        let mut points = Vec::with_capacity(buckets.len());
+        let mut replayed = 0;
+        let mut totals = CoverageTotals::default();
+        for bucket in &buckets {
+            if !bucket.entries.is_empty() {
+                let raw_dir = work_dir.join(format!("step_{}", bucket.end_ms));
+                fs::create_dir(&raw_dir)?;
+                self.observer
+                    .replay_started("timeline", bucket.entries.len(), queue_files.len());
+                self.process_queue_files(&bucket.entries, &raw_dir)?;
+                let base = profdata.exists().then_some(profdata.as_path());
+                self.merge_raw_coverage(&raw_dir, base, &profdata)?;
+                fs::remove_dir_all(&raw_dir)?;
+
+                replayed += bucket.entries.len();
+                let tracefile = self.export_lcov(&profdata)?;
+                totals = CoverageTotals::from_files(&coverage_export::parse_lcov(&tracefile)?);
+            }
+            points.push(TimelinePoint::new(bucket.end_ms / 1000, replayed, totals));
+        }
+        fs::remove_dir_all(&work_dir).with_context(|| {
+            format!(
+                "Failed to remove temporary directory: {}",
+                work_dir.display()
+            )
+        })?;
+
+        let timeline = Timeline::new(points);
+        for path in timeline.write(&self.afl_out)? {
+            self.record_artifact(ArtifactKind::Timeline, path);
+        }
+        if let Some(plateau) = timeline.plateau_secs {
+            self.observer.info(&format!(
+                "Coverage last increased after {}",
+                Tui::format_duration(&Duration::from_secs(plateau))
+            ));
         }
+
+        if self.config.format == ReportFormat::Html {
+            let html_dir = self.afl_out.join("coverage_html");
+            fs::create_dir_all(&html_dir)?;
+            let chart = html_dir.join("timeline.html");
+            fs::write(&chart, timeline.to_html())?;
+            Self::link_timeline(&html_dir.join("index.html"))?;
+            self.record_artifact(ArtifactKind::TimelineChart, chart);
+        }
+        self.result.timeline = Some(timeline);
         Ok(())
     }
 
-    fn is_base_dir_remove(bdir: &Path) -> Result<()> {
-        if bdir.exists() {
-            println!(
-                "[!] Existing HTML reports found in {}. Overwrite? [Y/n]",
-                bdir.display()
-            );
+    /// Inputs without crashes and the crashing inputs of all instances
+    fn crash_view_inputs(&self, queue_dirs: &[QueueDirectory]) -> (Vec<PathBuf>, Vec<PathBuf>) {
+        let mut baseline = Vec::new();
+        let mut crashes = Vec::new();
+        for dir in queue_dirs {
+            baseline.extend(Self::collect_queue_files(&dir.path));
+            if let Some(instance_dir) = dir.path.parent() {
+                if self.config.replay_hangs {
+                    baseline.extend(Self::collect_saved_inputs(&instance_dir.join("hangs")));
+                }
+                crashes.extend(Self::collect_saved_inputs(&instance_dir.join("crashes")));
+            }
+        }
+        (baseline, crashes)
+    }
 
-            if get_user_input() != 'y' {
-                anyhow::bail!("Aborting");
+    /// Writes an annotated view of the lines and functions only crashing inputs reach
+    fn process_crash_view(&mut self, baseline: &[PathBuf], crashes: &[PathBuf]) -> Result<()> {
+        if crashes.is_empty() {
+            self.observer
+                .warning("No crashing inputs found, skipping crash-only view");
+            return Ok(());
+        }
+
+        // The unified profile is the baseline unless crashes were merged into it
+        let baseline_profile = match &self.merged_profdata {
+            Some(merged) if !self.config.split_reporting && !self.config.replay_crashes => {
+                merged.clone()
+            }
+            _ => {
+                let profile = self
+                    .afl_out
+                    .join(self.backend.profile_name(CRASH_BASELINE_SCOPE));
+                let scope = self.replay(CRASH_BASELINE_SCOPE, baseline, &profile, None)?;
+                self.observer.replay_finished(&scope);
+                profile
+            }
+        };
+        let crash_profile = self.afl_out.join(self.backend.profile_name(CRASH_SCOPE));
+        match self.replay(CRASH_SCOPE, crashes, &crash_profile, None) {
+            Ok(scope) => self.observer.replay_finished(&scope),
+            Err(e) => {
+                self.observer
+                    .warning(&format!("Skipping crash-only view: {e:#}"));
+                return Ok(());
             }
-            fs::remove_dir_all(bdir).with_context(|| {
-                format!("Failed to remove existing directory: {}", bdir.display())
-            })?;
         }
+
+        let baseline_files = coverage_export::parse_lcov(&self.export_lcov(&baseline_profile)?)?;
+        let crash_files = coverage_export::parse_lcov(&self.export_lcov(&crash_profile)?)?;
+        let diff = CoverageDiff::new(
+            &baseline_files,
+            &coverage_export::merge_files(&baseline_files, &crash_files),
+        );
+        self.observer.info(&format!(
+            "{} lines in {} files are only reached by crashing inputs",
+            diff.files.iter().map(|f| f.new_lines.len()).sum::<usize>(),
+            diff.files.len()
+        ));
+        let page = diff.write_html(&self.afl_out.join(CRASH_VIEW_DIR))?;
+        self.record_artifact(ArtifactKind::CrashView, page);
         Ok(())
     }
 
-    fn process_unified_report(&mut self, queue_dirs: Vec<QueueDirectory>) -> Result<()> {
-        let tmp_dir = Self::create_persistent_tmpdir()?;
+    /// Adds a link to the timeline chart to the `llvm-cov show` index page
+    fn link_timeline(index: &Path) -> Result<()> {
+        let Ok(content) = fs::read_to_string(index) else {
+            return Ok(());
+        };
+        if content.contains("timeline.html") {
+            return Ok(());
+        }
+        let link = "<p><a href='timeline.html'>Coverage timeline</a></p>";
+        let content = match content.rfind("</body>") {
+            Some(pos) => format!("{}{link}{}", &content[..pos], &content[pos..]),
+            None => format!("{content}{link}"),
+        };
+        fs::write(index, content).with_context(|| format!("Failed to update {}", index.display()))
+    }
+
+    fn process_split_reports(&mut self, queue_dirs: Vec<QueueDirectory>) -> Result<()> {
+        let mut instances = Vec::with_capacity(queue_dirs.len());
+        for (idx, dir) in queue_dirs.into_iter().enumerate() {
+            let output_file = self
+                .afl_out
+                .join(self.backend.profile_name(&format!("merged_{idx}")));
+            let queue_files = self.instance_inputs(&dir);
+            let mut scope = self.replay(
+                &dir.instance_name.to_string_lossy(),
+                &queue_files,
+                &output_file,
+                Some(&dir),
+            )?;
+            self.merged_profdata = Some(output_file.clone());
+
+            self.generate_report(self.report_type(Some(idx)))?;
+
+            let tracefile = self.export_lcov(&output_file)?;
+            let files = coverage_export::parse_lcov(&tracefile)?;
+            scope.totals = Some(CoverageTotals::from_files(&files));
+            self.finish_scope(scope);
+            instances.push(InstanceCoverage {
+                name: dir.instance_name.to_string_lossy().into_owned(),
+                config: dir.path.parent().and_then(|instance_dir| {
+                    InstanceConfig::load(
+                        instance_dir,
+                        self.config.sanitizer_bin.as_deref(),
+                        self.config.cmpcov_bin.as_deref(),
+                    )
+                }),
+                files,
+            });
+        }
+
+        if instances.len() > 1 {
+            let report = ContributionReport::new(instances);
+            let path = report.write(&self.afl_out)?;
+            self.record_artifact(ArtifactKind::Contribution, path);
+            self.result.contribution = Some(report);
+        }
+        Ok(())
+    }
+
+    /// Removes an existing HTML report directory according to the overwrite policy
+    fn is_base_dir_remove(&self, bdir: &Path) -> Result<()> {
+        if !bdir.exists() {
+            return Ok(());
+        }
+        match self.config.overwrite {
+            OverwritePolicy::Overwrite => {}
+            OverwritePolicy::Fail => {
+                bail!("Existing HTML reports found in {}", bdir.display());
+            }
+            OverwritePolicy::Prompt => {
+                println!(
+                    "[!] Existing HTML reports found in {}. Overwrite? [Y/n]",
+                    bdir.display()
+                );
+                if get_user_input() != 'y' {
+                    bail!("Aborting");
+                }
+            }
+        }
+        fs::remove_dir_all(bdir)
+            .with_context(|| format!("Failed to remove existing directory: {}", bdir.display()))
+    }
 
+    fn merge_unified(&mut self, queue_dirs: Vec<QueueDirectory>) -> Result<ScopeResult> {
         let queue_files: Vec<_> = queue_dirs
-            .into_iter()
-            .flat_map(|dir| Self::collect_queue_files(&dir.path))
+            .iter()
+            .flat_map(|dir| self.instance_inputs(dir))
             .collect();
 
-        println!("[*] Processing {} queue files", queue_files.len());
-        self.process_queue_files(&queue_files, &tmp_dir);
-
-        let output_file = self.afl_out.join("merged.profdata");
-        Self::merge_raw_coverage(&tmp_dir, &output_file)?;
+        let output_file = self.afl_out.join(self.backend.profile_name("merged"));
+        let scope = self.replay(UNIFIED_SCOPE, &queue_files, &output_file, None)?;
         self.merged_profdata = Some(output_file);
+        Ok(scope)
+    }
+
+    fn process_unified_report(&mut self, queue_dirs: Vec<QueueDirectory>) -> Result<()> {
+        let mut scope = self.merge_unified(queue_dirs)?;
+        let tracefile = self.export_lcov(&scope.profile)?;
+        scope.totals = Some(CoverageTotals::from_files(&coverage_export::parse_lcov(
+            &tracefile,
+        )?));
+        self.finish_scope(scope);
+
+        let report_type = self.report_type(None);
+        if let ReportType::Html { base_dir, .. } = &report_type {
+            self.is_base_dir_remove(base_dir)?;
+        }
+
+        self.generate_report(report_type)
+    }
 
-        let report_type = if self.config.is_html {
-            let base_dir = self.afl_out.join("coverage_html");
-            Self::is_base_dir_remove(&base_dir)?;
-            ReportType::Html {
+    fn report_type(&self, instance: Option<usize>) -> ReportType {
+        match self.config.format {
+            ReportFormat::Html => ReportType::Html {
                 base_dir: self.afl_out.join("coverage_html"),
-                instance: None,
+                instance,
+            },
+            ReportFormat::Text => ReportType::Text,
+            format => ReportType::Export {
+                format,
+                output: self
+                    .afl_out
+                    .join(format.file_name(instance).unwrap_or_default()),
+            },
+        }
+    }
+
+    /// Replays the queue files of a report scope and merges their coverage into `output_file`
+    ///
+    /// With the coverage cache enabled, only entries that are new to the scope are executed
+    /// and merged into the existing profile.
+    fn replay(
+        &mut self,
+        scope: &str,
+        queue_files: &[PathBuf],
+        output_file: &Path,
+        dir: Option<&QueueDirectory>,
+    ) -> Result<ScopeResult> {
+        let start_time = Instant::now();
+        let mut result = ScopeResult {
+            scope: scope.to_string(),
+            queue_files: queue_files.len(),
+            replayed: 0,
+            elapsed: Duration::ZERO,
+            profile: output_file.to_path_buf(),
+            totals: None,
+        };
+        let (pending, base) = match self.cache.as_mut() {
+            Some(cache) => {
+                let pending = cache.pending(scope, output_file, queue_files)?;
+                let base = cache.has_profdata(scope).then_some(output_file);
+                (pending, base)
             }
-        } else {
-            ReportType::Text
+            None => (
+                queue_files
+                    .iter()
+                    .map(|f| (f.clone(), String::new()))
+                    .collect(),
+                None,
+            ),
         };
 
-        self.generate_report(report_type)?;
+        if pending.is_empty() && base.is_some() {
+            return Ok(result);
+        }
+
+        let files: Vec<PathBuf> = pending.iter().map(|(f, _)| f.clone()).collect();
+        self.observer
+            .replay_started(scope, files.len(), queue_files.len());
+        let (tmp_dir, summary) = match dir {
+            Some(dir) => self.process_queue_directory(dir, &files)?,
+            None => {
+                let tmp_dir = Self::create_persistent_tmpdir()?;
+                let summary = self.process_queue_files(&files, &tmp_dir)?;
+                (tmp_dir, summary)
+            }
+        };
+        self.result.replay.extend(summary);
+
+        self.merge_raw_coverage(&tmp_dir, base, output_file)?;
+        if let Some(cache) = self.cache.as_mut() {
+            cache.record(scope, output_file, pending.into_iter().map(|(_, h)| h));
+        }
+
         fs::remove_dir_all(&tmp_dir).with_context(|| {
             format!(
                 "Failed to remove temporary directory: {}",
                 tmp_dir.display()
             )
         })?;
-        Ok(())
+        result.replayed = files.len();
+        result.elapsed = start_time.elapsed();
+        Ok(result)
     }
 
-    fn generate_report(&self, report_type: ReportType) -> Result<()> {
-        let merged_profdata = self.get_merged_profdata()?;
+    fn generate_report(&mut self, report_type: ReportType) -> Result<()> {
+        let merged_profdata = self.get_merged_profdata()?.to_path_buf();
 
         match report_type {
             ReportType::Html { base_dir, instance } => {
@@ -283,87 +843,71 @@ impl CoverageCollector {
                     base_dir
                 };
                 fs::create_dir_all(&output_dir)?;
-                self.run_llvm_cov_show(merged_profdata, &output_dir)
+                self.backend.html_report(
+                    &self.target,
+                    &merged_profdata,
+                    &output_dir,
+                    &self.config.show_args,
+                    &self.config.filter,
+                )?;
+                self.record_artifact(ArtifactKind::HtmlReport, output_dir);
+                Ok(())
+            }
+            ReportType::Text => self.backend.text_report(
+                &self.target,
+                &merged_profdata,
+                &self.config.report_args,
+                &self.config.filter,
+            ),
+            ReportType::Export { format, output } => {
+                self.export_report(&merged_profdata, format, &output)?;
+                self.record_artifact(ArtifactKind::Export(format), output);
+                Ok(())
             }
-            ReportType::Text => self.run_llvm_cov_report(merged_profdata),
-        }
-    }
-
-    fn run_llvm_cov_show(&self, profdata: &Path, output_dir: &Path) -> Result<()> {
-        self.run_llvm_command(
-            "show",
-            profdata,
-            &[
-                "-format=html",
-                "-o",
-                &output_dir.to_string_lossy(),
-                "-show-line-counts-or-regions",
-                "-show-expansions",
-            ],
-            &self.config.show_args,
-        )?;
-
-        println!(
-            "[*] Generated HTML coverage report in: {}",
-            output_dir.display()
-        );
-        Ok(())
-    }
-
-    fn run_llvm_cov_report(&self, profdata: &Path) -> Result<()> {
-        let status = Command::new("llvm-cov")
-            .arg("report")
-            .arg(&self.target)
-            .arg("-instr-profile")
-            .arg(profdata)
-            .args(&self.config.report_args)
-            .status()
-            .with_context(|| "Failed to run llvm-cov report")?;
-
-        if !status.success() {
-            anyhow::bail!("llvm-cov report failed");
         }
-        Ok(())
     }
 
-    fn run_llvm_command(
-        &self,
-        subcommand: &str,
-        profdata: &Path,
-        additional_args: &[&str],
-        config_args: &[String],
-    ) -> Result<()> {
-        let status = Command::new("llvm-cov")
-            .arg(subcommand)
-            .arg(&self.target)
-            .arg("-instr-profile")
-            .arg(profdata)
-            .args(additional_args)
-            .args(config_args)
-            .status()
-            .with_context(|| format!("Failed to run llvm-cov {subcommand}"))?;
+    /// Exports the coverage as lcov tracefile and converts it into the requested format
+    fn export_report(&self, profdata: &Path, format: ReportFormat, output: &Path) -> Result<()> {
+        let tracefile = self.export_lcov(profdata)?;
+
+        let content = match format {
+            ReportFormat::Lcov => tracefile,
+            ReportFormat::Cobertura => coverage_export::to_cobertura(
+                &coverage_export::parse_lcov(&tracefile)?,
+                chrono::Utc::now().timestamp(),
+            ),
+            ReportFormat::Json => {
+                coverage_export::to_json_summary(&coverage_export::parse_lcov(&tracefile)?)?
+            }
+            ReportFormat::Html | ReportFormat::Text => {
+                anyhow::bail!("{format} is not an export format")
+            }
+        };
+        fs::write(output, content).with_context(|| format!("Failed to write {}", output.display()))
+    }
 
-        if !status.success() {
-            anyhow::bail!("llvm-cov {} failed", subcommand);
-        }
-        Ok(())
+    /// Exports the coverage of `profdata` as lcov tracefile
+    fn export_lcov(&self, profdata: &Path) -> Result<String> {
+        let tracefile = self.backend.export_lcov(&self.target, profdata)?;
+        Ok(self.config.filter.filter_tracefile(&tracefile))
     }
 
     fn find_queue_directories(&self) -> Result<Vec<QueueDirectory>> {
-        let dirs: Vec<_> = fs::read_dir(&self.afl_out)
-            .with_context(|| {
-                format!(
-                    "Failed to read AFL++ output directory: {}",
-                    self.afl_out.display()
-                )
-            })?
-            .filter_map(std::result::Result::ok)
-            .filter_map(|entry| {
-                let queue_path = entry.path().join("queue");
+        if !self.afl_out.is_dir() {
+            bail!(
+                "Failed to read AFL++ output directory: {}",
+                self.afl_out.display()
+            );
+        }
+        let mut dirs: Vec<_> = instance_dirs(&self.afl_out)
+            .into_iter()
+            .filter_map(|dir| {
+                let queue_path = dir.join("queue");
                 if queue_path.is_dir() {
                     Some(QueueDirectory {
                         path: queue_path,
-                        instance_name: entry.file_name(),
+                        instance_name: dir.file_name()?.to_os_string(),
                     })
                 } else {
                     None
@@ -374,9 +918,38 @@ impl CoverageCollector {
         if dirs.is_empty() {
             anyhow::bail!("No queue directories found in {}", self.afl_out.display());
         }
+        // Keep the instance order stable so split reports map to the same profiles
+        dirs.sort_by(|a, b| a.instance_name.cmp(&b.instance_name));
         Ok(dirs)
     }
 
+    /// Inputs of an instance that are replayed, the queue plus crashes and hangs if enabled
+    fn instance_inputs(&self, dir: &QueueDirectory) -> Vec<PathBuf> {
+        let mut inputs = Self::collect_queue_files(&dir.path);
+        if let Some(instance_dir) = dir.path.parent() {
+            for (enabled, name) in [
+                (self.config.replay_crashes, "crashes"),
+                (self.config.replay_hangs, "hangs"),
+            ] {
+                if enabled {
+                    inputs.extend(Self::collect_saved_inputs(&instance_dir.join(name)));
+                }
+            }
+        }
+        inputs
+    }
+
+    /// Inputs in a `crashes/` or `hangs/` directory, skipping the README AFL++ puts there
+    fn collect_saved_inputs(dir: &Path) -> Vec<PathBuf> {
+        Self::collect_queue_files(dir)
+            .into_iter()
+            .filter(|f| {
+                f.file_name()
+                    .is_some_and(|n| n.to_string_lossy().starts_with("id:"))
+            })
+            .collect()
+    }
+
     fn collect_queue_files(queue_path: &Path) -> Vec<PathBuf> {
         fs::read_dir(queue_path)
             .into_iter()
@@ -392,138 +965,94 @@ impl CoverageCollector {
             .collect()
     }
 
-    fn process_queue_directory(&self, dir: &QueueDirectory) -> Result<PathBuf> {
+    /// Replays the queue files of an instance into a new temporary directory
+    fn process_queue_directory(
+        &self,
+        dir: &QueueDirectory,
+        queue_files: &[PathBuf],
+    ) -> Result<(PathBuf, ReplaySummary)> {
         let tmp_dir = Self::create_persistent_tmpdir()?;
-        let queue_files = Self::collect_queue_files(&dir.path);
-
-        println!(
-            "[+] Processing queue directory for instance: {} with {} entries",
-            dir.instance_name.to_string_lossy(),
-            queue_files.len()
-        );
-
-        self.process_queue_files(&queue_files, &tmp_dir);
-
-        Ok(tmp_dir)
+        let summary = self
+            .process_queue_files(queue_files, &tmp_dir)
+            .with_context(|| {
+                format!(
+                    "Failed to replay instance {}",
+                    dir.instance_name.to_string_lossy()
+                )
+            })?;
+        Ok((tmp_dir, summary))
     }
 
-    #[allow(clippy::cast_precision_loss)]
-    fn process_queue_files(&self, queue_files: &[PathBuf], tmp_dir: &Path) {
-        let start_time = Instant::now();
-        let total_files = queue_files.len();
-
-        queue_files.par_iter().for_each(|file_path| {
-            let file_name = file_path.file_name().unwrap().to_str().unwrap();
-            let dst_path = tmp_dir.join(format!("cov_{file_name}_.profraw"));
-
-            if let Err(e) = self.run_target_with_input(file_path, &dst_path) {
-                eprintln!("[-] Failed to process {file_name}: {e}");
-            }
-        });
-
-        let total_time = start_time.elapsed();
-        println!(
-            "  [+] Finished in {:.1}s ({:.1} files/sec)",
-            total_time.as_secs_f64(),
-            total_files as f64 / total_time.as_secs_f64()
+    /// Replays `queue_files` in parallel, writing raw coverage into `tmp_dir`
+    fn process_queue_files(
+        &self,
+        queue_files: &[PathBuf],
+        tmp_dir: &Path,
+    ) -> Result<ReplaySummary> {
+        let mut runner = TargetRunner::new(
+            &self.target,
+            self.config.target_args.clone(),
+            self.config.exec_timeout,
         );
-    }
+        if let Some(mb) = self.config.mem_limit_mb {
+            runner = runner.with_mem_limit(mb);
+        }
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(self.config.jobs)
+            .build()
+            .context("Failed to create replay thread pool")?;
+
+        let results: Vec<_> = pool.install(|| {
+            queue_files
+                .par_iter()
+                .map(|file_path| {
+                    let file_name = file_path.file_name().unwrap().to_str().unwrap();
+                    let env = self.backend.replay_env(tmp_dir, file_name);
+                    let result = runner.run_with_envs(file_path, &env);
+                    let has_coverage = self.backend.raw_path(tmp_dir, file_name).exists();
+                    match &result {
+                        Ok(result) => self.observer.file_processed(result, has_coverage),
+                        Err(e) => self.observer.file_failed(file_path, e),
+                    }
+                    (file_path, result, has_coverage)
+                })
+                .collect()
+        });
 
-    fn run_target_with_input(&self, input_path: &Path, output_path: &Path) -> Result<()> {
-        if self.is_file_based_harness() {
-            self.run_file_based_target(input_path, output_path)
-        } else {
-            self.run_stdin_based_target(input_path, output_path)
+        let mut summary = ReplaySummary::default();
+        for (file_path, result, has_coverage) in results {
+            match result {
+                Ok(result) => summary.record(&result, has_coverage),
+                Err(_) => summary.record_failure(file_path),
+            }
         }
+        Ok(summary)
     }
 
     fn is_file_based_harness(&self) -> bool {
         self.config.target_args.iter().any(|arg| arg == "@@")
     }
 
-    fn run_file_based_target(&self, input_path: &Path, output_path: &Path) -> Result<()> {
-        // Filter out @@ from arguments and replace with actual input file
-        let args: Vec<_> = self
-            .config
-            .target_args
-            .iter()
-            .filter(|&arg| arg != "@@")
-            .collect();
-
-        Command::new(&self.target)
-            .args(args)
-            .arg(input_path)
-            .env("LLVM_PROFILE_FILE", output_path)
-            .stderr(Stdio::null())
-            .stdout(Stdio::null())
-            .status()
-            .with_context(|| {
-                format!(
-                    "Failed to execute file-based target with input: {}",
-                    input_path.display()
-                )
-            })?;
-        Ok(())
-    }
-
-    fn run_stdin_based_target(&self, input_path: &Path, output_path: &Path) -> Result<()> {
-        let input_content = fs::read(input_path)
-            .with_context(|| format!("Failed to read input file: {}", input_path.display()))?;
-
-        let mut child = Command::new(&self.target)
-            .args(&self.config.target_args)
-            .env("LLVM_PROFILE_FILE", output_path)
-            .stdin(Stdio::piped())
-            .stderr(Stdio::null())
-            .stdout(Stdio::null())
-            .spawn()
-            .with_context(|| "Failed to spawn stdin-based target")?;
-
-        if let Some(mut stdin) = child.stdin.take() {
-            use std::io::Write;
-            stdin
-                .write_all(&input_content)
-                .with_context(|| "Failed to write to target's stdin")?;
-            // Stdin will be closed when dropped
-        }
-
-        let status = child
-            .wait()
-            .with_context(|| "Failed to wait for target completion")?;
-
-        if !status.success() {
-            // This is expected for some inputs during fuzzing, so we just return Ok
-            println!(
-                "Note: Target exited with non-zero status for input: {}",
-                input_path.display()
-            );
-        }
-        Ok(())
-    }
-
-    fn merge_raw_coverage(raw_cov_dir: &Path, output_file: &Path) -> Result<()> {
-        let pattern = raw_cov_dir.join("cov_*.profraw");
-        let profraw_files: Vec<_> = glob(pattern.to_str().unwrap())?
-            .filter_map(Result::ok)
-            .collect();
-
-        if profraw_files.is_empty() {
-            anyhow::bail!("No .profraw files found in {}", raw_cov_dir.display());
+    /// Merges the raw coverage of `raw_cov_dir`, and optionally an existing profile, into
+    /// `output_file`
+    fn merge_raw_coverage(
+        &self,
+        raw_cov_dir: &Path,
+        base_profdata: Option<&Path>,
+        output_file: &Path,
+    ) -> Result<()> {
+        if self
+            .backend
+            .merge(raw_cov_dir, base_profdata, output_file)?
+        {
+            return Ok(());
         }
-
-        let status = Command::new("llvm-profdata")
-            .arg("merge")
-            .arg("-sparse")
-            .args(&profraw_files)
-            .arg("-o")
-            .arg(output_file)
-            .status()
-            .with_context(|| "Failed to execute llvm-profdata merge")?;
-
-        if !status.success() {
-            anyhow::bail!("Failed to merge coverage files");
+        if base_profdata.is_some() {
+            self.observer
+                .warning("New queue entries produced no coverage data");
+            return Ok(());
         }
-        Ok(())
+        anyhow::bail!("No raw coverage files found in {}", raw_cov_dir.display());
     }
 
     fn create_persistent_tmpdir() -> Result<PathBuf> {
@@ -542,9 +1071,39 @@ impl CoverageCollector {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::afl::coverage_observer::SilentObserver;
+    use crate::afl::target_exec::ExecResult;
+    use glob::glob;
     use std::fs::File;
     use std::io::Write;
     use std::process::Command;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Debug, Default)]
+    struct CountingObserver {
+        processed: AtomicUsize,
+    }
+
+    impl CoverageObserver for CountingObserver {
+        fn file_processed(&self, _result: &ExecResult, _has_coverage: bool) {
+            self.processed.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Collector with the gcov backend, bypassing the tool checks of the constructor
+    fn gcov_collector(target: PathBuf, afl_out: PathBuf) -> CoverageCollector {
+        CoverageCollector {
+            target,
+            afl_out,
+            kind: BackendKind::Gcov,
+            backend: coverage_backend::create_backend(BackendKind::Gcov, GcovTool::Lcov),
+            observer: Arc::new(SilentObserver),
+            config: CollectorConfig::default(),
+            merged_profdata: None,
+            cache: None,
+            result: CollectionResult::default(),
+        }
+    }
 
     fn setup_test_dir() -> Result<(PathBuf, PathBuf)> {
         let test_dir = PathBuf::from("/tmp").join(format!("test_coverage_{}", Uuid::new_v4()));
@@ -592,7 +1151,7 @@ mod tests {
 
         let binary_path = test_dir.join("test_binary");
         Command::new("clang")
-            .args(&["-fprofile-instr-generate", "-fcoverage-mapping"])
+            .args(["-fprofile-instr-generate", "-fcoverage-mapping"])
             .arg("-o")
             .arg(&binary_path)
             .arg(&source_path)
@@ -638,7 +1197,7 @@ mod tests {
             .with_misc_show_args(vec!["--show-branches".to_string()])
             .with_misc_report_args(vec!["--show-functions".to_string()]);
 
-        assert!(!collector.config.is_html);
+        assert_eq!(collector.config.format, ReportFormat::Text);
         assert!(collector.config.split_reporting);
         assert_eq!(collector.config.target_args, vec!["arg1"]);
         assert_eq!(collector.config.show_args, vec!["--show-branches"]);
@@ -672,7 +1231,8 @@ mod tests {
         collector.with_target_args(vec!["@@".to_string()]);
 
         let queue_dirs = collector.find_queue_directories()?;
-        let tmp_dir = collector.process_queue_directory(&queue_dirs[0])?;
+        let queue_files = CoverageCollector::collect_queue_files(&queue_dirs[0].path);
+        let (tmp_dir, _) = collector.process_queue_directory(&queue_dirs[0], &queue_files)?;
 
         // Check that profraw files were created
         let profraw_count = glob(tmp_dir.join("cov_*.profraw").to_str().unwrap())?.count();
@@ -704,4 +1264,91 @@ mod tests {
         fs::remove_dir_all(test_dir)?;
         Ok(())
     }
+
+    #[test]
+    fn test_replay_summary_with_gcov() -> Result<()> {
+        let test_dir = tempfile::tempdir()?;
+        let source = test_dir.path().join("target.c");
+        fs::write(
+            &source,
+            r#"
+            #include <stdio.h>
+            #include <signal.h>
+            #include <string.h>
+            #include <unistd.h>
+            int main(void) {
+                char buf[16] = {0};
+                fread(buf, 1, sizeof(buf) - 1, stdin);
+                if (!strcmp(buf, "hang")) sleep(10);
+                if (!strcmp(buf, "crash")) raise(SIGSEGV);
+                if (!strcmp(buf, "quit")) _exit(0);
+                return 0;
+            }
+        "#,
+        )?;
+        let target = test_dir.path().join("target");
+        let compiled = Command::new("gcc")
+            .arg("--coverage")
+            .arg("-o")
+            .arg(&target)
+            .arg(&source)
+            .current_dir(test_dir.path())
+            .status();
+        if !compiled.is_ok_and(|s| s.success()) {
+            eprintln!("gcc not available, skipping");
+            return Ok(());
+        }
+
+        let observer = Arc::new(CountingObserver::default());
+        let mut collector = gcov_collector(target, test_dir.path().to_path_buf());
+        collector
+            .with_exec_timeout(Duration::from_millis(300))
+            .with_mem_limit(Some(512))
+            .with_jobs(2)
+            .with_observer(observer.clone());
+
+        let queue = test_dir.path().join("queue");
+        fs::create_dir(&queue)?;
+        let inputs: Vec<PathBuf> = ["ok", "hang", "crash", "quit"]
+            .iter()
+            .map(|name| {
+                let path = queue.join(format!("id:{name}"));
+                fs::write(&path, name).map(|()| path)
+            })
+            .collect::<std::io::Result<_>>()?;
+        let raw_dir = test_dir.path().join("raw");
+        fs::create_dir(&raw_dir)?;
+
+        let start = Instant::now();
+        let summary = collector.process_queue_files(&inputs, &raw_dir)?;
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(summary.executed, 4);
+        assert_eq!(summary.timed_out, vec![inputs[1].clone()]);
+        assert_eq!(summary.crashed.len(), 1);
+        assert_eq!(summary.crashed[0].input, inputs[2]);
+        assert_eq!(summary.no_coverage, vec![inputs[3].clone()]);
+        assert!(raw_dir.join("cov_id:ok").is_dir());
+        assert_eq!(observer.processed.load(Ordering::Relaxed), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn test_overwrite_policy() -> Result<()> {
+        let test_dir = tempfile::tempdir()?;
+        let report_dir = test_dir.path().join("coverage_html");
+        fs::create_dir(&report_dir)?;
+        let mut collector = gcov_collector(
+            test_dir.path().join("target"),
+            test_dir.path().to_path_buf(),
+        );
+
+        collector.with_overwrite_policy(OverwritePolicy::Fail);
+        assert!(collector.is_base_dir_remove(&report_dir).is_err());
+        assert!(report_dir.exists());
+
+        collector.with_overwrite_policy(OverwritePolicy::Overwrite);
+        collector.is_base_dir_remove(&report_dir)?;
+        assert!(!report_dir.exists());
+        Ok(())
+    }
 }
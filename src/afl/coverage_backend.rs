@@ -0,0 +1,608 @@
+use std::{
+    ffi::OsString,
+    fmt, fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    str::FromStr,
+    sync::Arc,
+};
+
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use glob::glob;
+
+use crate::afl::coverage_filter::SourceFilter;
+
+/// Toolchain a coverage binary was instrumented with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum BackendKind {
+    /// LLVM source-based coverage (`-fprofile-instr-generate -fcoverage-mapping`)
+    #[default]
+    Llvm,
+    /// GCC gcov coverage (`--coverage`)
+    Gcov,
+}
+
+impl BackendKind {
+    /// Detects the instrumentation from the symbol table of `target`
+    ///
+    /// # Errors
+    /// * If `readelf` fails or the target carries no coverage instrumentation
+    pub fn detect(target: &Path) -> Result<Self> {
+        let symbols = read_symbols(target)?;
+        [Self::Llvm, Self::Gcov]
+            .into_iter()
+            .find(|kind| kind.is_instrumented(&symbols))
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Target binary is not compiled with LLVM coverage instrumentation or GCC --coverage"
+                )
+            })
+    }
+
+    fn is_instrumented(self, symbols: &str) -> bool {
+        let marker = match self {
+            Self::Llvm => "covrec",
+            Self::Gcov => "__gcov_",
+        };
+        symbols.lines().any(|line| line.contains(marker))
+    }
+
+    /// Checks that `target` carries the instrumentation of this backend
+    ///
+    /// # Errors
+    /// * If `readelf` fails or the instrumentation is missing
+    pub fn check_target(self, target: &Path) -> Result<()> {
+        if !self.is_instrumented(&read_symbols(target)?) {
+            bail!("Target binary is not compiled with {self} coverage instrumentation");
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for BackendKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "llvm" => Ok(Self::Llvm),
+            "gcov" | "gcc" => Ok(Self::Gcov),
+            _ => bail!("Unknown coverage backend '{s}'"),
+        }
+    }
+}
+
+impl fmt::Display for BackendKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Llvm => write!(f, "LLVM"),
+            Self::Gcov => write!(f, "gcov"),
+        }
+    }
+}
+
+/// Report generator of the gcov backend
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum GcovTool {
+    /// `lcov` tracefiles rendered with `genhtml`
+    #[default]
+    Lcov,
+    /// `gcovr`
+    Gcovr,
+}
+
+impl FromStr for GcovTool {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "lcov" | "genhtml" => Ok(Self::Lcov),
+            "gcovr" => Ok(Self::Gcovr),
+            _ => bail!("Unknown gcov report tool '{s}'"),
+        }
+    }
+}
+
+/// Toolchain specific part of coverage collection
+///
+/// A backend defines how a single replay writes raw coverage, how raw coverage is merged
+/// into an aggregated profile and how reports are generated from that profile.
+pub trait CoverageBackend: fmt::Debug + Send + Sync {
+    /// Tools that have to be installed for the backend
+    fn required_tools(&self) -> &'static [&'static str];
+
+    /// File name of an aggregated profile with the given stem, e.g. `merged.profdata`
+    fn profile_name(&self, stem: &str) -> String;
+
+    /// Raw coverage file or directory one execution writes into `raw_dir`
+    ///
+    /// `input_name` is unique per replayed input so parallel executions do not interfere.
+    fn raw_path(&self, raw_dir: &Path, input_name: &str) -> PathBuf;
+
+    /// Environment that makes one execution write its raw coverage to `raw_path`
+    fn replay_env(&self, raw_dir: &Path, input_name: &str) -> Vec<(&'static str, OsString)>;
+
+    /// Merges the raw coverage in `raw_dir`, and optionally an existing profile, into `output`
+    ///
+    /// Returns `false` if there was no raw coverage to merge.
+    ///
+    /// # Errors
+    /// * If merging fails
+    fn merge(&self, raw_dir: &Path, base: Option<&Path>, output: &Path) -> Result<bool>;
+
+    /// Exports the coverage of `profile` as lcov tracefile
+    ///
+    /// # Errors
+    /// * If the export tool fails
+    fn export_lcov(&self, target: &Path, profile: &Path) -> Result<String>;
+
+    /// Renders an HTML report of the files of `profile` matching `filter` into `output_dir`
+    ///
+    /// # Errors
+    /// * If the report tool fails
+    fn html_report(
+        &self,
+        target: &Path,
+        profile: &Path,
+        output_dir: &Path,
+        extra_args: &[String],
+        filter: &SourceFilter,
+    ) -> Result<()>;
+
+    /// Prints a summary table of the files of `profile` matching `filter`
+    ///
+    /// # Errors
+    /// * If the report tool fails
+    fn text_report(
+        &self,
+        target: &Path,
+        profile: &Path,
+        extra_args: &[String],
+        filter: &SourceFilter,
+    ) -> Result<()>;
+}
+
+/// Creates the backend for `kind`, `gcov_tool` selects the report generator of gcov
+pub fn create_backend(kind: BackendKind, gcov_tool: GcovTool) -> Arc<dyn CoverageBackend> {
+    match kind {
+        BackendKind::Llvm => Arc::new(LlvmBackend),
+        BackendKind::Gcov => Arc::new(GcovBackend { tool: gcov_tool }),
+    }
+}
+
+fn read_symbols(target: &Path) -> Result<String> {
+    let output = Command::new("readelf").arg("-s").arg(target).output()?;
+    if !output.status.success() {
+        bail!("readelf command failed to execute");
+    }
+    // Convert output bytes to string, ignoring invalid UTF-8 sequences
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn run_tool(cmd: &mut Command, name: &str) -> Result<()> {
+    let status = cmd
+        .status()
+        .with_context(|| format!("Failed to run {name}"))?;
+    if !status.success() {
+        bail!("{name} failed");
+    }
+    Ok(())
+}
+
+fn tool_stdout(cmd: &mut Command, name: &str) -> Result<String> {
+    let output = cmd
+        .stderr(Stdio::inherit())
+        .output()
+        .with_context(|| format!("Failed to run {name}"))?;
+    if !output.status.success() {
+        bail!("{name} failed");
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// LLVM source-based coverage via `llvm-profdata` and `llvm-cov`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LlvmBackend;
+
+impl LlvmBackend {
+    fn llvm_cov(subcommand: &str, target: &Path, profile: &Path) -> Command {
+        let mut cmd = Command::new("llvm-cov");
+        cmd.arg(subcommand)
+            .arg(target)
+            .arg("-instr-profile")
+            .arg(profile);
+        cmd
+    }
+
+    /// Source files passed to `llvm-cov show`/`report`, all of them if the filter is empty
+    fn sources(&self, target: &Path, profile: &Path, filter: &SourceFilter) -> Result<Vec<String>> {
+        if filter.is_empty() {
+            return Ok(Vec::new());
+        }
+        let sources = filter.sources(&self.export_lcov(target, profile)?);
+        if sources.is_empty() {
+            bail!("No source files match the include/exclude filters");
+        }
+        Ok(sources)
+    }
+}
+
+impl CoverageBackend for LlvmBackend {
+    fn required_tools(&self) -> &'static [&'static str] {
+        &["llvm-profdata", "llvm-cov", "genhtml", "lcov"]
+    }
+
+    fn profile_name(&self, stem: &str) -> String {
+        format!("{stem}.profdata")
+    }
+
+    fn raw_path(&self, raw_dir: &Path, input_name: &str) -> PathBuf {
+        raw_dir.join(format!("cov_{input_name}_.profraw"))
+    }
+
+    fn replay_env(&self, raw_dir: &Path, input_name: &str) -> Vec<(&'static str, OsString)> {
+        vec![(
+            "LLVM_PROFILE_FILE",
+            self.raw_path(raw_dir, input_name).into(),
+        )]
+    }
+
+    fn merge(&self, raw_dir: &Path, base: Option<&Path>, output: &Path) -> Result<bool> {
+        let pattern = raw_dir.join("cov_*.profraw");
+        let profraw_files: Vec<_> = glob(pattern.to_str().unwrap())?
+            .filter_map(Result::ok)
+            .collect();
+        if profraw_files.is_empty() {
+            return Ok(false);
+        }
+
+        // The existing profile is an input as well, so merge into a temporary file first
+        let merged_file = raw_dir.join("merged.profdata");
+        let status = Command::new("llvm-profdata")
+            .arg("merge")
+            .arg("-sparse")
+            .args(&profraw_files)
+            .args(base)
+            .arg("-o")
+            .arg(&merged_file)
+            .status()
+            .with_context(|| "Failed to execute llvm-profdata merge")?;
+
+        if !status.success() {
+            bail!("Failed to merge coverage files");
+        }
+        fs::copy(&merged_file, output)
+            .with_context(|| format!("Failed to write merged profile: {}", output.display()))?;
+        Ok(true)
+    }
+
+    fn export_lcov(&self, target: &Path, profile: &Path) -> Result<String> {
+        tool_stdout(
+            Self::llvm_cov("export", target, profile).arg("-format=lcov"),
+            "llvm-cov export",
+        )
+    }
+
+    fn html_report(
+        &self,
+        target: &Path,
+        profile: &Path,
+        output_dir: &Path,
+        extra_args: &[String],
+        filter: &SourceFilter,
+    ) -> Result<()> {
+        let sources = self.sources(target, profile, filter)?;
+        run_tool(
+            Self::llvm_cov("show", target, profile)
+                .arg("-format=html")
+                .arg("-o")
+                .arg(output_dir)
+                .args(["-show-line-counts-or-regions", "-show-expansions"])
+                .args(extra_args)
+                .args(sources),
+            "llvm-cov show",
+        )
+    }
+
+    fn text_report(
+        &self,
+        target: &Path,
+        profile: &Path,
+        extra_args: &[String],
+        filter: &SourceFilter,
+    ) -> Result<()> {
+        let sources = self.sources(target, profile, filter)?;
+        run_tool(
+            Self::llvm_cov("report", target, profile)
+                .args(extra_args)
+                .args(sources),
+            "llvm-cov report",
+        )
+    }
+}
+
+/// GCC coverage, `.gcda` files of each execution are isolated via `GCOV_PREFIX`
+///
+/// The aggregated profile is a directory that mirrors the absolute object paths with the
+/// merged `.gcda` files and the `.gcno` notes next to them.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GcovBackend {
+    pub tool: GcovTool,
+}
+
+impl GcovBackend {
+    /// Copies the `.gcno` notes of the build next to the merged `.gcda` files
+    fn link_notes(profile: &Path) -> Result<()> {
+        for gcda in find_gcda(profile)? {
+            let Ok(rel) = gcda.strip_prefix(profile) else {
+                continue;
+            };
+            let notes = Path::new("/").join(rel).with_extension("gcno");
+            let dst = gcda.with_extension("gcno");
+            if notes.exists() && !dst.exists() {
+                fs::copy(&notes, &dst)
+                    .with_context(|| format!("Failed to copy {}", notes.display()))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn gcovr(profile: &Path) -> Command {
+        let mut cmd = Command::new("gcovr");
+        cmd.args(["--root", "/"]).arg(profile);
+        cmd
+    }
+
+    fn lcov_capture(profile: &Path, output: &Path) -> Result<()> {
+        run_tool(
+            Command::new("lcov")
+                .args(["--quiet", "--capture", "--directory"])
+                .arg(profile)
+                .arg("--output-file")
+                .arg(output),
+            "lcov --capture",
+        )
+    }
+
+    /// Drops the files that do not match `filter` from a captured tracefile
+    fn filter_tracefile(tracefile: &Path, filter: &SourceFilter) -> Result<()> {
+        if filter.is_empty() {
+            return Ok(());
+        }
+        let content = fs::read_to_string(tracefile).context("Failed to read lcov tracefile")?;
+        fs::write(tracefile, filter.filter_tracefile(&content))
+            .with_context(|| format!("Failed to write {}", tracefile.display()))
+    }
+}
+
+impl CoverageBackend for GcovBackend {
+    fn required_tools(&self) -> &'static [&'static str] {
+        match self.tool {
+            GcovTool::Lcov => &["gcov", "gcov-tool", "lcov", "genhtml"],
+            GcovTool::Gcovr => &["gcov", "gcov-tool", "gcovr"],
+        }
+    }
+
+    fn profile_name(&self, stem: &str) -> String {
+        format!("{stem}_gcda")
+    }
+
+    fn raw_path(&self, raw_dir: &Path, input_name: &str) -> PathBuf {
+        raw_dir.join(format!("cov_{input_name}"))
+    }
+
+    fn replay_env(&self, raw_dir: &Path, input_name: &str) -> Vec<(&'static str, OsString)> {
+        vec![
+            ("GCOV_PREFIX", self.raw_path(raw_dir, input_name).into()),
+            ("GCOV_PREFIX_STRIP", "0".into()),
+        ]
+    }
+
+    fn merge(&self, raw_dir: &Path, base: Option<&Path>, output: &Path) -> Result<bool> {
+        // Executions that crashed before exiting did not dump any .gcda file
+        let mut runs = Vec::new();
+        for run in glob(raw_dir.join("cov_*").to_str().unwrap())?.filter_map(Result::ok) {
+            if run.is_dir() && !find_gcda(&run)?.is_empty() {
+                runs.push(run);
+            }
+        }
+        let Some((first, rest)) = runs.split_first() else {
+            return Ok(false);
+        };
+
+        let merged = raw_dir.join("merged_gcda");
+        copy_dir(base.unwrap_or(first), &merged)?;
+        let rest = if base.is_some() { &runs[..] } else { rest };
+        for run in rest {
+            run_tool(
+                Command::new("gcov-tool")
+                    .arg("merge")
+                    .arg("-o")
+                    .arg(&merged)
+                    .arg(&merged)
+                    .arg(run)
+                    .stdout(Stdio::null()),
+                "gcov-tool merge",
+            )?;
+        }
+
+        if output.exists() {
+            fs::remove_dir_all(output)
+                .with_context(|| format!("Failed to replace profile {}", output.display()))?;
+        }
+        copy_dir(&merged, output)?;
+        Self::link_notes(output)?;
+        Ok(true)
+    }
+
+    fn export_lcov(&self, _target: &Path, profile: &Path) -> Result<String> {
+        match self.tool {
+            GcovTool::Lcov => {
+                let tracefile = tempfile::NamedTempFile::new()?;
+                Self::lcov_capture(profile, tracefile.path())?;
+                fs::read_to_string(tracefile.path()).context("Failed to read lcov tracefile")
+            }
+            GcovTool::Gcovr => {
+                tool_stdout(Self::gcovr(profile).args(["--lcov", "-"]), "gcovr --lcov")
+            }
+        }
+    }
+
+    fn html_report(
+        &self,
+        _target: &Path,
+        profile: &Path,
+        output_dir: &Path,
+        extra_args: &[String],
+        filter: &SourceFilter,
+    ) -> Result<()> {
+        match self.tool {
+            GcovTool::Lcov => {
+                let tracefile = output_dir.join("coverage.info");
+                Self::lcov_capture(profile, &tracefile)?;
+                Self::filter_tracefile(&tracefile, filter)?;
+                run_tool(
+                    Command::new("genhtml")
+                        .arg("--quiet")
+                        .arg(&tracefile)
+                        .arg("--output-directory")
+                        .arg(output_dir)
+                        .args(extra_args),
+                    "genhtml",
+                )
+            }
+            GcovTool::Gcovr => run_tool(
+                Self::gcovr(profile)
+                    .arg("--html-details")
+                    .arg(output_dir.join("index.html"))
+                    .args(filter.gcovr_args())
+                    .args(extra_args),
+                "gcovr",
+            ),
+        }
+    }
+
+    fn text_report(
+        &self,
+        _target: &Path,
+        profile: &Path,
+        extra_args: &[String],
+        filter: &SourceFilter,
+    ) -> Result<()> {
+        match self.tool {
+            GcovTool::Lcov => {
+                let tracefile = tempfile::NamedTempFile::new()?;
+                Self::lcov_capture(profile, tracefile.path())?;
+                Self::filter_tracefile(tracefile.path(), filter)?;
+                run_tool(
+                    Command::new("lcov")
+                        .arg("--list")
+                        .arg(tracefile.path())
+                        .args(extra_args),
+                    "lcov --list",
+                )
+            }
+            GcovTool::Gcovr => run_tool(
+                Self::gcovr(profile)
+                    .args(filter.gcovr_args())
+                    .args(extra_args),
+                "gcovr",
+            ),
+        }
+    }
+}
+
+fn find_gcda(dir: &Path) -> Result<Vec<PathBuf>> {
+    let pattern = dir.join("**").join("*.gcda");
+    Ok(glob(pattern.to_str().unwrap())?
+        .filter_map(Result::ok)
+        .collect())
+}
+
+fn copy_dir(src: &Path, dst: &Path) -> Result<()> {
+    fs::create_dir_all(dst).with_context(|| format!("Failed to create {}", dst.display()))?;
+    for entry in fs::read_dir(src).with_context(|| format!("Failed to read {}", src.display()))? {
+        let entry = entry?;
+        let target = dst.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target)
+                .with_context(|| format!("Failed to copy {}", entry.path().display()))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_backend_kind() {
+        assert_eq!("GCC".parse::<BackendKind>().unwrap(), BackendKind::Gcov);
+        assert_eq!("llvm".parse::<BackendKind>().unwrap(), BackendKind::Llvm);
+        assert!("msvc".parse::<BackendKind>().is_err());
+        assert_eq!("gcovr".parse::<GcovTool>().unwrap(), GcovTool::Gcovr);
+        assert!(BackendKind::Gcov.is_instrumented("  42: 0 FUNC GLOBAL DEFAULT __gcov_dump"));
+        assert!(!BackendKind::Llvm.is_instrumented("  42: 0 FUNC GLOBAL DEFAULT __gcov_dump"));
+
+        let gcov = create_backend(BackendKind::Gcov, GcovTool::Gcovr);
+        assert_eq!(gcov.required_tools(), &["gcov", "gcov-tool", "gcovr"]);
+        assert_eq!(gcov.profile_name("merged_0"), "merged_0_gcda");
+        let env = gcov.replay_env(Path::new("/tmp/raw"), "id:000001");
+        assert_eq!(env[0], ("GCOV_PREFIX", "/tmp/raw/cov_id:000001".into()));
+    }
+
+    #[test]
+    fn test_gcov_replay_and_merge() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("target.c");
+        fs::write(
+            &source,
+            "#include <stdio.h>\nint main(int argc, char **argv) {\n  if (argc > 1) puts(argv[1]);\n  return 0;\n}\n",
+        )
+        .unwrap();
+        let target = dir.path().join("target");
+        let compiled = Command::new("gcc")
+            .arg("--coverage")
+            .arg("-o")
+            .arg(&target)
+            .arg(&source)
+            .current_dir(dir.path())
+            .status();
+        if !compiled.is_ok_and(|s| s.success()) {
+            eprintln!("gcc not available, skipping");
+            return;
+        }
+        assert_eq!(BackendKind::detect(&target).unwrap(), BackendKind::Gcov);
+
+        let backend = GcovBackend::default();
+        let raw_dir = dir.path().join("raw");
+        for (name, args) in [("a", vec!["x"]), ("b", vec![]), ("c", vec!["y"])] {
+            Command::new(&target)
+                .args(args)
+                .envs(backend.replay_env(&raw_dir, name))
+                .stdout(Stdio::null())
+                .status()
+                .unwrap();
+        }
+        let profile = dir.path().join(backend.profile_name("merged"));
+        if Command::new("gcov-tool").arg("--version").output().is_err() {
+            eprintln!("gcov-tool not available, skipping");
+            return;
+        }
+        assert!(backend.merge(&raw_dir, None, &profile).unwrap());
+        let gcda = find_gcda(&profile).unwrap();
+        assert_eq!(gcda.len(), 1);
+        assert!(gcda[0].with_extension("gcno").exists());
+
+        // Merging again on top of the profile accumulates runs
+        assert!(backend.merge(&raw_dir, Some(&profile), &profile).unwrap());
+        assert_eq!(find_gcda(&profile).unwrap().len(), 1);
+        assert!(!backend
+            .merge(&dir.path().join("empty"), None, &profile)
+            .unwrap());
+    }
+}
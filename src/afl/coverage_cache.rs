@@ -0,0 +1,223 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::hash::hash_file;
+
+/// File in the AFL++ output directory that tracks already replayed queue entries
+pub const CACHE_FILE: &str = ".aflr_cov_cache.toml";
+
+/// Replayed queue entries of a single report, e.g. the unified one or one per instance
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+struct CacheScope {
+    /// Aggregated profile the entries were merged into
+    profdata: PathBuf,
+    /// Content hashes of the replayed queue entries
+    entries: BTreeSet<String>,
+}
+
+/// Outcome of loading the cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheState {
+    /// No cache existed yet
+    Empty,
+    /// The cache was reused with this many replayed entries
+    Reused(usize),
+    /// The target binary or its arguments changed and the cache was discarded
+    Discarded,
+}
+
+/// Persistent cache that lets coverage collection only replay new queue entries
+///
+/// The cache is bound to the content hash of the coverage binary and the target arguments,
+/// changing either invalidates it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CoverageCache {
+    binary_hash: String,
+    target_args: Vec<String>,
+    #[serde(default)]
+    scopes: BTreeMap<String, CacheScope>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl CoverageCache {
+    /// Loads the cache from `afl_out`, discarding it if the target or its arguments changed
+    ///
+    /// # Errors
+    /// * If the target binary cannot be hashed
+    pub fn load(
+        afl_out: &Path,
+        target: &Path,
+        target_args: &[String],
+    ) -> Result<(Self, CacheState)> {
+        let path = afl_out.join(CACHE_FILE);
+        let binary_hash = hash_file(target)?;
+
+        let cached = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| toml::from_str::<Self>(&content).ok());
+        Ok(match cached {
+            Some(cache) if cache.binary_hash == binary_hash && cache.target_args == target_args => {
+                let replayed = cache.scopes.values().map(|s| s.entries.len()).sum();
+                (Self { path, ..cache }, CacheState::Reused(replayed))
+            }
+            cached => {
+                let state = if cached.is_some() {
+                    CacheState::Discarded
+                } else {
+                    CacheState::Empty
+                };
+                let cache = Self {
+                    binary_hash,
+                    target_args: target_args.to_vec(),
+                    scopes: BTreeMap::new(),
+                    path,
+                };
+                (cache, state)
+            }
+        })
+    }
+
+    /// Removes a cache from `afl_out` so the next incremental run starts over
+    ///
+    /// # Errors
+    /// * If an existing cache file cannot be removed
+    pub fn remove(afl_out: &Path) -> Result<()> {
+        let path = afl_out.join(CACHE_FILE);
+        if path.exists() {
+            fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove {}", path.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Returns the queue files of `scope` that still need to be replayed with their hashes
+    ///
+    /// Files with identical content are only returned once. If the aggregated profile of the
+    /// scope went missing, all files are returned again.
+    ///
+    /// # Errors
+    /// * If a queue file cannot be hashed
+    pub fn pending(
+        &mut self,
+        scope: &str,
+        profdata: &Path,
+        queue_files: &[PathBuf],
+    ) -> Result<Vec<(PathBuf, String)>> {
+        let stale = self
+            .scopes
+            .get(scope)
+            .is_some_and(|s| s.profdata != profdata || !profdata.exists());
+        if stale {
+            self.scopes.remove(scope);
+        }
+
+        let known = self.scopes.get(scope).map(|s| &s.entries);
+        let mut seen = HashSet::new();
+        let mut pending = Vec::new();
+        for file in queue_files {
+            let hash = hash_file(file)?;
+            if known.is_some_and(|k| k.contains(&hash)) || !seen.insert(hash.clone()) {
+                continue;
+            }
+            pending.push((file.clone(), hash));
+        }
+        Ok(pending)
+    }
+
+    /// Whether `scope` already has an aggregated profile new coverage can be merged into
+    pub fn has_profdata(&self, scope: &str) -> bool {
+        self.scopes.contains_key(scope)
+    }
+
+    /// Records replayed entries of `scope` that were merged into `profdata`
+    pub fn record<I: IntoIterator<Item = String>>(
+        &mut self,
+        scope: &str,
+        profdata: &Path,
+        hashes: I,
+    ) {
+        let entry = self
+            .scopes
+            .entry(scope.to_string())
+            .or_insert_with(|| CacheScope {
+                profdata: profdata.to_path_buf(),
+                entries: BTreeSet::new(),
+            });
+        entry.entries.extend(hashes);
+    }
+
+    /// Writes the cache back to the AFL++ output directory
+    ///
+    /// # Errors
+    /// * If the cache cannot be serialized or written
+    pub fn save(&self) -> Result<()> {
+        let content = toml::to_string(self).context("Failed to serialize coverage cache")?;
+        fs::write(&self.path, content)
+            .with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_pending_and_invalidation() {
+        let dir = tempdir().unwrap();
+        let target = dir.path().join("target");
+        fs::write(&target, "v1").unwrap();
+        let queue: Vec<PathBuf> = ["a", "b", "dup"]
+            .iter()
+            .map(|name| dir.path().join(name))
+            .collect();
+        fs::write(&queue[0], "input a").unwrap();
+        fs::write(&queue[1], "input b").unwrap();
+        fs::write(&queue[2], "input a").unwrap();
+        let profdata = dir.path().join("merged.profdata");
+        let args = vec!["@@".to_string()];
+
+        let (mut cache, state) = CoverageCache::load(dir.path(), &target, &args).unwrap();
+        assert_eq!(state, CacheState::Empty);
+        let pending = cache.pending("all", &profdata, &queue).unwrap();
+        assert_eq!(pending.len(), 2);
+        assert!(!cache.has_profdata("all"));
+        cache.record("all", &profdata, pending.into_iter().map(|(_, h)| h));
+        fs::write(&profdata, "profile").unwrap();
+        cache.save().unwrap();
+
+        fs::write(dir.path().join("c"), "input c").unwrap();
+        let queue: Vec<PathBuf> = queue.into_iter().chain([dir.path().join("c")]).collect();
+        let (mut cache, state) = CoverageCache::load(dir.path(), &target, &args).unwrap();
+        assert_eq!(state, CacheState::Reused(2));
+        let pending = cache.pending("all", &profdata, &queue).unwrap();
+        assert_eq!(pending.len(), 1);
+        assert!(pending[0].0.ends_with("c"));
+        assert!(cache.has_profdata("all"));
+        assert_eq!(cache.pending("other", &profdata, &queue).unwrap().len(), 3);
+
+        // A missing aggregate forces a full replay
+        fs::remove_file(&profdata).unwrap();
+        assert_eq!(cache.pending("all", &profdata, &queue).unwrap().len(), 3);
+        assert!(!cache.has_profdata("all"));
+
+        // So does a rebuilt target
+        fs::write(&profdata, "profile").unwrap();
+        cache.record("all", &profdata, ["x".to_string()]);
+        cache.save().unwrap();
+        fs::write(&target, "v2").unwrap();
+        let (cache, state) = CoverageCache::load(dir.path(), &target, &args).unwrap();
+        assert_eq!(state, CacheState::Discarded);
+        assert!(!cache.has_profdata("all"));
+
+        CoverageCache::remove(dir.path()).unwrap();
+        assert!(!dir.path().join(CACHE_FILE).exists());
+    }
+}
@@ -0,0 +1,351 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::afl::coverage_export::FileCoverage;
+
+/// Name of the contribution report in the AFL++ output directory
+pub const CONTRIBUTION_REPORT: &str = "coverage_contribution.json";
+/// Number of uniquely covered functions listed per instance on stdout
+const MAX_PRINTED_UNIQUE: usize = 10;
+
+/// Configuration an instance was started with, recovered from its `fuzzer_stats`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct InstanceConfig {
+    /// Target binary the instance fuzzed
+    pub binary: Option<PathBuf>,
+    /// Power schedule (`-p`)
+    pub schedule: String,
+    /// Whether a CMPLOG binary was attached (`-c`)
+    pub cmplog: bool,
+    /// Whether the instance fuzzed the CMPCOV/laf-intel binary
+    pub cmpcov: bool,
+    /// Whether the instance fuzzed the sanitizer binary
+    pub sanitizer: bool,
+    /// Whether MOpt mutators were enabled (`-L`)
+    pub mopt: bool,
+}
+
+impl InstanceConfig {
+    /// Parses the `command_line` of a `fuzzer_stats` file
+    ///
+    /// The binary is compared against the known sanitizer and CMPCOV binaries to tell which
+    /// of them the instance used.
+    pub fn from_command_line(
+        command_line: &str,
+        sanitizer_bin: Option<&Path>,
+        cmpcov_bin: Option<&Path>,
+    ) -> Self {
+        let mut config = Self {
+            schedule: "fast".to_string(),
+            ..Self::default()
+        };
+        let mut tokens = command_line.split_whitespace().skip(1);
+        while let Some(token) = tokens.next() {
+            match token {
+                "--" => {
+                    config.binary = tokens.next().map(PathBuf::from);
+                    break;
+                }
+                "-p" => {
+                    if let Some(schedule) = tokens.next() {
+                        schedule.clone_into(&mut config.schedule);
+                    }
+                }
+                "-c" => config.cmplog = true,
+                "-L" => config.mopt = true,
+                _ => {}
+            }
+        }
+
+        let is_binary = |other: Option<&Path>| {
+            config
+                .binary
+                .as_deref()
+                .zip(other)
+                .is_some_and(|(bin, other)| same_file(bin, other))
+        };
+        config.sanitizer = is_binary(sanitizer_bin);
+        config.cmpcov = is_binary(cmpcov_bin);
+        config
+    }
+
+    /// Reads the configuration from the `fuzzer_stats` of an instance directory
+    pub fn load(
+        instance_dir: &Path,
+        sanitizer_bin: Option<&Path>,
+        cmpcov_bin: Option<&Path>,
+    ) -> Option<Self> {
+        let stats = fs::read_to_string(instance_dir.join("fuzzer_stats")).ok()?;
+        let command_line = stats.lines().find_map(|line| {
+            line.split_once(':')
+                .filter(|(key, _)| key.trim() == "command_line")
+                .map(|(_, value)| value.trim().to_string())
+        })?;
+        Some(Self::from_command_line(
+            &command_line,
+            sanitizer_bin,
+            cmpcov_bin,
+        ))
+    }
+
+    /// Short human-readable description, e.g. `-p fast, cmplog, sanitizer`
+    pub fn describe(&self) -> String {
+        let mut parts = vec![format!("-p {}", self.schedule)];
+        for (label, set) in [
+            ("cmplog", self.cmplog),
+            ("cmpcov", self.cmpcov),
+            ("sanitizer", self.sanitizer),
+            ("mopt", self.mopt),
+        ] {
+            if set {
+                parts.push(label.to_string());
+            }
+        }
+        parts.join(", ")
+    }
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Coverage of one instance in relation to all other instances
+#[derive(Debug, Clone, Serialize)]
+pub struct InstanceContribution {
+    pub instance: String,
+    pub config: Option<InstanceConfig>,
+    /// Lines covered by the instance
+    pub lines: usize,
+    /// Functions covered by the instance
+    pub functions: usize,
+    /// Lines no other instance covered
+    pub unique_lines: usize,
+    /// Functions no other instance covered, as `file:function`
+    pub unique_functions: Vec<String>,
+    /// Share of all covered lines reached by this instance in percent
+    pub line_share: f64,
+}
+
+/// Attribution of covered lines and functions to the instances that reached them
+#[derive(Debug, Clone, Serialize)]
+pub struct ContributionReport {
+    pub total_lines: usize,
+    pub total_functions: usize,
+    pub instances: Vec<InstanceContribution>,
+}
+
+/// Coverage of a single instance as input to the contribution report
+pub struct InstanceCoverage {
+    pub name: String,
+    pub config: Option<InstanceConfig>,
+    pub files: Vec<FileCoverage>,
+}
+
+impl ContributionReport {
+    /// Attributes the coverage of all instances
+    #[allow(clippy::cast_precision_loss)]
+    pub fn new(instances: Vec<InstanceCoverage>) -> Self {
+        let mut line_owners: BTreeMap<(&Path, u32), BTreeSet<usize>> = BTreeMap::new();
+        let mut fn_owners: BTreeMap<(&Path, &str), BTreeSet<usize>> = BTreeMap::new();
+        for (idx, instance) in instances.iter().enumerate() {
+            for file in &instance.files {
+                for (&line, _) in file.lines.iter().filter(|(_, &hits)| hits > 0) {
+                    line_owners
+                        .entry((&file.path, line))
+                        .or_default()
+                        .insert(idx);
+                }
+                for (name, _) in file.functions.iter().filter(|(_, &hits)| hits > 0) {
+                    fn_owners.entry((&file.path, name)).or_default().insert(idx);
+                }
+            }
+        }
+
+        let total_lines = line_owners.len();
+        let contributions = instances
+            .iter()
+            .enumerate()
+            .map(|(idx, instance)| {
+                let lines = line_owners.values().filter(|o| o.contains(&idx)).count();
+                let unique_lines = line_owners
+                    .values()
+                    .filter(|o| o.len() == 1 && o.contains(&idx))
+                    .count();
+                let unique_functions = fn_owners
+                    .iter()
+                    .filter(|(_, o)| o.len() == 1 && o.contains(&idx))
+                    .map(|((path, name), _)| format!("{}:{name}", path.display()))
+                    .collect();
+                InstanceContribution {
+                    instance: instance.name.clone(),
+                    config: instance.config.clone(),
+                    lines,
+                    functions: fn_owners.values().filter(|o| o.contains(&idx)).count(),
+                    unique_lines,
+                    unique_functions,
+                    line_share: if total_lines == 0 {
+                        0.0
+                    } else {
+                        lines as f64 * 100.0 / total_lines as f64
+                    },
+                }
+            })
+            .collect();
+
+        Self {
+            total_lines,
+            total_functions: fn_owners.len(),
+            instances: contributions,
+        }
+    }
+
+    /// Prints a summary table, instances without unique coverage are flagged
+    pub fn print(&self) {
+        println!(
+            "[*] Coverage contribution ({} lines, {} functions covered in total):",
+            self.total_lines, self.total_functions
+        );
+        for c in &self.instances {
+            let config = c
+                .config
+                .as_ref()
+                .map_or_else(|| "unknown config".to_string(), InstanceConfig::describe);
+            println!(
+                "    {:<24} {:>6.2}% of lines, {:>6} unique lines, {:>4} unique functions  [{config}]",
+                c.instance,
+                c.line_share,
+                c.unique_lines,
+                c.unique_functions.len(),
+            );
+            for function in c.unique_functions.iter().take(MAX_PRINTED_UNIQUE) {
+                println!("        only here: {function}");
+            }
+            if c.unique_functions.len() > MAX_PRINTED_UNIQUE {
+                println!(
+                    "        ... and {} more",
+                    c.unique_functions.len() - MAX_PRINTED_UNIQUE
+                );
+            }
+        }
+        let idle: Vec<&str> = self
+            .instances
+            .iter()
+            .filter(|c| c.unique_lines == 0)
+            .map(|c| c.instance.as_str())
+            .collect();
+        if !idle.is_empty() && idle.len() < self.instances.len() {
+            println!("[!] Instances without unique coverage: {}", idle.join(", "));
+        }
+    }
+
+    /// Writes the report as JSON into `dir`
+    ///
+    /// # Errors
+    /// * If the report cannot be serialized or written
+    pub fn write(&self, dir: &Path) -> Result<PathBuf> {
+        let path = dir.join(CONTRIBUTION_REPORT);
+        let json =
+            serde_json::to_string_pretty(self).context("Failed to serialize contribution")?;
+        fs::write(&path, json).with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn coverage(path: &str, lines: &[(u32, u64)], functions: &[(&str, u64)]) -> FileCoverage {
+        FileCoverage {
+            path: PathBuf::from(path),
+            lines: lines.iter().copied().collect(),
+            functions: functions
+                .iter()
+                .map(|(n, h)| ((*n).to_string(), *h))
+                .collect(),
+            branches: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_instance_config() {
+        let dir = tempdir().unwrap();
+        let san = dir.path().join("target_asan");
+        fs::write(&san, "").unwrap();
+
+        let cfg = InstanceConfig::from_command_line(
+            &format!(
+                "afl-fuzz -i in -o out -S s0_target_cl -p rare -c /bin/cmplog -L 0 -- {} @@",
+                san.display()
+            ),
+            Some(&san),
+            None,
+        );
+        assert_eq!(cfg.schedule, "rare");
+        assert!(cfg.cmplog && cfg.mopt && cfg.sanitizer && !cfg.cmpcov);
+        assert_eq!(cfg.describe(), "-p rare, cmplog, sanitizer, mopt");
+
+        let instance = dir.path().join("m_target");
+        fs::create_dir(&instance).unwrap();
+        fs::write(
+            instance.join("fuzzer_stats"),
+            "start_time        : 1\ncommand_line      : afl-fuzz -M m_target -- /bin/target\n",
+        )
+        .unwrap();
+        let loaded = InstanceConfig::load(&instance, Some(&san), None).unwrap();
+        assert_eq!(loaded.binary, Some(PathBuf::from("/bin/target")));
+        assert_eq!(loaded.describe(), "-p fast");
+        assert!(InstanceConfig::load(dir.path(), None, None).is_none());
+    }
+
+    #[test]
+    fn test_contribution() {
+        let report = ContributionReport::new(vec![
+            InstanceCoverage {
+                name: "m_target".into(),
+                config: None,
+                files: vec![coverage(
+                    "/src/a.c",
+                    &[(1, 5), (2, 3), (3, 0)],
+                    &[("main", 1), ("parse", 0)],
+                )],
+            },
+            InstanceCoverage {
+                name: "s0_target".into(),
+                config: None,
+                files: vec![coverage(
+                    "/src/a.c",
+                    &[(1, 1), (3, 2), (4, 1)],
+                    &[("main", 1), ("parse", 2)],
+                )],
+            },
+        ]);
+        assert_eq!(report.total_lines, 4);
+        assert_eq!(report.total_functions, 2);
+
+        let main = &report.instances[0];
+        assert_eq!((main.lines, main.unique_lines), (2, 1));
+        assert!(main.unique_functions.is_empty());
+        assert!((main.line_share - 50.0).abs() < f64::EPSILON);
+
+        let secondary = &report.instances[1];
+        assert_eq!((secondary.lines, secondary.unique_lines), (3, 2));
+        assert_eq!(secondary.unique_functions, vec!["/src/a.c:parse"]);
+
+        let dir = tempdir().unwrap();
+        let path = report.write(dir.path()).unwrap();
+        assert!(fs::read_to_string(path)
+            .unwrap()
+            .contains("\"unique_lines\": 2"));
+    }
+}
@@ -0,0 +1,441 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+
+use crate::afl::coverage::CoverageCollector;
+use crate::afl::coverage_backend::{self, BackendKind, GcovTool};
+use crate::afl::coverage_export::{self, xml_escape, CoverageTotals, FileCoverage};
+use crate::afl::coverage_filter::SourceFilter;
+
+/// Unchanged source lines shown around a changed line in the HTML view
+const CONTEXT_LINES: u32 = 3;
+
+/// Whether `path` is an AFL++ output directory rather than a merged profile
+fn is_afl_output(path: &Path) -> bool {
+    fs::read_dir(path)
+        .into_iter()
+        .flatten()
+        .filter_map(std::result::Result::ok)
+        .any(|entry| entry.path().join("queue").is_dir())
+}
+
+/// Reads the coverage of the files matching `filter` of a merged profile or of an AFL++
+/// output directory
+///
+/// The queue entries of an AFL++ output directory are replayed into its merged profile
+/// first, which only executes entries that are new since the last `aflr cov` run.
+///
+/// # Errors
+/// * If the target is not instrumented for the backend or a tool is missing
+/// * If the queue cannot be replayed or the profile cannot be exported
+pub fn load_coverage(
+    input: &Path,
+    target: &Path,
+    target_args: &[String],
+    kind: BackendKind,
+    gcov_tool: GcovTool,
+    filter: &SourceFilter,
+) -> Result<Vec<FileCoverage>> {
+    let profile = if is_afl_output(input) {
+        println!("[*] Collecting merged profile of {}", input.display());
+        CoverageCollector::new_with_backend(target, input, kind, gcov_tool)?
+            .with_target_args(target_args.to_vec())
+            .collect_profile()?
+    } else if input.exists() {
+        input.to_path_buf()
+    } else {
+        anyhow::bail!("Coverage profile {} does not exist", input.display());
+    };
+    let tracefile = coverage_backend::create_backend(kind, gcov_tool)
+        .export_lcov(target, &profile)
+        .with_context(|| format!("Failed to export coverage of {}", profile.display()))?;
+    coverage_export::parse_lcov(&filter.filter_tracefile(&tracefile))
+}
+
+/// Coverage that changed in a single source file between two reports
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileDiff {
+    pub path: PathBuf,
+    /// Lines only covered in the second report
+    pub new_lines: BTreeSet<u32>,
+    /// Lines only covered in the first report
+    pub lost_lines: BTreeSet<u32>,
+    pub new_functions: BTreeSet<String>,
+    pub lost_functions: BTreeSet<String>,
+    /// Additionally taken branches per line
+    pub new_branches: BTreeMap<u32, u64>,
+    /// No longer taken branches per line
+    pub lost_branches: BTreeMap<u32, u64>,
+}
+
+impl FileDiff {
+    fn new(path: &Path, a: Option<&FileCoverage>, b: Option<&FileCoverage>) -> Self {
+        let covered_lines = |f: Option<&FileCoverage>| -> BTreeSet<u32> {
+            f.map(|f| {
+                f.lines
+                    .iter()
+                    .filter(|(_, &hits)| hits > 0)
+                    .map(|(&line, _)| line)
+                    .collect()
+            })
+            .unwrap_or_default()
+        };
+        let covered_functions = |f: Option<&FileCoverage>| -> BTreeSet<String> {
+            f.map(|f| {
+                f.functions
+                    .iter()
+                    .filter(|(_, &hits)| hits > 0)
+                    .map(|(name, _)| name.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+        };
+        let taken = |f: Option<&FileCoverage>, line: u32| {
+            f.and_then(|f| f.branches.get(&line))
+                .map_or(0, |b| b.covered)
+        };
+
+        let (lines_a, lines_b) = (covered_lines(a), covered_lines(b));
+        let (fns_a, fns_b) = (covered_functions(a), covered_functions(b));
+        let branch_lines: BTreeSet<u32> = [a, b]
+            .into_iter()
+            .flatten()
+            .flat_map(|f| f.branches.keys().copied())
+            .collect();
+
+        let mut diff = Self {
+            path: path.to_path_buf(),
+            new_lines: lines_b.difference(&lines_a).copied().collect(),
+            lost_lines: lines_a.difference(&lines_b).copied().collect(),
+            new_functions: fns_b.difference(&fns_a).cloned().collect(),
+            lost_functions: fns_a.difference(&fns_b).cloned().collect(),
+            ..Self::default()
+        };
+        for line in branch_lines {
+            let (taken_a, taken_b) = (taken(a, line), taken(b, line));
+            if taken_b > taken_a {
+                diff.new_branches.insert(line, taken_b - taken_a);
+            } else if taken_a > taken_b {
+                diff.lost_branches.insert(line, taken_a - taken_b);
+            }
+        }
+        diff
+    }
+
+    /// Whether nothing changed in the file
+    pub fn is_empty(&self) -> bool {
+        self.new_lines.is_empty()
+            && self.lost_lines.is_empty()
+            && self.new_functions.is_empty()
+            && self.lost_functions.is_empty()
+            && self.new_branches.is_empty()
+            && self.lost_branches.is_empty()
+    }
+}
+
+/// Newly covered and lost coverage between a baseline and a second report
+#[derive(Debug, Clone, Default)]
+pub struct CoverageDiff {
+    pub totals_a: CoverageTotals,
+    pub totals_b: CoverageTotals,
+    /// Files with changed coverage, sorted by path
+    pub files: Vec<FileDiff>,
+}
+
+impl CoverageDiff {
+    /// Compares the coverage of `a` (baseline) with `b`
+    pub fn new(a: &[FileCoverage], b: &[FileCoverage]) -> Self {
+        let by_path = |files: &[FileCoverage]| -> BTreeMap<PathBuf, FileCoverage> {
+            files.iter().map(|f| (f.path.clone(), f.clone())).collect()
+        };
+        let (map_a, map_b) = (by_path(a), by_path(b));
+        let paths: BTreeSet<&PathBuf> = map_a.keys().chain(map_b.keys()).collect();
+        let files = paths
+            .into_iter()
+            .map(|path| FileDiff::new(path, map_a.get(path), map_b.get(path)))
+            .filter(|diff| !diff.is_empty())
+            .collect();
+
+        Self {
+            totals_a: CoverageTotals::from_files(a),
+            totals_b: CoverageTotals::from_files(b),
+            files,
+        }
+    }
+
+    fn sum<F: Fn(&FileDiff) -> usize>(&self, metric: F) -> usize {
+        self.files.iter().map(metric).sum()
+    }
+
+    /// Renders the diff as plain text, listing changed functions and line ranges per file
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        let _ = writeln!(
+            text,
+            "Lines:     {:>6} -> {:>6} covered (+{} / -{})",
+            self.totals_a.lines.covered,
+            self.totals_b.lines.covered,
+            self.sum(|f| f.new_lines.len()),
+            self.sum(|f| f.lost_lines.len())
+        );
+        let _ = writeln!(
+            text,
+            "Functions: {:>6} -> {:>6} covered (+{} / -{})",
+            self.totals_a.functions.covered,
+            self.totals_b.functions.covered,
+            self.sum(|f| f.new_functions.len()),
+            self.sum(|f| f.lost_functions.len())
+        );
+        let _ = writeln!(
+            text,
+            "Branches:  {:>6} -> {:>6} covered (+{} / -{})",
+            self.totals_a.branches.covered,
+            self.totals_b.branches.covered,
+            self.sum(|f| f.new_branches.values().sum::<u64>() as usize),
+            self.sum(|f| f.lost_branches.values().sum::<u64>() as usize)
+        );
+
+        for file in &self.files {
+            let _ = writeln!(text, "\n{}", file.path.display());
+            for name in &file.new_functions {
+                let _ = writeln!(text, "  + function {name}");
+            }
+            for name in &file.lost_functions {
+                let _ = writeln!(text, "  - function {name}");
+            }
+            if !file.new_lines.is_empty() {
+                let _ = writeln!(text, "  + lines {}", line_ranges(&file.new_lines));
+            }
+            if !file.lost_lines.is_empty() {
+                let _ = writeln!(text, "  - lines {}", line_ranges(&file.lost_lines));
+            }
+            for (line, count) in &file.new_branches {
+                let _ = writeln!(text, "  + {count} branch(es) on line {line}");
+            }
+            for (line, count) in &file.lost_branches {
+                let _ = writeln!(text, "  - {count} branch(es) on line {line}");
+            }
+        }
+        if self.files.is_empty() {
+            text.push_str("\nNo coverage changes\n");
+        }
+        text
+    }
+
+    /// Renders a standalone HTML page with the changed source lines highlighted
+    ///
+    /// Sources that cannot be read are shown as lists of line numbers instead.
+    pub fn to_html(&self) -> String {
+        let mut html = String::from(
+            "<!doctype html>\n<html><head><meta charset=\"utf-8\"><title>Coverage diff</title>\n<style>\nbody{font-family:sans-serif}pre{font-size:12px;line-height:1.3}\n.new{background:#d4f8d4}.lost{background:#f8d4d4}.gap{color:#999}\ntable{border-collapse:collapse}td,th{padding:2px 8px;text-align:right}\n</style></head>\n<body>\n<h2>Coverage diff</h2>\n<table><tr><th></th><th>baseline</th><th>compared</th></tr>\n",
+        );
+        for (name, a, b) in [
+            ("lines", self.totals_a.lines, self.totals_b.lines),
+            (
+                "functions",
+                self.totals_a.functions,
+                self.totals_b.functions,
+            ),
+            ("branches", self.totals_a.branches, self.totals_b.branches),
+        ] {
+            let _ = writeln!(
+                html,
+                "<tr><th>{name}</th><td>{} ({:.2}%)</td><td>{} ({:.2}%)</td></tr>",
+                a.covered,
+                a.percent(),
+                b.covered,
+                b.percent()
+            );
+        }
+        html.push_str("</table>\n");
+
+        for file in &self.files {
+            let path = xml_escape(&file.path.to_string_lossy());
+            let _ = writeln!(
+                html,
+                "<h3>{path}</h3>\n<p><span class=\"new\">+{} lines</span> <span class=\"lost\">-{} lines</span></p>",
+                file.new_lines.len(),
+                file.lost_lines.len()
+            );
+            let functions: Vec<String> = file
+                .new_functions
+                .iter()
+                .map(|f| format!("<li class=\"new\">{}</li>", xml_escape(f)))
+                .chain(
+                    file.lost_functions
+                        .iter()
+                        .map(|f| format!("<li class=\"lost\">{}</li>", xml_escape(f))),
+                )
+                .collect();
+            if !functions.is_empty() {
+                let _ = writeln!(html, "<ul>{}</ul>", functions.concat());
+            }
+            match fs::read_to_string(&file.path) {
+                Ok(source) => write_annotated_source(&mut html, file, &source),
+                Err(_) => {
+                    let _ = writeln!(
+                        html,
+                        "<p>Source not available. New: {}<br>Lost: {}</p>",
+                        line_ranges(&file.new_lines),
+                        line_ranges(&file.lost_lines)
+                    );
+                }
+            }
+        }
+        html.push_str("</body></html>\n");
+        html
+    }
+
+    /// Writes the HTML view into `dir`, returns the path of the page
+    ///
+    /// # Errors
+    /// * If the directory or page cannot be written
+    pub fn write_html(&self, dir: &Path) -> Result<PathBuf> {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        let path = dir.join("index.html");
+        fs::write(&path, self.to_html())
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    }
+}
+
+/// Appends the changed lines of `source` with some context as a `<pre>` block
+fn write_annotated_source(html: &mut String, file: &FileDiff, source: &str) {
+    let changed: BTreeSet<u32> = file
+        .new_lines
+        .iter()
+        .chain(&file.lost_lines)
+        .chain(file.new_branches.keys())
+        .chain(file.lost_branches.keys())
+        .copied()
+        .collect();
+
+    html.push_str("<pre>");
+    let mut last_shown = 0;
+    for (idx, text) in source.lines().enumerate() {
+        let line = u32::try_from(idx + 1).unwrap_or(u32::MAX);
+        let near_change = changed
+            .range(line.saturating_sub(CONTEXT_LINES)..=line.saturating_add(CONTEXT_LINES))
+            .next()
+            .is_some();
+        if !near_change {
+            continue;
+        }
+        if last_shown != 0 && line > last_shown + 1 {
+            html.push_str("<span class=\"gap\">...</span>\n");
+        }
+        last_shown = line;
+
+        let class = if file.new_lines.contains(&line) || file.new_branches.contains_key(&line) {
+            " class=\"new\""
+        } else if file.lost_lines.contains(&line) || file.lost_branches.contains_key(&line) {
+            " class=\"lost\""
+        } else {
+            ""
+        };
+        let _ = writeln!(html, "<span{class}>{line:>6}  {}</span>", xml_escape(text));
+    }
+    html.push_str("</pre>\n");
+}
+
+/// Formats sorted line numbers as ranges, e.g. `3-5, 9`
+fn line_ranges(lines: &BTreeSet<u32>) -> String {
+    let mut ranges: Vec<(u32, u32)> = Vec::new();
+    for &line in lines {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == line => *end = line,
+            _ => ranges.push((line, line)),
+        }
+    }
+    ranges
+        .iter()
+        .map(|&(start, end)| {
+            if start == end {
+                start.to_string()
+            } else {
+                format!("{start}-{end}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::afl::coverage_export::{parse_lcov, Counter};
+
+    #[test]
+    fn test_coverage_diff() {
+        let a = parse_lcov(
+            "SF:/src/a.c\nFN:1,main\nFN:5,parse\nFNDA:1,main\nFNDA:3,parse\nDA:1,1\nDA:2,1\nDA:5,3\nDA:6,0\nBRDA:5,0,0,3\nBRDA:5,0,1,0\nend_of_record\nSF:/src/gone.c\nFN:1,old\nFNDA:1,old\nDA:1,1\nend_of_record\n",
+        )
+        .unwrap();
+        let b = parse_lcov(
+            "SF:/src/a.c\nFN:1,main\nFN:5,parse\nFNDA:1,main\nFNDA:0,parse\nDA:1,1\nDA:2,1\nDA:3,2\nDA:4,2\nDA:5,0\nDA:6,0\nBRDA:5,0,0,0\nBRDA:5,0,1,0\nend_of_record\nSF:/src/new.c\nFN:1,fresh\nFNDA:2,fresh\nDA:1,2\nBRDA:1,0,0,1\nend_of_record\n",
+        )
+        .unwrap();
+
+        let diff = CoverageDiff::new(&a, &b);
+        assert_eq!(diff.files.len(), 3);
+        let file_a = &diff.files[0];
+        assert_eq!(file_a.path, PathBuf::from("/src/a.c"));
+        assert_eq!(file_a.new_lines, BTreeSet::from([3, 4]));
+        assert_eq!(file_a.lost_lines, BTreeSet::from([5]));
+        assert_eq!(file_a.lost_functions, BTreeSet::from(["parse".to_string()]));
+        assert_eq!(file_a.lost_branches, BTreeMap::from([(5, 1)]));
+        assert!(diff.files[1].lost_functions.contains("old"));
+        assert_eq!(diff.files[2].new_branches, BTreeMap::from([(1, 1)]));
+        assert_eq!(
+            diff.totals_b.lines,
+            Counter {
+                count: 7,
+                covered: 5
+            }
+        );
+
+        let text = diff.to_text();
+        assert!(text.contains("Lines:          4 ->      5 covered (+3 / -2)"));
+        assert!(text.contains("  + lines 3-4\n  - lines 5\n"));
+        assert!(text.contains("  + function fresh"));
+        assert!(CoverageDiff::new(&a, &a)
+            .to_text()
+            .contains("No coverage changes"));
+    }
+
+    #[test]
+    fn test_annotated_html() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("a.c");
+        let lines: Vec<String> = (1..=20).map(|i| format!("int l{i}; // <{i}>")).collect();
+        fs::write(&source, lines.join("\n")).unwrap();
+
+        let diff = CoverageDiff {
+            files: vec![FileDiff {
+                path: source,
+                new_lines: BTreeSet::from([2]),
+                lost_lines: BTreeSet::from([15]),
+                ..FileDiff::default()
+            }],
+            ..CoverageDiff::default()
+        };
+        let html = diff.to_html();
+        assert!(html.contains("<span class=\"new\">     2  int l2; // &lt;2&gt;</span>"));
+        assert!(html.contains("<span class=\"lost\">    15  "));
+        assert!(html.contains("<span class=\"gap\">...</span>"));
+        // Lines far away from any change are omitted
+        assert!(!html.contains("l9;"));
+
+        let path = diff.write_html(&dir.path().join("diff")).unwrap();
+        assert!(path.ends_with("diff/index.html"));
+        assert_eq!(
+            line_ranges(&BTreeSet::from([1, 2, 3, 7, 9, 10])),
+            "1-3, 7, 9-10"
+        );
+    }
+}
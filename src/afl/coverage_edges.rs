@@ -0,0 +1,418 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::afl::sync_groups::instance_dirs;
+use crate::afl::target_exec::DEFAULT_EXEC_TIMEOUT_MS;
+
+/// Name of the edge coverage report in the AFL++ output directory
+pub const EDGE_REPORT: &str = "edge_coverage.json";
+/// Edges reached by at most this many queue entries are considered rare
+pub const RARE_EDGE_MAX_INPUTS: usize = 2;
+/// Number of inputs with the most rare edges listed on stdout
+const MAX_PRINTED_INPUTS: usize = 10;
+
+/// Binary-only instrumentation `afl-showmap` traces the target with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum BinaryOnlyMode {
+    /// QEMU mode (`-Q`)
+    Qemu,
+    /// FRIDA mode (`-O`)
+    Frida,
+}
+
+impl BinaryOnlyMode {
+    fn flag(self) -> &'static str {
+        match self {
+            Self::Qemu => "-Q",
+            Self::Frida => "-O",
+        }
+    }
+}
+
+/// Parses an `afl-showmap` map file, one `edge:hits` tuple per line
+pub fn parse_map(content: &str) -> BTreeSet<u32> {
+    content
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .filter(|(_, hits)| hits.trim() != "0")
+        .filter_map(|(edge, _)| edge.trim().parse().ok())
+        .collect()
+}
+
+/// Traces queue entries with `afl-showmap` and the regular AFL++-instrumented target
+#[derive(Debug, Clone)]
+pub struct EdgeCollector {
+    showmap: PathBuf,
+    target: PathBuf,
+    target_args: Vec<String>,
+    afl_out: PathBuf,
+    timeout: Duration,
+    mem_limit_mb: Option<u64>,
+    binary_only: Option<BinaryOnlyMode>,
+    jobs: usize,
+}
+
+impl EdgeCollector {
+    /// Creates a collector using the `afl-showmap` next to `afl_fuzz`
+    ///
+    /// # Errors
+    /// * If `afl-showmap` or the target binary do not exist
+    pub fn new(afl_fuzz: &Path, target: PathBuf, afl_out: PathBuf) -> Result<Self> {
+        let showmap = afl_fuzz.with_file_name("afl-showmap");
+        if !showmap.is_file() {
+            bail!("afl-showmap not found next to {}", afl_fuzz.display());
+        }
+        if !target.is_file() {
+            bail!("Target binary {} does not exist", target.display());
+        }
+        Ok(Self {
+            showmap,
+            target,
+            target_args: Vec::new(),
+            afl_out,
+            timeout: Duration::from_millis(DEFAULT_EXEC_TIMEOUT_MS),
+            mem_limit_mb: None,
+            binary_only: None,
+            jobs: 1,
+        })
+    }
+
+    pub fn with_target_args(&mut self, args: Vec<String>) -> &mut Self {
+        self.target_args = args;
+        self
+    }
+
+    pub fn with_exec_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_mem_limit(&mut self, mb: Option<u64>) -> &mut Self {
+        self.mem_limit_mb = mb;
+        self
+    }
+
+    pub fn with_binary_only(&mut self, mode: Option<BinaryOnlyMode>) -> &mut Self {
+        self.binary_only = mode;
+        self
+    }
+
+    /// Sets the number of instances traced in parallel
+    pub fn with_jobs(&mut self, jobs: usize) -> &mut Self {
+        self.jobs = jobs.max(1);
+        self
+    }
+
+    /// Instance directories with a queue, sorted by name
+    fn instances(&self) -> Result<Vec<(String, PathBuf)>> {
+        if !self.afl_out.is_dir() {
+            bail!(
+                "Failed to read AFL++ output directory: {}",
+                self.afl_out.display()
+            );
+        }
+        let mut instances: Vec<(String, PathBuf)> = instance_dirs(&self.afl_out)
+            .into_iter()
+            .map(|dir| {
+                (
+                    dir.file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .into_owned(),
+                    dir.join("queue"),
+                )
+            })
+            .filter(|(_, queue)| queue.is_dir())
+            .collect();
+        if instances.is_empty() {
+            bail!("No queue directories found in {}", self.afl_out.display());
+        }
+        instances.sort();
+        Ok(instances)
+    }
+
+    /// Runs `afl-showmap` over a whole queue directory, writing one map per entry to `map_dir`
+    fn trace_queue(&self, queue: &Path, map_dir: &Path) -> Result<()> {
+        Command::new(&self.showmap)
+            .arg("-q")
+            .arg("-e")
+            .arg("-i")
+            .arg(queue)
+            .arg("-o")
+            .arg(map_dir)
+            .arg("-t")
+            .arg(self.timeout.as_millis().to_string())
+            .arg("-m")
+            .arg(
+                self.mem_limit_mb
+                    .map_or_else(|| "none".to_string(), |mb| mb.to_string()),
+            )
+            .args(self.binary_only.map(BinaryOnlyMode::flag))
+            .arg("--")
+            .arg(&self.target)
+            .args(&self.target_args)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            // afl-showmap exits non-zero if any entry crashed, the maps are written regardless
+            .status()
+            .with_context(|| format!("Failed to run {}", self.showmap.display()))?;
+        Ok(())
+    }
+
+    /// Reads the maps `afl-showmap` wrote for the entries of `queue`
+    fn read_maps(queue: &Path, map_dir: &Path) -> Result<BTreeMap<PathBuf, BTreeSet<u32>>> {
+        let mut maps = BTreeMap::new();
+        for entry in fs::read_dir(map_dir)?.filter_map(std::result::Result::ok) {
+            let content = fs::read_to_string(entry.path())
+                .with_context(|| format!("Failed to read map {}", entry.path().display()))?;
+            maps.insert(queue.join(entry.file_name()), parse_map(&content));
+        }
+        Ok(maps)
+    }
+
+    /// Traces all instances and aggregates their edge maps
+    ///
+    /// # Errors
+    /// * If the AFL++ output directory cannot be read
+    /// * If `afl-showmap` cannot be executed or produced no maps
+    pub fn collect(&self) -> Result<EdgeReport> {
+        let instances = self.instances()?;
+        let work_dir = tempfile::Builder::new().prefix(".aflr_edges_").tempdir()?;
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(self.jobs)
+            .build()
+            .context("Failed to create showmap thread pool")?;
+
+        let traced: Vec<Result<InstanceEdges>> = pool.install(|| {
+            instances
+                .par_iter()
+                .map(|(name, queue)| {
+                    let map_dir = work_dir.path().join(name);
+                    fs::create_dir(&map_dir)?;
+                    println!("[+] Tracing edges of instance: {name}");
+                    self.trace_queue(queue, &map_dir)?;
+                    Ok(InstanceEdges {
+                        name: name.clone(),
+                        maps: Self::read_maps(queue, &map_dir)?,
+                    })
+                })
+                .collect()
+        });
+        let traced = traced.into_iter().collect::<Result<Vec<_>>>()?;
+        if traced.iter().all(|i| i.maps.is_empty()) {
+            bail!("afl-showmap produced no maps, check the target and its arguments");
+        }
+        Ok(EdgeReport::new(&traced))
+    }
+}
+
+/// Edge maps of the queue entries of a single instance
+#[derive(Debug, Clone, Default)]
+pub struct InstanceEdges {
+    pub name: String,
+    pub maps: BTreeMap<PathBuf, BTreeSet<u32>>,
+}
+
+/// Edge counts of a single instance
+#[derive(Debug, Clone, Serialize)]
+pub struct InstanceSummary {
+    pub instance: String,
+    pub inputs: usize,
+    pub edges: usize,
+    /// Edges no other instance reached
+    pub unique_edges: usize,
+}
+
+/// Queue entry that reaches rare edges
+#[derive(Debug, Clone, Serialize)]
+pub struct RareInput {
+    pub input: PathBuf,
+    pub rare_edges: Vec<u32>,
+}
+
+/// Aggregated edge coverage of all instances
+#[derive(Debug, Clone, Serialize)]
+pub struct EdgeReport {
+    pub total_edges: usize,
+    pub instances: Vec<InstanceSummary>,
+    /// Number of edges reached by at most `RARE_EDGE_MAX_INPUTS` entries
+    pub rare_edges: usize,
+    /// Entries reaching rare edges, most rare edges first
+    pub rare_inputs: Vec<RareInput>,
+}
+
+impl EdgeReport {
+    pub fn new(instances: &[InstanceEdges]) -> Self {
+        let mut edge_inputs: BTreeMap<u32, BTreeSet<&Path>> = BTreeMap::new();
+        let mut edge_instances: BTreeMap<u32, BTreeSet<usize>> = BTreeMap::new();
+        for (idx, instance) in instances.iter().enumerate() {
+            for (input, edges) in &instance.maps {
+                for &edge in edges {
+                    edge_inputs.entry(edge).or_default().insert(input);
+                    edge_instances.entry(edge).or_default().insert(idx);
+                }
+            }
+        }
+
+        let summaries = instances
+            .iter()
+            .enumerate()
+            .map(|(idx, instance)| InstanceSummary {
+                instance: instance.name.clone(),
+                inputs: instance.maps.len(),
+                edges: edge_instances.values().filter(|i| i.contains(&idx)).count(),
+                unique_edges: edge_instances
+                    .values()
+                    .filter(|i| i.len() == 1 && i.contains(&idx))
+                    .count(),
+            })
+            .collect();
+
+        let rare: Vec<(u32, &BTreeSet<&Path>)> = edge_inputs
+            .iter()
+            .filter(|(_, inputs)| inputs.len() <= RARE_EDGE_MAX_INPUTS)
+            .map(|(&edge, inputs)| (edge, inputs))
+            .collect();
+        let mut by_input: BTreeMap<&Path, Vec<u32>> = BTreeMap::new();
+        for (edge, inputs) in &rare {
+            for input in *inputs {
+                by_input.entry(input).or_default().push(*edge);
+            }
+        }
+        let mut rare_inputs: Vec<RareInput> = by_input
+            .into_iter()
+            .map(|(input, rare_edges)| RareInput {
+                input: input.to_path_buf(),
+                rare_edges,
+            })
+            .collect();
+        rare_inputs.sort_by_key(|i| std::cmp::Reverse(i.rare_edges.len()));
+
+        Self {
+            total_edges: edge_inputs.len(),
+            instances: summaries,
+            rare_edges: rare.len(),
+            rare_inputs,
+        }
+    }
+
+    /// Prints the edge counts per instance and the entries with the most rare edges
+    pub fn print(&self) {
+        println!("[*] Edge coverage: {} edges in total", self.total_edges);
+        for i in &self.instances {
+            println!(
+                "    {:<24} {:>7} edges, {:>6} unique, {:>6} queue entries",
+                i.instance, i.edges, i.unique_edges, i.inputs
+            );
+        }
+        println!(
+            "[*] {} rare edges reached by at most {RARE_EDGE_MAX_INPUTS} queue entries",
+            self.rare_edges
+        );
+        for input in self.rare_inputs.iter().take(MAX_PRINTED_INPUTS) {
+            println!(
+                "    {:>5} rare edges: {}",
+                input.rare_edges.len(),
+                input.input.display()
+            );
+        }
+    }
+
+    /// Writes the report as JSON into `dir`
+    ///
+    /// # Errors
+    /// * If the report cannot be serialized or written
+    pub fn write(&self, dir: &Path) -> Result<PathBuf> {
+        let path = dir.join(EDGE_REPORT);
+        let json = serde_json::to_string_pretty(self).context("Failed to serialize edges")?;
+        fs::write(&path, json).with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn instance(name: &str, maps: &[(&str, &[u32])]) -> InstanceEdges {
+        InstanceEdges {
+            name: name.to_string(),
+            maps: maps
+                .iter()
+                .map(|(input, edges)| (PathBuf::from(input), edges.iter().copied().collect()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_parse_map() {
+        assert_eq!(
+            parse_map("000012:1\n000420:8\n000007:0\ngarbage\n"),
+            BTreeSet::from([12, 420])
+        );
+    }
+
+    #[test]
+    fn test_edge_report() {
+        let report = EdgeReport::new(&[
+            instance("m_target", &[("m/a", &[1, 2, 3]), ("m/b", &[1, 2, 3, 4])]),
+            instance("s0_target", &[("s/a", &[1, 2, 3]), ("s/b", &[1, 5, 6])]),
+        ]);
+        assert_eq!(report.total_edges, 6);
+        assert_eq!(report.instances[0].edges, 4);
+        assert_eq!(report.instances[0].unique_edges, 1);
+        assert_eq!(report.instances[1].unique_edges, 2);
+        // Edges 4, 5 and 6 are reached by a single entry each
+        assert_eq!(report.rare_edges, 3);
+        assert_eq!(report.rare_inputs[0].input, PathBuf::from("s/b"));
+        assert_eq!(report.rare_inputs[0].rare_edges, vec![5, 6]);
+        assert_eq!(report.rare_inputs.len(), 2);
+    }
+
+    #[test]
+    fn test_collect_with_showmap_stub() {
+        let dir = tempfile::tempdir().unwrap();
+        let bin = dir.path().join("bin");
+        fs::create_dir(&bin).unwrap();
+        // Stub that writes the input size as the only edge of every map
+        let showmap = bin.join("afl-showmap");
+        fs::write(
+            &showmap,
+            "#!/bin/sh\nwhile [ $# -gt 0 ]; do case $1 in -i) in=$2; shift;; -o) out=$2; shift;; esac; shift; done\nfor f in \"$in\"/*; do echo \"$(wc -c < \"$f\" | tr -d ' '):1\" > \"$out/$(basename \"$f\")\"; done\n",
+        )
+        .unwrap();
+        fs::set_permissions(&showmap, fs::Permissions::from_mode(0o755)).unwrap();
+        let target = bin.join("target");
+        fs::write(&target, "").unwrap();
+
+        let out = dir.path().join("out");
+        for (instance, inputs) in [("m_t", ["a", "bb"]), ("s0_t", ["a", "ccc"])] {
+            let queue = out.join(instance).join("queue");
+            fs::create_dir_all(&queue).unwrap();
+            for (i, content) in inputs.iter().enumerate() {
+                fs::write(queue.join(format!("id:{i:06}")), content).unwrap();
+            }
+        }
+
+        let mut collector = EdgeCollector::new(&bin.join("afl-fuzz"), target, out.clone()).unwrap();
+        collector.with_jobs(2);
+        let report = collector.collect().unwrap();
+        assert_eq!(report.total_edges, 3);
+        assert_eq!(report.instances[0].inputs, 2);
+        assert_eq!(report.instances[1].unique_edges, 1);
+        assert!(report.write(&out).unwrap().exists());
+
+        assert!(EdgeCollector::new(&dir.path().join("afl-fuzz"), bin.join("target"), out).is_err());
+    }
+}
@@ -0,0 +1,510 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Write as _},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+/// Output format of a coverage report
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ReportFormat {
+    /// Annotated sources via `llvm-cov show`
+    #[default]
+    Html,
+    /// Summary table via `llvm-cov report`
+    Text,
+    /// lcov tracefile via `llvm-cov export -format=lcov`
+    Lcov,
+    /// Cobertura XML for CI coverage widgets
+    Cobertura,
+    /// Per-file line/function/branch percentages as JSON
+    Json,
+}
+
+impl ReportFormat {
+    /// File name of the exported report, `None` for formats that are not exported to a file
+    pub fn file_name(self, instance: Option<usize>) -> Option<String> {
+        let (stem, ext) = match self {
+            Self::Html | Self::Text => return None,
+            Self::Lcov => ("coverage", "lcov"),
+            Self::Cobertura => ("coverage", "xml"),
+            Self::Json => ("coverage_summary", "json"),
+        };
+        Some(instance.map_or_else(
+            || format!("{stem}.{ext}"),
+            |idx| format!("{stem}_{idx}.{ext}"),
+        ))
+    }
+}
+
+impl FromStr for ReportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "html" => Ok(Self::Html),
+            "text" => Ok(Self::Text),
+            "lcov" => Ok(Self::Lcov),
+            "cobertura" | "xml" => Ok(Self::Cobertura),
+            "json" => Ok(Self::Json),
+            _ => bail!("Unknown report type '{s}'"),
+        }
+    }
+}
+
+impl fmt::Display for ReportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Html => "html",
+            Self::Text => "text",
+            Self::Lcov => "lcov",
+            Self::Cobertura => "cobertura",
+            Self::Json => "json",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Covered and total items of one coverage metric
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Counter {
+    pub count: u64,
+    pub covered: u64,
+}
+
+impl Counter {
+    /// Covered share in percent, 0 if there is nothing to cover
+    #[allow(clippy::cast_precision_loss)]
+    pub fn percent(self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.covered as f64 * 100.0 / self.count as f64
+        }
+    }
+
+    fn rate(self) -> f64 {
+        self.percent() / 100.0
+    }
+
+    fn add(&mut self, other: Self) {
+        self.count += other.count;
+        self.covered += other.covered;
+    }
+}
+
+/// Coverage of a single source file as read from an lcov tracefile
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileCoverage {
+    pub path: PathBuf,
+    /// Execution count per line
+    pub lines: BTreeMap<u32, u64>,
+    /// Execution count per function
+    pub functions: BTreeMap<String, u64>,
+    /// Taken and total branches per line
+    pub branches: BTreeMap<u32, Counter>,
+}
+
+impl FileCoverage {
+    pub fn line_counter(&self) -> Counter {
+        Counter {
+            count: self.lines.len() as u64,
+            covered: self.lines.values().filter(|&&c| c > 0).count() as u64,
+        }
+    }
+
+    pub fn function_counter(&self) -> Counter {
+        Counter {
+            count: self.functions.len() as u64,
+            covered: self.functions.values().filter(|&&c| c > 0).count() as u64,
+        }
+    }
+
+    pub fn branch_counter(&self) -> Counter {
+        self.branches
+            .values()
+            .fold(Counter::default(), |mut acc, c| {
+                acc.add(*c);
+                acc
+            })
+    }
+}
+
+/// Line, function and branch totals over a set of files
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoverageTotals {
+    pub lines: Counter,
+    pub functions: Counter,
+    pub branches: Counter,
+}
+
+impl CoverageTotals {
+    pub fn from_files(files: &[FileCoverage]) -> Self {
+        let mut totals = Self::default();
+        for file in files {
+            totals.lines.add(file.line_counter());
+            totals.functions.add(file.function_counter());
+            totals.branches.add(file.branch_counter());
+        }
+        totals
+    }
+}
+
+/// Parses an lcov tracefile as written by `llvm-cov export -format=lcov`
+///
+/// # Errors
+/// * If a record is malformed or a file record is not terminated
+pub fn parse_lcov(content: &str) -> Result<Vec<FileCoverage>> {
+    let mut files = Vec::new();
+    let mut current: Option<FileCoverage> = None;
+
+    for (lineno, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with("TN:") {
+            continue;
+        }
+        if line == "end_of_record" {
+            files.extend(current.take());
+            continue;
+        }
+        let (tag, value) = line
+            .split_once(':')
+            .with_context(|| format!("Malformed lcov record on line {}", lineno + 1))?;
+        if tag == "SF" {
+            current = Some(FileCoverage {
+                path: PathBuf::from(value),
+                ..FileCoverage::default()
+            });
+            continue;
+        }
+        let file = current
+            .as_mut()
+            .with_context(|| format!("lcov record outside of a file on line {}", lineno + 1))?;
+        let fields: Vec<&str> = value.split(',').collect();
+        let parse_err = || format!("Malformed lcov {tag} record on line {}", lineno + 1);
+        match (tag, fields.as_slice()) {
+            ("DA", [line_no, count, ..]) => {
+                let line_no: u32 = line_no.parse().with_context(parse_err)?;
+                let count: u64 = count.parse().with_context(parse_err)?;
+                *file.lines.entry(line_no).or_default() += count;
+            }
+            ("FN", [_, .., name]) => {
+                file.functions.entry((*name).to_string()).or_default();
+            }
+            ("FNDA", [count, name]) => {
+                let count: u64 = count.parse().with_context(parse_err)?;
+                *file.functions.entry((*name).to_string()).or_default() += count;
+            }
+            ("BRDA", [line_no, _, _, taken]) => {
+                let line_no: u32 = line_no.parse().with_context(parse_err)?;
+                let branch = file.branches.entry(line_no).or_default();
+                branch.count += 1;
+                if *taken != "-" && taken.parse::<u64>().with_context(parse_err)? > 0 {
+                    branch.covered += 1;
+                }
+            }
+            // Totals are recomputed from the individual records
+            ("FNF" | "FNH" | "LF" | "LH" | "BRF" | "BRH", _) => {}
+            _ => bail!("{}", parse_err()),
+        }
+    }
+    if current.is_some() {
+        bail!("lcov tracefile ends without end_of_record");
+    }
+    Ok(files)
+}
+
+/// Sums the coverage of two reports of the same binary file by file
+pub fn merge_files(a: &[FileCoverage], b: &[FileCoverage]) -> Vec<FileCoverage> {
+    let mut merged: BTreeMap<PathBuf, FileCoverage> =
+        a.iter().map(|f| (f.path.clone(), f.clone())).collect();
+    for file in b {
+        let target = merged
+            .entry(file.path.clone())
+            .or_insert_with(|| FileCoverage {
+                path: file.path.clone(),
+                ..FileCoverage::default()
+            });
+        for (&line, &hits) in &file.lines {
+            *target.lines.entry(line).or_default() += hits;
+        }
+        for (name, &hits) in &file.functions {
+            *target.functions.entry(name.clone()).or_default() += hits;
+        }
+        for (&line, &branches) in &file.branches {
+            let taken = target.branches.entry(line).or_insert(branches);
+            taken.covered = taken.covered.max(branches.covered);
+        }
+    }
+    merged.into_values().collect()
+}
+
+/// Coverage percentages of a single file in the JSON summary
+#[derive(Debug, Serialize)]
+struct FileSummary<'a> {
+    path: &'a Path,
+    lines: MetricSummary,
+    functions: MetricSummary,
+    branches: MetricSummary,
+}
+
+#[derive(Debug, Serialize)]
+struct MetricSummary {
+    count: u64,
+    covered: u64,
+    percent: f64,
+}
+
+impl From<Counter> for MetricSummary {
+    fn from(counter: Counter) -> Self {
+        Self {
+            count: counter.count,
+            covered: counter.covered,
+            percent: (counter.percent() * 100.0).round() / 100.0,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Summary<'a> {
+    totals: FileSummary<'a>,
+    files: Vec<FileSummary<'a>>,
+}
+
+/// Renders a compact JSON summary with per-file and total line/function/branch percentages
+///
+/// # Errors
+/// * If the summary cannot be serialized
+pub fn to_json_summary(files: &[FileCoverage]) -> Result<String> {
+    let totals = CoverageTotals::from_files(files);
+    let file_summaries = files
+        .iter()
+        .map(|f| FileSummary {
+            path: &f.path,
+            lines: f.line_counter().into(),
+            functions: f.function_counter().into(),
+            branches: f.branch_counter().into(),
+        })
+        .collect();
+    let summary = Summary {
+        totals: FileSummary {
+            path: Path::new("TOTAL"),
+            lines: totals.lines.into(),
+            functions: totals.functions.into(),
+            branches: totals.branches.into(),
+        },
+        files: file_summaries,
+    };
+    serde_json::to_string_pretty(&summary).context("Failed to serialize coverage summary")
+}
+
+/// Renders a Cobertura XML report, grouping files into packages by directory
+pub fn to_cobertura(files: &[FileCoverage], timestamp: i64) -> String {
+    let mut packages: BTreeMap<String, Vec<&FileCoverage>> = BTreeMap::new();
+    for file in files {
+        let package = file
+            .path
+            .parent()
+            .map(|p| p.to_string_lossy().replace('/', "."))
+            .unwrap_or_default();
+        packages.entry(package).or_default().push(file);
+    }
+
+    let CoverageTotals {
+        lines, branches, ..
+    } = CoverageTotals::from_files(files);
+
+    let mut xml = String::from("<?xml version=\"1.0\" ?>\n");
+    xml.push_str(
+        "<!DOCTYPE coverage SYSTEM \"http://cobertura.sourceforge.net/xml/coverage-04.dtd\">\n",
+    );
+    let _ = writeln!(
+        xml,
+        "<coverage line-rate=\"{:.4}\" branch-rate=\"{:.4}\" lines-covered=\"{}\" lines-valid=\"{}\" branches-covered=\"{}\" branches-valid=\"{}\" complexity=\"0\" version=\"{}\" timestamp=\"{timestamp}\">",
+        lines.rate(),
+        branches.rate(),
+        lines.covered,
+        lines.count,
+        branches.covered,
+        branches.count,
+        env!("CARGO_PKG_VERSION"),
+    );
+    xml.push_str("  <sources>\n    <source>/</source>\n  </sources>\n  <packages>\n");
+    for (package, files) in &packages {
+        let (mut pkg_lines, mut pkg_branches) = (Counter::default(), Counter::default());
+        for file in files {
+            pkg_lines.add(file.line_counter());
+            pkg_branches.add(file.branch_counter());
+        }
+        let _ = writeln!(
+            xml,
+            "    <package name=\"{}\" line-rate=\"{:.4}\" branch-rate=\"{:.4}\" complexity=\"0\">\n      <classes>",
+            xml_escape(package),
+            pkg_lines.rate(),
+            pkg_branches.rate(),
+        );
+        for file in files {
+            write_cobertura_class(&mut xml, file);
+        }
+        xml.push_str("      </classes>\n    </package>\n");
+    }
+    xml.push_str("  </packages>\n</coverage>\n");
+    xml
+}
+
+fn write_cobertura_class(xml: &mut String, file: &FileCoverage) {
+    let path = file.path.to_string_lossy();
+    let filename = path.trim_start_matches('/');
+    let name = file
+        .path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let _ = writeln!(
+        xml,
+        "        <class name=\"{}\" filename=\"{}\" line-rate=\"{:.4}\" branch-rate=\"{:.4}\" complexity=\"0\">\n          <methods/>\n          <lines>",
+        xml_escape(&name),
+        xml_escape(filename),
+        file.line_counter().rate(),
+        file.branch_counter().rate(),
+    );
+    for (line, hits) in &file.lines {
+        match file.branches.get(line) {
+            Some(branch) if branch.count > 0 => {
+                let _ = writeln!(
+                    xml,
+                    "            <line number=\"{line}\" hits=\"{hits}\" branch=\"true\" condition-coverage=\"{:.0}% ({}/{})\"/>",
+                    branch.percent(),
+                    branch.covered,
+                    branch.count,
+                );
+            }
+            _ => {
+                let _ = writeln!(
+                    xml,
+                    "            <line number=\"{line}\" hits=\"{hits}\" branch=\"false\"/>"
+                );
+            }
+        }
+    }
+    xml.push_str("          </lines>\n        </class>\n");
+}
+
+/// Escapes the XML/HTML special characters of `s`
+pub fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRACEFILE: &str = "\
+SF:/src/lib/parse.c
+FN:3,parse
+FN:10,unused
+FNDA:5,parse
+FNDA:0,unused
+FNF:2
+FNH:1
+BRDA:4,0,0,5
+BRDA:4,0,1,0
+DA:3,5
+DA:4,5
+DA:10,0
+BRF:2
+BRH:1
+LF:3
+LH:2
+end_of_record
+SF:/src/main.c
+FN:1,main
+FNDA:1,main
+DA:1,1
+DA:2,1
+end_of_record
+";
+
+    #[test]
+    fn test_parse_lcov() {
+        let files = parse_lcov(TRACEFILE).unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(
+            files[0].line_counter(),
+            Counter {
+                count: 3,
+                covered: 2
+            }
+        );
+        assert_eq!(files[0].function_counter().covered, 1);
+        assert_eq!(
+            files[0].branch_counter(),
+            Counter {
+                count: 2,
+                covered: 1
+            }
+        );
+        assert!((files[1].line_counter().percent() - 100.0).abs() < f64::EPSILON);
+
+        assert!(parse_lcov("SF:/a.c\nDA:1,1\n").is_err());
+        assert!(parse_lcov("DA:1,1\n").is_err());
+        assert!(parse_lcov("SF:/a.c\nDA:x,1\nend_of_record\n").is_err());
+    }
+
+    #[test]
+    fn test_exports() {
+        let files = parse_lcov(TRACEFILE).unwrap();
+
+        let json: serde_json::Value =
+            serde_json::from_str(&to_json_summary(&files).unwrap()).unwrap();
+        assert_eq!(json["totals"]["lines"]["count"], 5);
+        assert_eq!(json["totals"]["lines"]["percent"], 80.0);
+        assert_eq!(json["files"][0]["branches"]["percent"], 50.0);
+
+        let xml = to_cobertura(&files, 0);
+        assert!(xml.contains("lines-covered=\"4\" lines-valid=\"5\""));
+        assert!(xml.contains("<package name=\".src.lib\""));
+        assert!(xml.contains("filename=\"src/lib/parse.c\""));
+        assert!(xml.contains("condition-coverage=\"50% (1/2)\""));
+    }
+
+    #[test]
+    fn test_merge_files() {
+        let files = parse_lcov(TRACEFILE).unwrap();
+        let extra = FileCoverage {
+            path: PathBuf::from("/src/extra.c"),
+            lines: BTreeMap::from([(1, 1)]),
+            ..FileCoverage::default()
+        };
+        let merged = merge_files(&files, &[files[0].clone(), extra]);
+        assert_eq!(merged.len(), 3);
+        let first = merged.iter().find(|f| f.path == files[0].path).unwrap();
+        assert_eq!(first.line_counter(), files[0].line_counter());
+        assert_eq!(
+            first.lines.values().sum::<u64>(),
+            2 * files[0].lines.values().sum::<u64>()
+        );
+    }
+
+    #[test]
+    fn test_report_format() {
+        assert_eq!("HTML".parse::<ReportFormat>().unwrap(), ReportFormat::Html);
+        assert_eq!(
+            "xml".parse::<ReportFormat>().unwrap(),
+            ReportFormat::Cobertura
+        );
+        assert!("pdf".parse::<ReportFormat>().is_err());
+        assert_eq!(ReportFormat::Text.file_name(None), None);
+        assert_eq!(
+            ReportFormat::Json.file_name(Some(2)).as_deref(),
+            Some("coverage_summary_2.json")
+        );
+    }
+}
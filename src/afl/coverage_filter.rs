@@ -0,0 +1,119 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+
+/// Source file filter applied to HTML reports, text reports and exports alike
+#[derive(Debug, Clone, Default)]
+pub struct SourceFilter {
+    include: Vec<Regex>,
+    exclude: Vec<Regex>,
+}
+
+impl SourceFilter {
+    /// Creates a filter from include and exclude path regexes
+    ///
+    /// A file is reported if it matches any include regex, or if there are none, and does
+    /// not match any exclude regex.
+    ///
+    /// # Errors
+    /// * If a regex is invalid
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self> {
+        let compile = |patterns: &[String]| {
+            patterns
+                .iter()
+                .map(|p| Regex::new(p).with_context(|| format!("Invalid path regex '{p}'")))
+                .collect::<Result<Vec<_>>>()
+        };
+        Ok(Self {
+            include: compile(include)?,
+            exclude: compile(exclude)?,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    /// Whether the source file at `path` is reported
+    pub fn matches(&self, path: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|re| re.is_match(path)))
+            && !self.exclude.iter().any(|re| re.is_match(path))
+    }
+
+    /// Drops the records of all files that do not match from an lcov tracefile
+    pub fn filter_tracefile(&self, tracefile: &str) -> String {
+        if self.is_empty() {
+            return tracefile.to_string();
+        }
+        let mut filtered = String::with_capacity(tracefile.len());
+        let mut keep = true;
+        for line in tracefile.lines() {
+            if let Some(path) = line.strip_prefix("SF:") {
+                keep = self.matches(path);
+            }
+            if keep {
+                filtered.push_str(line);
+                filtered.push('\n');
+            }
+            if line == "end_of_record" {
+                keep = true;
+            }
+        }
+        filtered
+    }
+
+    /// Matching source files of an lcov tracefile
+    pub fn sources(&self, tracefile: &str) -> Vec<String> {
+        tracefile
+            .lines()
+            .filter_map(|line| line.strip_prefix("SF:"))
+            .filter(|path| self.matches(path))
+            .map(String::from)
+            .collect()
+    }
+
+    /// Equivalent `gcovr` arguments
+    pub fn gcovr_args(&self) -> Vec<String> {
+        let include = self
+            .include
+            .iter()
+            .flat_map(|re| ["--filter".to_string(), re.as_str().to_string()]);
+        let exclude = self
+            .exclude
+            .iter()
+            .flat_map(|re| ["--exclude".to_string(), re.as_str().to_string()]);
+        include.chain(exclude).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRACEFILE: &str = "SF:/src/parser.c\nDA:1,1\nend_of_record\nSF:/src/third_party/zlib/inflate.c\nDA:2,0\nend_of_record\nSF:/usr/include/stdio.h\nDA:3,4\nend_of_record\n";
+
+    #[test]
+    fn test_source_filter() {
+        let filter = SourceFilter::new(&["^/src/".into()], &["third_party".into()]).unwrap();
+        assert!(filter.matches("/src/parser.c"));
+        assert!(!filter.matches("/src/third_party/zlib/inflate.c"));
+        assert!(!filter.matches("/usr/include/stdio.h"));
+
+        assert_eq!(
+            filter.filter_tracefile(TRACEFILE),
+            "SF:/src/parser.c\nDA:1,1\nend_of_record\n"
+        );
+        assert_eq!(filter.sources(TRACEFILE), vec!["/src/parser.c"]);
+        assert_eq!(
+            filter.gcovr_args(),
+            vec!["--filter", "^/src/", "--exclude", "third_party"]
+        );
+
+        let exclude_only = SourceFilter::new(&[], &["^/usr/".into()]).unwrap();
+        assert_eq!(exclude_only.sources(TRACEFILE).len(), 2);
+        assert_eq!(
+            SourceFilter::default().filter_tracefile(TRACEFILE),
+            TRACEFILE
+        );
+        assert!(SourceFilter::new(&["(".into()], &[]).is_err());
+    }
+}
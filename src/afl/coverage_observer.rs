@@ -0,0 +1,105 @@
+use std::{fmt::Debug, path::Path};
+
+use crate::afl::coverage_replay::ReplaySummary;
+use crate::afl::coverage_result::{Artifact, CollectionResult, ScopeResult};
+use crate::afl::target_exec::ExecResult;
+
+/// Receives progress events of a `CoverageCollector`
+///
+/// All methods default to doing nothing. Per-file events are emitted from the replay
+/// worker threads.
+pub trait CoverageObserver: Debug + Send + Sync {
+    /// General progress message
+    fn info(&self, _message: &str) {}
+
+    /// Problem that does not abort the collection
+    fn warning(&self, _message: &str) {}
+
+    /// `pending` of the `total` queue entries of `scope` are about to be replayed
+    fn replay_started(&self, _scope: &str, _pending: usize, _total: usize) {}
+
+    /// A queue entry was replayed, `has_coverage` tells whether raw coverage was written
+    fn file_processed(&self, _result: &ExecResult, _has_coverage: bool) {}
+
+    /// The target could not be executed with a queue entry
+    fn file_failed(&self, _input: &Path, _error: &anyhow::Error) {}
+
+    /// The queue entries of a scope were replayed and merged
+    fn replay_finished(&self, _scope: &ScopeResult) {}
+
+    /// A report or summary was written
+    fn artifact_written(&self, _artifact: &Artifact) {}
+
+    /// All replays of a collection are done
+    fn replay_summary(&self, _summary: &ReplaySummary) {}
+
+    /// The collection finished successfully
+    fn finished(&self, _result: &CollectionResult) {}
+}
+
+/// Observer that ignores all events
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SilentObserver;
+
+impl CoverageObserver for SilentObserver {}
+
+/// Observer that prints progress to stdout, as `aflr cov` does
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConsoleObserver;
+
+impl CoverageObserver for ConsoleObserver {
+    fn info(&self, message: &str) {
+        println!("[*] {message}");
+    }
+
+    fn warning(&self, message: &str) {
+        println!("[!] {message}");
+    }
+
+    fn replay_started(&self, scope: &str, pending: usize, total: usize) {
+        if pending == total {
+            println!("[+] Processing {total} queue files of {scope}");
+        } else {
+            println!("[+] Processing {pending} new of {total} queue files of {scope}");
+        }
+    }
+
+    fn file_failed(&self, input: &Path, error: &anyhow::Error) {
+        eprintln!("[-] Failed to process {}: {error}", input.display());
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn replay_finished(&self, scope: &ScopeResult) {
+        if scope.replayed == 0 {
+            println!(
+                "[*] No new queue entries for {}, reusing {}",
+                scope.scope,
+                scope.profile.display()
+            );
+            return;
+        }
+        let secs = scope.elapsed.as_secs_f64();
+        println!(
+            "  [+] Finished in {secs:.1}s ({:.1} files/sec)",
+            scope.replayed as f64 / secs
+        );
+    }
+
+    fn artifact_written(&self, artifact: &Artifact) {
+        println!(
+            "[*] Wrote {} to: {}",
+            artifact.kind,
+            artifact.path.display()
+        );
+    }
+
+    fn replay_summary(&self, summary: &ReplaySummary) {
+        summary.print();
+    }
+
+    fn finished(&self, result: &CollectionResult) {
+        if let Some(contribution) = &result.contribution {
+            contribution.print();
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::afl::target_exec::{ExecOutcome, ExecResult};
+
+/// Name of the replay summary written next to the coverage report
+pub const REPLAY_SUMMARY: &str = "coverage_replay_summary.json";
+/// Default timeout of a single replay in ms, coverage builds run slower than AFL++ builds
+pub const DEFAULT_REPLAY_TIMEOUT_MS: u64 = 5000;
+
+/// Queue entry that crashed the coverage binary
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CrashedInput {
+    pub input: PathBuf,
+    pub signal: i32,
+}
+
+/// Inputs whose replay did not contribute coverage, or only partially
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReplaySummary {
+    /// Number of executed queue entries
+    pub executed: usize,
+    /// Executions that exited with a non-zero status, their coverage is still recorded
+    pub nonzero_exits: usize,
+    /// Inputs that exceeded the timeout and were killed
+    pub timed_out: Vec<PathBuf>,
+    /// Inputs that were terminated by a signal
+    pub crashed: Vec<CrashedInput>,
+    /// Inputs that exited but wrote no raw coverage, e.g. due to `_exit` or a failed spawn
+    pub no_coverage: Vec<PathBuf>,
+}
+
+impl ReplaySummary {
+    /// Records one execution, `has_coverage` tells whether raw coverage was written
+    pub fn record(&mut self, result: &ExecResult, has_coverage: bool) {
+        self.executed += 1;
+        match result.outcome {
+            ExecOutcome::TimedOut => self.timed_out.push(result.input.clone()),
+            ExecOutcome::Crashed(signal) => self.crashed.push(CrashedInput {
+                input: result.input.clone(),
+                signal,
+            }),
+            ExecOutcome::Exited(code) => {
+                if code != 0 {
+                    self.nonzero_exits += 1;
+                }
+                if !has_coverage {
+                    self.no_coverage.push(result.input.clone());
+                }
+            }
+        }
+    }
+
+    /// Records an input the target could not be executed with
+    pub fn record_failure(&mut self, input: &Path) {
+        self.executed += 1;
+        self.no_coverage.push(input.to_path_buf());
+    }
+
+    /// Adds the executions of another replay
+    pub fn extend(&mut self, other: Self) {
+        self.executed += other.executed;
+        self.nonzero_exits += other.nonzero_exits;
+        self.timed_out.extend(other.timed_out);
+        self.crashed.extend(other.crashed);
+        self.no_coverage.extend(other.no_coverage);
+    }
+
+    /// Whether every executed input contributed coverage
+    pub fn is_clean(&self) -> bool {
+        self.timed_out.is_empty() && self.crashed.is_empty() && self.no_coverage.is_empty()
+    }
+
+    /// Prints a one-line summary, with a warning if inputs were lost
+    pub fn print(&self) {
+        if self.is_clean() {
+            println!(
+                "[+] Replayed {} queue entries without timeouts or crashes",
+                self.executed
+            );
+        } else {
+            println!(
+                "[!] Replayed {} queue entries: {} timed out, {} crashed, {} without coverage data",
+                self.executed,
+                self.timed_out.len(),
+                self.crashed.len(),
+                self.no_coverage.len()
+            );
+        }
+    }
+
+    /// Writes the summary as JSON into `dir`
+    ///
+    /// # Errors
+    /// * If the summary cannot be serialized or written
+    pub fn write(&self, dir: &Path) -> Result<PathBuf> {
+        let path = dir.join(REPLAY_SUMMARY);
+        let json = serde_json::to_string_pretty(self).context("Failed to serialize summary")?;
+        fs::write(&path, json).with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn result(input: &str, outcome: ExecOutcome) -> ExecResult {
+        ExecResult {
+            input: PathBuf::from(input),
+            outcome,
+            exec_time: Duration::from_millis(1),
+            peak_rss_mb: 0,
+        }
+    }
+
+    #[test]
+    fn test_replay_summary() {
+        let mut summary = ReplaySummary::default();
+        summary.record(&result("ok", ExecOutcome::Exited(0)), true);
+        summary.record(&result("err", ExecOutcome::Exited(1)), true);
+        summary.record(&result("quit", ExecOutcome::Exited(0)), false);
+        assert_eq!(summary.no_coverage.len(), 1);
+
+        let mut other = ReplaySummary::default();
+        other.record(&result("hang", ExecOutcome::TimedOut), false);
+        other.record(&result("segv", ExecOutcome::Crashed(11)), false);
+        other.record_failure(Path::new("missing"));
+        summary.extend(other);
+
+        assert_eq!(summary.executed, 6);
+        assert_eq!(summary.nonzero_exits, 1);
+        assert_eq!(summary.timed_out, vec![PathBuf::from("hang")]);
+        assert_eq!(summary.crashed[0].signal, 11);
+        assert_eq!(
+            summary.no_coverage,
+            vec![PathBuf::from("quit"), PathBuf::from("missing")]
+        );
+        assert!(!summary.is_clean());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = summary.write(dir.path()).unwrap();
+        assert!(fs::read_to_string(path)
+            .unwrap()
+            .contains("\"timed_out\": [\n    \"hang\""));
+    }
+}
@@ -0,0 +1,95 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::afl::coverage_contrib::ContributionReport;
+use crate::afl::coverage_export::{CoverageTotals, ReportFormat};
+use crate::afl::coverage_replay::ReplaySummary;
+use crate::afl::coverage_timeline::Timeline;
+
+/// Kind of a file or directory written during coverage collection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArtifactKind {
+    /// HTML report directory
+    HtmlReport,
+    /// Exported report in the given format
+    Export(ReportFormat),
+    /// Summary of timed out, crashing and coverage-less inputs
+    ReplaySummary,
+    /// Per-instance contribution report of a split collection
+    Contribution,
+    /// Coverage timeline as CSV or JSON
+    Timeline,
+    /// HTML chart of the coverage timeline
+    TimelineChart,
+    /// HTML view of the lines only crashing inputs reach
+    CrashView,
+}
+
+impl fmt::Display for ArtifactKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::HtmlReport => write!(f, "HTML coverage report"),
+            Self::Export(format) => write!(f, "{format} coverage report"),
+            Self::ReplaySummary => write!(f, "replay summary"),
+            Self::Contribution => write!(f, "coverage contribution report"),
+            Self::Timeline => write!(f, "coverage timeline"),
+            Self::TimelineChart => write!(f, "timeline chart"),
+            Self::CrashView => write!(f, "crash-only coverage view"),
+        }
+    }
+}
+
+/// File or directory written during coverage collection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Artifact {
+    pub kind: ArtifactKind,
+    pub path: PathBuf,
+}
+
+/// Replay of a single report scope, either one instance or all of them
+#[derive(Debug, Clone)]
+pub struct ScopeResult {
+    /// Instance name, or `all` for a unified report
+    pub scope: String,
+    /// Number of queue entries of the scope
+    pub queue_files: usize,
+    /// Number of queue entries replayed, lower than `queue_files` with a warm cache
+    pub replayed: usize,
+    /// Wall-clock time of replaying and merging
+    pub elapsed: Duration,
+    /// Merged profile of the scope
+    pub profile: PathBuf,
+    /// Line, function and branch totals, if the profile was exported
+    pub totals: Option<CoverageTotals>,
+}
+
+/// Outcome of `CoverageCollector::collect`
+#[derive(Debug, Clone, Default)]
+pub struct CollectionResult {
+    pub scopes: Vec<ScopeResult>,
+    pub artifacts: Vec<Artifact>,
+    pub replay: ReplaySummary,
+    pub contribution: Option<ContributionReport>,
+    pub timeline: Option<Timeline>,
+}
+
+impl CollectionResult {
+    /// Paths of the generated HTML reports and exports
+    pub fn reports(&self) -> impl Iterator<Item = &Path> {
+        self.artifacts
+            .iter()
+            .filter(|a| matches!(a.kind, ArtifactKind::HtmlReport | ArtifactKind::Export(_)))
+            .map(|a| a.path.as_path())
+    }
+
+    /// Coverage totals of the unified report
+    pub fn totals(&self) -> Option<CoverageTotals> {
+        match self.scopes.as_slice() {
+            [scope] => scope.totals,
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use std::{
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::afl::coverage::CoverageCollector;
+use crate::afl::coverage_export::CoverageTotals;
+use crate::utils::system::{any_fuzzer_alive, spawn_detached};
+
+/// Directory in the AFL++ output directory the snapshots are stored in
+pub const SNAPSHOT_DIR: &str = "coverage_snapshots";
+/// Log of the background snapshot process
+const WATCH_LOG: &str = "watch.log";
+/// Niceness of the background snapshot process
+const WATCH_NICENESS: libc::c_int = 19;
+
+/// Source coverage of a live campaign at one point in time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoverageSnapshot {
+    /// Unix timestamp the snapshot was taken at
+    pub timestamp: i64,
+    /// Number of queue entries the coverage was collected from
+    pub queue_entries: usize,
+    pub totals: CoverageTotals,
+}
+
+impl CoverageSnapshot {
+    pub fn new(queue_entries: usize, totals: CoverageTotals) -> Self {
+        Self {
+            timestamp: chrono::Utc::now().timestamp(),
+            queue_entries,
+            totals,
+        }
+    }
+
+    /// Covered lines in percent
+    pub fn line_percent(&self) -> f64 {
+        self.totals.lines.percent()
+    }
+
+    /// Covered functions in percent
+    pub fn function_percent(&self) -> f64 {
+        self.totals.functions.percent()
+    }
+
+    /// Writes the snapshot as `coverage_snapshots/<timestamp>.json`
+    ///
+    /// # Errors
+    /// * If the snapshot cannot be serialized or written
+    pub fn write(&self, afl_out: &Path) -> Result<PathBuf> {
+        let dir = afl_out.join(SNAPSHOT_DIR);
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("{}.json", self.timestamp));
+        let json = serde_json::to_string_pretty(self).context("Failed to serialize snapshot")?;
+        fs::write(&path, json).with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    }
+
+    /// Reads the most recent snapshot of a campaign, if any
+    pub fn latest(afl_out: &Path) -> Option<Self> {
+        fs::read_dir(afl_out.join(SNAPSHOT_DIR))
+            .ok()?
+            .filter_map(std::result::Result::ok)
+            .filter_map(|entry| {
+                let name = entry.file_name();
+                let timestamp: i64 = name.to_str()?.strip_suffix(".json")?.parse().ok()?;
+                Some((timestamp, entry.path()))
+            })
+            .max_by_key(|(timestamp, _)| *timestamp)
+            .and_then(|(_, path)| serde_json::from_str(&fs::read_to_string(path).ok()?).ok())
+    }
+}
+
+/// Collects coverage every `interval` until no instance of the campaign is alive anymore
+///
+/// The process lowers its own priority first, so the snapshots do not slow down the
+/// fuzzers. Failed snapshots are logged and retried at the next interval.
+///
+/// # Errors
+/// * If the priority cannot be lowered
+pub fn watch(collector: &mut CoverageCollector, afl_out: &Path, interval: Duration) -> Result<()> {
+    if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, WATCH_NICENESS) } != 0 {
+        return Err(std::io::Error::last_os_error()).context("Failed to lower the priority");
+    }
+    println!(
+        "[*] Taking coverage snapshots every {}s",
+        interval.as_secs()
+    );
+    loop {
+        thread::sleep(interval);
+        if !any_fuzzer_alive(afl_out) {
+            println!("[*] No fuzzer alive anymore, stopping coverage snapshots");
+            return Ok(());
+        }
+        match collector.collect() {
+            Ok(result) => {
+                let queue_entries = result.scopes.iter().map(|s| s.queue_files).sum();
+                let snapshot =
+                    CoverageSnapshot::new(queue_entries, result.totals().unwrap_or_default());
+                let path = snapshot.write(afl_out)?;
+                println!(
+                    "[+] Snapshot: {:.2}% lines, {:.2}% functions covered ({})",
+                    snapshot.line_percent(),
+                    snapshot.function_percent(),
+                    path.display()
+                );
+            }
+            Err(e) => eprintln!("[-] Coverage snapshot failed: {e:#}"),
+        }
+    }
+}
+
+/// Starts `aflr cov --watch` as a detached background process for a campaign
+///
+/// `cov_args` are the remaining `aflr cov` arguments, e.g. the coverage binary and the
+/// target arguments. Returns the PID and the log file of the process.
+///
+/// # Errors
+/// * If the snapshot directory cannot be created or the process cannot be started
+pub fn spawn_watcher(
+    afl_out: &Path,
+    interval: Duration,
+    cov_args: &[String],
+) -> Result<(u32, PathBuf)> {
+    let dir = afl_out.join(SNAPSHOT_DIR);
+    fs::create_dir_all(&dir)?;
+    let log_path = dir.join(WATCH_LOG);
+
+    let mut args: Vec<OsString> = vec![
+        "cov".into(),
+        "--watch".into(),
+        format!("{}s", interval.as_secs()).into(),
+        "-i".into(),
+        afl_out.as_os_str().to_owned(),
+    ];
+    args.extend(cov_args.iter().map(Into::into));
+    let pid = spawn_detached(&args, &log_path)
+        .context("Failed to start the coverage snapshot process")?;
+    Ok((pid, log_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::afl::coverage_export::Counter;
+
+    #[test]
+    fn test_snapshots() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(CoverageSnapshot::latest(dir.path()).is_none());
+
+        let totals = CoverageTotals {
+            lines: Counter {
+                count: 200,
+                covered: 50,
+            },
+            ..CoverageTotals::default()
+        };
+        for (timestamp, entries) in [(100, 3), (300, 9), (200, 5)] {
+            CoverageSnapshot {
+                timestamp,
+                queue_entries: entries,
+                totals,
+            }
+            .write(dir.path())
+            .unwrap();
+        }
+        fs::write(dir.path().join(SNAPSHOT_DIR).join(WATCH_LOG), "").unwrap();
+
+        let latest = CoverageSnapshot::latest(dir.path()).unwrap();
+        assert_eq!(latest.timestamp, 300);
+        assert_eq!(latest.queue_entries, 9);
+        assert!((latest.line_percent() - 25.0).abs() < f64::EPSILON);
+        assert!(latest.function_percent().abs() < f64::EPSILON);
+    }
+}
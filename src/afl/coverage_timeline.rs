@@ -0,0 +1,323 @@
+use std::{
+    fmt::Write as _,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::afl::coverage_export::{Counter, CoverageTotals};
+use crate::tui::Tui;
+
+/// Upper bound of timeline points, each point costs a profile merge and an export
+pub const MAX_TIMELINE_POINTS: u64 = 200;
+/// Number of points the interval is derived from if none is given
+const DEFAULT_TIMELINE_POINTS: u64 = 50;
+
+/// Discovery time of a queue entry in ms, taken from the `time:` field of its file name
+///
+/// Seeds and entries of older AFL++ versions without the field are treated as found at start.
+pub fn queue_entry_time(path: &Path) -> u64 {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .and_then(|name| {
+            name.split(',')
+                .find_map(|part| part.strip_prefix("time:"))
+                .and_then(|t| t.parse().ok())
+        })
+        .unwrap_or(0)
+}
+
+/// Queue entries discovered up to `end_ms`, but after the previous bucket
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimelineBucket {
+    pub end_ms: u64,
+    pub entries: Vec<PathBuf>,
+}
+
+/// Groups queue entries by discovery time into buckets of `interval`
+///
+/// Without an interval, or if the interval would exceed `MAX_TIMELINE_POINTS`, the interval
+/// is widened to cover the campaign with a bounded number of points.
+pub fn bucketize(queue_files: &[PathBuf], interval: Option<Duration>) -> Vec<TimelineBucket> {
+    let mut entries: Vec<(u64, &PathBuf)> = queue_files
+        .iter()
+        .map(|f| (queue_entry_time(f), f))
+        .collect();
+    entries.sort();
+    let Some(&(max_ms, _)) = entries.last() else {
+        return Vec::new();
+    };
+
+    let min_interval = max_ms.div_ceil(MAX_TIMELINE_POINTS).max(1000);
+    let interval_ms = match interval.map(|i| u64::try_from(i.as_millis()).unwrap_or(u64::MAX)) {
+        Some(ms) if ms < min_interval => {
+            println!(
+                "[!] Timeline interval too small for the campaign length, using {}",
+                Tui::format_duration(&Duration::from_millis(min_interval))
+            );
+            min_interval
+        }
+        Some(ms) => ms,
+        None => max_ms.div_ceil(DEFAULT_TIMELINE_POINTS).max(1000),
+    };
+
+    let mut buckets: Vec<TimelineBucket> = Vec::new();
+    for (time, path) in entries {
+        let end_ms = time.div_ceil(interval_ms).max(1) * interval_ms;
+        // Fill gaps so the curve has a point for every interval
+        while buckets.last().is_none_or(|b| b.end_ms < end_ms) {
+            let next = buckets
+                .last()
+                .map_or(interval_ms, |b| b.end_ms + interval_ms);
+            buckets.push(TimelineBucket {
+                end_ms: next,
+                entries: Vec::new(),
+            });
+        }
+        if let Some(bucket) = buckets.last_mut() {
+            bucket.entries.push(path.clone());
+        }
+    }
+    buckets
+}
+
+/// Cumulative coverage after all entries up to `time_secs` were replayed
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct TimelinePoint {
+    pub time_secs: u64,
+    pub entries: usize,
+    pub lines: Counter,
+    pub functions: Counter,
+    pub branches: Counter,
+}
+
+impl TimelinePoint {
+    pub fn new(time_secs: u64, entries: usize, totals: CoverageTotals) -> Self {
+        Self {
+            time_secs,
+            entries,
+            lines: totals.lines,
+            functions: totals.functions,
+            branches: totals.branches,
+        }
+    }
+}
+
+/// Coverage over the campaign time
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Timeline {
+    pub points: Vec<TimelinePoint>,
+    /// Time in seconds after which line and branch coverage did not increase anymore
+    pub plateau_secs: Option<u64>,
+}
+
+impl Timeline {
+    pub fn new(points: Vec<TimelinePoint>) -> Self {
+        let plateau_secs = points
+            .windows(2)
+            .rev()
+            .find(|w| {
+                w[1].lines.covered > w[0].lines.covered
+                    || w[1].branches.covered > w[0].branches.covered
+            })
+            .map_or_else(
+                || points.first().map(|p| p.time_secs),
+                |w| Some(w[1].time_secs),
+            );
+        Self {
+            points,
+            plateau_secs,
+        }
+    }
+
+    /// Renders the curve as CSV with one row per point
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "time_secs,entries,lines_covered,lines_total,line_percent,functions_covered,functions_total,function_percent,branches_covered,branches_total,branch_percent\n",
+        );
+        for p in &self.points {
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{:.2},{},{},{:.2},{},{},{:.2}",
+                p.time_secs,
+                p.entries,
+                p.lines.covered,
+                p.lines.count,
+                p.lines.percent(),
+                p.functions.covered,
+                p.functions.count,
+                p.functions.percent(),
+                p.branches.covered,
+                p.branches.count,
+                p.branches.percent(),
+            );
+        }
+        csv
+    }
+
+    /// Renders a standalone HTML page with an SVG chart of line and branch coverage
+    #[allow(clippy::cast_precision_loss)]
+    pub fn to_html(&self) -> String {
+        const WIDTH: f64 = 800.0;
+        const HEIGHT: f64 = 300.0;
+        const PAD: f64 = 40.0;
+
+        let max_time = self.points.last().map_or(1, |p| p.time_secs.max(1)) as f64;
+        let x = |secs: u64| PAD + secs as f64 / max_time * (WIDTH - 2.0 * PAD);
+        let y = |pct: f64| HEIGHT - PAD - pct / 100.0 * (HEIGHT - 2.0 * PAD);
+        let polyline = |metric: fn(&TimelinePoint) -> f64| {
+            self.points
+                .iter()
+                .map(|p| format!("{:.1},{:.1}", x(p.time_secs), y(metric(p))))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{WIDTH}\" height=\"{HEIGHT}\" font-family=\"sans-serif\" font-size=\"11\">\n"
+        );
+        for pct in [0, 25, 50, 75, 100] {
+            let _ = writeln!(
+                svg,
+                "<line x1=\"{PAD}\" x2=\"{:.1}\" y1=\"{y:.1}\" y2=\"{y:.1}\" stroke=\"#ddd\"/><text x=\"4\" y=\"{:.1}\">{pct}%</text>",
+                WIDTH - PAD,
+                y(f64::from(pct)) + 4.0,
+                y = y(f64::from(pct)),
+            );
+        }
+        let _ = writeln!(
+            svg,
+            "<text x=\"{PAD}\" y=\"{:.1}\">0s</text><text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"end\">{}</text>",
+            HEIGHT - 10.0,
+            WIDTH - PAD,
+            HEIGHT - 10.0,
+            Tui::format_duration(&Duration::from_secs_f64(max_time)),
+        );
+        if let Some(plateau) = self.plateau_secs {
+            let _ = writeln!(
+                svg,
+                "<line x1=\"{x:.1}\" x2=\"{x:.1}\" y1=\"{PAD}\" y2=\"{:.1}\" stroke=\"#999\" stroke-dasharray=\"4\"/><text x=\"{:.1}\" y=\"{:.1}\">plateau</text>",
+                HEIGHT - PAD,
+                x(plateau) + 4.0,
+                PAD + 10.0,
+                x = x(plateau),
+            );
+        }
+        let _ = writeln!(
+            svg,
+            "<polyline fill=\"none\" stroke=\"#1f77b4\" stroke-width=\"2\" points=\"{}\"/>",
+            polyline(|p| p.lines.percent())
+        );
+        let _ = writeln!(
+            svg,
+            "<polyline fill=\"none\" stroke=\"#ff7f0e\" stroke-width=\"2\" points=\"{}\"/>",
+            polyline(|p| p.branches.percent())
+        );
+        svg.push_str("</svg>\n");
+
+        let plateau = self.plateau_secs.map_or_else(
+            || "-".to_string(),
+            |s| Tui::format_duration(&Duration::from_secs(s)),
+        );
+        format!(
+            "<!doctype html>\n<html><head><meta charset=\"utf-8\"><title>Coverage timeline</title></head>\n<body>\n<h2>Coverage timeline</h2>\n<p><span style=\"color:#1f77b4\">&#9632; line</span> <span style=\"color:#ff7f0e\">&#9632; branch</span> &mdash; coverage last increased at {plateau}</p>\n{svg}</body></html>\n"
+        )
+    }
+
+    /// Writes the CSV and JSON curve into `dir`, returns the paths written
+    ///
+    /// # Errors
+    /// * If a file cannot be written
+    pub fn write(&self, dir: &Path) -> Result<[PathBuf; 2]> {
+        let csv_path = dir.join("coverage_timeline.csv");
+        let json_path = dir.join("coverage_timeline.json");
+        std::fs::write(&csv_path, self.to_csv())
+            .with_context(|| format!("Failed to write {}", csv_path.display()))?;
+        let json = serde_json::to_string_pretty(self).context("Failed to serialize timeline")?;
+        std::fs::write(&json_path, json)
+            .with_context(|| format!("Failed to write {}", json_path.display()))?;
+        Ok([csv_path, json_path])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(time_secs: u64, lines: u64, branches: u64) -> TimelinePoint {
+        TimelinePoint {
+            time_secs,
+            entries: 0,
+            lines: Counter {
+                count: 100,
+                covered: lines,
+            },
+            functions: Counter::default(),
+            branches: Counter {
+                count: 10,
+                covered: branches,
+            },
+        }
+    }
+
+    #[test]
+    fn test_queue_entry_time() {
+        assert_eq!(
+            queue_entry_time(Path::new(
+                "/out/m/queue/id:000004,src:000001,time:5300,execs:99,op:havoc,rep:2,+cov"
+            )),
+            5300
+        );
+        assert_eq!(
+            queue_entry_time(Path::new("id:000000,time:0,execs:0,orig:seed")),
+            0
+        );
+        assert_eq!(queue_entry_time(Path::new("id:000001,orig:seed")), 0);
+    }
+
+    #[test]
+    fn test_bucketize() {
+        let files: Vec<PathBuf> = [0, 1500, 1900, 6200]
+            .iter()
+            .enumerate()
+            .map(|(i, t)| PathBuf::from(format!("id:{i:06},time:{t}")))
+            .collect();
+        let buckets = bucketize(&files, Some(Duration::from_secs(2)));
+        let ends: Vec<u64> = buckets.iter().map(|b| b.end_ms).collect();
+        assert_eq!(ends, vec![2000, 4000, 6000, 8000]);
+        let sizes: Vec<usize> = buckets.iter().map(|b| b.entries.len()).collect();
+        assert_eq!(sizes, vec![3, 0, 0, 1]);
+
+        // Too many points widen the interval
+        assert!(bucketize(&files, Some(Duration::from_millis(1))).len() <= 7);
+        assert!(bucketize(&[], None).is_empty());
+    }
+
+    #[test]
+    fn test_plateau_and_outputs() {
+        let timeline = Timeline::new(vec![
+            point(60, 10, 1),
+            point(120, 20, 2),
+            point(180, 20, 3),
+            point(240, 20, 3),
+        ]);
+        assert_eq!(timeline.plateau_secs, Some(180));
+        assert_eq!(
+            Timeline::new(vec![point(60, 10, 1), point(120, 10, 1)]).plateau_secs,
+            Some(60)
+        );
+
+        let csv = timeline.to_csv();
+        assert_eq!(csv.lines().count(), 5);
+        assert!(csv
+            .lines()
+            .nth(2)
+            .unwrap()
+            .starts_with("120,0,20,100,20.00,"));
+        let html = timeline.to_html();
+        assert!(html.contains("<polyline"));
+        assert!(html.contains("plateau"));
+    }
+}
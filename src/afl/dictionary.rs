@@ -0,0 +1,467 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::afl::preflight::list_seeds;
+use crate::afl::target_exec::{TargetRunner, DEFAULT_EXEC_TIMEOUT_MS};
+
+/// Directory in the AFL++ output directory generated dictionaries are written to
+pub const DICT_DIR: &str = "aflr_dicts";
+/// Longest token AFL++ accepts in a dictionary (`MAX_DICT_FILE` in `config.h`)
+pub const MAX_DICT_FILE: usize = 128;
+/// Shortest printable string that is considered a token candidate
+const MIN_STRING_LEN: usize = 4;
+/// Longest printable string that is considered a token candidate
+const MAX_STRING_LEN: usize = 32;
+/// Upper bound of tokens extracted per source to keep dictionaries focused
+const MAX_EXTRACTED_TOKENS: usize = 256;
+/// Length of the file header used as magic value candidate
+const MAGIC_LEN: usize = 4;
+/// Minimum number of tokens per subset when splitting a dictionary across instances
+const MIN_SUBSET_TOKENS: usize = 16;
+
+/// How dictionaries are built and handed out to the instances
+#[derive(Debug, Clone, Default)]
+pub struct DictionaryConfig {
+    /// User-provided dictionary files or directories
+    pub paths: Vec<PathBuf>,
+    /// Extract tokens from the target binary and seed corpus
+    pub auto_extract: bool,
+    /// Path to `libtokencap.so` to capture compared tokens at runtime
+    pub tokencap: Option<PathBuf>,
+    /// Give secondaries different subsets of the tokens instead of the full dictionary
+    pub split: bool,
+}
+
+impl DictionaryConfig {
+    /// Creates a config that passes the given dictionaries to every instance
+    pub fn new(paths: Vec<PathBuf>) -> Self {
+        Self {
+            paths,
+            ..Default::default()
+        }
+    }
+
+    /// Whether any dictionary is going to be used
+    pub fn is_enabled(&self) -> bool {
+        !self.paths.is_empty() || self.auto_extract || self.tokencap.is_some()
+    }
+
+    /// Whether the user dictionaries can be passed to AFL++ unmodified
+    pub fn is_passthrough(&self) -> bool {
+        !self.auto_extract && self.tokencap.is_none() && !self.split
+    }
+}
+
+/// A deduplicated set of dictionary tokens
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Dictionary {
+    tokens: Vec<Vec<u8>>,
+    seen: HashSet<Vec<u8>>,
+}
+
+impl Dictionary {
+    /// Creates a dictionary from raw tokens, dropping duplicates and invalid tokens
+    pub fn from_tokens<I: IntoIterator<Item = Vec<u8>>>(tokens: I) -> Self {
+        let mut dict = Self::default();
+        for token in tokens {
+            dict.insert(token);
+        }
+        dict
+    }
+
+    /// Adds a token, returns `false` if it is a duplicate or not a valid AFL++ token
+    pub fn insert(&mut self, token: Vec<u8>) -> bool {
+        if token.is_empty() || token.len() > MAX_DICT_FILE || self.seen.contains(&token) {
+            return false;
+        }
+        self.seen.insert(token.clone());
+        self.tokens.push(token);
+        true
+    }
+
+    /// Adds all tokens of `other`
+    pub fn extend(&mut self, other: Self) {
+        for token in other.tokens {
+            self.insert(token);
+        }
+    }
+
+    pub fn tokens(&self) -> &[Vec<u8>] {
+        &self.tokens
+    }
+
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// Loads a dictionary file or a directory with one token per file, as accepted by `-x`
+    ///
+    /// # Errors
+    /// * If the path does not exist or cannot be read
+    /// * If a line or token file is not valid AFL++ dictionary syntax
+    pub fn load(path: &Path) -> Result<Self> {
+        if path.is_dir() {
+            let mut dict = Self::default();
+            for file in list_seeds(path)? {
+                let token = fs::read(&file)
+                    .with_context(|| format!("Failed to read token file {}", file.display()))?;
+                if token.is_empty() || token.len() > MAX_DICT_FILE {
+                    bail!(
+                        "Token file {} must be between 1 and {MAX_DICT_FILE} bytes",
+                        file.display()
+                    );
+                }
+                dict.insert(token);
+            }
+            return Ok(dict);
+        }
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read dictionary {}", path.display()))?;
+        Self::parse(&content, true)
+            .with_context(|| format!("Invalid dictionary {}", path.display()))
+    }
+
+    /// Parses AFL++ dictionary syntax, i.e. lines like `name@level="value"` or `"value"`
+    ///
+    /// With `strict` set, invalid lines are an error instead of being skipped.
+    ///
+    /// # Errors
+    /// * If `strict` is set and a line is invalid
+    pub fn parse(content: &str, strict: bool) -> Result<Self> {
+        let mut dict = Self::default();
+        for (lineno, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match parse_line(line) {
+                Ok(token) => {
+                    dict.insert(token);
+                }
+                Err(e) if strict => bail!("Line {}: {e}", lineno + 1),
+                Err(_) => {}
+            }
+        }
+        Ok(dict)
+    }
+
+    /// Extracts printable string constants from a binary, similar to `strings`
+    ///
+    /// # Errors
+    /// * If the binary cannot be read
+    pub fn from_binary(path: &Path) -> Result<Self> {
+        let data =
+            fs::read(path).with_context(|| format!("Failed to read binary {}", path.display()))?;
+        let strings = printable_strings(&data)
+            .into_iter()
+            .filter(|s| !is_toolchain_string(s));
+        Ok(Self::from_tokens(strings.take(MAX_EXTRACTED_TOKENS)))
+    }
+
+    /// Extracts magic values and strings shared by multiple seeds
+    ///
+    /// File headers and strings become tokens when they appear in at least two seeds, or in
+    /// the only seed of a single-file corpus.
+    ///
+    /// # Errors
+    /// * If the seed directory or a seed cannot be read
+    pub fn from_seeds(seed_dir: &Path) -> Result<Self> {
+        let seeds = list_seeds(seed_dir)?;
+        let min_count = seeds.len().min(2);
+
+        let mut counts: HashMap<Vec<u8>, usize> = HashMap::new();
+        let mut order: Vec<Vec<u8>> = Vec::new();
+        for seed in &seeds {
+            let data =
+                fs::read(seed).with_context(|| format!("Failed to read {}", seed.display()))?;
+            let mut candidates: HashSet<Vec<u8>> = printable_strings(&data).into_iter().collect();
+            if data.len() >= MAGIC_LEN {
+                candidates.insert(data[..MAGIC_LEN].to_vec());
+            }
+            for candidate in candidates {
+                let count = counts.entry(candidate.clone()).or_insert(0);
+                if *count == 0 {
+                    order.push(candidate);
+                }
+                *count += 1;
+            }
+        }
+        order.sort();
+        Ok(Self::from_tokens(
+            order
+                .into_iter()
+                .filter(|t| counts[t] >= min_count)
+                .take(MAX_EXTRACTED_TOKENS),
+        ))
+    }
+
+    /// Runs the target over the seeds with `libtokencap` preloaded and collects the tokens
+    /// passed to `strcmp`, `memcmp` and friends
+    ///
+    /// # Errors
+    /// * If the library does not exist or the target cannot be executed
+    pub fn from_tokencap(
+        libtokencap: &Path,
+        target: &Path,
+        target_args: Vec<String>,
+        seed_dir: &Path,
+    ) -> Result<Self> {
+        if !libtokencap.is_file() {
+            bail!("libtokencap not found: {}", libtokencap.display());
+        }
+        let token_dir = tempfile::tempdir()?;
+        let token_file = token_dir.path().join("tokens.dict");
+        let runner = TargetRunner::new(
+            target,
+            target_args,
+            Duration::from_millis(DEFAULT_EXEC_TIMEOUT_MS),
+        )
+        .with_env("LD_PRELOAD", &libtokencap.to_string_lossy())
+        .with_env("AFL_TOKEN_FILE", &token_file.to_string_lossy());
+        for seed in list_seeds(seed_dir)? {
+            runner.run(&seed)?;
+        }
+        let content = fs::read_to_string(&token_file).unwrap_or_default();
+        Self::parse(&content, false)
+    }
+
+    /// Splits the tokens into `count` disjoint subsets after shuffling them
+    pub fn split<R: Rng>(&self, count: usize, rng: &mut R) -> Vec<Self> {
+        let count = count.max(1);
+        let mut tokens = self.tokens.clone();
+        tokens.shuffle(rng);
+        let mut subsets = vec![Self::default(); count];
+        for (i, token) in tokens.into_iter().enumerate() {
+            subsets[i % count].insert(token);
+        }
+        subsets
+    }
+
+    /// Number of subsets the dictionary can be split into for `secondaries` instances
+    pub fn subset_count(&self, secondaries: usize) -> usize {
+        secondaries.min(self.len() / MIN_SUBSET_TOKENS).max(1)
+    }
+
+    /// Serializes the tokens in AFL++ dictionary syntax
+    pub fn to_afl_format(&self) -> String {
+        let mut out = String::new();
+        for (i, token) in self.tokens.iter().enumerate() {
+            let _ = writeln!(out, "token_{i}=\"{}\"", escape(token));
+        }
+        out
+    }
+
+    /// Writes the dictionary in AFL++ syntax to `path`
+    ///
+    /// # Errors
+    /// * If the file cannot be written
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_afl_format())
+            .with_context(|| format!("Failed to write dictionary {}", path.display()))
+    }
+}
+
+/// Parses a single non-empty dictionary line into the raw token bytes
+fn parse_line(line: &str) -> Result<Vec<u8>> {
+    let value = match line.find('"') {
+        Some(0) => line,
+        Some(pos) => {
+            let (key, rest) = line.split_at(pos);
+            let key = key.trim_end();
+            let Some(key) = key.strip_suffix('=') else {
+                bail!("Expected '=' before the token value");
+            };
+            let name = key.trim_end().split('@').next().unwrap_or_default();
+            if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                bail!("Invalid token name '{name}'");
+            }
+            rest
+        }
+        None => bail!("Token value must be enclosed in double quotes"),
+    };
+
+    let Some(inner) = value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .filter(|_| value.len() >= 2)
+    else {
+        bail!("Token value must be enclosed in double quotes");
+    };
+
+    let mut token = Vec::with_capacity(inner.len());
+    let mut bytes = inner.bytes();
+    while let Some(b) = bytes.next() {
+        match b {
+            b'\\' => match bytes.next() {
+                Some(b'\\') => token.push(b'\\'),
+                Some(b'"') => token.push(b'"'),
+                Some(b'x') => {
+                    let hex: Vec<u8> = bytes.by_ref().take(2).collect();
+                    let byte = std::str::from_utf8(&hex)
+                        .ok()
+                        .filter(|h| h.len() == 2)
+                        .and_then(|h| u8::from_str_radix(h, 16).ok())
+                        .context("Invalid \\x escape sequence")?;
+                    token.push(byte);
+                }
+                _ => bail!("Invalid escape sequence"),
+            },
+            b'"' => bail!("Unescaped double quote in token"),
+            0x20..=0x7e => token.push(b),
+            _ => bail!("Non-printable character in token, use \\xNN instead"),
+        }
+    }
+    if token.is_empty() {
+        bail!("Empty token");
+    }
+    if token.len() > MAX_DICT_FILE {
+        bail!("Token exceeds {MAX_DICT_FILE} bytes");
+    }
+    Ok(token)
+}
+
+/// Escapes raw token bytes for AFL++ dictionary syntax
+fn escape(token: &[u8]) -> String {
+    let mut out = String::with_capacity(token.len());
+    for &b in token {
+        match b {
+            b'\\' => out.push_str("\\\\"),
+            b'"' => out.push_str("\\\""),
+            0x20..=0x7e => out.push(char::from(b)),
+            _ => {
+                let _ = write!(out, "\\x{b:02x}");
+            }
+        }
+    }
+    out
+}
+
+/// Collects runs of printable ASCII between `MIN_STRING_LEN` and `MAX_STRING_LEN` bytes
+fn printable_strings(data: &[u8]) -> Vec<Vec<u8>> {
+    data.split(|b| !(0x20..=0x7e).contains(b))
+        .filter(|s| (MIN_STRING_LEN..=MAX_STRING_LEN).contains(&s.len()))
+        .map(<[u8]>::to_vec)
+        .collect()
+}
+
+/// Filters out symbol and toolchain strings that are present in every binary
+fn is_toolchain_string(s: &[u8]) -> bool {
+    s.starts_with(b"_")
+        || s.starts_with(b".")
+        || s.windows(5).any(|w| w == b"GLIBC")
+        || s.windows(3).any(|w| w == b".so")
+        || s.windows(4).any(|w| w == b"GCC:")
+        || s.windows(5).any(|w| w == b"clang")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parse_and_escape() {
+        let dict = Dictionary::parse(
+            "# comment\nkw1=\"GET\"\nkw2@1 = \"\\x00\\xffA\"\n\"quote\\\"\\\\\"\n\nkw1=\"GET\"\n",
+            true,
+        )
+        .unwrap();
+        assert_eq!(
+            dict.tokens(),
+            &[
+                b"GET".to_vec(),
+                vec![0x00, 0xff, b'A'],
+                b"quote\"\\".to_vec()
+            ]
+        );
+
+        let roundtrip = Dictionary::parse(&dict.to_afl_format(), true).unwrap();
+        assert_eq!(roundtrip.tokens(), dict.tokens());
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        for line in [
+            "kw=GET",
+            "kw=\"\"",
+            "kw=\"\\q\"",
+            "kw=\"\\x0\"",
+            "k-w=\"a\"",
+            "kw \"a\"",
+            "kw=\"a\"b\"",
+        ] {
+            assert!(Dictionary::parse(line, true).is_err(), "{line}");
+            assert!(Dictionary::parse(line, false).unwrap().is_empty(), "{line}");
+        }
+        let too_long = format!("\"{}\"", "a".repeat(MAX_DICT_FILE + 1));
+        assert!(Dictionary::parse(&too_long, true).is_err());
+    }
+
+    #[test]
+    fn test_load_dir_and_file() {
+        let dir = tempdir().unwrap();
+        let tokens = dir.path().join("tokens");
+        fs::create_dir(&tokens).unwrap();
+        fs::write(tokens.join("a"), "IHDR").unwrap();
+        fs::write(tokens.join("b"), "IEND").unwrap();
+        assert_eq!(Dictionary::load(&tokens).unwrap().len(), 2);
+
+        let file = dir.path().join("bad.dict");
+        fs::write(&file, "ok=\"1\"\nbroken\n").unwrap();
+        let err = Dictionary::load(&file).unwrap_err();
+        assert!(format!("{err:#}").contains("Line 2"));
+        assert!(Dictionary::load(&dir.path().join("missing.dict")).is_err());
+    }
+
+    #[test]
+    fn test_extraction() {
+        let dir = tempdir().unwrap();
+        let binary = dir.path().join("bin");
+        fs::write(
+            &binary,
+            b"\x7fELF\x00\x00MAGIC_HEADER\x00GLIBC_2.2.5\x00_start\x00ab\x00",
+        )
+        .unwrap();
+        let from_bin = Dictionary::from_binary(&binary).unwrap();
+        assert_eq!(from_bin.tokens(), &[b"MAGIC_HEADER".to_vec()]);
+
+        let seeds = dir.path().join("seeds");
+        fs::create_dir(&seeds).unwrap();
+        fs::write(seeds.join("1"), b"\x89PNG\x00chunk-one\x00shared").unwrap();
+        fs::write(seeds.join("2"), b"\x89PNG\x00chunk-two\x00shared").unwrap();
+        let from_seeds = Dictionary::from_seeds(&seeds).unwrap();
+        assert_eq!(
+            from_seeds.tokens(),
+            &[b"shared".to_vec(), b"\x89PNG".to_vec()]
+        );
+    }
+
+    #[test]
+    fn test_split() {
+        let dict = Dictionary::from_tokens((0..40).map(|i| format!("tok{i}").into_bytes()));
+        assert_eq!(dict.subset_count(5), 2);
+        assert_eq!(dict.subset_count(0), 1);
+
+        let subsets = dict.split(2, &mut StdRng::seed_from_u64(1));
+        assert_eq!(subsets.len(), 2);
+        assert_eq!(subsets[0].len() + subsets[1].len(), 40);
+        assert!(subsets[0]
+            .tokens()
+            .iter()
+            .all(|t| !subsets[1].tokens().contains(t)));
+    }
+}
@@ -7,9 +7,33 @@ use std::{collections::HashSet, str::FromStr};
 use rand::Rng;
 
 use crate::afl::mode::Mode;
+use crate::afl::resources::testcache_size;
+use crate::afl::version::{Capabilities, Capability};
 use crate::utils::system;
 
+/// The kind of value an AFL++ environment variable accepts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnvVarKind {
+    /// Enabled by being set, e.g. `AFL_SKIP_CPUFREQ=1`
+    Flag,
+    /// Unsigned integer within an inclusive range
+    Number { min: u64, max: u64 },
+    /// One or more existing files separated by the given character
+    Paths(char),
+    /// An existing directory
+    Dir,
+    /// Free-form text
+    Text,
+}
+
+const fn num(min: u64, max: u64) -> EnvVarKind {
+    EnvVarKind::Number { min, max }
+}
+
 /// Enum representing the different AFL++ environment flags
+///
+/// Besides the flags `aflr` sets itself, this covers the runtime variables of `afl-fuzz` that
+/// can be set by the user, see <https://aflplus.plus/docs/env_variables/#3-settings-for-afl-fuzz>
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum AFLFlag {
     /// `AFL_AUTORESUME` will resume a fuzz run (same as providing -i -) for an existing out folder, even if a different -i was provided.
@@ -36,11 +60,144 @@ pub enum AFLFlag {
     /// Only perform the expensive cmplog feature for newly found test cases and not for test cases that are loaded on
     /// startup (-i in). This is an important feature to set when resuming a fuzzing session.
     CmplogOnlyNew,
+    /// `AFL_BENCH_JUST_ONE` exits after the first test case has been processed.
+    BenchJustOne,
+    /// `AFL_BENCH_UNTIL_CRASH` exits as soon as the first crash is found.
+    BenchUntilCrash,
+    /// `AFL_CRASH_EXITCODE` treats this exit code of the target as a crash.
+    CrashExitcode,
+    /// `AFL_CUSTOM_MUTATOR_LIBRARY` loads the `;` separated custom mutator libraries.
+    CustomMutatorLibrary,
+    /// `AFL_CUSTOM_MUTATOR_ONLY` disables all mutations except the custom mutator ones.
+    CustomMutatorOnly,
+    /// `AFL_CYCLE_SCHEDULES` switches the power schedule after every queue cycle.
+    CycleSchedules,
+    /// `AFL_DEBUG` enables debug output of AFL++.
+    Debug,
+    /// `AFL_DEBUG_CHILD` shows the output of the target.
+    DebugChild,
+    /// `AFL_EXIT_ON_SEED_ISSUES` exits if any seed crashes or times out.
+    ExitOnSeedIssues,
+    /// `AFL_EXIT_ON_TIME` exits after this many seconds without new coverage.
+    ExitOnTime,
+    /// `AFL_EXIT_WHEN_DONE` exits once all queue entries have been fuzzed and no new ones are found.
+    ExitWhenDone,
+    /// `AFL_FORCE_UI` shows the status screen even if no terminal is detected.
+    ForceUi,
+    /// `AFL_FORKSRV_INIT_TMOUT` waits this many ms for the fork server to come up.
+    ForksrvInitTmout,
+    /// `AFL_HANG_TMOUT` sets the timeout in ms used to confirm hangs.
+    HangTmout,
+    /// `AFL_IGNORE_UNKNOWN_ENVS` suppresses warnings about unknown `AFL_` variables.
+    IgnoreUnknownEnvs,
+    /// `AFL_INPUT_LEN_MAX` limits the size of generated inputs.
+    InputLenMax,
+    /// `AFL_INPUT_LEN_MIN` sets the minimum size of generated inputs.
+    InputLenMin,
+    /// `AFL_KILL_SIGNAL` is sent to the target on timeouts.
+    KillSignal,
+    /// `AFL_FORK_SERVER_KILL_SIGNAL` is sent to the fork server on exit.
+    ForkServerKillSignal,
+    /// `AFL_MAP_SIZE` sets the size of the coverage map.
+    MapSize,
+    /// `AFL_MAX_DET_EXTRAS` limits the dictionary entries used in deterministic mutations.
+    MaxDetExtras,
+    /// `AFL_NO_AFFINITY` disables binding to a free CPU core.
+    NoAffinity,
+    /// `AFL_NO_ARITH` skips arithmetic mutations.
+    NoArith,
+    /// `AFL_NO_AUTODICT` ignores the dictionary embedded by the compiler.
+    NoAutodict,
+    /// `AFL_NO_CPU_RED` hides the red CPU load warning in the status screen.
+    NoCpuRed,
+    /// `AFL_NO_FORKSRV` disables the fork server.
+    NoForksrv,
+    /// `AFL_NO_STARTUP_CALIBRATION` skips the initial calibration of the seeds.
+    NoStartupCalibration,
+    /// `AFL_NO_UI` disables the status screen.
+    NoUi,
+    /// `AFL_NO_WARN_INSTABILITY` suppresses the stability warning.
+    NoWarnInstability,
+    /// `AFL_PRELOAD` preloads the `:` separated libraries into the target.
+    Preload,
+    /// `AFL_PYTHON_MODULE` loads a Python custom mutator module.
+    PythonModule,
+    /// `AFL_SHUFFLE_QUEUE` randomly reorders the input queue on startup.
+    ShuffleQueue,
+    /// `AFL_SKIP_BIN_CHECK` skips checking the target for instrumentation.
+    SkipBinCheck,
+    /// `AFL_SKIP_CPUFREQ` skips the CPU frequency scaling check.
+    SkipCpufreq,
+    /// `AFL_SYNC_TIME` sets the minutes between syncs with the other fuzzers.
+    SyncTime,
+    /// `AFL_TARGET_ENV` passes extra environment variables to the target.
+    TargetEnv,
+    /// `AFL_TESTCACHE_ENTRIES` limits the number of cached test cases.
+    TestcacheEntries,
+    /// `AFL_TESTCACHE_SIZE` sets the size of the test case cache in MB.
+    TestcacheSize,
+    /// `AFL_TMPDIR` puts the temporary input file into this directory.
+    Tmpdir,
+    /// `AFL_TRY_AFFINITY` falls back to running unbound if no free core is found.
+    TryAffinity,
 }
 
 impl AFLFlag {
+    /// Every known flag
+    pub const ALL: &'static [Self] = &[
+        Self::AutoResume,
+        Self::FinalSync,
+        Self::DisableTrim,
+        Self::KeepTimeouts,
+        Self::ExpandHavocNow,
+        Self::IgnoreSeedProblems,
+        Self::ImportFirst,
+        Self::FastCal,
+        Self::CmplogOnlyNew,
+        Self::BenchJustOne,
+        Self::BenchUntilCrash,
+        Self::CrashExitcode,
+        Self::CustomMutatorLibrary,
+        Self::CustomMutatorOnly,
+        Self::CycleSchedules,
+        Self::Debug,
+        Self::DebugChild,
+        Self::ExitOnSeedIssues,
+        Self::ExitOnTime,
+        Self::ExitWhenDone,
+        Self::ForceUi,
+        Self::ForksrvInitTmout,
+        Self::HangTmout,
+        Self::IgnoreUnknownEnvs,
+        Self::InputLenMax,
+        Self::InputLenMin,
+        Self::KillSignal,
+        Self::ForkServerKillSignal,
+        Self::MapSize,
+        Self::MaxDetExtras,
+        Self::NoAffinity,
+        Self::NoArith,
+        Self::NoAutodict,
+        Self::NoCpuRed,
+        Self::NoForksrv,
+        Self::NoStartupCalibration,
+        Self::NoUi,
+        Self::NoWarnInstability,
+        Self::Preload,
+        Self::PythonModule,
+        Self::ShuffleQueue,
+        Self::SkipBinCheck,
+        Self::SkipCpufreq,
+        Self::SyncTime,
+        Self::TargetEnv,
+        Self::TestcacheEntries,
+        Self::TestcacheSize,
+        Self::Tmpdir,
+        Self::TryAffinity,
+    ];
+
     /// Get the environment variable name for this flag
-    const fn as_str(&self) -> &'static str {
+    pub const fn as_str(&self) -> &'static str {
         match self {
             Self::AutoResume => "AFL_AUTORESUME",
             Self::FinalSync => "AFL_FINAL_SYNC",
@@ -51,6 +208,65 @@ impl AFLFlag {
             Self::ImportFirst => "AFL_IMPORT_FIRST",
             Self::FastCal => "AFL_FAST_CAL",
             Self::CmplogOnlyNew => "AFL_CMPLOG_ONLY_NEW",
+            Self::BenchJustOne => "AFL_BENCH_JUST_ONE",
+            Self::BenchUntilCrash => "AFL_BENCH_UNTIL_CRASH",
+            Self::CrashExitcode => "AFL_CRASH_EXITCODE",
+            Self::CustomMutatorLibrary => "AFL_CUSTOM_MUTATOR_LIBRARY",
+            Self::CustomMutatorOnly => "AFL_CUSTOM_MUTATOR_ONLY",
+            Self::CycleSchedules => "AFL_CYCLE_SCHEDULES",
+            Self::Debug => "AFL_DEBUG",
+            Self::DebugChild => "AFL_DEBUG_CHILD",
+            Self::ExitOnSeedIssues => "AFL_EXIT_ON_SEED_ISSUES",
+            Self::ExitOnTime => "AFL_EXIT_ON_TIME",
+            Self::ExitWhenDone => "AFL_EXIT_WHEN_DONE",
+            Self::ForceUi => "AFL_FORCE_UI",
+            Self::ForksrvInitTmout => "AFL_FORKSRV_INIT_TMOUT",
+            Self::HangTmout => "AFL_HANG_TMOUT",
+            Self::IgnoreUnknownEnvs => "AFL_IGNORE_UNKNOWN_ENVS",
+            Self::InputLenMax => "AFL_INPUT_LEN_MAX",
+            Self::InputLenMin => "AFL_INPUT_LEN_MIN",
+            Self::KillSignal => "AFL_KILL_SIGNAL",
+            Self::ForkServerKillSignal => "AFL_FORK_SERVER_KILL_SIGNAL",
+            Self::MapSize => "AFL_MAP_SIZE",
+            Self::MaxDetExtras => "AFL_MAX_DET_EXTRAS",
+            Self::NoAffinity => "AFL_NO_AFFINITY",
+            Self::NoArith => "AFL_NO_ARITH",
+            Self::NoAutodict => "AFL_NO_AUTODICT",
+            Self::NoCpuRed => "AFL_NO_CPU_RED",
+            Self::NoForksrv => "AFL_NO_FORKSRV",
+            Self::NoStartupCalibration => "AFL_NO_STARTUP_CALIBRATION",
+            Self::NoUi => "AFL_NO_UI",
+            Self::NoWarnInstability => "AFL_NO_WARN_INSTABILITY",
+            Self::Preload => "AFL_PRELOAD",
+            Self::PythonModule => "AFL_PYTHON_MODULE",
+            Self::ShuffleQueue => "AFL_SHUFFLE_QUEUE",
+            Self::SkipBinCheck => "AFL_SKIP_BIN_CHECK",
+            Self::SkipCpufreq => "AFL_SKIP_CPUFREQ",
+            Self::SyncTime => "AFL_SYNC_TIME",
+            Self::TargetEnv => "AFL_TARGET_ENV",
+            Self::TestcacheEntries => "AFL_TESTCACHE_ENTRIES",
+            Self::TestcacheSize => "AFL_TESTCACHE_SIZE",
+            Self::Tmpdir => "AFL_TMPDIR",
+            Self::TryAffinity => "AFL_TRY_AFFINITY",
+        }
+    }
+
+    /// The kind of value the variable accepts
+    pub const fn kind(&self) -> EnvVarKind {
+        const U32_MAX: u64 = u32::MAX as u64;
+        match self {
+            Self::CrashExitcode => num(0, 255),
+            Self::ExitOnTime | Self::ForksrvInitTmout | Self::HangTmout => num(1, u64::MAX),
+            Self::InputLenMax | Self::SyncTime | Self::TestcacheEntries => num(1, U32_MAX),
+            Self::InputLenMin | Self::MaxDetExtras => num(0, U32_MAX),
+            Self::KillSignal | Self::ForkServerKillSignal => num(1, 64),
+            Self::MapSize => num(64, 1 << 32),
+            Self::TestcacheSize => num(2, U32_MAX),
+            Self::CustomMutatorLibrary => EnvVarKind::Paths(';'),
+            Self::Preload => EnvVarKind::Paths(':'),
+            Self::Tmpdir => EnvVarKind::Dir,
+            Self::PythonModule | Self::TargetEnv => EnvVarKind::Text,
+            _ => EnvVarKind::Flag,
         }
     }
 }
@@ -66,18 +282,11 @@ impl FromStr for AFLFlag {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "AFL_AUTORESUME" => Ok(Self::AutoResume),
-            "AFL_FINAL_SYNC" => Ok(Self::FinalSync),
-            "AFL_DISABLE_TRIM" => Ok(Self::DisableTrim),
-            "AFL_KEEP_TIMEOUTS" => Ok(Self::KeepTimeouts),
-            "AFL_EXPAND_HAVOC_NOW" => Ok(Self::ExpandHavocNow),
-            "AFL_IGNORE_SEED_PROBLEMS" => Ok(Self::IgnoreSeedProblems),
-            "AFL_IMPORT_FIRST" => Ok(Self::ImportFirst),
-            "AFL_FAST_CAL" => Ok(Self::FastCal),
-            "AFL_CMPLOG_ONLY_NEW" => Ok(Self::CmplogOnlyNew),
-            _ => Err(format!("Unknown AFL++ flag: {s}")),
-        }
+        Self::ALL
+            .iter()
+            .find(|flag| flag.as_str() == s)
+            .cloned()
+            .ok_or_else(|| format!("Unknown AFL++ flag: {s}"))
     }
 }
 
@@ -108,6 +317,7 @@ impl AFLEnv {
         mode: Mode,
         runners: u32,
         ramdisk: Option<&String>,
+        capabilities: &Capabilities,
         rng: &mut impl Rng,
     ) -> Vec<Self> {
         let mut envs = vec![Self::default(); runners as usize];
@@ -135,19 +345,15 @@ impl AFLEnv {
             Mode::Default => {}
         }
 
-        if mode != Mode::CIFuzzing {
+        if mode != Mode::CIFuzzing && capabilities.allows(Capability::FinalSync) {
             // Enable FinalSync for the first configuration (-M)
             envs.first_mut().unwrap().enable_flag(AFLFlag::FinalSync);
         }
 
         // Set testcache size based on available memory
-        let free_mb = system::get_free_mem_in_mb();
+        let size = testcache_size(runners, system::get_free_mem_in_mb());
         for env in &mut envs {
-            match free_mb {
-                x if x > u64::from(runners * 500 + 4096) => env.set_testcache_size(500),
-                x if x > u64::from(runners * 250 + 4096) => env.set_testcache_size(250),
-                _ => {}
-            }
+            env.set_testcache_size(size);
         }
 
         envs
@@ -219,6 +425,7 @@ impl AFLEnv {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::afl::version::AflVersion;
     use rand::SeedableRng;
 
     // Helper function to create a deterministic RNG for testing
@@ -257,6 +464,20 @@ mod tests {
             AFLFlag::ImportFirst
         );
         assert!("INVALID_FLAG".parse::<AFLFlag>().is_err());
+        assert!(AFLFlag::ALL
+            .iter()
+            .all(|flag| flag.as_str().parse::<AFLFlag>().as_ref() == Ok(flag)));
+    }
+
+    #[test]
+    fn test_flag_kind() {
+        assert_eq!(AFLFlag::NoUi.kind(), EnvVarKind::Flag);
+        assert_eq!(
+            AFLFlag::KillSignal.kind(),
+            EnvVarKind::Number { min: 1, max: 64 }
+        );
+        assert_eq!(AFLFlag::Preload.kind(), EnvVarKind::Paths(':'));
+        assert_eq!(AFLFlag::Tmpdir.kind(), EnvVarKind::Dir);
     }
 
     #[test]
@@ -318,6 +539,7 @@ mod tests {
             Mode::MultipleCores,
             4,
             Some(&"/ramdisk".to_string()),
+            &Capabilities::default(),
             &mut rng,
         );
         let cmd_w_ramdisk = aflenv_w_ramdisk[0].generate();
@@ -332,7 +554,13 @@ mod tests {
     #[test]
     fn test_new_multiple_environments() {
         let mut rng = get_test_rng();
-        let envs = AFLEnv::new(Mode::MultipleCores, 4_u32, None, &mut rng);
+        let envs = AFLEnv::new(
+            Mode::MultipleCores,
+            4_u32,
+            None,
+            &Capabilities::default(),
+            &mut rng,
+        );
 
         // Test number of environments
         assert_eq!(envs.len(), 4);
@@ -360,7 +588,13 @@ mod tests {
     fn test_new_with_afl_defaults() {
         let mut rng = get_test_rng();
 
-        let envs = AFLEnv::new(Mode::Default, 4_u32, None, &mut rng);
+        let envs = AFLEnv::new(
+            Mode::Default,
+            4_u32,
+            None,
+            &Capabilities::default(),
+            &mut rng,
+        );
 
         assert!(envs.iter().take(0).all(|env| env.flags.len() == 1));
         // Check that the main fuzzer has at least the FINAL_SYNC flag set
@@ -370,10 +604,28 @@ mod tests {
             .all(|env| env.flags.contains(&AFLFlag::FinalSync)));
     }
 
+    #[test]
+    fn test_new_without_final_sync() {
+        let mut rng = get_test_rng();
+        let capabilities = Capabilities::new(Some(AflVersion::new(4, 10, 'c')));
+        let envs = AFLEnv::new(Mode::MultipleCores, 4_u32, None, &capabilities, &mut rng);
+
+        assert!(!envs
+            .iter()
+            .any(|env| env.flags.contains(&AFLFlag::FinalSync)));
+        assert_eq!(capabilities.downgraded(), vec![Capability::FinalSync]);
+    }
+
     #[test]
     fn test_new_with_many_runners() {
         let mut rng = get_test_rng();
-        let envs = AFLEnv::new(Mode::MultipleCores, 20_u32, None, &mut rng);
+        let envs = AFLEnv::new(
+            Mode::MultipleCores,
+            20_u32,
+            None,
+            &Capabilities::default(),
+            &mut rng,
+        );
 
         // Test that ImportFirst is not applied when runners >= 16
         assert!(!envs
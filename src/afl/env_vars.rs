@@ -0,0 +1,305 @@
+// -----------------------------------------
+// Typed AFL++ environment variables that can be set by the user
+// Based on: https://aflplus.plus/docs/env_variables/#3-settings-for-afl-fuzz
+// -----------------------------------------
+use std::{collections::HashMap, fmt, path::Path};
+
+use anyhow::{bail, Context, Result};
+
+use crate::afl::env::{AFLFlag, EnvVarKind};
+
+/// Pairs of variables that must not be set on the same instance
+const CONFLICTS: &[(AFLFlag, AFLFlag)] = &[
+    (AFLFlag::NoUi, AFLFlag::ForceUi),
+    (AFLFlag::NoAffinity, AFLFlag::TryAffinity),
+    (AFLFlag::CustomMutatorLibrary, AFLFlag::PythonModule),
+];
+
+/// Variables that only make sense if one of the listed variables is set as well
+const REQUIRES: &[(AFLFlag, &[AFLFlag])] = &[(
+    AFLFlag::CustomMutatorOnly,
+    &[AFLFlag::CustomMutatorLibrary, AFLFlag::PythonModule],
+)];
+
+/// A validated value of an AFL++ environment variable
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AFLEnvValue {
+    Flag(bool),
+    Number(u64),
+    Text(String),
+}
+
+/// A validated AFL++ environment variable, optionally restricted to some instances
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AFLEnvSetting {
+    pub flag: AFLFlag,
+    pub value: AFLEnvValue,
+    /// Instance indices this setting applies to, `None` applies it to all instances
+    pub instances: Option<Vec<usize>>,
+}
+
+impl AFLEnvSetting {
+    /// Parses and validates a raw string value, e.g. from the exported shell environment
+    ///
+    /// # Errors
+    /// * If the variable is unknown or the value is invalid for its kind
+    pub fn parse(name: &str, raw: &str) -> Result<Self> {
+        let flag: AFLFlag = name
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Unknown AFL++ variable: {name}"))?;
+        let raw = raw.trim();
+        let value = match flag.kind() {
+            EnvVarKind::Flag => match raw {
+                "" | "0" | "false" => AFLEnvValue::Flag(false),
+                _ => AFLEnvValue::Flag(true),
+            },
+            EnvVarKind::Number { .. } => AFLEnvValue::Number(
+                raw.parse()
+                    .with_context(|| format!("{name} expects a number, got '{raw}'"))?,
+            ),
+            EnvVarKind::Paths(_) | EnvVarKind::Dir | EnvVarKind::Text => {
+                AFLEnvValue::Text(raw.to_string())
+            }
+        };
+        let setting = Self {
+            flag,
+            value,
+            instances: None,
+        };
+        setting.validate()?;
+        Ok(setting)
+    }
+
+    /// Creates a setting from a config value
+    ///
+    /// # Errors
+    /// * If the variable is unknown or the value is invalid for its kind
+    pub fn from_toml(name: &str, value: &toml::Value) -> Result<Self> {
+        let raw = match value {
+            toml::Value::Boolean(b) => if *b { "1" } else { "0" }.to_string(),
+            toml::Value::Integer(i) => {
+                if *i < 0 {
+                    bail!("{name} must not be negative");
+                }
+                i.to_string()
+            }
+            toml::Value::String(s) => s.clone(),
+            other => bail!("Unsupported value for {name}: {other}"),
+        };
+        Self::parse(name, &raw)
+    }
+
+    /// Restricts the setting to the given instance indices
+    pub fn for_instances(mut self, instances: Vec<usize>) -> Self {
+        self.instances = Some(instances);
+        self
+    }
+
+    /// Whether this setting applies to the instance at `idx`
+    pub fn applies_to(&self, idx: usize) -> bool {
+        self.instances.as_ref().is_none_or(|i| i.contains(&idx))
+    }
+
+    fn validate(&self) -> Result<()> {
+        let name = self.flag.as_str();
+        match (&self.flag.kind(), &self.value) {
+            (EnvVarKind::Number { min, max }, AFLEnvValue::Number(n)) if n < min || n > max => {
+                bail!("{name}={n} is out of range ({min}-{max})");
+            }
+            (EnvVarKind::Paths(sep), AFLEnvValue::Text(paths)) => {
+                for path in paths.split(*sep).filter(|p| !p.is_empty()) {
+                    if !Path::new(path).is_file() {
+                        bail!("{name}: {path} does not exist or is not a file");
+                    }
+                }
+            }
+            (EnvVarKind::Dir, AFLEnvValue::Text(dir)) if !Path::new(dir).is_dir() => {
+                bail!("{name}: {dir} does not exist or is not a directory");
+            }
+            (EnvVarKind::Text, AFLEnvValue::Text(text)) if text.is_empty() => {
+                bail!("{name} must not be empty");
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Renders the setting as `NAME=value`, or `None` if a flag is disabled
+    pub fn to_env_string(&self) -> Option<String> {
+        match &self.value {
+            AFLEnvValue::Flag(false) => None,
+            AFLEnvValue::Flag(true) => Some(format!("{}=1", self.flag)),
+            AFLEnvValue::Number(n) => Some(format!("{}={n}", self.flag)),
+            AFLEnvValue::Text(t) => Some(format!("{}={t}", self.flag)),
+        }
+    }
+}
+
+impl fmt::Display for AFLEnvSetting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.to_env_string() {
+            Some(s) => f.write_str(&s),
+            None => write!(f, "{}=<unset>", self.flag),
+        }
+    }
+}
+
+/// Checks the exported `AFL_*` variables of the shell environment, returning a warning for
+/// every known variable with an invalid value
+///
+/// Unknown variables are passed through untouched, as they might target other AFL++ tools.
+/// Exported variables are inherited as they are, so problems are only reported.
+pub fn validate_exported(env: &[String]) -> Vec<String> {
+    env.iter()
+        .filter_map(|entry| {
+            let (key, value) = entry.split_once('=').unwrap_or((entry, ""));
+            key.parse::<AFLFlag>().ok()?;
+            AFLEnvSetting::parse(key, value)
+                .err()
+                .map(|err| format!("Exported {key} is invalid: {err:#}"))
+        })
+        .collect()
+}
+
+/// Checks the final environment of a single instance for conflicting variables
+///
+/// # Errors
+/// * If two mutually exclusive variables are set
+/// * If a variable is missing a variable it depends on
+/// * If `AFL_INPUT_LEN_MIN` exceeds `AFL_INPUT_LEN_MAX`
+pub fn check_conflicts(env: &[String]) -> Result<()> {
+    let vars: HashMap<&str, &str> = env
+        .iter()
+        .map(|e| {
+            let (k, v) = e.split_once('=').unwrap_or((e, ""));
+            (k.trim(), v.trim())
+        })
+        .filter(|(_, v)| !v.is_empty() && *v != "0")
+        .collect();
+
+    for (a, b) in CONFLICTS {
+        if vars.contains_key(a.as_str()) && vars.contains_key(b.as_str()) {
+            bail!("{a} and {b} cannot be used together");
+        }
+    }
+    for (var, deps) in REQUIRES {
+        if vars.contains_key(var.as_str()) && !deps.iter().any(|d| vars.contains_key(d.as_str())) {
+            let deps: Vec<_> = deps.iter().map(AFLFlag::as_str).collect();
+            bail!("{var} requires one of: {}", deps.join(", "));
+        }
+    }
+    let len = |key: &str| vars.get(key).and_then(|v| v.parse::<u64>().ok());
+    if let (Some(min), Some(max)) = (len("AFL_INPUT_LEN_MIN"), len("AFL_INPUT_LEN_MAX")) {
+        if min > max {
+            bail!("AFL_INPUT_LEN_MIN ({min}) is larger than AFL_INPUT_LEN_MAX ({max})");
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parse_typed_values() {
+        let map = AFLEnvSetting::parse("AFL_MAP_SIZE", "65536").unwrap();
+        assert_eq!(map.value, AFLEnvValue::Number(65536));
+        assert_eq!(map.to_env_string().unwrap(), "AFL_MAP_SIZE=65536");
+
+        let flag = AFLEnvSetting::parse("AFL_SKIP_CPUFREQ", "1").unwrap();
+        assert_eq!(flag.to_env_string().unwrap(), "AFL_SKIP_CPUFREQ=1");
+
+        let off = AFLEnvSetting::parse("AFL_NO_AFFINITY", "0").unwrap();
+        assert!(off.to_env_string().is_none());
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid() {
+        assert!(AFLEnvSetting::parse("AFL_NOT_A_THING", "1").is_err());
+        assert!(AFLEnvSetting::parse("AFL_SYNC_TIME", "soon").is_err());
+        assert!(AFLEnvSetting::parse("AFL_SYNC_TIME", "0").is_err());
+        assert!(AFLEnvSetting::parse("AFL_MAP_SIZE", "8").is_err());
+        assert!(AFLEnvSetting::parse("AFL_PRELOAD", "/nonexistent/lib.so").is_err());
+    }
+
+    #[test]
+    fn test_path_lists() {
+        let dir = tempdir().unwrap();
+        let a = dir.path().join("a.so");
+        let b = dir.path().join("b.so");
+        fs_touch(&a);
+        fs_touch(&b);
+
+        let preload = format!("{}:{}", a.display(), b.display());
+        assert!(AFLEnvSetting::parse("AFL_PRELOAD", &preload).is_ok());
+
+        let mutators = format!("{};{}", a.display(), b.display());
+        assert!(AFLEnvSetting::parse("AFL_CUSTOM_MUTATOR_LIBRARY", &mutators).is_ok());
+        assert!(AFLEnvSetting::parse("AFL_TMPDIR", &dir.path().to_string_lossy()).is_ok());
+    }
+
+    fn fs_touch(path: &Path) {
+        std::fs::File::create(path).unwrap();
+    }
+
+    #[test]
+    fn test_from_toml() {
+        let flag = AFLEnvSetting::from_toml("AFL_SKIP_CPUFREQ", &toml::Value::Boolean(true));
+        assert_eq!(flag.unwrap().value, AFLEnvValue::Flag(true));
+
+        let sync = AFLEnvSetting::from_toml("AFL_SYNC_TIME", &toml::Value::Integer(5)).unwrap();
+        assert_eq!(sync.value, AFLEnvValue::Number(5));
+
+        assert!(AFLEnvSetting::from_toml("AFL_SYNC_TIME", &toml::Value::Integer(-1)).is_err());
+        assert!(AFLEnvSetting::from_toml("AFL_SYNC_TIME", &toml::Value::Float(1.5)).is_err());
+    }
+
+    #[test]
+    fn test_instance_scoping() {
+        let setting = AFLEnvSetting::parse("AFL_NO_UI", "1")
+            .unwrap()
+            .for_instances(vec![0, 2]);
+        assert!(setting.applies_to(0));
+        assert!(!setting.applies_to(1));
+        assert!(AFLEnvSetting::parse("AFL_NO_UI", "1")
+            .unwrap()
+            .applies_to(7));
+    }
+
+    #[test]
+    fn test_check_conflicts() {
+        let ok = vec![
+            "AFL_NO_UI=1".to_string(),
+            "AFL_TESTCACHE_SIZE=50 ".to_string(),
+        ];
+        assert!(check_conflicts(&ok).is_ok());
+
+        let ui = vec!["AFL_NO_UI=1".to_string(), "AFL_FORCE_UI=1".to_string()];
+        assert!(check_conflicts(&ui).is_err());
+
+        let only = vec!["AFL_CUSTOM_MUTATOR_ONLY=1".to_string()];
+        assert!(check_conflicts(&only).is_err());
+
+        let len = vec![
+            "AFL_INPUT_LEN_MIN=100".to_string(),
+            "AFL_INPUT_LEN_MAX=10".to_string(),
+        ];
+        assert!(check_conflicts(&len).is_err());
+    }
+
+    #[test]
+    fn test_validate_exported() {
+        assert!(validate_exported(&["AFL_SOMETHING_NEW=1".to_string()]).is_empty());
+        assert!(validate_exported(&["AFL_SYNC_TIME=10".to_string()]).is_empty());
+        assert_eq!(
+            validate_exported(&[
+                "AFL_SYNC_TIME=abc".to_string(),
+                "AFL_PRELOAD=/nonexistent/lib.so".to_string(),
+            ])
+            .len(),
+            2
+        );
+    }
+}
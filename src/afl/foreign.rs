@@ -0,0 +1,129 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+
+use crate::afl::overrides::InstanceSelector;
+
+/// Maximum number of `-F` directories AFL++ accepts per instance
+pub const MAX_FOREIGN_DIRS: usize = 32;
+
+/// Foreign corpus directories imported via `-F`, e.g. a libFuzzer or honggfuzz corpus
+#[derive(Debug, Clone, Default)]
+pub struct ForeignDirs {
+    pub dirs: Vec<PathBuf>,
+    /// Instances importing the directories, the `-M` instance if none are selected
+    pub selector: Option<InstanceSelector>,
+}
+
+impl ForeignDirs {
+    pub fn new(dirs: Vec<PathBuf>) -> Self {
+        Self {
+            dirs,
+            selector: None,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dirs.is_empty()
+    }
+
+    /// Creates missing directories and replaces all paths by their canonical form
+    ///
+    /// # Errors
+    /// * If there are more directories than AFL++ accepts
+    /// * If a path is a file or cannot be created
+    /// * If a directory lies inside the AFL++ output directory, which is cleaned on start
+    pub fn prepare(&mut self, output_dir: &Path) -> Result<()> {
+        if self.dirs.len() > MAX_FOREIGN_DIRS {
+            bail!(
+                "{} foreign directories given but AFL++ only accepts {MAX_FOREIGN_DIRS}",
+                self.dirs.len()
+            );
+        }
+        let output_dir = fs::canonicalize(output_dir).unwrap_or_else(|_| output_dir.to_path_buf());
+        let mut prepared: Vec<PathBuf> = Vec::with_capacity(self.dirs.len());
+        for dir in &self.dirs {
+            if dir.is_file() {
+                bail!("Foreign directory {} is a file", dir.display());
+            }
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create foreign directory {}", dir.display()))?;
+            let dir = fs::canonicalize(dir)?;
+            if dir.starts_with(&output_dir) {
+                bail!(
+                    "Foreign directory {} lies inside the output directory",
+                    dir.display()
+                );
+            }
+            if !prepared.contains(&dir) {
+                prepared.push(dir);
+            }
+        }
+        self.dirs = prepared;
+        Ok(())
+    }
+
+    /// Resolves the instances importing the directories
+    ///
+    /// `names` holds the `-M`/`-S` name of each generated instance, `main` the index of
+    /// the `-M` instance if there is one.
+    ///
+    /// # Errors
+    /// * If the selector matches no instance
+    pub fn resolve(&self, names: &[Option<&str>], main: Option<usize>) -> Result<Vec<usize>> {
+        match &self.selector {
+            Some(selector) => selector
+                .resolve(names)
+                .context("Invalid foreign directory instances"),
+            None => Ok(main.into_iter().collect()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prepare() {
+        let dir = tempfile::tempdir().unwrap();
+        let output_dir = dir.path().join("out");
+        fs::create_dir(&output_dir).unwrap();
+        let libfuzzer = dir.path().join("libfuzzer");
+
+        let mut foreign = ForeignDirs::new(vec![libfuzzer.clone(), libfuzzer.clone()]);
+        foreign.prepare(&output_dir).unwrap();
+        assert!(libfuzzer.is_dir());
+        assert_eq!(foreign.dirs, vec![fs::canonicalize(&libfuzzer).unwrap()]);
+
+        let file = dir.path().join("file");
+        fs::write(&file, "").unwrap();
+        assert!(ForeignDirs::new(vec![file]).prepare(&output_dir).is_err());
+        assert!(ForeignDirs::new(vec![output_dir.join("sub")])
+            .prepare(&output_dir)
+            .is_err());
+        assert!(ForeignDirs::new(vec![libfuzzer; MAX_FOREIGN_DIRS + 1])
+            .prepare(&output_dir)
+            .is_err());
+    }
+
+    #[test]
+    fn test_resolve() {
+        let names = [Some("m_target"), Some("s1_target"), Some("s2_target_cl")];
+        assert_eq!(
+            ForeignDirs::default().resolve(&names, Some(0)).unwrap(),
+            vec![0]
+        );
+        assert!(ForeignDirs::default()
+            .resolve(&names, None)
+            .unwrap()
+            .is_empty());
+
+        let subset = ForeignDirs {
+            selector: Some("s*_cl".parse().unwrap()),
+            ..ForeignDirs::default()
+        };
+        assert_eq!(subset.resolve(&names, Some(0)).unwrap(), vec![2]);
+    }
+}
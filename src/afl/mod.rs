@@ -1,8 +1,33 @@
+pub mod adaptive;
 pub mod base_cfg;
+pub mod calibration;
 pub mod cmd;
 pub mod cmd_gen;
+pub mod corpus_sync;
 pub mod coverage;
+pub mod coverage_backend;
+pub mod coverage_cache;
+pub mod coverage_contrib;
+pub mod coverage_diff;
+pub mod coverage_edges;
+pub mod coverage_export;
+pub mod coverage_filter;
+pub mod coverage_observer;
+pub mod coverage_replay;
+pub mod coverage_result;
+pub mod coverage_snapshot;
+pub mod coverage_timeline;
+pub mod dictionary;
 pub mod env;
+pub mod env_vars;
+pub mod foreign;
 pub mod harness;
 pub mod mode;
+pub mod overrides;
+pub mod phases;
+pub mod preflight;
+pub mod resources;
 pub mod strategies;
+pub mod sync_groups;
+pub mod target_exec;
+pub mod version;
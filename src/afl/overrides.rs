@@ -0,0 +1,230 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use glob::Pattern;
+
+use crate::afl::cmd::split_flags;
+use crate::afl::env_vars::AFLEnvSetting;
+use crate::afl::strategies::PowerSchedule;
+
+/// Flags that are always managed by the generator and cannot be pinned
+const RESERVED_FLAGS: [(&str, &str); 6] = [
+    ("-M", "roles are assigned by the generator"),
+    ("-S", "roles are assigned by the generator"),
+    ("-i", "use seed_dir instead"),
+    ("-o", "use solution_dir instead"),
+    ("-x", "use the dictionary override instead"),
+    ("-F", "use foreign_dirs and foreign_instances instead"),
+];
+
+/// Selects the instances an override applies to
+#[derive(Debug, Clone)]
+pub enum InstanceSelector {
+    /// Instance indices, 0 being the first (`-M`) instance
+    Indices(Vec<usize>),
+    /// Glob pattern matched against the `-M`/`-S` instance names, e.g. `s*_cl`
+    Name(Pattern),
+}
+
+impl InstanceSelector {
+    /// Resolves the selector to instance indices
+    ///
+    /// `names` holds the `-M`/`-S` name of each generated instance.
+    ///
+    /// # Errors
+    /// * If an index is out of range or a name pattern matches no instance
+    pub fn resolve(&self, names: &[Option<&str>]) -> Result<Vec<usize>> {
+        match self {
+            Self::Indices(indices) => {
+                if let Some(idx) = indices.iter().find(|&&i| i >= names.len()) {
+                    bail!(
+                        "Instance {idx} is selected but only {} instances are generated",
+                        names.len()
+                    );
+                }
+                Ok(indices.clone())
+            }
+            Self::Name(pattern) => {
+                let matched: Vec<usize> = names
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, name)| name.is_some_and(|n| pattern.matches(n)))
+                    .map(|(idx, _)| idx)
+                    .collect();
+                if matched.is_empty() {
+                    bail!("Name pattern '{pattern}' matches no generated instance");
+                }
+                Ok(matched)
+            }
+        }
+    }
+}
+
+impl FromStr for InstanceSelector {
+    type Err = anyhow::Error;
+
+    /// Parses a comma-separated index list like `0,2` or a name pattern like `s*_cl`
+    fn from_str(s: &str) -> Result<Self> {
+        let indices: Option<Vec<usize>> = s.split(',').map(|i| i.trim().parse().ok()).collect();
+        match indices {
+            Some(indices) => Ok(Self::Indices(indices)),
+            None => Ok(Self::Name(
+                Pattern::new(s).with_context(|| format!("Invalid name pattern: {s}"))?,
+            )),
+        }
+    }
+}
+
+impl fmt::Display for InstanceSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Indices(indices) => write!(f, "instances {indices:?}"),
+            Self::Name(pattern) => write!(f, "instances matching '{pattern}'"),
+        }
+    }
+}
+
+/// Settings pinned to specific instances via `[[instance]]`
+#[derive(Debug, Clone)]
+pub struct InstanceOverride {
+    /// Instances the override applies to
+    pub selector: InstanceSelector,
+    /// Target binary to fuzz instead of the generated one
+    pub binary: Option<PathBuf>,
+    /// Power schedule replacing the generated `-p`
+    pub schedule: Option<PowerSchedule>,
+    /// Additional AFL++ flags, replacing generated flags of the same kind
+    pub flags: Vec<String>,
+    /// Environment variables for the selected instances
+    pub env: Vec<AFLEnvSetting>,
+    /// Dictionary replacing the global `-x`
+    pub dictionary: Option<PathBuf>,
+    /// Custom mutator library loaded via `AFL_CUSTOM_MUTATOR_LIBRARY`
+    pub custom_mutator: Option<PathBuf>,
+}
+
+impl InstanceOverride {
+    /// Creates an empty override for the given instances
+    pub fn new(selector: InstanceSelector) -> Self {
+        Self {
+            selector,
+            binary: None,
+            schedule: None,
+            flags: Vec::new(),
+            env: Vec::new(),
+            dictionary: None,
+            custom_mutator: None,
+        }
+    }
+
+    /// Sets the pinned AFL++ flags from a raw flag string like `-t 1000+ -L 0`
+    ///
+    /// # Errors
+    /// * If a flag is managed by the generator
+    pub fn with_flags(mut self, raw_flags: &str) -> Result<Self> {
+        let flags = split_flags(raw_flags);
+        for flag in &flags {
+            let option = flag.split_whitespace().next().unwrap_or_default();
+            if let Some((_, reason)) = RESERVED_FLAGS.iter().find(|(f, _)| *f == option) {
+                bail!("{option} cannot be pinned for {}: {reason}", self.selector);
+            }
+        }
+        self.flags = flags;
+        Ok(self)
+    }
+
+    /// Options (e.g. `-p`, `-L`) set by this override, used to drop generated flags of the same kind
+    pub fn pinned_options(&self) -> Vec<&str> {
+        let mut options: Vec<&str> = self
+            .flags
+            .iter()
+            .filter_map(|f| f.split_whitespace().next())
+            .collect();
+        if self.schedule.is_some() {
+            options.push("-p");
+        }
+        if self.dictionary.is_some() {
+            options.push("-x");
+        }
+        options
+    }
+
+    /// Names of the settings pinned by this override
+    pub fn pinned_fields(&self) -> Vec<String> {
+        let mut fields: Vec<String> = [
+            ("binary", self.binary.is_some()),
+            ("schedule", self.schedule.is_some()),
+            ("dictionary", self.dictionary.is_some()),
+            ("custom_mutator", self.custom_mutator.is_some()),
+        ]
+        .into_iter()
+        .filter(|(_, set)| *set)
+        .map(|(name, _)| name.to_string())
+        .collect();
+        fields.extend(
+            self.flags
+                .iter()
+                .filter_map(|f| f.split_whitespace().next())
+                .map(String::from),
+        );
+        fields.extend(self.env.iter().map(|s| s.flag.to_string()));
+        fields
+    }
+
+    /// Resolves the selector to instance indices
+    ///
+    /// # Errors
+    /// * If an index is out of range or a name pattern matches no instance
+    pub fn resolve(&self, names: &[Option<&str>]) -> Result<Vec<usize>> {
+        self.selector
+            .resolve(names)
+            .context("Invalid [[instance]] selector")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flag_grouping_and_reserved() {
+        let ovr = InstanceOverride::new(InstanceSelector::Indices(vec![1]))
+            .with_flags("-t 1000+ -L 0 -Z")
+            .unwrap();
+        assert_eq!(ovr.flags, vec!["-t 1000+", "-L 0", "-Z"]);
+        assert_eq!(ovr.pinned_options(), vec!["-t", "-L", "-Z"]);
+
+        assert!(InstanceOverride::new(InstanceSelector::Indices(vec![1]))
+            .with_flags("-S foo")
+            .is_err());
+        assert!(InstanceOverride::new(InstanceSelector::Indices(vec![1]))
+            .with_flags("-x /tmp/dict")
+            .is_err());
+        assert!(InstanceOverride::new(InstanceSelector::Indices(vec![1]))
+            .with_flags("-F /tmp/foreign")
+            .is_err());
+    }
+
+    #[test]
+    fn test_resolve() {
+        let names = [Some("m_target"), Some("s1_target_cl"), Some("s2_target")];
+
+        let by_idx = InstanceOverride::new(InstanceSelector::Indices(vec![0, 2]));
+        assert_eq!(by_idx.resolve(&names).unwrap(), vec![0, 2]);
+        let out_of_range = InstanceOverride::new(InstanceSelector::Indices(vec![3]));
+        assert!(out_of_range.resolve(&names).is_err());
+
+        let by_name = InstanceOverride::new(InstanceSelector::Name(Pattern::new("s*").unwrap()));
+        assert_eq!(by_name.resolve(&names).unwrap(), vec![1, 2]);
+        let no_match = InstanceOverride::new(InstanceSelector::Name(Pattern::new("x*").unwrap()));
+        assert!(no_match.resolve(&names).is_err());
+
+        let parsed: InstanceSelector = "0, 2".parse().unwrap();
+        assert_eq!(parsed.resolve(&names).unwrap(), vec![0, 2]);
+        let parsed: InstanceSelector = "s*_cl".parse().unwrap();
+        assert_eq!(parsed.resolve(&names).unwrap(), vec![1]);
+        assert!("[".parse::<InstanceSelector>().is_err());
+    }
+}
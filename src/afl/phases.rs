@@ -0,0 +1,222 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+
+use crate::afl::mode::Mode;
+use crate::afl::strategies::{AFLStrategyBuilder, PowerSchedule};
+use crate::afl::sync_groups::instance_dirs;
+use crate::utils::hash::content_hash;
+use crate::utils::system::any_fuzzer_alive;
+
+/// Interval at which a running phase checks whether the campaign is still alive
+const ALIVE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// One phase of a phased campaign
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Phase {
+    pub name: String,
+    /// Run time of the phase, the last phase runs until the campaign is stopped if unset
+    pub duration: Option<Duration>,
+    /// Strategy mode replacing the one of the campaign
+    pub mode: Option<Mode>,
+    /// Share of the secondaries running the cmplog binary
+    pub cmplog_ratio: Option<f64>,
+    /// Power schedules cycled through instead of those of the mode
+    pub schedules: Vec<PowerSchedule>,
+    /// Minimize all queues with `afl-cmin` and restart from the result before the phase
+    pub cmin: bool,
+}
+
+impl Phase {
+    /// Applies the strategy overrides of the phase
+    pub fn apply(&self, builder: AFLStrategyBuilder) -> AFLStrategyBuilder {
+        if self.schedules.is_empty() {
+            builder
+        } else {
+            builder.with_power_schedules(self.schedules.clone())
+        }
+    }
+}
+
+/// Checks that the phases form a valid schedule
+///
+/// # Errors
+/// * If a phase other than the last one has no duration
+/// * If the first phase minimizes a corpus that does not exist yet
+/// * If a cmplog ratio lies outside of 0..=1
+pub fn validate(phases: &[Phase]) -> Result<()> {
+    for (i, phase) in phases.iter().enumerate() {
+        if phase.duration.is_none() && i + 1 < phases.len() {
+            bail!(
+                "Phase '{}' needs a duration, only the last phase may run until stopped",
+                phase.name
+            );
+        }
+        if phase.cmin && i == 0 {
+            bail!("The first phase '{}' has no queues to minimize", phase.name);
+        }
+        if phase
+            .cmplog_ratio
+            .is_some_and(|r| !(0.0..=1.0).contains(&r))
+        {
+            bail!("Phase '{}' has a cmplog_ratio outside of 0..=1", phase.name);
+        }
+    }
+    Ok(())
+}
+
+/// Waits for `duration` while the campaign in `afl_out` runs
+///
+/// Returns `false` if no instance was alive anymore at one of the periodic checks.
+pub fn wait_phase(afl_out: &Path, duration: Duration) -> bool {
+    let end = Instant::now() + duration;
+    loop {
+        let remaining = end.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return true;
+        }
+        thread::sleep(remaining.min(ALIVE_CHECK_INTERVAL));
+        if !any_fuzzer_alive(afl_out) {
+            return false;
+        }
+    }
+}
+
+/// Copies the queue entries of all instances in `afl_out` into `dest`, skipping
+/// duplicates. Returns the number of copied entries.
+///
+/// # Errors
+/// * If `dest` cannot be created or an entry cannot be copied
+pub fn collect_queues(afl_out: &Path, dest: &Path) -> Result<usize> {
+    fs::create_dir_all(dest).with_context(|| format!("Failed to create {}", dest.display()))?;
+    let mut seen = HashSet::new();
+    let mut copied = 0;
+    for instance in instance_dirs(afl_out) {
+        let Ok(entries) = fs::read_dir(instance.join("queue")) else {
+            continue;
+        };
+        for entry in entries.filter_map(std::result::Result::ok) {
+            let path = entry.path();
+            if !path.is_file() {
+                continue;
+            }
+            let data = fs::read(&path)?;
+            let hash = content_hash(&data);
+            if seen.insert(hash) {
+                fs::write(dest.join(format!("{hash:016x}")), data)?;
+                copied += 1;
+            }
+        }
+    }
+    Ok(copied)
+}
+
+/// Locates `afl-cmin` next to the used `afl-fuzz` binary
+///
+/// # Errors
+/// * If `afl-cmin` does not exist alongside `afl-fuzz`
+pub fn find_afl_cmin(afl_fuzz: &Path) -> Result<PathBuf> {
+    let cmin = afl_fuzz.with_file_name("afl-cmin");
+    if !cmin.is_file() {
+        bail!(
+            "afl-cmin not found next to {}, cannot minimize the corpus",
+            afl_fuzz.display()
+        );
+    }
+    Ok(cmin)
+}
+
+/// Minimizes the corpus in `input` into `output` with `afl-cmin`, returning the number
+/// of remaining entries
+///
+/// # Errors
+/// * If `afl-cmin` fails or produces no entries
+pub fn cmin(
+    afl_cmin: &Path,
+    input: &Path,
+    output: &Path,
+    target: &Path,
+    target_args: &[String],
+) -> Result<usize> {
+    let status = Command::new(afl_cmin)
+        .arg("-i")
+        .arg(input)
+        .arg("-o")
+        .arg(output)
+        .arg("--")
+        .arg(target)
+        .args(target_args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .with_context(|| format!("Failed to run {}", afl_cmin.display()))?;
+    if !status.success() {
+        bail!("afl-cmin exited with {status}");
+    }
+    let entries = fs::read_dir(output)?.count();
+    if entries == 0 {
+        bail!("afl-cmin produced an empty corpus");
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn phase(name: &str, duration: Option<u64>, cmin: bool) -> Phase {
+        Phase {
+            name: name.to_string(),
+            duration: duration.map(Duration::from_secs),
+            cmin,
+            ..Phase::default()
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate(&[]).is_ok());
+        assert!(validate(&[
+            phase("explore", Some(7200), false),
+            phase("exploit", None, true)
+        ])
+        .is_ok());
+        assert!(validate(&[phase("explore", None, false), phase("exploit", None, false)]).is_err());
+        assert!(validate(&[phase("explore", Some(60), true)]).is_err());
+        let ratio = Phase {
+            cmplog_ratio: Some(1.5),
+            ..phase("explore", None, false)
+        };
+        assert!(validate(&[ratio]).is_err());
+    }
+
+    #[test]
+    fn test_collect_queues() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("out");
+        for (instance, entries) in [("m_target", ["a", "b"]), ("s1_target", ["b", "c"])] {
+            let queue = out.join(instance).join("queue");
+            fs::create_dir_all(queue.join(".state")).unwrap();
+            for (i, data) in entries.iter().enumerate() {
+                fs::write(queue.join(format!("id:{i:06}")), data).unwrap();
+            }
+        }
+        let dest = dir.path().join("merged");
+        assert_eq!(collect_queues(&out, &dest).unwrap(), 3);
+        assert_eq!(fs::read_dir(&dest).unwrap().count(), 3);
+    }
+
+    #[test]
+    fn test_find_afl_cmin() {
+        let dir = tempfile::tempdir().unwrap();
+        let fuzz = dir.path().join("afl-fuzz");
+        assert!(find_afl_cmin(&fuzz).is_err());
+        fs::write(dir.path().join("afl-cmin"), "").unwrap();
+        assert_eq!(find_afl_cmin(&fuzz).unwrap(), dir.path().join("afl-cmin"));
+    }
+}
@@ -0,0 +1,370 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+
+use crate::afl::target_exec::{ExecOutcome, TargetRunner, DEFAULT_EXEC_TIMEOUT_MS};
+use crate::utils::hash::content_hash;
+use crate::utils::system::mkdir_helper;
+
+/// Largest test case AFL++ loads from the input directory (`MAX_FILE` in `config.h`)
+pub const MAX_FILE: u64 = 1024 * 1024;
+/// File the preflight results are written to inside the AFL++ output directory
+pub const PREFLIGHT_REPORT: &str = "aflr_preflight.toml";
+/// Placeholder seed used when the input directory is empty
+const PLACEHOLDER_SEED: &[u8] = b"fuzz";
+
+/// Lists the seed files in `dir`, skipping hidden files and subdirectories
+///
+/// # Errors
+/// * If the directory cannot be read
+pub fn list_seeds(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut seeds: Vec<PathBuf> = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read seed directory: {}", dir.display()))?
+        .filter_map(Result::ok)
+        .map(|e| e.path())
+        .filter(|p| {
+            p.is_file()
+                && !p
+                    .file_name()
+                    .is_some_and(|n| n.to_string_lossy().starts_with('.'))
+        })
+        .collect();
+    seeds.sort();
+    Ok(seeds)
+}
+
+/// Creates the seed directory if needed and adds a placeholder seed when it is empty
+///
+/// # Errors
+/// * If the directory or placeholder seed cannot be created
+pub fn ensure_seed_dir(dir: &Path) -> Result<()> {
+    mkdir_helper(dir, false)?;
+    if list_seeds(dir)?.is_empty() {
+        println!(
+            "[!] Warning: Seed directory {} is empty, adding a placeholder seed. Provide real seeds for better results!",
+            dir.display()
+        );
+        fs::write(dir.join("1"), PLACEHOLDER_SEED)
+            .with_context(|| format!("Failed to write placeholder seed to {}", dir.display()))?;
+    }
+    Ok(())
+}
+
+/// Results of checking a seed corpus before a campaign
+#[derive(Debug, Default, Serialize)]
+pub struct PreflightReport {
+    /// Every seed that was found
+    pub seeds: Vec<PathBuf>,
+    /// Seeds with the same content as an earlier seed, mapped to that seed
+    pub duplicates: Vec<(PathBuf, PathBuf)>,
+    /// Empty seeds, which AFL++ skips
+    pub empty: Vec<PathBuf>,
+    /// Seeds larger than `MAX_FILE`, which AFL++ skips
+    pub oversized: Vec<PathBuf>,
+    /// Seeds that crash the target
+    pub crashing: Vec<PathBuf>,
+    /// Seeds that exceed the timeout
+    pub hanging: Vec<PathBuf>,
+}
+
+impl PreflightReport {
+    /// Seeds that are unique, loadable by AFL++ and neither crash nor hang the target
+    pub fn clean_seeds(&self) -> Vec<&Path> {
+        self.seeds
+            .iter()
+            .filter(|s| {
+                !self.duplicates.iter().any(|(dup, _)| dup == *s)
+                    && !self.empty.contains(s)
+                    && !self.oversized.contains(s)
+                    && !self.crashing.contains(s)
+                    && !self.hanging.contains(s)
+            })
+            .map(PathBuf::as_path)
+            .collect()
+    }
+
+    /// Whether AFL++ would abort on the corpus without `AFL_IGNORE_SEED_PROBLEMS`
+    pub fn needs_ignore_seed_problems(&self) -> bool {
+        !self.crashing.is_empty() || !self.hanging.is_empty()
+    }
+
+    /// Prints a summary of all findings
+    pub fn print(&self) {
+        println!("[+] Seed preflight results:");
+        println!("    Seeds:       {}", self.seeds.len());
+        println!("    Duplicates:  {}", self.duplicates.len());
+        println!("    Empty:       {}", self.empty.len());
+        println!(
+            "    Oversized:   {} (> {MAX_FILE} bytes)",
+            self.oversized.len()
+        );
+        println!("    Crashing:    {}", self.crashing.len());
+        println!("    Hanging:     {}", self.hanging.len());
+        println!("    Clean seeds: {}", self.clean_seeds().len());
+        for (dup, orig) in &self.duplicates {
+            println!(
+                "[*] Duplicate seed: {} (same as {})",
+                dup.display(),
+                orig.display()
+            );
+        }
+        for (label, seeds) in [
+            ("Empty", &self.empty),
+            ("Oversized", &self.oversized),
+            ("Crashing", &self.crashing),
+            ("Hanging", &self.hanging),
+        ] {
+            for seed in seeds {
+                println!("[!] {label} seed: {}", seed.display());
+            }
+        }
+    }
+
+    /// Writes the report to `dir` for later review
+    ///
+    /// # Errors
+    /// * If the report cannot be serialized or written
+    pub fn write(&self, dir: &Path) -> Result<PathBuf> {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create directory: {}", dir.display()))?;
+        let path = dir.join(PREFLIGHT_REPORT);
+        fs::write(&path, toml::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    }
+}
+
+/// Checks a seed corpus for duplicates, size problems, crashes and hangs
+#[derive(Debug, Clone)]
+pub struct Preflight {
+    runner: TargetRunner,
+    target: PathBuf,
+    target_args: Vec<String>,
+}
+
+impl Preflight {
+    /// Creates a new preflight check for the target and its arguments
+    pub fn new(target: &Path, target_args: Vec<String>) -> Self {
+        Self {
+            runner: TargetRunner::new(
+                target,
+                target_args.clone(),
+                Duration::from_millis(DEFAULT_EXEC_TIMEOUT_MS),
+            ),
+            target: target.to_path_buf(),
+            target_args,
+        }
+    }
+
+    /// Checks every seed in `seed_dir`, executing each unique seed once
+    ///
+    /// # Errors
+    /// * If the seed directory or a seed cannot be read
+    /// * If the target cannot be executed
+    pub fn run(&self, seed_dir: &Path) -> Result<PreflightReport> {
+        let seeds = list_seeds(seed_dir)?;
+        println!("[*] Running seed preflight on {} seeds...", seeds.len());
+
+        let mut report = PreflightReport::default();
+        let mut by_hash: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+        for seed in &seeds {
+            let data =
+                fs::read(seed).with_context(|| format!("Failed to read {}", seed.display()))?;
+            if data.is_empty() {
+                report.empty.push(seed.clone());
+                continue;
+            }
+            if data.len() as u64 > MAX_FILE {
+                report.oversized.push(seed.clone());
+                continue;
+            }
+
+            let candidates = by_hash.entry(content_hash(&data)).or_default();
+            let original = candidates
+                .iter()
+                .find(|c| fs::read(c).is_ok_and(|other| other == data))
+                .cloned();
+            if let Some(original) = original {
+                report.duplicates.push((seed.clone(), original));
+                continue;
+            }
+            candidates.push(seed.clone());
+
+            match self.runner.run(seed)?.outcome {
+                ExecOutcome::Crashed(_) => report.crashing.push(seed.clone()),
+                ExecOutcome::TimedOut => report.hanging.push(seed.clone()),
+                ExecOutcome::Exited(_) => {}
+            }
+        }
+        report.seeds = seeds;
+        Ok(report)
+    }
+
+    /// Copies the clean seeds into `stage_dir`, optionally minimizing them with `afl-tmin`
+    ///
+    /// # Errors
+    /// * If no clean seeds are left
+    /// * If the staging directory is not empty, it is never cleaned as it may hold seeds
+    /// * If the staging directory cannot be prepared or a seed cannot be copied
+    pub fn stage(
+        &self,
+        report: &PreflightReport,
+        stage_dir: &Path,
+        tmin: Option<&Path>,
+    ) -> Result<usize> {
+        let clean = report.clean_seeds();
+        if clean.is_empty() {
+            bail!("No clean seeds left to stage");
+        }
+        if stage_dir
+            .read_dir()
+            .is_ok_and(|mut entries| entries.next().is_some())
+        {
+            bail!(
+                "Staging directory {} is not empty, choose a new or empty directory",
+                stage_dir.display()
+            );
+        }
+        mkdir_helper(stage_dir, false)?;
+
+        let mut trimmed = 0;
+        for seed in &clean {
+            let dst = stage_dir.join(seed.file_name().unwrap_or_default());
+            if let Some(tmin) = tmin {
+                if self.trim(tmin, seed, &dst) {
+                    trimmed += 1;
+                    continue;
+                }
+                println!("[!] afl-tmin failed for {}, copying as-is", seed.display());
+            }
+            fs::copy(seed, &dst)
+                .with_context(|| format!("Failed to copy seed {}", seed.display()))?;
+        }
+        if tmin.is_some() {
+            println!("[+] Trimmed {trimmed}/{} seeds", clean.len());
+        }
+        println!(
+            "[+] Staged {} clean seeds in {}",
+            clean.len(),
+            stage_dir.display()
+        );
+        Ok(clean.len())
+    }
+
+    /// Minimizes a single seed with `afl-tmin`
+    fn trim(&self, tmin: &Path, seed: &Path, dst: &Path) -> bool {
+        Command::new(tmin)
+            .arg("-i")
+            .arg(seed)
+            .arg("-o")
+            .arg(dst)
+            .arg("-t")
+            .arg(DEFAULT_EXEC_TIMEOUT_MS.to_string())
+            .arg("--")
+            .arg(&self.target)
+            .args(&self.target_args)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|s| s.success())
+            && dst.is_file()
+    }
+}
+
+/// Locates `afl-tmin` next to the used `afl-fuzz` binary
+///
+/// # Errors
+/// * If `afl-tmin` does not exist alongside `afl-fuzz`
+pub fn find_afl_tmin(afl_fuzz: &Path) -> Result<PathBuf> {
+    let tmin = afl_fuzz.with_file_name("afl-tmin");
+    if !tmin.is_file() {
+        bail!(
+            "afl-tmin not found next to {}, cannot trim seeds",
+            afl_fuzz.display()
+        );
+    }
+    Ok(tmin)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn crash_on(pattern: &str) -> Preflight {
+        Preflight::new(
+            Path::new("/bin/sh"),
+            vec![
+                "-c".into(),
+                format!("grep -q {pattern} \"$1\" && kill -SEGV $$; exit 0"),
+                "sh".into(),
+                "@@".into(),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_ensure_seed_dir() {
+        let dir = tempdir().unwrap();
+        let seeds = dir.path().join("in");
+        ensure_seed_dir(&seeds).unwrap();
+        assert_eq!(list_seeds(&seeds).unwrap().len(), 1);
+
+        // Existing seeds are left alone
+        ensure_seed_dir(&seeds).unwrap();
+        assert_eq!(list_seeds(&seeds).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_preflight_and_stage() {
+        let dir = tempdir().unwrap();
+        let seeds = dir.path().join("in");
+        fs::create_dir(&seeds).unwrap();
+        fs::write(seeds.join("a"), "hello").unwrap();
+        fs::write(seeds.join("b"), "hello").unwrap();
+        fs::write(seeds.join("c"), "crash").unwrap();
+        fs::write(seeds.join("d"), "").unwrap();
+        fs::write(seeds.join("e"), vec![b'x'; MAX_FILE as usize + 1]).unwrap();
+        fs::write(seeds.join("f"), "world").unwrap();
+
+        let preflight = crash_on("crash");
+        let report = preflight.run(&seeds).unwrap();
+        assert_eq!(report.seeds.len(), 6);
+        assert_eq!(report.duplicates, vec![(seeds.join("b"), seeds.join("a"))]);
+        assert_eq!(report.crashing, vec![seeds.join("c")]);
+        assert_eq!(report.empty, vec![seeds.join("d")]);
+        assert_eq!(report.oversized, vec![seeds.join("e")]);
+        assert!(report.needs_ignore_seed_problems());
+        assert_eq!(
+            report.clean_seeds(),
+            vec![seeds.join("a").as_path(), seeds.join("f").as_path()]
+        );
+
+        let stage = dir.path().join("stage");
+        assert_eq!(preflight.stage(&report, &stage, None).unwrap(), 2);
+        assert_eq!(list_seeds(&stage).unwrap().len(), 2);
+        // Staging never cleans a directory, e.g. the seed corpus itself
+        assert!(preflight.stage(&report, &stage, None).is_err());
+        assert!(preflight.stage(&report, &seeds, None).is_err());
+        assert_eq!(list_seeds(&seeds).unwrap().len(), 6);
+
+        let written = report.write(&dir.path().join("out")).unwrap();
+        assert!(fs::read_to_string(written).unwrap().contains("crashing"));
+    }
+
+    #[test]
+    fn test_find_afl_tmin() {
+        let dir = tempdir().unwrap();
+        let fuzz = dir.path().join("afl-fuzz");
+        assert!(find_afl_tmin(&fuzz).is_err());
+        fs::write(dir.path().join("afl-tmin"), "").unwrap();
+        assert_eq!(find_afl_tmin(&fuzz).unwrap(), dir.path().join("afl-tmin"));
+    }
+}
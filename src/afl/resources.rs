@@ -0,0 +1,361 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use sysinfo::System;
+
+use crate::utils::system::{get_free_mem_in_mb, RAMDISK_SIZE_MB};
+use crate::utils::topology::CpuTopology;
+
+/// Memory kept free for the OS and everything else running on the host
+const RESERVED_MEM_MB: u64 = 4096;
+/// Rough footprint of a single `afl-fuzz` instance plus its target, excluding the testcache
+const INSTANCE_OVERHEAD_MB: u64 = 256;
+/// AFL++ default for `AFL_TESTCACHE_SIZE`
+pub const DEFAULT_TESTCACHE_MB: u32 = 50;
+
+/// Number of AFL++ runners as requested by the user
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(try_from = "RawRunnerCount")]
+pub enum RunnerCount {
+    /// Exactly this many runners
+    Fixed(u32),
+    /// Derive the runner count from the host, using at most this percentage of physical cores
+    Auto(u8),
+}
+
+impl Default for RunnerCount {
+    fn default() -> Self {
+        Self::Fixed(1)
+    }
+}
+
+impl FromStr for RunnerCount {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s == "auto" {
+            return Ok(Self::Auto(100));
+        }
+        if let Some(pct) = s.strip_prefix("auto:") {
+            let pct = pct
+                .trim_end_matches('%')
+                .parse::<u8>()
+                .map_err(|_| format!("Invalid runner percentage: {s}"))?;
+            if pct == 0 || pct > 100 {
+                return Err(format!("Runner percentage must be within 1-100%: {s}"));
+            }
+            return Ok(Self::Auto(pct));
+        }
+        match s.parse::<u32>() {
+            Ok(0) => Err("Amount of runners must be greater than 0".to_string()),
+            Ok(n) => Ok(Self::Fixed(n)),
+            Err(_) => Err(format!(
+                "Invalid runner count '{s}', expected a number, 'auto' or 'auto:<PCT>%'"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for RunnerCount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Fixed(n) => write!(f, "{n}"),
+            Self::Auto(100) => write!(f, "auto"),
+            Self::Auto(pct) => write!(f, "auto:{pct}%"),
+        }
+    }
+}
+
+/// Config representation of `runners`, which may be an integer or a string
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawRunnerCount {
+    Number(u32),
+    Text(String),
+}
+
+impl TryFrom<RawRunnerCount> for RunnerCount {
+    type Error = String;
+
+    fn try_from(raw: RawRunnerCount) -> Result<Self, Self::Error> {
+        match raw {
+            RawRunnerCount::Number(n) => n.to_string().parse(),
+            RawRunnerCount::Text(s) => s.parse(),
+        }
+    }
+}
+
+/// Snapshot of the host resources relevant for planning a campaign
+#[derive(Debug, Clone)]
+pub struct HostResources {
+    /// Number of physical cores
+    pub physical_cores: usize,
+    /// Number of logical CPUs
+    pub logical_cpus: usize,
+    /// 1-minute load average
+    pub load_avg: f64,
+    /// Free memory in MB
+    pub free_mem_mb: u64,
+}
+
+impl HostResources {
+    /// Gathers the resources of the running system
+    pub fn detect() -> Self {
+        let topology = CpuTopology::detect().ok();
+        let logical_cpus = topology
+            .as_ref()
+            .map(|t| t.cpus.len())
+            .filter(|&n| n > 0)
+            .or_else(|| std::thread::available_parallelism().ok().map(usize::from))
+            .unwrap_or(1);
+        let physical_cores = topology
+            .as_ref()
+            .map(CpuTopology::physical_cores)
+            .filter(|&n| n > 0)
+            .or_else(|| System::new().physical_core_count())
+            .unwrap_or(logical_cpus);
+
+        Self {
+            physical_cores,
+            logical_cpus,
+            load_avg: System::load_average().one,
+            free_mem_mb: get_free_mem_in_mb(),
+        }
+    }
+}
+
+/// Resources a campaign is going to use
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourcePlan {
+    /// Number of AFL++ runners
+    pub runners: u32,
+    /// Logical CPUs on the host
+    pub total_cpus: usize,
+    /// Expected memory usage per instance in MB
+    pub mem_per_instance_mb: u64,
+    /// `AFL_TESTCACHE_SIZE` per instance in MB
+    pub testcache_mb: u32,
+    /// Size of the `RAMDisk` in MB, if one is used
+    pub ramdisk_mb: Option<u64>,
+}
+
+impl ResourcePlan {
+    /// Creates a resource plan for the requested runner count
+    ///
+    /// `-n auto` picks a count within the CPU and memory limits. Explicit counts that exceed
+    /// them are refused unless `allow_oversubscribe` is set, then they merely print a warning.
+    ///
+    /// # Errors
+    /// * If the host does not have enough memory for a single instance with `-n auto`
+    /// * If the plan oversubscribes the host and `allow_oversubscribe` is not set
+    pub fn new(
+        host: &HostResources,
+        requested: RunnerCount,
+        is_ramdisk: bool,
+        allow_oversubscribe: bool,
+    ) -> Result<Self> {
+        let usable_mem = host.free_mem_mb.saturating_sub(RESERVED_MEM_MB);
+
+        let runners = match requested {
+            RunnerCount::Fixed(n) => n,
+            RunnerCount::Auto(pct) => {
+                #[allow(clippy::cast_possible_truncation)]
+                #[allow(clippy::cast_sign_loss)]
+                let busy = host.load_avg.round().max(0.0) as usize;
+                let by_cores = (host.physical_cores * usize::from(pct) / 100)
+                    .max(1)
+                    .saturating_sub(busy)
+                    .max(1);
+                // Free memory is unknown on some platforms, only the cores bound the count then
+                let by_mem = if host.free_mem_mb == 0 {
+                    u64::MAX
+                } else {
+                    usable_mem / (INSTANCE_OVERHEAD_MB + u64::from(DEFAULT_TESTCACHE_MB))
+                };
+                if by_mem == 0 {
+                    bail!(
+                        "Not enough free memory for a single instance ({} MB free, {} MB reserved)",
+                        host.free_mem_mb,
+                        RESERVED_MEM_MB
+                    );
+                }
+                u32::try_from((by_cores as u64).min(by_mem)).unwrap_or(u32::MAX)
+            }
+        };
+
+        let testcache_mb = testcache_size(runners, host.free_mem_mb);
+        let plan = Self {
+            runners,
+            total_cpus: host.logical_cpus,
+            mem_per_instance_mb: INSTANCE_OVERHEAD_MB + u64::from(testcache_mb),
+            testcache_mb,
+            ramdisk_mb: is_ramdisk.then_some(RAMDISK_SIZE_MB),
+        };
+        let problems = plan.problems(host);
+        if !problems.is_empty() && !allow_oversubscribe {
+            bail!(
+                "{}\nPass --allow-oversubscribe to start the runners anyway",
+                problems.join("\n")
+            );
+        }
+        for problem in problems {
+            println!("[!] Warning: {problem}");
+        }
+        // The load is transient, so exceeding it together with the runners is not refused
+        #[allow(clippy::cast_precision_loss)]
+        if plan.runners as f64 + host.load_avg > host.logical_cpus as f64 {
+            println!(
+                "[!] Warning: Current load average ({:.1}) plus {} runners exceeds the available CPUs",
+                host.load_avg, plan.runners
+            );
+        }
+        Ok(plan)
+    }
+
+    /// Describes where the plan needs more CPUs or memory than the host has
+    fn problems(&self, host: &HostResources) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.runners as usize > host.logical_cpus {
+            problems.push(format!(
+                "{} runners oversubscribe the {} logical CPUs of this host. Consider lowering -n or using -n auto",
+                self.runners, host.logical_cpus
+            ));
+        }
+        let needed = u64::from(self.runners) * self.mem_per_instance_mb;
+        if host.free_mem_mb > 0 && needed > host.free_mem_mb.saturating_sub(RESERVED_MEM_MB) {
+            problems.push(format!(
+                "{} runners need ~{} MB but only {} MB are free ({} MB reserved). Consider lowering -n or using -n auto",
+                self.runners, needed, host.free_mem_mb, RESERVED_MEM_MB
+            ));
+        }
+        problems
+    }
+
+    /// Prints a summary of the plan
+    pub fn print(&self) {
+        println!("[*] Resource plan:");
+        println!(
+            "    Cores used:           {}/{}",
+            self.runners, self.total_cpus
+        );
+        println!("    Memory per instance:  ~{} MB", self.mem_per_instance_mb);
+        println!("    Testcache size:       {} MB", self.testcache_mb);
+        match self.ramdisk_mb {
+            Some(size) => println!("    RAMDisk size:         {size} MB"),
+            None => println!("    RAMDisk size:         -"),
+        }
+    }
+}
+
+/// Picks `AFL_TESTCACHE_SIZE` based on the runner count and available memory
+pub fn testcache_size(runners: u32, free_mb: u64) -> u32 {
+    let per_instance = |cache_mb: u64| u64::from(runners) * (cache_mb + INSTANCE_OVERHEAD_MB);
+    match free_mb {
+        x if x > per_instance(500) + RESERVED_MEM_MB => 500,
+        x if x > per_instance(250) + RESERVED_MEM_MB => 250,
+        _ => DEFAULT_TESTCACHE_MB,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn host(physical_cores: usize, load_avg: f64, free_mem_mb: u64) -> HostResources {
+        HostResources {
+            physical_cores,
+            logical_cpus: physical_cores * 2,
+            load_avg,
+            free_mem_mb,
+        }
+    }
+
+    #[test]
+    fn test_runner_count_parsing() {
+        assert_eq!("4".parse::<RunnerCount>(), Ok(RunnerCount::Fixed(4)));
+        assert_eq!("auto".parse::<RunnerCount>(), Ok(RunnerCount::Auto(100)));
+        assert_eq!("auto:80%".parse::<RunnerCount>(), Ok(RunnerCount::Auto(80)));
+        assert_eq!("auto:50".parse::<RunnerCount>(), Ok(RunnerCount::Auto(50)));
+        assert!("0".parse::<RunnerCount>().is_err());
+        assert!("auto:0%".parse::<RunnerCount>().is_err());
+        assert!("auto:120%".parse::<RunnerCount>().is_err());
+        assert!("many".parse::<RunnerCount>().is_err());
+        assert_eq!(RunnerCount::Auto(80).to_string(), "auto:80%");
+    }
+
+    #[test]
+    fn test_runner_count_deserialize() {
+        #[derive(Deserialize)]
+        struct Cfg {
+            runners: RunnerCount,
+        }
+        let num: Cfg = toml::from_str("runners = 8").unwrap();
+        assert_eq!(num.runners, RunnerCount::Fixed(8));
+        let auto: Cfg = toml::from_str("runners = \"auto:75%\"").unwrap();
+        assert_eq!(auto.runners, RunnerCount::Auto(75));
+        assert!(toml::from_str::<Cfg>("runners = \"lots\"").is_err());
+    }
+
+    #[test]
+    fn test_auto_bounded_by_cores_and_load() {
+        let plan = ResourcePlan::new(&host(16, 0.0, 64_000), RunnerCount::Auto(100), false, false)
+            .unwrap();
+        assert_eq!(plan.runners, 16);
+
+        let plan =
+            ResourcePlan::new(&host(16, 0.0, 64_000), RunnerCount::Auto(50), false, false).unwrap();
+        assert_eq!(plan.runners, 8);
+
+        let plan = ResourcePlan::new(&host(16, 4.2, 64_000), RunnerCount::Auto(100), false, false)
+            .unwrap();
+        assert_eq!(plan.runners, 12);
+    }
+
+    #[test]
+    fn test_auto_bounded_by_memory() {
+        let plan =
+            ResourcePlan::new(&host(64, 0.0, 5_000), RunnerCount::Auto(100), false, false).unwrap();
+        assert_eq!(plan.runners, 2);
+        assert_eq!(plan.testcache_mb, DEFAULT_TESTCACHE_MB);
+
+        assert!(
+            ResourcePlan::new(&host(64, 0.0, 4_000), RunnerCount::Auto(100), false, false).is_err()
+        );
+
+        // Unknown free memory only bounds by cores
+        let plan =
+            ResourcePlan::new(&host(8, 0.0, 0), RunnerCount::Auto(100), false, false).unwrap();
+        assert_eq!(plan.runners, 8);
+    }
+
+    #[test]
+    fn test_fixed_oversubscription() {
+        let few_cpus = host(4, 0.0, 64_000);
+        assert!(ResourcePlan::new(&few_cpus, RunnerCount::Fixed(9), false, false).is_err());
+        let plan = ResourcePlan::new(&few_cpus, RunnerCount::Fixed(9), false, true).unwrap();
+        assert_eq!(plan.runners, 9);
+        assert_eq!(plan.problems(&few_cpus).len(), 1);
+
+        let low_mem = host(4, 0.0, 4_500);
+        assert!(ResourcePlan::new(&low_mem, RunnerCount::Fixed(4), false, false).is_err());
+        let plan = ResourcePlan::new(&low_mem, RunnerCount::Fixed(4), false, true).unwrap();
+        assert_eq!(plan.problems(&low_mem).len(), 1);
+        assert!(plan.problems(&host(4, 0.0, 0)).is_empty());
+
+        // A high load alone is only a warning
+        let plan =
+            ResourcePlan::new(&host(4, 12.0, 64_000), RunnerCount::Fixed(8), true, false).unwrap();
+        assert_eq!(plan.testcache_mb, 500);
+        assert_eq!(plan.ramdisk_mb, Some(RAMDISK_SIZE_MB));
+    }
+
+    #[test]
+    fn test_testcache_size() {
+        assert_eq!(testcache_size(4, 10_000), 500);
+        assert_eq!(testcache_size(4, 7_000), 250);
+        assert_eq!(testcache_size(4, 5_500), DEFAULT_TESTCACHE_MB);
+        assert_eq!(testcache_size(4, 4_000), DEFAULT_TESTCACHE_MB);
+    }
+}
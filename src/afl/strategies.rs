@@ -1,9 +1,10 @@
 use crate::afl::cmd::AFLCmd;
 use crate::afl::mode::Mode;
+use crate::afl::version::{Capabilities, Capability};
 use once_cell::sync::Lazy;
 use rand::seq::SliceRandom;
 use std::collections::HashSet;
-use std::{fmt, path::PathBuf};
+use std::{fmt, path::PathBuf, str::FromStr};
 
 /// These structs contain the AFL++ strategies and their probabilities of being applied in the command generation.
 ///
@@ -50,7 +51,7 @@ impl CmpcovConfig {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CmplogMode {
     Standard,   // -l 2
     Extended,   // -l 3
@@ -108,7 +109,7 @@ impl CmplogConfig {
 }
 
 /// Represents different types of AFL++ mutation modes
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MutationMode {
     Explore,
     Exploit,
@@ -123,6 +124,18 @@ impl fmt::Display for MutationMode {
     }
 }
 
+impl FromStr for MutationMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "explore" => Ok(Self::Explore),
+            "exploit" => Ok(Self::Exploit),
+            _ => Err(format!("Unknown mutation mode: {s}")),
+        }
+    }
+}
+
 /// Represents different input format types
 #[derive(Debug, Clone, Copy)]
 pub enum FormatMode {
@@ -140,7 +153,7 @@ impl fmt::Display for FormatMode {
 }
 
 /// Represents power schedule options
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PowerSchedule {
     Fast,
     Explore,
@@ -166,6 +179,23 @@ impl fmt::Display for PowerSchedule {
     }
 }
 
+impl FromStr for PowerSchedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "fast" => Ok(Self::Fast),
+            "explore" => Ok(Self::Explore),
+            "coe" => Ok(Self::Coe),
+            "lin" => Ok(Self::Lin),
+            "quad" => Ok(Self::Quad),
+            "exploit" => Ok(Self::Exploit),
+            "rare" => Ok(Self::Rare),
+            _ => Err(format!("Unknown power schedule: {s}")),
+        }
+    }
+}
+
 /// Represents how multiple flags of the same type should be applied
 #[allow(dead_code)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -215,6 +245,8 @@ pub struct AFLStrategy {
     /// internal state to check where to apply some configurations
     /// (e.g. in CI mode we apply all configurations to all commands as we do not have a -M fuzzer)
     is_ci_fuzzing: bool,
+    /// Options supported by the used `afl-fuzz`
+    capabilities: Capabilities,
 }
 
 impl AFLStrategy {
@@ -294,7 +326,10 @@ impl AFLStrategy {
         };
 
         // Apply mutation modes
-        if !self.mutation_modes.is_empty() {
+        if !target_cmds.is_empty()
+            && !self.mutation_modes.is_empty()
+            && self.capabilities.allows(Capability::MutationModes)
+        {
             Self::apply_exclusive_args(
                 target_cmds,
                 &self
@@ -307,7 +342,10 @@ impl AFLStrategy {
         }
 
         // Apply format modes
-        if !self.format_modes.is_empty() {
+        if !target_cmds.is_empty()
+            && !self.format_modes.is_empty()
+            && self.capabilities.allows(Capability::InputFormat)
+        {
             Self::apply_exclusive_args(
                 target_cmds,
                 &self
@@ -449,18 +487,25 @@ impl AFLStrategy {
         #[allow(clippy::cast_possible_truncation)]
         #[allow(clippy::cast_precision_loss)]
         #[allow(clippy::cast_sign_loss)]
-        let num_cmplog_cfgs = (cmds.len() as f64 * config.runner_ratio) as usize;
+        let num_cmplog_cfgs =
+            ((cmds.len() as f64 * config.runner_ratio) as usize).min(cmds.len().saturating_sub(1));
 
+        let modes = |modes: &[CmplogMode]| -> Vec<CmplogMode> {
+            modes
+                .iter()
+                .map(|m| self.supported_cmplog_mode(*m))
+                .collect()
+        };
         match num_cmplog_cfgs {
             0 => {}
             1 => {
-                Self::apply_cmplog_1_to_3(cmds, config, &[CmplogMode::Transforms], rng);
+                Self::apply_cmplog_1_to_3(cmds, config, &modes(&[CmplogMode::Transforms]), rng);
             }
             2 => {
                 Self::apply_cmplog_1_to_3(
                     cmds,
                     config,
-                    &[CmplogMode::Standard, CmplogMode::Transforms],
+                    &modes(&[CmplogMode::Standard, CmplogMode::Transforms]),
                     rng,
                 );
             }
@@ -468,21 +513,36 @@ impl AFLStrategy {
                 Self::apply_cmplog_1_to_3(
                     cmds,
                     config,
-                    &[
+                    &modes(&[
                         CmplogMode::Standard,
                         CmplogMode::Transforms,
                         CmplogMode::Extended,
-                    ],
+                    ]),
                     rng,
                 );
             }
 
             _ => {
-                Self::apply_many_cmplog(cmds, num_cmplog_cfgs, config, rng);
+                let mode_args: Vec<(String, f64)> = config
+                    .mode_distribution
+                    .iter()
+                    .map(|(mode, prob)| (self.supported_cmplog_mode(*mode).to_string(), *prob))
+                    .collect();
+                Self::apply_many_cmplog(cmds, num_cmplog_cfgs, config, &mode_args, rng);
             }
         }
     }
 
+    /// Substitutes `mode` with `-l 2` if the used `afl-fuzz` lacks transforms
+    fn supported_cmplog_mode(&self, mode: CmplogMode) -> CmplogMode {
+        if mode == CmplogMode::Transforms && !self.capabilities.allows(Capability::CmplogTransforms)
+        {
+            CmplogMode::Standard
+        } else {
+            mode
+        }
+    }
+
     fn apply_cmplog_1_to_3<R: rand::Rng>(
         cmds: &mut [AFLCmd],
         config: &CmplogConfig,
@@ -507,21 +567,15 @@ impl AFLStrategy {
         cmds: &mut [AFLCmd],
         num_cmplog_cfgs: usize,
         config: &CmplogConfig,
+        mode_args: &[(String, f64)],
         rng: &mut R,
     ) {
         if num_cmplog_cfgs >= cmds.len() {
             return;
         }
 
-        // Convert CmplogMode to string arguments with probabilities
-        let mode_args: Vec<(String, f64)> = config
-            .mode_distribution
-            .iter()
-            .map(|(mode, prob)| (mode.to_string(), *prob))
-            .collect();
-
         // Apply modes exclusively to the selected range
-        Self::apply_exclusive_args(&mut cmds[1..=num_cmplog_cfgs], &mode_args, rng);
+        Self::apply_exclusive_args(&mut cmds[1..=num_cmplog_cfgs], mode_args, rng);
 
         // Add the binary path to all CMPLOG-enabled commands
         for cmd in &mut cmds[1..=num_cmplog_cfgs] {
@@ -578,6 +632,7 @@ pub struct AFLStrategyBuilder {
     cmplog_config: Option<CmplogConfig>,
     cmpcov_config: Option<CmpcovConfig>,
     is_ci_fuzzing: bool,
+    capabilities: Capabilities,
 }
 
 impl AFLStrategyBuilder {
@@ -621,6 +676,12 @@ impl AFLStrategyBuilder {
         self.cmpcov_config = Some(config);
     }
 
+    /// Restricts the strategy to the options supported by the used `afl-fuzz`
+    pub fn with_capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
     fn with_ci(mut self) -> Self {
         self.is_ci_fuzzing = true;
         self
@@ -636,6 +697,7 @@ impl AFLStrategyBuilder {
             cmplog_config: self.cmplog_config,
             cmpcov_config: self.cmpcov_config,
             is_ci_fuzzing: self.is_ci_fuzzing,
+            capabilities: self.capabilities,
         }
     }
 }
@@ -964,6 +1026,7 @@ mod tests {
 
     mod cmplog_tests {
         use super::*;
+        use crate::afl::version::AflVersion;
 
         #[test]
         fn test_single_cmplog() {
@@ -1009,6 +1072,56 @@ mod tests {
                 .count();
             assert_eq!(cmplog_count, 6); // 60% of 10
         }
+
+        #[test]
+        fn test_cmplog_everywhere() {
+            let mut rng = get_test_rng();
+            let mut cmds = create_test_cmds(10);
+
+            let mut strategy_bld = AFLStrategy::builder(Mode::CIFuzzing);
+            strategy_bld.with_cmplog(CmplogConfig {
+                runner_ratio: 1.0,
+                ..CmplogConfig::new(PathBuf::from("/bin/cmplog"))
+            });
+            strategy_bld.build().apply(&mut cmds, &mut rng, false);
+
+            // Every instance but the first one
+            let cmplog_count = cmds
+                .iter()
+                .filter(|cmd| cmd.misc_afl_flags.iter().any(|f| f.contains("-c")))
+                .count();
+            assert_eq!(cmplog_count, 9);
+        }
+
+        #[test]
+        fn test_capability_downgrades() {
+            let mut rng = get_test_rng();
+            let mut cmds = create_test_cmds(10);
+            let capabilities = Capabilities::new(Some(AflVersion::new(3, 0, 'c')));
+
+            let mut strategy_bld =
+                AFLStrategy::builder(Mode::MultipleCores).with_capabilities(capabilities.clone());
+            strategy_bld.with_cmplog(CmplogConfig {
+                binary: PathBuf::from("/bin/cmplog"),
+                runner_ratio: 0.6,
+                mode_distribution: vec![(CmplogMode::Transforms, 1.0)],
+            });
+            strategy_bld.build().apply(&mut cmds, &mut rng, false);
+
+            let flags: Vec<_> = cmds.iter().flat_map(|c| &c.misc_afl_flags).collect();
+            assert!(flags.iter().any(|f| *f == "-l 2"));
+            assert!(flags
+                .iter()
+                .all(|f| *f != "-l 2AT" && !f.starts_with("-P ") && !f.starts_with("-a ")));
+            assert_eq!(
+                capabilities.downgraded(),
+                vec![
+                    Capability::CmplogTransforms,
+                    Capability::MutationModes,
+                    Capability::InputFormat
+                ]
+            );
+        }
     }
 
     mod cmpcov_tests {
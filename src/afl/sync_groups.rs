@@ -0,0 +1,328 @@
+use std::{
+    collections::BTreeSet,
+    ffi::OsString,
+    fs,
+    ops::Range,
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::afl::cmd::AFLCmd;
+use crate::utils::hash::content_hash;
+use crate::utils::system::{any_fuzzer_alive, spawn_detached};
+
+/// Prefix of the output directory of a sync group, followed by the group index
+pub const GROUP_DIR_PREFIX: &str = "group-";
+/// Directory in a group the entries of the other groups are copied into, read via `-F`
+pub const GROUP_SYNC_DIR: &str = "aflr_groups";
+/// Log of the background group sync in the AFL++ output directory
+pub const GROUP_SYNC_LOG: &str = "aflr_groups.log";
+/// PID of the background group sync in the AFL++ output directory
+pub const GROUP_SYNC_PID: &str = "aflr_groups.pid";
+/// Interval between cross-group syncs if none is configured
+pub const DEFAULT_GROUP_SYNC_INTERVAL: Duration = Duration::from_secs(10 * 60);
+/// State of the group sync in the AFL++ output directory
+const GROUP_SYNC_STATE: &str = "aflr_groups_state.json";
+
+/// Splits `instances` into the fewest groups of at most `size` instances with balanced
+/// sizes and returns their index ranges
+pub fn split(instances: usize, size: usize) -> Vec<Range<usize>> {
+    let groups = instances.div_ceil(size.max(1)).max(1);
+    (0..groups)
+        .map(|g| g * instances / groups..(g + 1) * instances / groups)
+        .collect()
+}
+
+/// Output directory of group `group` in `afl_out`
+pub fn group_dir(afl_out: &Path, group: usize) -> PathBuf {
+    afl_out.join(format!("{GROUP_DIR_PREFIX}{group}"))
+}
+
+/// Whether `path` is the output directory of a sync group
+pub fn is_group_dir(path: &Path) -> bool {
+    path.is_dir()
+        && path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(|n| n.strip_prefix(GROUP_DIR_PREFIX))
+            .is_some_and(|i| i.parse::<usize>().is_ok())
+}
+
+/// Output directories of the groups `cmds` are split into, empty for a flat campaign
+pub fn group_dirs(afl_out: &Path, cmds: &[AFLCmd]) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = cmds
+        .iter()
+        .map(|c| c.output_dir.clone())
+        .filter(|d| d != afl_out)
+        .collect();
+    dirs.dedup();
+    dirs
+}
+
+fn sorted_subdirs(dir: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(std::result::Result::ok)
+        .map(|e| e.path())
+        .filter(|p| p.is_dir())
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+fn is_instance_dir(path: &Path) -> bool {
+    path.join("queue").is_dir() || path.join("fuzzer_stats").is_file()
+}
+
+/// Instance directories in `afl_out`, including those nested in sync groups, sorted by path
+pub fn instance_dirs(afl_out: &Path) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    for path in sorted_subdirs(afl_out) {
+        if is_instance_dir(&path) {
+            dirs.push(path);
+        } else if is_group_dir(&path) {
+            dirs.extend(
+                sorted_subdirs(&path)
+                    .into_iter()
+                    .filter(|p| is_instance_dir(p)),
+            );
+        }
+    }
+    dirs
+}
+
+/// Directory of the instance `name` in `afl_out`, wherever it is nested
+pub fn find_instance_dir(afl_out: &Path, name: &str) -> PathBuf {
+    instance_dirs(afl_out)
+        .into_iter()
+        .find(|d| d.file_name().is_some_and(|n| n == name))
+        .unwrap_or_else(|| afl_out.join(name))
+}
+
+/// Persistent state of the group sync of a campaign
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct GroupSyncState {
+    /// Queue entries already shared, relative to the output directory
+    offered: BTreeSet<String>,
+    /// Content hashes of all shared entries
+    known: BTreeSet<String>,
+}
+
+/// Shares the queue entries of each sync group with all other groups of a campaign
+///
+/// New entries are copied into `aflr_groups/` of every other group, which the `-M`
+/// instance of the group imports via `-F` and hands on to its secondaries.
+#[derive(Debug, Clone)]
+pub struct GroupSync {
+    afl_out: PathBuf,
+}
+
+impl GroupSync {
+    pub fn new(afl_out: &Path) -> Self {
+        Self {
+            afl_out: afl_out.to_path_buf(),
+        }
+    }
+
+    /// Copies the new entries of every group into the other groups once and returns the
+    /// number of shared entries
+    ///
+    /// # Errors
+    /// * If the output directory has fewer than two groups
+    /// * If an entry or the state cannot be read or written
+    pub fn sync(&self) -> Result<usize> {
+        let groups: Vec<PathBuf> = sorted_subdirs(&self.afl_out)
+            .into_iter()
+            .filter(|d| is_group_dir(d))
+            .collect();
+        if groups.len() < 2 {
+            bail!("No sync groups found in {}", self.afl_out.display());
+        }
+        let state_path = self.afl_out.join(GROUP_SYNC_STATE);
+        let mut state: GroupSyncState = fs::read_to_string(&state_path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+
+        let mut shared = 0;
+        for (g, group) in groups.iter().enumerate() {
+            for instance in sorted_subdirs(group).iter().filter(|p| is_instance_dir(p)) {
+                for entry in fs::read_dir(instance.join("queue"))
+                    .into_iter()
+                    .flatten()
+                    .filter_map(std::result::Result::ok)
+                {
+                    let path = entry.path();
+                    let is_entry = entry.file_name().to_string_lossy().starts_with("id:");
+                    if !is_entry || !path.is_file() {
+                        continue;
+                    }
+                    let rel = path
+                        .strip_prefix(&self.afl_out)
+                        .unwrap_or(&path)
+                        .to_string_lossy()
+                        .into_owned();
+                    if !state.offered.insert(rel) {
+                        continue;
+                    }
+                    let data = fs::read(&path)?;
+                    let hash = format!("{:016x}", content_hash(&data));
+                    if !state.known.insert(hash.clone()) {
+                        continue;
+                    }
+                    for (_, other) in groups.iter().enumerate().filter(|(o, _)| *o != g) {
+                        let sync_dir = other.join(GROUP_SYNC_DIR);
+                        fs::create_dir_all(&sync_dir)?;
+                        fs::write(sync_dir.join(format!("id:{hash}")), &data)?;
+                    }
+                    shared += 1;
+                }
+            }
+        }
+
+        fs::write(&state_path, serde_json::to_string(&state)?)
+            .with_context(|| format!("Failed to write {}", state_path.display()))?;
+        Ok(shared)
+    }
+
+    /// Syncs every `interval` until no instance of the campaign is alive anymore
+    pub fn run(&self, interval: Duration) {
+        println!(
+            "[*] Syncing the groups of {} every {}s",
+            self.afl_out.display(),
+            interval.as_secs()
+        );
+        loop {
+            thread::sleep(interval);
+            if !any_fuzzer_alive(&self.afl_out) {
+                println!("[*] No instance alive anymore, stopping the group sync");
+                return;
+            }
+            match self.sync() {
+                Ok(shared) => println!("[+] Shared {shared} new entries across groups"),
+                Err(e) => eprintln!("[-] Group sync failed: {e:#}"),
+            }
+        }
+    }
+}
+
+/// Stops the background group sync of the campaign in `afl_out`, returns whether one ran
+pub fn stop(afl_out: &Path) -> bool {
+    let pid_path = afl_out.join(GROUP_SYNC_PID);
+    let Some(pid) = fs::read_to_string(&pid_path)
+        .ok()
+        .and_then(|p| p.trim().parse::<libc::pid_t>().ok())
+    else {
+        return false;
+    };
+    let _ = fs::remove_file(&pid_path);
+    // Guard against the PID having been reused by an unrelated process
+    let is_group_sync = fs::read(format!("/proc/{pid}/cmdline"))
+        .is_ok_and(|c| c.split(|&b| b == 0).any(|arg| arg == b"group-sync"));
+    is_group_sync && unsafe { libc::kill(pid, libc::SIGTERM) } == 0
+}
+
+/// Starts `aflr group-sync` for the campaign in `afl_out` in the background, replacing a
+/// running one. Returns the PID and the log of the process.
+///
+/// # Errors
+/// * If the process cannot be started or its PID cannot be recorded
+pub fn spawn(afl_out: &Path, interval: Duration) -> Result<(u32, PathBuf)> {
+    stop(afl_out);
+    let log_path = afl_out.join(GROUP_SYNC_LOG);
+    let args: [OsString; 5] = [
+        "group-sync".into(),
+        "-i".into(),
+        afl_out.as_os_str().to_owned(),
+        "--interval".into(),
+        format!("{}s", interval.as_secs()).into(),
+    ];
+    let pid = spawn_detached(args, &log_path).context("Failed to start the group sync process")?;
+    fs::write(afl_out.join(GROUP_SYNC_PID), pid.to_string())?;
+    Ok((pid, log_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instance(afl_out: &Path, group: &str, name: &str, entries: &[&str]) {
+        let queue = afl_out.join(group).join(name).join("queue");
+        fs::create_dir_all(queue.join(".state")).unwrap();
+        for (i, data) in entries.iter().enumerate() {
+            fs::write(queue.join(format!("id:{i:06},time:0")), data).unwrap();
+        }
+    }
+
+    fn imported(group: &Path) -> Vec<String> {
+        let mut data: Vec<_> = fs::read_dir(group.join(GROUP_SYNC_DIR))
+            .unwrap()
+            .map(|e| fs::read_to_string(e.unwrap().path()).unwrap())
+            .collect();
+        data.sort();
+        data
+    }
+
+    #[test]
+    fn test_split() {
+        assert_eq!(split(8, 16), vec![0..8]);
+        assert_eq!(split(33, 16), vec![0..11, 11..22, 22..33]);
+        assert_eq!(split(64, 16).len(), 4);
+        assert!(split(64, 16).iter().all(|g| g.len() == 16));
+    }
+
+    #[test]
+    fn test_instance_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path();
+        instance(out, "", "m_target", &[]);
+        instance(out, "group-1", "m1_target", &[]);
+        instance(out, "group-1", "s5_target", &[]);
+        fs::create_dir_all(out.join("group-1").join(GROUP_SYNC_DIR)).unwrap();
+        fs::create_dir_all(out.join("aflr_sync")).unwrap();
+
+        let names: Vec<_> = instance_dirs(out)
+            .iter()
+            .map(|d| d.strip_prefix(out).unwrap().to_path_buf())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                PathBuf::from("group-1/m1_target"),
+                PathBuf::from("group-1/s5_target"),
+                PathBuf::from("m_target"),
+            ]
+        );
+        assert_eq!(
+            find_instance_dir(out, "s5_target"),
+            out.join("group-1/s5_target")
+        );
+    }
+
+    #[test]
+    fn test_group_sync() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path();
+        instance(out, "group-0", "m_target", &["shared", "a"]);
+        instance(out, "group-0", "s1_target", &["a1"]);
+        instance(out, "group-1", "m1_target", &["shared", "b"]);
+
+        let sync = GroupSync::new(out);
+        assert_eq!(sync.sync().unwrap(), 4);
+        assert_eq!(imported(&out.join("group-0")), vec!["b"]);
+        assert_eq!(imported(&out.join("group-1")), vec!["a", "a1", "shared"]);
+        assert_eq!(sync.sync().unwrap(), 0);
+
+        // Imported entries that reach a queue are not shared back
+        instance(out, "group-1", "s2_target", &["a", "c"]);
+        assert_eq!(sync.sync().unwrap(), 1);
+        assert_eq!(imported(&out.join("group-0")), vec!["b", "c"]);
+
+        assert!(GroupSync::new(&out.join("group-0")).sync().is_err());
+    }
+}
@@ -0,0 +1,308 @@
+use std::{
+    ffi::OsStr,
+    fmt, fs,
+    io::Write,
+    os::unix::process::{CommandExt, ExitStatusExt},
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus, Stdio},
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+
+/// Placeholder AFL++ replaces with the path of the current input
+pub const INPUT_PLACEHOLDER: &str = "@@";
+/// AFL++ default execution timeout (`-t`) in ms
+pub const DEFAULT_EXEC_TIMEOUT_MS: u64 = 1000;
+
+/// How a single execution of the target ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecOutcome {
+    /// The target exited on its own
+    Exited(i32),
+    /// The target was terminated by a signal
+    Crashed(i32),
+    /// The target exceeded the timeout and was killed
+    TimedOut,
+}
+
+impl fmt::Display for ExecOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Exited(code) => write!(f, "exited ({code})"),
+            Self::Crashed(sig) => write!(f, "crashed (signal {sig})"),
+            Self::TimedOut => write!(f, "timed out"),
+        }
+    }
+}
+
+/// Result of running the target with a single input
+#[derive(Debug, Clone)]
+pub struct ExecResult {
+    /// Input the target was executed with
+    pub input: PathBuf,
+    /// How the execution ended
+    pub outcome: ExecOutcome,
+    /// Wall-clock time of the execution
+    pub exec_time: Duration,
+    /// Peak resident set size of the execution in MB
+    pub peak_rss_mb: u64,
+}
+
+/// Runs a target binary with single inputs, either via `@@` or stdin
+#[derive(Debug, Clone)]
+pub struct TargetRunner {
+    binary: PathBuf,
+    args: Vec<String>,
+    envs: Vec<(String, String)>,
+    timeout: Duration,
+    mem_limit_mb: Option<u64>,
+}
+
+impl TargetRunner {
+    /// Creates a new runner for `binary`, `@@` in `args` is replaced with the input path
+    pub fn new<P: AsRef<Path>>(binary: P, args: Vec<String>, timeout: Duration) -> Self {
+        Self {
+            binary: binary.as_ref().to_path_buf(),
+            args,
+            envs: Vec::new(),
+            timeout,
+            mem_limit_mb: None,
+        }
+    }
+
+    /// Limits the address space of every execution to `mb` MB via `RLIMIT_AS`
+    pub fn with_mem_limit(mut self, mb: u64) -> Self {
+        self.mem_limit_mb = Some(mb);
+        self
+    }
+
+    /// Sets an environment variable for every execution
+    pub fn with_env(mut self, key: &str, value: &str) -> Self {
+        self.envs.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Whether the input is passed as a file instead of via stdin
+    pub fn is_file_based(&self) -> bool {
+        self.args.iter().any(|arg| arg.contains(INPUT_PLACEHOLDER))
+    }
+
+    /// Executes the target once with the given input, killing it after the timeout
+    ///
+    /// # Errors
+    /// * If the input cannot be read or the target cannot be spawned
+    pub fn run(&self, input: &Path) -> Result<ExecResult> {
+        self.run_with_envs::<&str, &str>(input, &[])
+    }
+
+    /// Executes the target once like `run`, with additional environment variables
+    ///
+    /// # Errors
+    /// * If the input cannot be read or the target cannot be spawned
+    pub fn run_with_envs<K, V>(&self, input: &Path, extra_envs: &[(K, V)]) -> Result<ExecResult>
+    where
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        let input_str = input.to_string_lossy();
+        let args: Vec<String> = self
+            .args
+            .iter()
+            .map(|arg| arg.replace(INPUT_PLACEHOLDER, &input_str))
+            .collect();
+        let stdin_data = if self.is_file_based() {
+            None
+        } else {
+            Some(
+                fs::read(input)
+                    .with_context(|| format!("Failed to read input file: {}", input.display()))?,
+            )
+        };
+
+        let mut cmd = Command::new(&self.binary);
+        if let Some(limit) = self.mem_limit_mb.map(|mb| mb.saturating_mul(1024 * 1024)) {
+            // SAFETY: setrlimit is async-signal-safe and only affects the forked child
+            unsafe {
+                cmd.pre_exec(move || {
+                    let rlim = libc::rlimit {
+                        rlim_cur: limit,
+                        rlim_max: limit,
+                    };
+                    if libc::setrlimit(libc::RLIMIT_AS, &rlim) != 0 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                    Ok(())
+                });
+            }
+        }
+
+        let start = Instant::now();
+        let mut child = cmd
+            .args(&args)
+            .envs(self.envs.iter().map(|(k, v)| (k, v)))
+            .envs(extra_envs.iter().map(|(k, v)| (k, v)))
+            .stdin(if stdin_data.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .process_group(0)
+            .spawn()
+            .with_context(|| format!("Failed to execute {}", self.binary.display()))?;
+
+        // Feed stdin from a separate thread so a target that never reads cannot block us
+        if let (Some(data), Some(mut stdin)) = (stdin_data, child.stdin.take()) {
+            thread::spawn(move || {
+                let _ = stdin.write_all(&data);
+            });
+        }
+
+        let pid = libc::pid_t::try_from(child.id()).context("Invalid target PID")?;
+        let (tx, rx) = mpsc::channel::<std::io::Result<(ExitStatus, u64)>>();
+        thread::spawn(move || {
+            let _ = tx.send(wait_with_rss(pid));
+        });
+
+        let (status, peak_rss_mb) = match rx.recv_timeout(self.timeout) {
+            Ok(waited) => {
+                let (status, rss) = waited.context("Failed to wait for target completion")?;
+                (Some(status), rss)
+            }
+            Err(_) => {
+                kill_process(pid);
+                // Reap the killed process so it does not linger as a zombie
+                let rss = rx
+                    .recv()
+                    .ok()
+                    .and_then(Result::ok)
+                    .map_or(0, |(_, rss)| rss);
+                (None, rss)
+            }
+        };
+        let exec_time = start.elapsed();
+
+        let outcome = match status {
+            None => ExecOutcome::TimedOut,
+            Some(status) => status.signal().map_or_else(
+                || ExecOutcome::Exited(status.code().unwrap_or_default()),
+                ExecOutcome::Crashed,
+            ),
+        };
+
+        Ok(ExecResult {
+            input: input.to_path_buf(),
+            outcome,
+            exec_time,
+            peak_rss_mb,
+        })
+    }
+}
+
+/// Kills the process group of a spawned target, including any processes it forked
+fn kill_process(pid: libc::pid_t) {
+    // SAFETY: sending a signal to the process group of a child we have not reaped yet
+    unsafe {
+        libc::kill(-pid, libc::SIGKILL);
+    }
+}
+
+/// Reaps the child `pid` and returns its exit status and peak resident set size in MB
+///
+/// The usage is taken from `wait4` so it only covers this child, not every child the
+/// process has reaped so far.
+fn wait_with_rss(pid: libc::pid_t) -> std::io::Result<(ExitStatus, u64)> {
+    let mut status = 0;
+    let mut usage = std::mem::MaybeUninit::<libc::rusage>::zeroed();
+    // SAFETY: wait4 only writes into the provided status and struct
+    while unsafe { libc::wait4(pid, &mut status, 0, usage.as_mut_ptr()) } != pid {
+        let err = std::io::Error::last_os_error();
+        if err.kind() != std::io::ErrorKind::Interrupted {
+            return Err(err);
+        }
+    }
+    // SAFETY: wait4 succeeded and initialized the struct
+    let usage = unsafe { usage.assume_init() };
+    // ru_maxrss is reported in KB on Linux
+    let rss = u64::try_from(usage.ru_maxrss).unwrap_or_default() / 1024;
+    Ok((ExitStatus::from_raw(status), rss))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn sh(script: &str, timeout_ms: u64) -> TargetRunner {
+        TargetRunner::new(
+            "/bin/sh",
+            vec!["-c".into(), script.into(), "sh".into(), "@@".into()],
+            Duration::from_millis(timeout_ms),
+        )
+    }
+
+    #[test]
+    fn test_outcomes() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("seed");
+        fs::write(&input, "data").unwrap();
+
+        let ok = sh("test -f \"$1\"", 5000).run(&input).unwrap();
+        assert_eq!(ok.outcome, ExecOutcome::Exited(0));
+        assert!(sh("exit 3", 5000).is_file_based());
+        assert_eq!(
+            sh("exit 3", 5000).run(&input).unwrap().outcome,
+            ExecOutcome::Exited(3)
+        );
+        assert_eq!(
+            sh("kill -SEGV $$", 5000).run(&input).unwrap().outcome,
+            ExecOutcome::Crashed(libc::SIGSEGV)
+        );
+
+        let hang = sh("sleep 5", 100).run(&input).unwrap();
+        assert_eq!(hang.outcome, ExecOutcome::TimedOut);
+        assert!(hang.exec_time < Duration::from_secs(5));
+
+        let big = sh("x=$(head -c 33554432 /dev/zero | tr '\\0' a); true", 5000)
+            .run(&input)
+            .unwrap();
+        assert!(big.peak_rss_mb >= 32);
+
+        let env = sh("test \"$REPLAY\" = yes", 5000)
+            .run_with_envs(&input, &[("REPLAY", "yes")])
+            .unwrap();
+        assert_eq!(env.outcome, ExecOutcome::Exited(0));
+    }
+
+    #[test]
+    fn test_mem_limit() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("seed");
+        fs::write(&input, "data").unwrap();
+
+        let limited = sh("test \"$(ulimit -v)\" = 65536", 5000).with_mem_limit(64);
+        assert_eq!(limited.run(&input).unwrap().outcome, ExecOutcome::Exited(0));
+    }
+
+    #[test]
+    fn test_stdin_input() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("seed");
+        fs::write(&input, "crash").unwrap();
+
+        let runner = TargetRunner::new(
+            "/bin/sh",
+            vec!["-c".into(), "grep -q crash && kill -ABRT $$".into()],
+            Duration::from_secs(5),
+        );
+        assert!(!runner.is_file_based());
+        assert_eq!(
+            runner.run(&input).unwrap().outcome,
+            ExecOutcome::Crashed(libc::SIGABRT)
+        );
+    }
+}
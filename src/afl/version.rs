@@ -0,0 +1,246 @@
+use std::{
+    cell::RefCell,
+    collections::BTreeSet,
+    fmt,
+    path::Path,
+    process::{Command, Stdio},
+    rc::Rc,
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+
+/// AFL++ release version, e.g. `4.21c`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct AflVersion {
+    pub major: u32,
+    pub minor: u32,
+    /// Release suffix, `a` for development builds and `c` for releases
+    pub suffix: char,
+}
+
+impl AflVersion {
+    pub const fn new(major: u32, minor: u32, suffix: char) -> Self {
+        Self {
+            major,
+            minor,
+            suffix,
+        }
+    }
+
+    /// Extracts the version from the `afl-fuzz++4.21c based on afl ...` banner
+    pub fn from_banner(banner: &str) -> Option<Self> {
+        banner
+            .split_whitespace()
+            .filter_map(|token| token.split_once("++"))
+            .find_map(|(_, version)| version.parse().ok())
+    }
+
+    /// Runs `afl-fuzz --version` and parses its banner, `None` if it cannot be determined
+    pub fn probe(afl_fuzz: &Path) -> Option<Self> {
+        let output = Command::new(afl_fuzz)
+            .arg("--version")
+            .stdin(Stdio::null())
+            .output()
+            .ok()?;
+        Self::from_banner(&String::from_utf8_lossy(&output.stdout))
+            .or_else(|| Self::from_banner(&String::from_utf8_lossy(&output.stderr)))
+    }
+}
+
+impl FromStr for AflVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid AFL++ version: {s}");
+        let (major, rest) = s.split_once('.').ok_or_else(invalid)?;
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let suffix = rest[digits..].chars().next().unwrap_or('c');
+        if !suffix.is_ascii_lowercase() {
+            return Err(invalid());
+        }
+        Ok(Self::new(
+            major.parse().map_err(|_| invalid())?,
+            rest[..digits].parse().map_err(|_| invalid())?,
+            suffix,
+        ))
+    }
+}
+
+impl fmt::Display for AflVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{:02}{}", self.major, self.minor, self.suffix)
+    }
+}
+
+/// Generated options that only exist in newer AFL++ releases
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Capability {
+    /// `-l 2AT`, replaced by `-l 2`
+    CmplogTransforms,
+    /// `-P explore`/`-P exploit`
+    MutationModes,
+    /// `-a binary`/`-a text`
+    InputFormat,
+    /// `AFL_IGNORE_SEED_PROBLEMS`
+    IgnoreSeedProblems,
+    /// `AFL_FINAL_SYNC`
+    FinalSync,
+}
+
+/// First AFL++ release supporting each capability
+const CAPABILITIES: &[(Capability, AflVersion)] = &[
+    (Capability::CmplogTransforms, AflVersion::new(3, 14, 'c')),
+    (Capability::MutationModes, AflVersion::new(4, 9, 'c')),
+    (Capability::InputFormat, AflVersion::new(4, 9, 'c')),
+    (Capability::IgnoreSeedProblems, AflVersion::new(4, 10, 'c')),
+    (Capability::FinalSync, AflVersion::new(4, 21, 'c')),
+];
+
+impl Capability {
+    /// First AFL++ release supporting the capability
+    pub fn min_version(self) -> AflVersion {
+        CAPABILITIES
+            .iter()
+            .find(|(cap, _)| *cap == self)
+            .map(|(_, version)| *version)
+            .unwrap_or_else(|| unreachable!("{self:?} is missing in the capability table"))
+    }
+
+    /// What happens to the option on releases without the capability
+    const fn downgrade(self) -> &'static str {
+        match self {
+            Self::CmplogTransforms => "-l 2AT replaced by -l 2",
+            Self::MutationModes => "-P dropped",
+            Self::InputFormat => "-a dropped",
+            Self::IgnoreSeedProblems => "AFL_IGNORE_SEED_PROBLEMS dropped",
+            Self::FinalSync => "AFL_FINAL_SYNC dropped",
+        }
+    }
+}
+
+/// Options available with the used `afl-fuzz`, recording every option it had to deny
+///
+/// Clones share the record, so a strategy and the generator report the same downgrades.
+#[derive(Debug, Clone, Default)]
+pub struct Capabilities {
+    /// Version of `afl-fuzz`, all options are assumed to be supported if unknown
+    version: Option<AflVersion>,
+    downgraded: Rc<RefCell<BTreeSet<Capability>>>,
+}
+
+impl Capabilities {
+    pub fn new(version: Option<AflVersion>) -> Self {
+        Self {
+            version,
+            downgraded: Rc::default(),
+        }
+    }
+
+    /// Probes the version of `afl_fuzz`
+    pub fn probe(afl_fuzz: &Path) -> Self {
+        let version = AflVersion::probe(afl_fuzz);
+        if version.is_none() {
+            println!(
+                "[!] Warning: Could not determine the AFL++ version of {}, assuming all options are supported",
+                afl_fuzz.display()
+            );
+        }
+        Self::new(version)
+    }
+
+    pub fn version(&self) -> Option<AflVersion> {
+        self.version
+    }
+
+    /// Whether `capability` is supported, recording it as downgraded if not
+    pub fn allows(&self, capability: Capability) -> bool {
+        let supported = self
+            .version
+            .is_none_or(|version| version >= capability.min_version());
+        if !supported {
+            self.downgraded.borrow_mut().insert(capability);
+        }
+        supported
+    }
+
+    /// Options that were dropped or substituted so far
+    pub fn downgraded(&self) -> Vec<Capability> {
+        self.downgraded.borrow().iter().copied().collect()
+    }
+
+    /// Prints a warning listing the downgraded options, if any
+    pub fn report(&self) {
+        let (Some(version), downgraded) = (self.version, self.downgraded()) else {
+            return;
+        };
+        if downgraded.is_empty() {
+            return;
+        }
+        let changes: Vec<_> = downgraded.iter().map(|c| c.downgrade()).collect();
+        println!(
+            "[!] Warning: AFL++ {version} lacks options of the generated commands: {}. Update AFL++ to use them",
+            changes.join(", ")
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn test_version_parsing() {
+        let version: AflVersion = "4.21c".parse().unwrap();
+        assert_eq!(version, AflVersion::new(4, 21, 'c'));
+        assert_eq!(version.to_string(), "4.21c");
+        assert_eq!("4.09c".parse::<AflVersion>().unwrap().to_string(), "4.09c");
+        assert!("4.22a".parse::<AflVersion>().unwrap() > version);
+        assert!("4.10c".parse::<AflVersion>().unwrap() > "4.09c".parse().unwrap());
+        assert!("4".parse::<AflVersion>().is_err());
+        assert!("x.10c".parse::<AflVersion>().is_err());
+
+        assert_eq!(
+            AflVersion::from_banner("afl-fuzz++4.21c based on afl by Michal Zalewski"),
+            Some(version)
+        );
+        assert_eq!(AflVersion::from_banner("afl-fuzz 2.57b by <lcamtuf>"), None);
+    }
+
+    #[test]
+    fn test_probe() {
+        let dir = tempfile::tempdir().unwrap();
+        let afl_fuzz = dir.path().join("afl-fuzz");
+        fs::write(
+            &afl_fuzz,
+            "#!/bin/sh\necho 'afl-fuzz++4.05c based on afl by Michal Zalewski' >&2\nexit 1\n",
+        )
+        .unwrap();
+        fs::set_permissions(&afl_fuzz, fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(
+            AflVersion::probe(&afl_fuzz),
+            Some(AflVersion::new(4, 5, 'c'))
+        );
+        assert_eq!(AflVersion::probe(&dir.path().join("missing")), None);
+    }
+
+    #[test]
+    fn test_capabilities() {
+        let old = Capabilities::new(Some(AflVersion::new(4, 9, 'c')));
+        assert!(old.allows(Capability::MutationModes));
+        assert!(!old.allows(Capability::FinalSync));
+        assert!(!old.clone().allows(Capability::IgnoreSeedProblems));
+        assert_eq!(
+            old.downgraded(),
+            vec![Capability::IgnoreSeedProblems, Capability::FinalSync]
+        );
+
+        let unknown = Capabilities::default();
+        assert!(CAPABILITIES.iter().all(|(cap, _)| unknown.allows(*cap)));
+        assert!(unknown.downgraded().is_empty());
+    }
+}
@@ -1,4 +1,11 @@
-use crate::cli::{ArgMerge, Args, CovArgs, GenArgs, RunArgs};
+use crate::afl::env_vars::AFLEnvSetting;
+use crate::afl::overrides::InstanceOverride;
+use crate::afl::phases::{self, Phase};
+use crate::cli::{
+    AdaptArgs, ArgMerge, Args, CovArgs, GenArgs, GroupSyncArgs, RunArgs, SyncClientArgs,
+    SyncServerArgs,
+};
+use crate::runners::remote::Host;
 use anyhow::{bail, Context, Result};
 use std::{env, fs, path::PathBuf};
 
@@ -82,6 +89,124 @@ impl ArgumentAggregator {
         Ok((merged, raw_afl_flags))
     }
 
+    /// Retrieve the validated `[afl_env]` settings from the config
+    ///
+    /// # Errors
+    /// * If a variable in the config is unknown or has an invalid value
+    pub fn afl_env_settings(&self) -> Result<Vec<AFLEnvSetting>> {
+        self.config
+            .as_ref()
+            .map_or_else(|| Ok(Vec::new()), |c| c.afl_env.to_settings())
+    }
+
+    /// Retrieve the validated `[[instance]]` overrides from the config
+    ///
+    /// # Errors
+    /// * If an entry has an invalid selector or setting
+    pub fn instance_overrides(&self) -> Result<Vec<InstanceOverride>> {
+        self.config.as_ref().map_or_else(
+            || Ok(Vec::new()),
+            |c| {
+                c.instance
+                    .iter()
+                    .enumerate()
+                    .map(|(i, entry)| {
+                        entry
+                            .to_override()
+                            .with_context(|| format!("Invalid [[instance]] entry {i}"))
+                    })
+                    .collect()
+            },
+        )
+    }
+
+    /// Retrieve the validated `[[phase]]` schedule of a phased campaign from the config
+    ///
+    /// # Errors
+    /// * If an entry is invalid or the phases do not form a valid schedule
+    pub fn phases(&self) -> Result<Vec<Phase>> {
+        let phases = self.config.as_ref().map_or_else(
+            || Ok(Vec::new()),
+            |c| {
+                c.phase
+                    .iter()
+                    .enumerate()
+                    .map(|(i, entry)| {
+                        entry
+                            .to_phase(i)
+                            .with_context(|| format!("Invalid [[phase]] entry {i}"))
+                    })
+                    .collect::<Result<Vec<_>>>()
+            },
+        )?;
+        phases::validate(&phases)?;
+        Ok(phases)
+    }
+
+    /// Retrieve the validated `[[remote.hosts]]` of a multi-host campaign from the config
+    ///
+    /// # Errors
+    /// * If an entry has no address or no cores
+    pub fn remote_hosts(&self) -> Result<Vec<Host>> {
+        self.config.as_ref().map_or_else(
+            || Ok(Vec::new()),
+            |c| c.remote.hosts.iter().map(Host::from_config).collect(),
+        )
+    }
+
+    /// Retrieve the extra `ssh` options of a multi-host campaign from the config
+    pub fn ssh_opts(&self) -> Vec<String> {
+        self.config
+            .as_ref()
+            .and_then(|c| c.remote.ssh_opts.clone())
+            .unwrap_or_default()
+    }
+
+    /// Retrieve the address of the corpus sync server from the config
+    pub fn sync_server(&self) -> Option<String> {
+        self.config
+            .as_ref()
+            .and_then(|c| c.sync.server.clone())
+            .filter(|s| !s.is_empty())
+    }
+
+    /// Retrieve the share of secondaries the adaptive mode may restart per round
+    pub fn restart_ratio(&self) -> Option<f64> {
+        self.config.as_ref().and_then(|c| c.adaptive.restart_ratio)
+    }
+
+    /// Merge the provided adaptive mode arguments with the config
+    pub fn merge_adapt_args(&self, args: &AdaptArgs) -> AdaptArgs {
+        self.config.as_ref().map_or_else(
+            || args.merge_with_config(&Args::default()),
+            |config| args.merge_with_config(config),
+        )
+    }
+
+    /// Merge the provided sync server arguments with the config
+    pub fn merge_sync_server_args(&self, args: &SyncServerArgs) -> SyncServerArgs {
+        self.config.as_ref().map_or_else(
+            || args.merge_with_config(&Args::default()),
+            |config| args.merge_with_config(config),
+        )
+    }
+
+    /// Merge the provided sync client arguments with the config
+    pub fn merge_sync_client_args(&self, args: &SyncClientArgs) -> SyncClientArgs {
+        self.config.as_ref().map_or_else(
+            || args.merge_with_config(&Args::default()),
+            |config| args.merge_with_config(config),
+        )
+    }
+
+    /// Merge the provided group sync arguments with the config
+    pub fn merge_group_sync_args(&self, args: &GroupSyncArgs) -> GroupSyncArgs {
+        self.config.as_ref().map_or_else(
+            || args.merge_with_config(&Args::default()),
+            |config| args.merge_with_config(config),
+        )
+    }
+
     /// Merge the provided coverage arguments with the config
     ///
     /// # Errors
@@ -0,0 +1,34 @@
+use clap::Args;
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::utils::duration::parse_duration;
+
+#[derive(Args, Clone, Debug, Default)]
+pub struct AdaptArgs {
+    /// Output directory
+    #[arg(short = 'i', long, help = "Top-level AFL++ output directory")]
+    pub output_dir: Option<PathBuf>,
+
+    /// Interval between adaptation rounds
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = parse_duration,
+        help = "Interval between adaptation rounds, e.g. '2h' [default: 1h]"
+    )]
+    pub interval: Option<Duration>,
+
+    /// Path to a TOML config file
+    #[arg(long, help = "Path to TOML config file")]
+    pub config: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Default, Debug, Clone)]
+pub struct AdaptiveArgs {
+    /// Interval between adaptation rounds, enables the adaptive mode in `aflr run`
+    pub interval: Option<String>,
+    /// Share of the secondaries that may be restarted per round
+    pub restart_ratio: Option<f64>,
+}
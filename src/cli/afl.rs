@@ -1,21 +1,81 @@
+use std::path::PathBuf;
+
 use serde::Deserialize;
 
-use crate::afl::mode::Mode;
+use crate::afl::{mode::Mode, resources::RunnerCount};
 
 #[derive(Deserialize, Default, Debug, Clone)]
 pub struct AflArgs {
-    /// Number of AFL++ runners
-    pub runners: Option<u32>,
+    /// Number of AFL++ runners, or `auto`/`auto:<PCT>%`
+    pub runners: Option<RunnerCount>,
+    /// Start an explicit runner count even if it oversubscribes the host
+    pub allow_oversubscribe: Option<bool>,
     /// Path to the AFL++ binary
     pub afl_binary: Option<String>,
     /// Path to the seed directory
     pub seed_dir: Option<String>,
     /// Path to the solution directory
     pub solution_dir: Option<String>,
-    /// Path to the dictionary
-    pub dictionary: Option<String>,
+    /// Path to one or more dictionaries
+    pub dictionary: Option<DictionaryPaths>,
+    /// Extract dictionary tokens from the target binary and seeds
+    pub auto_dict: Option<bool>,
+    /// Path to `libtokencap.so` to capture tokens at runtime
+    pub tokencap: Option<String>,
+    /// Split the dictionary into subsets across secondary instances
+    pub dict_split: Option<bool>,
+    /// Foreign corpus directories imported via `-F`
+    pub foreign_dirs: Option<Vec<String>>,
+    /// Instances importing the foreign directories, e.g. `0,2` or `s*_cl`
+    pub foreign_instances: Option<String>,
     /// Additional AFL++ flags
     pub afl_flags: Option<String>,
     /// Mode to generate commands
     pub mode: Option<Mode>,
+    /// CPU cores to bind the instances to
+    pub cores: Option<String>,
+    /// Only use one logical CPU per physical core
+    pub avoid_smt: Option<bool>,
+}
+
+/// A single dictionary path or a list of them
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum DictionaryPaths {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+impl DictionaryPaths {
+    /// Returns the non-empty paths
+    pub fn to_paths(&self) -> Vec<PathBuf> {
+        let paths = match self {
+            Self::Single(path) => std::slice::from_ref(path),
+            Self::Multiple(paths) => paths.as_slice(),
+        };
+        paths
+            .iter()
+            .filter(|p| !p.is_empty())
+            .map(PathBuf::from)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dictionary_paths() {
+        let single: AflArgs = toml::from_str("dictionary = \"/a.dict\"").unwrap();
+        assert_eq!(
+            single.dictionary.unwrap().to_paths(),
+            vec![PathBuf::from("/a.dict")]
+        );
+        let multiple: AflArgs = toml::from_str("dictionary = [\"/a.dict\", \"\", \"/b\"]").unwrap();
+        assert_eq!(
+            multiple.dictionary.unwrap().to_paths(),
+            vec![PathBuf::from("/a.dict"), PathBuf::from("/b")]
+        );
+    }
 }
@@ -0,0 +1,88 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::afl::env_vars::AFLEnvSetting;
+
+#[derive(Deserialize, Default, Debug, Clone)]
+pub struct AflEnvArgs {
+    /// Environment variables restricted to a subset of instances
+    #[serde(default)]
+    pub scoped: Vec<ScopedAflEnvArgs>,
+    /// Environment variables applied to every instance
+    #[serde(flatten)]
+    pub vars: BTreeMap<String, toml::Value>,
+}
+
+#[derive(Deserialize, Default, Debug, Clone)]
+pub struct ScopedAflEnvArgs {
+    /// Indices of the instances the variables are applied to
+    pub instances: Vec<usize>,
+    /// Environment variables for the selected instances
+    #[serde(flatten)]
+    pub vars: BTreeMap<String, toml::Value>,
+}
+
+impl AflEnvArgs {
+    /// Converts the config table into validated AFL++ environment settings
+    ///
+    /// # Errors
+    /// * If a variable is unknown or has an invalid value
+    pub fn to_settings(&self) -> Result<Vec<AFLEnvSetting>> {
+        let mut settings = Self::parse_vars(&self.vars)?;
+        for scoped in &self.scoped {
+            settings.extend(
+                Self::parse_vars(&scoped.vars)?
+                    .into_iter()
+                    .map(|s| s.for_instances(scoped.instances.clone())),
+            );
+        }
+        Ok(settings)
+    }
+
+    fn parse_vars(vars: &BTreeMap<String, toml::Value>) -> Result<Vec<AFLEnvSetting>> {
+        vars.iter()
+            .map(|(name, value)| {
+                AFLEnvSetting::from_toml(name, value)
+                    .with_context(|| format!("Invalid [afl_env] entry: {name}"))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::afl::env::AFLFlag;
+
+    #[test]
+    fn test_afl_env_table() {
+        let args: AflEnvArgs = toml::from_str(
+            r#"
+            AFL_SKIP_CPUFREQ = true
+            AFL_MAP_SIZE = 65536
+
+            [[scoped]]
+            instances = [0, 2]
+            AFL_SYNC_TIME = 5
+            "#,
+        )
+        .unwrap();
+
+        let settings = args.to_settings().unwrap();
+        assert_eq!(settings.len(), 3);
+        let sync = settings
+            .iter()
+            .find(|s| s.flag == AFLFlag::SyncTime)
+            .unwrap();
+        assert!(sync.applies_to(2));
+        assert!(!sync.applies_to(1));
+    }
+
+    #[test]
+    fn test_afl_env_table_rejects_unknown() {
+        let args: AflEnvArgs = toml::from_str("AFL_SKIP_CPUFRQ = true").unwrap();
+        assert!(args.to_settings().is_err());
+    }
+}
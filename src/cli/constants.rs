@@ -2,3 +2,5 @@
 pub const AFL_CORPUS: &str = "/tmp/afl_input";
 /// Default output directory
 pub const AFL_OUTPUT: &str = "/tmp/afl_output";
+/// Default corpus store of `aflr sync-server`
+pub const AFL_SYNC_STORE: &str = "/tmp/afl_sync_store";
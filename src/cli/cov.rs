@@ -1,8 +1,19 @@
-use clap::{ArgAction, Args};
+use clap::{ArgAction, Args, Subcommand};
 use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::afl::coverage_backend::{BackendKind, GcovTool};
+use crate::afl::coverage_edges::BinaryOnlyMode;
+use crate::afl::coverage_export::ReportFormat;
+use crate::utils::duration::parse_duration;
 
 #[derive(Args, Clone, Debug, Default)]
+#[command(args_conflicts_with_subcommands = true)]
 pub struct CovArgs {
+    /// Coverage subcommand, collects coverage if none is given
+    #[command(subcommand)]
+    pub command: Option<CovCommands>,
+
     /// Target binary instrumented for coverage collection
     #[arg(
         short,
@@ -27,15 +38,205 @@ pub struct CovArgs {
     #[arg(long, help = "Force text-based coverage report", action = ArgAction::SetTrue)]
     pub text_report: bool,
 
+    /// Coverage report format
+    #[arg(
+        value_enum,
+        long,
+        help = "Coverage report format",
+        conflicts_with = "text_report"
+    )]
+    pub report_type: Option<ReportFormat>,
+
+    /// Coverage toolchain of the target binary
+    #[arg(
+        value_enum,
+        long,
+        help = "Coverage backend [default: detected from the target binary]"
+    )]
+    pub backend: Option<BackendKind>,
+
+    /// Report generator of the gcov backend
+    #[arg(
+        value_enum,
+        long,
+        help = "Report tool of the gcov backend [default: lcov]"
+    )]
+    pub gcov_tool: Option<GcovTool>,
+
+    /// Only report source files matching one of these regexes
+    #[arg(
+        long,
+        value_name = "REGEX",
+        help = "Only report source files whose path matches this regex (repeatable)"
+    )]
+    pub include: Vec<String>,
+
+    /// Do not report source files matching one of these regexes
+    #[arg(
+        long,
+        value_name = "REGEX",
+        help = "Leave out source files whose path matches this regex, e.g. third-party code (repeatable)"
+    )]
+    pub exclude: Vec<String>,
+
+    /// Also replay the inputs in crashes/
+    #[arg(long, help = "Also replay the saved crashes of each instance", action = ArgAction::SetTrue)]
+    pub crashes: bool,
+
+    /// Also replay the inputs in hangs/
+    #[arg(long, help = "Also replay the saved hangs of each instance", action = ArgAction::SetTrue)]
+    pub hangs: bool,
+
+    /// Highlight the lines only crashing inputs reach
+    #[arg(long, help = "Write a view of the lines only crashing inputs reach to coverage_crash_only/", action = ArgAction::SetTrue)]
+    pub crash_view: bool,
+
+    /// Replace an existing HTML report without asking
+    #[arg(long, help = "Overwrite an existing HTML report without asking", action = ArgAction::SetTrue)]
+    pub overwrite: bool,
+
+    /// Replay all queue entries instead of only the ones new since the last run
+    #[arg(long, help = "Ignore the coverage cache and replay all queue entries", action = ArgAction::SetTrue)]
+    pub no_cache: bool,
+
+    /// Timeout of a single replay in ms
+    #[arg(
+        long,
+        value_name = "MS",
+        help = "Kill a replayed queue entry after this many ms [default: 5000]"
+    )]
+    pub timeout: Option<u64>,
+
+    /// Memory limit of a single replay in MB
+    #[arg(
+        long,
+        value_name = "MB",
+        help = "Address space limit of a replayed queue entry in MB [default: none]"
+    )]
+    pub mem_limit: Option<u64>,
+
+    /// Number of parallel replays
+    #[arg(
+        short = 'j',
+        long,
+        help = "Number of queue entries replayed in parallel [default: number of CPUs]"
+    )]
+    pub jobs: Option<usize>,
+
+    /// Compute cumulative coverage by queue entry discovery time
+    #[arg(long, help = "Compute a coverage timeline by queue entry discovery time", action = ArgAction::SetTrue)]
+    pub timeline: bool,
+
+    /// Distance between two timeline points
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = parse_duration,
+        help = "Interval between timeline points, e.g. '10m' or '1h' [default: derived from the campaign length]"
+    )]
+    pub timeline_interval: Option<Duration>,
+
+    /// Trace edges with afl-showmap instead of collecting source coverage
+    #[arg(long, help = "Collect edge coverage with afl-showmap and the AFL++ target (-t then names the AFL++ target) [default: if no coverage binary is set]", action = ArgAction::SetTrue)]
+    pub edges: bool,
+
+    /// Binary-only mode of afl-showmap
+    #[arg(
+        value_enum,
+        long,
+        value_name = "MODE",
+        help = "Trace a binary-only target in QEMU or FRIDA mode (edge coverage only)"
+    )]
+    pub binary_only: Option<BinaryOnlyMode>,
+
+    /// Keep collecting coverage at this interval while the campaign runs
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = parse_duration,
+        help = "Take a coverage snapshot at this interval until all fuzzers exited, e.g. '1h'"
+    )]
+    pub watch: Option<Duration>,
+
     /// Misc llvm-cov show arguments
-    #[arg(short = 'a', long, help = "Miscellaneous llvm-cov show arguments")]
+    #[arg(
+        short = 'a',
+        long,
+        help = "Miscellaneous llvm-cov show (genhtml/gcovr for gcov) arguments"
+    )]
     pub show_args: Option<Vec<String>>,
 
     /// Misc llvm-cov report arguments
-    #[arg(short = 'r', long, help = "Miscellaneous llvm-cov report arguments")]
+    #[arg(
+        short = 'r',
+        long,
+        help = "Miscellaneous llvm-cov report (lcov --list/gcovr for gcov) arguments"
+    )]
     pub report_args: Option<Vec<String>>,
 
     /// Path to a TOML config file
     #[arg(long, help = "Path to TOML config file")]
     pub config: Option<PathBuf>,
 }
+
+/// Subcommands of `aflr cov`
+#[derive(Subcommand, Clone, Debug)]
+pub enum CovCommands {
+    /// Show coverage newly reached or lost between two profiles or AFL++ output directories
+    Diff(CovDiffArgs),
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct CovDiffArgs {
+    /// Baseline profile or AFL++ output directory
+    #[arg(help = "Baseline merged profile (e.g. merged.profdata) or AFL++ output directory")]
+    pub baseline: PathBuf,
+
+    /// Profile or AFL++ output directory compared against the baseline
+    #[arg(help = "Compared merged profile or AFL++ output directory")]
+    pub compared: PathBuf,
+
+    /// Coverage binary of both profiles
+    #[arg(
+        short,
+        long,
+        help = "Coverage binary the profiles were recorded with [default: cov_path from the config]"
+    )]
+    pub target: Option<PathBuf>,
+
+    /// Coverage binary of the compared profile, if the harness changed
+    #[arg(
+        long,
+        help = "Coverage binary of the compared profile [default: --target]"
+    )]
+    pub target_b: Option<PathBuf>,
+
+    /// Output directory of the annotated HTML view
+    #[arg(
+        short,
+        long,
+        default_value = "coverage_diff",
+        help = "Directory the annotated HTML diff is written to"
+    )]
+    pub output: PathBuf,
+
+    /// Coverage toolchain of the target binaries
+    #[arg(
+        value_enum,
+        long,
+        help = "Coverage backend [default: detected from the target binary]"
+    )]
+    pub backend: Option<BackendKind>,
+
+    /// Report generator of the gcov backend
+    #[arg(
+        value_enum,
+        long,
+        help = "Report tool of the gcov backend [default: lcov]"
+    )]
+    pub gcov_tool: Option<GcovTool>,
+
+    /// Target binary arguments used to replay AFL++ output directories
+    #[arg(help = "Target binary arguments, including @@ if needed", raw = true)]
+    pub target_args: Option<Vec<String>>,
+}
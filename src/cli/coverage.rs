@@ -4,8 +4,40 @@ use serde::Deserialize;
 pub struct CoverageArgs {
     /// HTML- or Text-based coverage report
     pub report_type: Option<String>,
+    /// Coverage backend, "llvm" or "gcov"
+    pub backend: Option<String>,
+    /// Report tool of the gcov backend, "lcov" or "gcovr"
+    pub gcov_tool: Option<String>,
     /// Split coverage report
     pub split_report: Option<bool>,
+    /// Only replay queue entries that are new since the last run
+    pub incremental: Option<bool>,
+    /// Timeout of a single replay in ms
+    pub timeout: Option<u64>,
+    /// Address space limit of a single replay in MB
+    pub mem_limit: Option<u64>,
+    /// Number of queue entries replayed in parallel
+    pub jobs: Option<usize>,
+    /// Compute a coverage timeline by queue entry discovery time
+    pub timeline: Option<bool>,
+    /// Interval between timeline points, e.g. "10m"
+    pub timeline_interval: Option<String>,
+    /// Collect edge coverage with afl-showmap instead of source coverage
+    pub edges: Option<bool>,
+    /// Binary-only mode of afl-showmap, "qemu" or "frida"
+    pub binary_only: Option<String>,
+    /// Interval of background coverage snapshots during `aflr run`, e.g. "1h"
+    pub snapshot_interval: Option<String>,
+    /// Only report source files whose path matches one of these regexes
+    pub include_regex: Option<Vec<String>>,
+    /// Leave out source files whose path matches one of these regexes
+    pub exclude_regex: Option<Vec<String>>,
+    /// Also replay the saved crashes of each instance
+    pub replay_crashes: Option<bool>,
+    /// Also replay the saved hangs of each instance
+    pub replay_hangs: Option<bool>,
+    /// Write a view of the lines only crashing inputs reach
+    pub crash_view: Option<bool>,
     /// Misc llvm-cov show arguments
     pub misc_show_args: Option<Vec<String>>,
     /// Misc llvm-cov report arguments
@@ -1,7 +1,7 @@
 use clap::{ArgAction, Args};
 use std::path::PathBuf;
 
-use crate::afl::mode::Mode;
+use crate::afl::{mode::Mode, overrides::InstanceSelector, resources::RunnerCount};
 
 #[derive(Args, Clone, Debug, Default)]
 pub struct GenArgs {
@@ -34,9 +34,17 @@ pub struct GenArgs {
         short = 'n',
         long,
         value_name = "NUM_PROCS",
-        help = "Amount of processes to spin up"
+        help = "Amount of processes to spin up, or 'auto'/'auto:<PCT>%' to derive it from the host"
     )]
-    pub runners: Option<u32>,
+    pub runners: Option<RunnerCount>,
+
+    /// Toggle to start an explicit runner count even if it oversubscribes the host
+    #[arg(
+        long,
+        help = "Start the requested runners even if they oversubscribe the host's CPUs or memory",
+        action = ArgAction::SetTrue
+    )]
+    pub allow_oversubscribe: bool,
 
     /// Corpus directory
     #[arg(short = 'i', long, help = "Seed corpus directory")]
@@ -46,14 +54,55 @@ pub struct GenArgs {
     #[arg(short = 'o', long, help = "Solution/Crash output directory")]
     pub output_dir: Option<PathBuf>,
 
-    /// Path to dictionary
+    /// Paths to dictionaries
     #[arg(
         short = 'x',
         long,
         value_name = "DICT_FILE",
-        help = "Token dictionary to use"
+        help = "Token dictionary to use, can be given multiple times"
+    )]
+    pub dictionary: Vec<PathBuf>,
+
+    /// Foreign corpus directories to import testcases from
+    #[arg(
+        short = 'F',
+        long = "foreign-dir",
+        value_name = "DIR",
+        help = "Import testcases from a foreign corpus directory via -F, can be given multiple times"
+    )]
+    pub foreign_dirs: Vec<PathBuf>,
+
+    /// Instances importing the foreign directories
+    #[arg(
+        long,
+        value_name = "SELECTOR",
+        help = "Instances importing the foreign directories, e.g. '0,2' or 's*_cl' [default: the -M instance]"
+    )]
+    pub foreign_instances: Option<InstanceSelector>,
+
+    /// Maximum number of instances per sync group
+    #[arg(
+        long,
+        value_name = "NUM_PROCS",
+        help = "Split the instances into sync groups of at most this size, each with its own -M"
+    )]
+    pub group_size: Option<usize>,
+
+    /// Toggle to extract tokens from the target binary and seed corpus
+    #[arg(long, help = "Extract dictionary tokens from the target binary and seeds", action = ArgAction::SetTrue)]
+    pub auto_dict: bool,
+
+    /// Path to libtokencap
+    #[arg(
+        long,
+        value_name = "LIBTOKENCAP",
+        help = "Capture dictionary tokens by running the seeds with libtokencap.so preloaded"
     )]
-    pub dictionary: Option<PathBuf>,
+    pub tokencap: Option<PathBuf>,
+
+    /// Toggle to hand out different dictionary subsets to the secondaries
+    #[arg(long, help = "Split the dictionary into subsets across secondary instances", action = ArgAction::SetTrue)]
+    pub dict_split: bool,
 
     /// AFL-Fuzz binary
     #[arg(short = 'b', long, help = "Custom path to 'afl-fuzz' binary")]
@@ -84,6 +133,22 @@ pub struct GenArgs {
     /// Toggle to relay the seed to AFL++ as well
     #[arg(long, help = "Forward AFLR seed to AFL++", action = ArgAction::SetTrue, requires="seed")]
     pub use_seed_afl: bool,
+
+    /// CPU cores to bind the instances to
+    #[arg(
+        long,
+        value_name = "CORE_LIST",
+        help = "Bind instances to CPU cores via -b, e.g. '0-15,32-47' or 'all'"
+    )]
+    pub cores: Option<String>,
+
+    /// Toggle to only use one logical CPU per physical core, implies `--cores all` if unset
+    #[arg(
+        long,
+        help = "Do not bind instances to SMT siblings, binds to all cores if --cores is unset",
+        action = ArgAction::SetTrue
+    )]
+    pub avoid_smt: bool,
 }
 
 #[cfg(test)]
@@ -103,13 +168,13 @@ mod tests {
     fn test_gen_args_with_values() {
         let args = GenArgs {
             target: Some(PathBuf::from("/path/to/target")),
-            runners: Some(4),
+            runners: Some(RunnerCount::Fixed(4)),
             use_seed_afl: true,
             ..GenArgs::default()
         };
 
         assert_eq!(args.target.unwrap(), PathBuf::from("/path/to/target"));
-        assert_eq!(args.runners.unwrap(), 4);
+        assert_eq!(args.runners.unwrap(), RunnerCount::Fixed(4));
         assert!(args.use_seed_afl);
     }
 }
@@ -0,0 +1,38 @@
+use clap::Args;
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::utils::duration::parse_duration;
+
+#[derive(Args, Clone, Debug, Default)]
+pub struct GroupSyncArgs {
+    /// Output directory
+    #[arg(short = 'i', long, help = "Top-level AFL++ output directory")]
+    pub output_dir: Option<PathBuf>,
+
+    /// Interval between sync rounds
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = parse_duration,
+        help = "Interval between cross-group syncs, e.g. '5m' [default: 10m]"
+    )]
+    pub interval: Option<Duration>,
+
+    /// Sync once and exit
+    #[arg(long, help = "Sync once and exit")]
+    pub once: bool,
+
+    /// Path to a TOML config file
+    #[arg(long, help = "Path to TOML config file")]
+    pub config: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Default, Debug, Clone)]
+pub struct GroupsArgs {
+    /// Maximum number of instances per sync group, enables sync groups
+    pub size: Option<usize>,
+    /// Interval between cross-group syncs
+    pub sync_interval: Option<String>,
+}
@@ -0,0 +1,137 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use glob::Pattern;
+use serde::Deserialize;
+
+use crate::afl::env_vars::AFLEnvSetting;
+use crate::afl::overrides::{InstanceOverride, InstanceSelector};
+
+#[derive(Deserialize, Default, Debug, Clone)]
+pub struct InstanceArgs {
+    /// Indices of the instances to pin the settings to
+    pub instances: Option<Vec<usize>>,
+    /// Glob pattern matched against the `-M`/`-S` instance names
+    pub name: Option<String>,
+    /// Target binary to use for these instances
+    pub binary: Option<String>,
+    /// Power schedule
+    pub schedule: Option<String>,
+    /// Additional AFL++ flags
+    pub flags: Option<String>,
+    /// Path to the dictionary
+    pub dictionary: Option<String>,
+    /// Path to a custom mutator library
+    pub custom_mutator: Option<String>,
+    /// AFL++ environment variables
+    #[serde(default)]
+    pub env: BTreeMap<String, toml::Value>,
+}
+
+impl InstanceArgs {
+    /// Converts an `[[instance]]` entry into a validated override
+    ///
+    /// # Errors
+    /// * If neither or both of `instances` and `name` are given
+    /// * If the schedule, name pattern, flags or environment variables are invalid
+    pub fn to_override(&self) -> Result<InstanceOverride> {
+        let selector = match (&self.instances, &self.name) {
+            (Some(indices), None) if !indices.is_empty() => {
+                InstanceSelector::Indices(indices.clone())
+            }
+            (None, Some(name)) => InstanceSelector::Name(
+                Pattern::new(name).with_context(|| format!("Invalid name pattern: {name}"))?,
+            ),
+            _ => bail!("[[instance]] entries need exactly one of 'instances' or 'name'"),
+        };
+
+        let non_empty = |opt: &Option<String>| opt.clone().filter(|s| !s.is_empty());
+        let mut ovr = InstanceOverride::new(selector)
+            .with_flags(self.flags.as_deref().unwrap_or_default())?;
+        ovr.binary = non_empty(&self.binary).map(PathBuf::from);
+        ovr.dictionary = non_empty(&self.dictionary).map(PathBuf::from);
+        ovr.custom_mutator = non_empty(&self.custom_mutator).map(PathBuf::from);
+        ovr.schedule = non_empty(&self.schedule)
+            .map(|s| s.parse())
+            .transpose()
+            .map_err(anyhow::Error::msg)?;
+        ovr.env = self
+            .env
+            .iter()
+            .map(|(name, value)| {
+                AFLEnvSetting::from_toml(name, value)
+                    .with_context(|| format!("Invalid [[instance]] env entry: {name}"))
+            })
+            .collect::<Result<_>>()?;
+
+        if let Some(binary) = &ovr.binary {
+            if !binary.is_file() {
+                bail!("[[instance]] binary not found: {}", binary.display());
+            }
+        }
+        Ok(ovr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct Cfg {
+        instance: Vec<InstanceArgs>,
+    }
+
+    #[test]
+    fn test_instance_table() {
+        let cfg: Cfg = toml::from_str(
+            r#"
+            [[instance]]
+            instances = [1, 2]
+            schedule = "rare"
+            flags = "-t 500+"
+            env = { AFL_SYNC_TIME = 5 }
+
+            [[instance]]
+            name = "s*_cl"
+            flags = "-L 0"
+            "#,
+        )
+        .unwrap();
+
+        let first = cfg.instance[0].to_override().unwrap();
+        assert!(matches!(first.selector, InstanceSelector::Indices(ref i) if i == &[1, 2]));
+        assert_eq!(first.schedule.unwrap().to_string(), "-p rare");
+        assert_eq!(first.flags, vec!["-t 500+"]);
+        assert_eq!(first.env.len(), 1);
+
+        let second = cfg.instance[1].to_override().unwrap();
+        assert!(matches!(second.selector, InstanceSelector::Name(_)));
+    }
+
+    #[test]
+    fn test_instance_table_invalid() {
+        let both = InstanceArgs {
+            instances: Some(vec![0]),
+            name: Some("m_*".into()),
+            ..InstanceArgs::default()
+        };
+        assert!(both.to_override().is_err());
+        assert!(InstanceArgs::default().to_override().is_err());
+
+        let bad_schedule = InstanceArgs {
+            instances: Some(vec![0]),
+            schedule: Some("slow".into()),
+            ..InstanceArgs::default()
+        };
+        assert!(bad_schedule.to_override().is_err());
+
+        let role_flag = InstanceArgs {
+            instances: Some(vec![1]),
+            flags: Some("-M main".into()),
+            ..InstanceArgs::default()
+        };
+        assert!(role_flag.to_override().is_err());
+    }
+}
@@ -1,4 +1,5 @@
 use clap::{Args, ValueHint};
+use std::path::PathBuf;
 
 #[derive(Args, Clone, Debug)]
 pub struct KillArgs {
@@ -9,4 +10,12 @@ pub struct KillArgs {
         value_hint = ValueHint::Other
     )]
     pub session_name: String,
+
+    /// Output directory of the session
+    #[arg(
+        short = 'i',
+        long,
+        help = "Top-level AFL++ output directory, also stops the group sync and remaining instances in it"
+    )]
+    pub output_dir: Option<PathBuf>,
 }
@@ -12,4 +12,12 @@ pub struct MiscArgs {
     pub seed: Option<u64>,
     /// Use seed for AFL++ as well
     pub use_seed_afl: Option<bool>,
+    /// Calibrate `-t` and `-m` on the seed corpus before running
+    pub calibrate: Option<bool>,
+    /// Check the seed corpus before running
+    pub preflight: Option<bool>,
+    /// Directory the cleaned seed corpus is staged in
+    pub stage_dir: Option<String>,
+    /// Trim staged seeds with `afl-tmin`
+    pub trim_seeds: Option<bool>,
 }
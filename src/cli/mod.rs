@@ -1,29 +1,51 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use serde::Deserialize;
 
+use crate::afl::corpus_sync::DEFAULT_SYNC_PORT;
+use crate::afl::coverage_edges::BinaryOnlyMode;
+use crate::afl::coverage_export::ReportFormat;
+use crate::utils::duration::parse_duration;
+
+mod adaptive;
 mod afl;
+mod afl_env;
 pub mod constants;
 mod cov;
 mod coverage;
 mod gen;
+mod groups;
+mod instance;
 mod kill;
 mod misc;
+mod phase;
+mod remote;
 mod run;
 pub mod session;
+mod sync;
 mod target;
 mod tui;
 mod utils;
 
-pub use afl::AflArgs;
-use constants::{AFL_CORPUS, AFL_OUTPUT};
-pub use cov::CovArgs;
+pub use adaptive::AdaptArgs;
+use adaptive::AdaptiveArgs;
+pub use afl::{AflArgs, DictionaryPaths};
+pub use afl_env::AflEnvArgs;
+use constants::{AFL_CORPUS, AFL_OUTPUT, AFL_SYNC_STORE};
+pub use cov::{CovArgs, CovCommands, CovDiffArgs};
 use coverage::CoverageArgs;
 pub use gen::GenArgs;
+pub use groups::GroupSyncArgs;
+use groups::GroupsArgs;
+pub use instance::InstanceArgs;
 pub use kill::KillArgs;
 use misc::MiscArgs;
+pub use phase::PhaseArgs;
+pub use remote::{HostArgs, RemoteArgs};
 pub use run::RunArgs;
 use session::SessionArgs;
 pub use session::SessionRunner;
+use sync::SyncArgs;
+pub use sync::{SyncClientArgs, SyncServerArgs};
 use target::TargetArgs;
 pub use tui::TuiArgs;
 
@@ -51,6 +73,14 @@ pub enum Commands {
     Tui(TuiArgs),
     /// Kills a running session and all spawned processes inside
     Kill(KillArgs),
+    /// Serve a corpus exchange for the campaigns of `aflr sync-client`s
+    SyncServer(SyncServerArgs),
+    /// Exchange new queue entries of a campaign with an `aflr sync-server`
+    SyncClient(SyncClientArgs),
+    /// Reallocate the strategies of a running campaign based on per-instance performance
+    Adapt(AdaptArgs),
+    /// Share new queue entries between the sync groups of a campaign
+    GroupSync(GroupSyncArgs),
 }
 
 #[derive(Deserialize, Default, Debug, Clone)]
@@ -61,10 +91,31 @@ pub struct Args {
     pub coverage: CoverageArgs,
     /// AFL++ configuration
     pub afl_cfg: AflArgs,
+    /// AFL++ environment variables
+    #[serde(default)]
+    pub afl_env: AflEnvArgs,
+    /// Settings pinned to specific instances
+    #[serde(default)]
+    pub instance: Vec<InstanceArgs>,
     /// Session configuration
     pub session: SessionArgs,
     /// Miscellaneous configuration
     pub misc: MiscArgs,
+    /// Multi-host campaign configuration
+    #[serde(default)]
+    pub remote: RemoteArgs,
+    /// Corpus sync service configuration
+    #[serde(default)]
+    pub sync: SyncArgs,
+    /// Adaptive strategy reallocation configuration
+    #[serde(default)]
+    pub adaptive: AdaptiveArgs,
+    /// Phases of a phased campaign
+    #[serde(default)]
+    pub phase: Vec<PhaseArgs>,
+    /// Sync group configuration
+    #[serde(default)]
+    pub groups: GroupsArgs,
 }
 
 pub trait ArgMerge<T> {
@@ -90,12 +141,48 @@ impl ArgMerge<Self> for GenArgs {
                 .target_args
                 .clone()
                 .or_else(|| args.target.args.clone().filter(|args| !args.is_empty())),
-            runners: Some(self.runners.or(args.afl_cfg.runners).unwrap_or(1)),
+            runners: Some(self.runners.or(args.afl_cfg.runners).unwrap_or_default()),
+            allow_oversubscribe: self.allow_oversubscribe
+                || args.afl_cfg.allow_oversubscribe.unwrap_or(false),
             input_dir: merge_path(self.input_dir.clone(), args.afl_cfg.seed_dir.clone())
                 .or_else(|| Some(std::path::PathBuf::from(AFL_CORPUS))),
             output_dir: merge_path(self.output_dir.clone(), args.afl_cfg.solution_dir.clone())
                 .or_else(|| Some(std::path::PathBuf::from(AFL_OUTPUT))),
-            dictionary: merge_path(self.dictionary.clone(), args.afl_cfg.dictionary.clone()),
+            dictionary: if self.dictionary.is_empty() {
+                args.afl_cfg
+                    .dictionary
+                    .as_ref()
+                    .map(DictionaryPaths::to_paths)
+                    .unwrap_or_default()
+            } else {
+                self.dictionary.clone()
+            },
+            foreign_dirs: if self.foreign_dirs.is_empty() {
+                args.afl_cfg
+                    .foreign_dirs
+                    .iter()
+                    .flatten()
+                    .filter(|d| !d.is_empty())
+                    .map(std::path::PathBuf::from)
+                    .collect()
+            } else {
+                self.foreign_dirs.clone()
+            },
+            foreign_instances: self.foreign_instances.clone().or_else(|| {
+                args.afl_cfg
+                    .foreign_instances
+                    .as_deref()
+                    .filter(|s| !s.is_empty())
+                    .and_then(|s| {
+                        s.parse()
+                            .map_err(|e| eprintln!("Warning: {e}, using the -M instance"))
+                            .ok()
+                    })
+            }),
+            group_size: self.group_size.or(args.groups.size),
+            auto_dict: self.auto_dict || args.afl_cfg.auto_dict.unwrap_or(false),
+            tokencap: merge_path(self.tokencap.clone(), args.afl_cfg.tokencap.clone()),
+            dict_split: self.dict_split || args.afl_cfg.dict_split.unwrap_or(false),
             afl_binary: self
                 .afl_binary
                 .clone()
@@ -103,6 +190,11 @@ impl ArgMerge<Self> for GenArgs {
             mode: args.afl_cfg.mode.unwrap_or(self.mode),
             seed: self.seed.or(args.misc.seed),
             use_seed_afl: args.misc.use_seed_afl.unwrap_or(self.use_seed_afl),
+            cores: self
+                .cores
+                .clone()
+                .or_else(|| args.afl_cfg.cores.clone().filter(|c| !c.is_empty())),
+            avoid_smt: self.avoid_smt || args.afl_cfg.avoid_smt.unwrap_or(false),
             config: self.config.clone(),
         }
     }
@@ -140,6 +232,64 @@ impl ArgMerge<Self> for RunArgs {
             } else {
                 self.is_ramdisk || args.misc.is_ramdisk.unwrap_or(false)
             },
+            calibrate: self.calibrate || args.misc.calibrate.unwrap_or(false),
+            preflight: self.preflight || args.misc.preflight.unwrap_or(false),
+            stage_dir: self.stage_dir.clone().or_else(|| {
+                args.misc
+                    .stage_dir
+                    .clone()
+                    .filter(|d| !d.is_empty())
+                    .map(std::path::PathBuf::from)
+            }),
+            trim: self.trim || args.misc.trim_seeds.unwrap_or(false),
+            cov_interval: self.cov_interval.or_else(|| {
+                args.coverage
+                    .snapshot_interval
+                    .as_deref()
+                    .filter(|i| !i.is_empty())
+                    .and_then(|i| {
+                        parse_duration(i)
+                            .map_err(|e| eprintln!("Warning: {e}, disabling coverage snapshots"))
+                            .ok()
+                    })
+            }),
+            sync_interval: self.sync_interval.or_else(|| {
+                args.remote
+                    .sync_interval
+                    .as_deref()
+                    .filter(|i| !i.is_empty())
+                    .and_then(|i| {
+                        parse_duration(i)
+                            .map_err(|e| eprintln!("Warning: {e}, using the default sync interval"))
+                            .ok()
+                    })
+            }),
+            adapt_interval: self.adapt_interval.or_else(|| {
+                args.adaptive
+                    .interval
+                    .as_deref()
+                    .filter(|i| !i.is_empty())
+                    .and_then(|i| {
+                        parse_duration(i)
+                            .map_err(|e| eprintln!("Warning: {e}, disabling the adaptive mode"))
+                            .ok()
+                    })
+            }),
+            group_sync_interval: self.group_sync_interval.or_else(|| {
+                args.groups
+                    .sync_interval
+                    .as_deref()
+                    .filter(|i| !i.is_empty())
+                    .and_then(|i| {
+                        parse_duration(i)
+                            .map_err(|e| {
+                                eprintln!("Warning: {e}, using the default group sync interval")
+                            })
+                            .ok()
+                    })
+            }),
+            phase_controller: self.phase_controller.clone(),
+            phase_afl_fuzz: self.phase_afl_fuzz.clone(),
         }
     }
 }
@@ -163,19 +313,89 @@ impl ArgMerge<Self> for CovArgs {
             output_dir: merge_path(self.output_dir.clone(), args.afl_cfg.solution_dir.clone())
                 .or_else(|| Some(std::path::PathBuf::from(AFL_OUTPUT))),
             split_report: args.coverage.split_report.unwrap_or(self.split_report),
-            text_report: match args.coverage.report_type.as_deref() {
-                Some("HTML" | "html") => false,
-                Some("TEXT" | "text") => true,
-                Some(unknown) => {
-                    eprintln!(
-                        "Warning: Unknown report type '{}', defaulting to {}",
-                        unknown,
-                        if self.text_report { "text" } else { "html" }
-                    );
-                    self.text_report
-                }
-                None => self.text_report,
+            no_cache: self.no_cache || args.coverage.incremental == Some(false),
+            overwrite: self.overwrite,
+            include: if self.include.is_empty() {
+                args.coverage.include_regex.clone().unwrap_or_default()
+            } else {
+                self.include.clone()
             },
+            exclude: if self.exclude.is_empty() {
+                args.coverage.exclude_regex.clone().unwrap_or_default()
+            } else {
+                self.exclude.clone()
+            },
+            crashes: self.crashes || args.coverage.replay_crashes.unwrap_or(false),
+            hangs: self.hangs || args.coverage.replay_hangs.unwrap_or(false),
+            crash_view: self.crash_view || args.coverage.crash_view.unwrap_or(false),
+            timeout: self.timeout.or(args.coverage.timeout).filter(|&ms| ms > 0),
+            mem_limit: self
+                .mem_limit
+                .or(args.coverage.mem_limit)
+                .filter(|&mb| mb > 0),
+            jobs: self.jobs.or(args.coverage.jobs).filter(|&jobs| jobs > 0),
+            timeline: self.timeline || args.coverage.timeline.unwrap_or(false),
+            timeline_interval: self.timeline_interval.or_else(|| {
+                args.coverage
+                    .timeline_interval
+                    .as_deref()
+                    .filter(|i| !i.is_empty())
+                    .and_then(|i| {
+                        parse_duration(i)
+                            .map_err(|e| eprintln!("Warning: {e}, deriving timeline interval"))
+                            .ok()
+                    })
+            }),
+            backend: self.backend.or_else(|| {
+                args.coverage
+                    .backend
+                    .as_deref()
+                    .filter(|b| !b.is_empty())
+                    .and_then(|b| {
+                        b.parse()
+                            .map_err(|e| eprintln!("Warning: {e}, detecting backend"))
+                            .ok()
+                    })
+            }),
+            gcov_tool: self.gcov_tool.or_else(|| {
+                args.coverage
+                    .gcov_tool
+                    .as_deref()
+                    .filter(|t| !t.is_empty())
+                    .and_then(|t| {
+                        t.parse()
+                            .map_err(|e| eprintln!("Warning: {e}, defaulting to lcov"))
+                            .ok()
+                    })
+            }),
+            edges: self.edges || args.coverage.edges.unwrap_or(false),
+            binary_only: self.binary_only.or_else(|| {
+                args.coverage
+                    .binary_only
+                    .as_deref()
+                    .filter(|m| !m.is_empty())
+                    .and_then(|m| {
+                        BinaryOnlyMode::from_str(m, true)
+                            .map_err(|e| eprintln!("Warning: {e}, tracing natively"))
+                            .ok()
+                    })
+            }),
+            watch: self.watch,
+            text_report: self.text_report,
+            report_type: self
+                .report_type
+                .or_else(|| self.text_report.then_some(ReportFormat::Text))
+                .or_else(|| {
+                    args.coverage
+                        .report_type
+                        .as_deref()
+                        .filter(|t| !t.is_empty())
+                        .and_then(|t| {
+                            t.parse()
+                                .map_err(|e| eprintln!("Warning: {e}, defaulting to html"))
+                                .ok()
+                        })
+                }),
             show_args: self.show_args.clone().or_else(|| {
                 args.coverage
                     .misc_show_args
@@ -188,6 +408,127 @@ impl ArgMerge<Self> for CovArgs {
                     .clone()
                     .filter(|args| !args.is_empty())
             }),
+            command: self.command.clone(),
+            config: self.config.clone(),
+        }
+    }
+}
+
+impl ArgMerge<Self> for SyncServerArgs {
+    fn merge_with_config(&self, args: &Args) -> Self {
+        Self {
+            listen: self
+                .listen
+                .clone()
+                .or_else(|| args.sync.listen.clone().filter(|l| !l.is_empty()))
+                .or_else(|| Some(format!("127.0.0.1:{DEFAULT_SYNC_PORT}"))),
+            store: self
+                .store
+                .clone()
+                .or_else(|| {
+                    args.sync
+                        .store
+                        .clone()
+                        .filter(|s| !s.is_empty())
+                        .map(std::path::PathBuf::from)
+                })
+                .or_else(|| Some(std::path::PathBuf::from(AFL_SYNC_STORE))),
+            config: self.config.clone(),
+        }
+    }
+}
+
+impl ArgMerge<Self> for SyncClientArgs {
+    fn merge_with_config(&self, args: &Args) -> Self {
+        Self {
+            server: self
+                .server
+                .clone()
+                .or_else(|| args.sync.server.clone().filter(|s| !s.is_empty())),
+            output_dir: self
+                .output_dir
+                .clone()
+                .or_else(|| {
+                    args.afl_cfg
+                        .solution_dir
+                        .clone()
+                        .filter(|d| !d.is_empty())
+                        .map(std::path::PathBuf::from)
+                })
+                .or_else(|| Some(std::path::PathBuf::from(AFL_OUTPUT))),
+            interval: self.interval.or_else(|| {
+                args.sync
+                    .interval
+                    .as_deref()
+                    .filter(|i| !i.is_empty())
+                    .and_then(|i| {
+                        parse_duration(i)
+                            .map_err(|e| eprintln!("Warning: {e}, using the default interval"))
+                            .ok()
+                    })
+            }),
+            once: self.once,
+            config: self.config.clone(),
+        }
+    }
+}
+
+impl ArgMerge<Self> for GroupSyncArgs {
+    fn merge_with_config(&self, args: &Args) -> Self {
+        Self {
+            output_dir: self
+                .output_dir
+                .clone()
+                .or_else(|| {
+                    args.afl_cfg
+                        .solution_dir
+                        .clone()
+                        .filter(|d| !d.is_empty())
+                        .map(std::path::PathBuf::from)
+                })
+                .or_else(|| Some(std::path::PathBuf::from(AFL_OUTPUT))),
+            interval: self.interval.or_else(|| {
+                args.groups
+                    .sync_interval
+                    .as_deref()
+                    .filter(|i| !i.is_empty())
+                    .and_then(|i| {
+                        parse_duration(i)
+                            .map_err(|e| eprintln!("Warning: {e}, using the default interval"))
+                            .ok()
+                    })
+            }),
+            once: self.once,
+            config: self.config.clone(),
+        }
+    }
+}
+
+impl ArgMerge<Self> for AdaptArgs {
+    fn merge_with_config(&self, args: &Args) -> Self {
+        Self {
+            output_dir: self
+                .output_dir
+                .clone()
+                .or_else(|| {
+                    args.afl_cfg
+                        .solution_dir
+                        .clone()
+                        .filter(|d| !d.is_empty())
+                        .map(std::path::PathBuf::from)
+                })
+                .or_else(|| Some(std::path::PathBuf::from(AFL_OUTPUT))),
+            interval: self.interval.or_else(|| {
+                args.adaptive
+                    .interval
+                    .as_deref()
+                    .filter(|i| !i.is_empty())
+                    .and_then(|i| {
+                        parse_duration(i)
+                            .map_err(|e| eprintln!("Warning: {e}, using the default interval"))
+                            .ok()
+                    })
+            }),
             config: self.config.clone(),
         }
     }
@@ -196,6 +537,7 @@ impl ArgMerge<Self> for CovArgs {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::afl::resources::RunnerCount;
     use crate::cli::{GenArgs, RunArgs};
     use std::path::PathBuf;
 
@@ -203,7 +545,7 @@ mod tests {
     fn test_gen_args_merge() {
         let args = GenArgs {
             target: Some(PathBuf::from("/custom/path")),
-            runners: Some(4),
+            runners: Some(RunnerCount::Fixed(4)),
             ..GenArgs::default()
         };
 
@@ -213,7 +555,7 @@ mod tests {
                 ..TargetArgs::default()
             },
             afl_cfg: AflArgs {
-                runners: Some(2),
+                runners: Some(RunnerCount::Fixed(2)),
                 ..AflArgs::default()
             },
             ..Args::default()
@@ -221,7 +563,7 @@ mod tests {
 
         let merged = args.merge_with_config(&config);
         assert_eq!(merged.target.unwrap(), PathBuf::from("/custom/path"));
-        assert_eq!(merged.runners, Some(4));
+        assert_eq!(merged.runners, Some(RunnerCount::Fixed(4)));
     }
 
     #[test]
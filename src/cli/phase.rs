@@ -0,0 +1,102 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::afl::{mode::Mode, phases::Phase};
+use crate::utils::duration::parse_duration;
+
+#[derive(Deserialize, Default, Debug, Clone)]
+pub struct PhaseArgs {
+    /// Name of the phase shown in the logs
+    pub name: Option<String>,
+    /// Run time of the phase, e.g. "2h"
+    pub duration: Option<String>,
+    /// Strategy mode of the phase
+    pub mode: Option<Mode>,
+    /// Share of the secondaries running the cmplog binary
+    pub cmplog_ratio: Option<f64>,
+    /// Power schedules cycled through
+    pub schedules: Option<Vec<String>>,
+    /// Minimize the corpus with afl-cmin and restart before the phase
+    pub cmin: Option<bool>,
+}
+
+impl PhaseArgs {
+    /// Converts the `index`-th `[[phase]]` entry into a phase
+    ///
+    /// # Errors
+    /// * If the duration or a power schedule is invalid
+    pub fn to_phase(&self, index: usize) -> Result<Phase> {
+        Ok(Phase {
+            name: self
+                .name
+                .clone()
+                .filter(|n| !n.is_empty())
+                .unwrap_or_else(|| format!("phase-{index}")),
+            duration: self
+                .duration
+                .as_deref()
+                .filter(|d| !d.is_empty())
+                .map(parse_duration)
+                .transpose()
+                .context("Invalid duration")?,
+            mode: self.mode,
+            cmplog_ratio: self.cmplog_ratio,
+            schedules: self
+                .schedules
+                .iter()
+                .flatten()
+                .map(|s| s.parse())
+                .collect::<Result<_, _>>()
+                .map_err(anyhow::Error::msg)?,
+            cmin: self.cmin.unwrap_or(false),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[derive(Deserialize)]
+    struct Cfg {
+        phase: Vec<PhaseArgs>,
+    }
+
+    #[test]
+    fn test_phase_table() {
+        let cfg: Cfg = toml::from_str(
+            r#"
+            [[phase]]
+            name = "explore"
+            duration = "2h"
+            mode = "CIFuzzing"
+            cmplog_ratio = 1.0
+
+            [[phase]]
+            mode = "MultipleCores"
+            schedules = ["exploit", "coe"]
+            cmin = true
+            "#,
+        )
+        .unwrap();
+
+        let explore = cfg.phase[0].to_phase(0).unwrap();
+        assert_eq!(explore.name, "explore");
+        assert_eq!(explore.duration, Some(Duration::from_secs(7200)));
+        assert_eq!(explore.mode, Some(Mode::CIFuzzing));
+        assert!(!explore.cmin);
+
+        let exploit = cfg.phase[1].to_phase(1).unwrap();
+        assert_eq!(exploit.name, "phase-1");
+        assert!(exploit.duration.is_none());
+        assert_eq!(exploit.schedules.len(), 2);
+        assert!(exploit.cmin);
+
+        let invalid = PhaseArgs {
+            schedules: Some(vec!["slow".into()]),
+            ..PhaseArgs::default()
+        };
+        assert!(invalid.to_phase(0).is_err());
+    }
+}
@@ -0,0 +1,58 @@
+use serde::Deserialize;
+
+#[derive(Deserialize, Default, Debug, Clone)]
+pub struct RemoteArgs {
+    /// Interval between corpus synchronizations, e.g. "10m"
+    pub sync_interval: Option<String>,
+    /// Extra `ssh` options, also used by `rsync`
+    pub ssh_opts: Option<Vec<String>>,
+    /// Hosts the campaign is split across
+    #[serde(default)]
+    pub hosts: Vec<HostArgs>,
+}
+
+#[derive(Deserialize, Default, Debug, Clone)]
+pub struct HostArgs {
+    /// Hostname or IP address
+    pub address: String,
+    /// SSH user
+    pub user: Option<String>,
+    /// SSH port
+    pub port: Option<u16>,
+    /// Number of instances to run on the host
+    pub cores: usize,
+    /// Whether the host runs the `-M` instance
+    pub main: Option<bool>,
+    /// Output directory on the host, defaults to the local one
+    pub output_dir: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remote_table() {
+        let remote: RemoteArgs = toml::from_str(
+            r#"
+            sync_interval = "5m"
+
+            [[hosts]]
+            address = "10.0.0.1"
+            cores = 16
+            main = true
+
+            [[hosts]]
+            address = "fuzz2"
+            user = "fuzz"
+            port = 2222
+            cores = 8
+            "#,
+        )
+        .unwrap();
+        assert_eq!(remote.sync_interval.as_deref(), Some("5m"));
+        assert_eq!(remote.hosts.len(), 2);
+        assert_eq!(remote.hosts[0].main, Some(true));
+        assert_eq!(remote.hosts[1].port, Some(2222));
+    }
+}
@@ -1,7 +1,11 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
 use clap::Args;
 
 use super::GenArgs;
 use crate::cli::SessionRunner;
+use crate::utils::duration::parse_duration;
 
 #[derive(Args, Clone, Debug, Default)]
 #[allow(clippy::struct_excessive_bools)]
@@ -34,6 +38,73 @@ pub struct RunArgs {
     /// Use `RAMDisk` for AFL++
     #[arg(long, help = "Use RAMDisk for AFL++")]
     pub is_ramdisk: bool,
+
+    /// Derive `-t` and `-m` by executing the target over the seed corpus
+    #[arg(
+        long,
+        help = "Calibrate timeout (-t) and memory limit (-m) on the seed corpus before starting"
+    )]
+    pub calibrate: bool,
+
+    /// Check the seed corpus for duplicates, oversized, crashing and hanging seeds
+    #[arg(long, help = "Check the seed corpus before starting the campaign")]
+    pub preflight: bool,
+
+    /// Stage the cleaned seed corpus in this directory and use it as `-i`
+    #[arg(
+        long = "stage-dir",
+        value_name = "DIR",
+        help = "Copy the cleaned seed corpus into this new or empty directory and use it as input directory (implies --preflight)"
+    )]
+    pub stage_dir: Option<PathBuf>,
+
+    /// Minimize staged seeds with `afl-tmin`
+    #[arg(long, requires = "stage_dir", help = "Trim staged seeds with afl-tmin")]
+    pub trim: bool,
+
+    /// Collect coverage in the background at this interval
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = parse_duration,
+        help = "Take coverage snapshots with cov_path from the config at this interval, e.g. '1h'"
+    )]
+    pub cov_interval: Option<Duration>,
+
+    /// Synchronize the corpora of a multi-host campaign at this interval
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = parse_duration,
+        help = "Interval between corpus synchronizations across [[remote.hosts]], e.g. '10m'"
+    )]
+    pub sync_interval: Option<Duration>,
+
+    /// Reallocate the strategies of the secondaries at this interval
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = parse_duration,
+        help = "Restart the worst-performing secondaries with better strategies at this interval, e.g. '2h'"
+    )]
+    pub adapt_interval: Option<Duration>,
+
+    /// Share new entries between the sync groups at this interval
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = parse_duration,
+        help = "Interval between corpus syncs across sync groups, e.g. '5m' [default: 10m]"
+    )]
+    pub group_sync_interval: Option<Duration>,
+
+    /// Drive the phase transitions of a running phased campaign, set by `aflr run` itself
+    #[arg(long, value_name = "PID_FILE", hide = true)]
+    pub phase_controller: Option<PathBuf>,
+
+    /// `afl-fuzz` binary resolved by `aflr run`, handed to the phase controller
+    #[arg(long, value_name = "PATH", hide = true, requires = "phase_controller")]
+    pub phase_afl_fuzz: Option<PathBuf>,
 }
 
 #[cfg(test)]
@@ -47,6 +118,7 @@ mod tests {
         assert!(!args.tui);
         assert!(!args.detached);
         assert!(!args.is_ramdisk);
+        assert!(!args.calibrate);
     }
 
     #[test]
@@ -0,0 +1,75 @@
+use clap::Args;
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::utils::duration::parse_duration;
+
+#[derive(Args, Clone, Debug, Default)]
+pub struct SyncServerArgs {
+    /// Address to listen on
+    #[arg(
+        short,
+        long,
+        value_name = "ADDR",
+        help = "Address to listen on, the server is unauthenticated so only expose it to trusted networks [default: 127.0.0.1:7411]"
+    )]
+    pub listen: Option<String>,
+
+    /// Directory the exchanged corpus is stored in
+    #[arg(
+        short,
+        long,
+        help = "Directory the exchanged corpus and sync log are stored in"
+    )]
+    pub store: Option<PathBuf>,
+
+    /// Path to a TOML config file
+    #[arg(long, help = "Path to TOML config file")]
+    pub config: Option<PathBuf>,
+}
+
+#[derive(Args, Clone, Debug, Default)]
+pub struct SyncClientArgs {
+    /// Address of the sync server
+    #[arg(
+        short,
+        long,
+        value_name = "ADDR",
+        help = "Address of the aflr sync-server"
+    )]
+    pub server: Option<String>,
+
+    /// Output directory
+    #[arg(short = 'i', long, help = "Top-level AFL++ output directory")]
+    pub output_dir: Option<PathBuf>,
+
+    /// Interval between sync rounds
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = parse_duration,
+        help = "Interval between sync rounds, e.g. '5m' [default: 5m]"
+    )]
+    pub interval: Option<Duration>,
+
+    /// Sync once and exit
+    #[arg(long, help = "Sync once and exit")]
+    pub once: bool,
+
+    /// Path to a TOML config file
+    #[arg(long, help = "Path to TOML config file")]
+    pub config: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Default, Debug, Clone)]
+pub struct SyncArgs {
+    /// Address of the sync server, enables `-F` on the `-M` instance in `aflr run`
+    pub server: Option<String>,
+    /// Address the sync server listens on
+    pub listen: Option<String>,
+    /// Directory the sync server stores the corpus in
+    pub store: Option<String>,
+    /// Interval between sync rounds
+    pub interval: Option<String>,
+}
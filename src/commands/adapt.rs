@@ -0,0 +1,43 @@
+use anyhow::{bail, Result};
+
+use crate::{
+    argument_aggregator::ArgumentAggregator,
+    cli::{AdaptArgs, SessionRunner},
+    commands::Command,
+    runners::{
+        adaptive::{adapt, AdaptiveState, DEFAULT_ADAPT_INTERVAL},
+        screen::Screen,
+        tmux::Tmux,
+    },
+};
+
+pub struct AdaptCommand<'a> {
+    args: &'a AdaptArgs,
+    arg_aggregator: &'a ArgumentAggregator,
+}
+
+impl<'a> AdaptCommand<'a> {
+    pub fn new(args: &'a AdaptArgs, arg_aggregator: &'a ArgumentAggregator) -> Self {
+        Self {
+            args,
+            arg_aggregator,
+        }
+    }
+}
+
+impl Command for AdaptCommand<'_> {
+    fn execute(&self) -> Result<()> {
+        let merged_args = self.arg_aggregator.merge_adapt_args(self.args);
+        let output_dir = merged_args.output_dir.unwrap_or_default();
+        if !output_dir.is_dir() {
+            bail!("AFL++ output directory {} not found", output_dir.display());
+        }
+        let interval = merged_args.interval.unwrap_or(DEFAULT_ADAPT_INTERVAL);
+
+        let state = AdaptiveState::load(&output_dir)?;
+        match SessionRunner::from(state.runner.as_str()) {
+            SessionRunner::Screen => adapt::<Screen>(&output_dir, interval),
+            SessionRunner::Tmux => adapt::<Tmux>(&output_dir, interval),
+        }
+    }
+}
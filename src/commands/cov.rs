@@ -1,8 +1,21 @@
-use anyhow::Result;
+use std::{path::PathBuf, time::Duration};
+
+use anyhow::{bail, Result};
 
 use crate::{
-    afl::coverage::CoverageCollector, argument_aggregator::ArgumentAggregator, cli::CovArgs,
+    afl::{
+        coverage::{CoverageCollector, OverwritePolicy},
+        coverage_backend::BackendKind,
+        coverage_diff::{self, CoverageDiff},
+        coverage_edges::EdgeCollector,
+        coverage_export::ReportFormat,
+        coverage_filter::SourceFilter,
+        coverage_snapshot,
+    },
+    argument_aggregator::ArgumentAggregator,
+    cli::{CovArgs, CovCommands, CovDiffArgs, GenArgs},
     commands::Command,
+    utils::system::find_binary_in_path,
 };
 
 pub struct CovCommand<'a> {
@@ -19,18 +32,117 @@ impl<'a> CovCommand<'a> {
     }
 }
 
+impl CovCommand<'_> {
+    fn execute_diff(&self, diff_args: &CovDiffArgs) -> Result<()> {
+        let merged_args = self.arg_aggregator.merge_cov_args(self.args)?;
+        let Some(target_a) = diff_args.target.clone().or(merged_args.target) else {
+            bail!("No coverage binary given, use --target or set cov_path in the config");
+        };
+        let target_b = diff_args
+            .target_b
+            .clone()
+            .unwrap_or_else(|| target_a.clone());
+        let target_args = diff_args
+            .target_args
+            .clone()
+            .or(merged_args.target_args)
+            .unwrap_or_default();
+        let gcov_tool = diff_args
+            .gcov_tool
+            .or(merged_args.gcov_tool)
+            .unwrap_or_default();
+        let filter = SourceFilter::new(&merged_args.include, &merged_args.exclude)?;
+
+        let mut coverage = Vec::with_capacity(2);
+        for (input, target) in [
+            (&diff_args.baseline, &target_a),
+            (&diff_args.compared, &target_b),
+        ] {
+            let kind = match diff_args.backend.or(merged_args.backend) {
+                Some(kind) => kind,
+                None => BackendKind::detect(target)?,
+            };
+            coverage.push(coverage_diff::load_coverage(
+                input,
+                target,
+                &target_args,
+                kind,
+                gcov_tool,
+                &filter,
+            )?);
+        }
+
+        let diff = CoverageDiff::new(&coverage[0], &coverage[1]);
+        print!("{}", diff.to_text());
+        let page = diff.write_html(&diff_args.output)?;
+        println!("[*] Wrote annotated coverage diff to: {}", page.display());
+        Ok(())
+    }
+
+    fn execute_edges(&self, merged_args: &CovArgs) -> Result<()> {
+        let (gen_args, _) = self.arg_aggregator.merge_gen_args(&GenArgs::default())?;
+        // With --edges, -t names the AFL++ target instead of the coverage binary
+        let Some(target) = self.args.target.clone().or(gen_args.target) else {
+            bail!("No target binary given, use --target or set the target path in the config");
+        };
+        let afl_fuzz = find_binary_in_path(gen_args.afl_binary.map(PathBuf::from))?;
+        let afl_out = merged_args.output_dir.clone().unwrap();
+
+        let mut collector = EdgeCollector::new(&afl_fuzz, target, afl_out.clone())?;
+        collector
+            .with_target_args(merged_args.target_args.clone().unwrap_or_default())
+            .with_mem_limit(merged_args.mem_limit)
+            .with_binary_only(merged_args.binary_only);
+        if let Some(timeout) = merged_args.timeout {
+            collector.with_exec_timeout(Duration::from_millis(timeout));
+        }
+        if let Some(jobs) = merged_args.jobs {
+            collector.with_jobs(jobs);
+        }
+
+        let report = collector.collect()?;
+        report.print();
+        let path = report.write(&afl_out)?;
+        println!("[*] Wrote edge coverage report to: {}", path.display());
+        Ok(())
+    }
+}
+
 impl Command for CovCommand<'_> {
     fn execute(&self) -> Result<()> {
+        if let Some(CovCommands::Diff(diff_args)) = &self.args.command {
+            return self.execute_diff(diff_args);
+        }
+
         let merged_args = self.arg_aggregator.merge_cov_args(self.args)?;
-        let mut cov_collector =
-            CoverageCollector::new(merged_args.target.unwrap(), merged_args.output_dir.unwrap())?;
+        if merged_args.edges {
+            return self.execute_edges(&merged_args);
+        }
+        if merged_args.target.is_none() {
+            println!("[!] No coverage binary given, falling back to afl-showmap edge coverage");
+            return self.execute_edges(&merged_args);
+        }
+        let (target, afl_out) = (merged_args.target.unwrap(), merged_args.output_dir.unwrap());
+        let mut cov_collector = match merged_args.backend {
+            Some(kind) => CoverageCollector::new_with_backend(
+                target,
+                afl_out.clone(),
+                kind,
+                merged_args.gcov_tool.unwrap_or_default(),
+            )?,
+            None => CoverageCollector::new(target, afl_out.clone())?,
+        };
 
         if let Some(target_args) = &merged_args.target_args {
             cov_collector.with_target_args(target_args.clone());
         }
 
         if merged_args.split_report {
-            cov_collector.with_split_report(true);
+            // Label instances that fuzzed the sanitizer or CMPCOV binary from the config
+            let (gen_args, _) = self.arg_aggregator.merge_gen_args(&GenArgs::default())?;
+            cov_collector
+                .with_split_report(true)
+                .with_instance_binaries(gen_args.san_target, gen_args.cmpc_target);
         }
 
         if merged_args.show_args.is_some() {
@@ -41,10 +153,46 @@ impl Command for CovCommand<'_> {
             cov_collector.with_misc_report_args(merged_args.report_args.clone().unwrap());
         }
 
-        if merged_args.text_report {
-            cov_collector.with_html(false);
+        if let Some(format) = merged_args.report_type {
+            cov_collector.with_report_format(format);
+        }
+
+        cov_collector
+            .with_source_filter(SourceFilter::new(
+                &merged_args.include,
+                &merged_args.exclude,
+            )?)
+            .with_crash_inputs(merged_args.crashes, merged_args.hangs)
+            .with_crash_view(merged_args.crash_view);
+
+        cov_collector
+            .with_overwrite_policy(if merged_args.overwrite {
+                OverwritePolicy::Overwrite
+            } else {
+                OverwritePolicy::Prompt
+            })
+            .with_incremental(!merged_args.no_cache)
+            .with_mem_limit(merged_args.mem_limit);
+        if let Some(timeout) = merged_args.timeout {
+            cov_collector.with_exec_timeout(Duration::from_millis(timeout));
+        }
+        if let Some(jobs) = merged_args.jobs {
+            cov_collector.with_jobs(jobs);
+        }
+
+        if let Some(interval) = merged_args.watch {
+            // Snapshots only need the totals, skip the HTML report
+            cov_collector
+                .with_report_format(ReportFormat::Json)
+                .with_overwrite_policy(OverwritePolicy::Overwrite);
+            return coverage_snapshot::watch(&mut cov_collector, &afl_out, interval);
+        }
+
+        if merged_args.timeline {
+            cov_collector.with_timeline(merged_args.timeline_interval);
         }
 
-        cov_collector.collect()
+        cov_collector.collect()?;
+        Ok(())
     }
 }
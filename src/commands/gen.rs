@@ -2,11 +2,22 @@ use anyhow::{Context, Result};
 use std::path::Path;
 
 use crate::{
-    afl::{base_cfg::Bcfg, cmd::Printable, cmd_gen::AFLCmdGenerator, harness::Harness},
+    afl::{
+        base_cfg::Bcfg,
+        cmd::Printable,
+        cmd_gen::AFLCmdGenerator,
+        dictionary::DictionaryConfig,
+        foreign::ForeignDirs,
+        harness::Harness,
+        phases::Phase,
+        resources::{HostResources, ResourcePlan, RunnerCount},
+        sync_groups,
+    },
     argument_aggregator::ArgumentAggregator,
     cli::constants,
     cli::GenArgs,
     commands::Command,
+    utils::topology::CoreBinding,
 };
 
 pub struct GenCommand<'a> {
@@ -24,15 +35,26 @@ impl<'a> GenCommand<'a> {
 
     /// Create an AFL++ runner
     ///
+    /// `phase` replaces the strategy of the commands by the one of a campaign phase.
+    /// `remote_cores` is the total core count of the hosts of a multi-host campaign, which
+    /// replaces the resource plan and core binding of the local machine.
+    ///
     /// # Errors
     /// * If any of the provided target binaries are invalid
+    /// * If the core list cannot be parsed
+    /// * If `-n auto` finds not enough free memory for a single instance
+    /// * If the config-only `[afl_env]` or `[[instance]]` settings are invalid
+    /// * If the sync group size is too small
     ///
     /// # Panics
     /// If the main target binary is empty
     pub fn create_afl_runner(
         gen_args: &GenArgs,
         raw_afl_flags: Option<&String>,
+        arg_aggregator: &ArgumentAggregator,
         is_ramdisk: bool,
+        phase: Option<&Phase>,
+        remote_cores: Option<usize>,
     ) -> Result<AFLCmdGenerator> {
         let harness = Harness::new(
             gen_args.target.clone().unwrap(),
@@ -49,6 +71,42 @@ impl<'a> GenCommand<'a> {
             None
         };
 
+        let requested = gen_args.runners.unwrap_or_default();
+        let runners = if let Some(cores) = remote_cores {
+            // The hosts check their share against their cores, the local machine is irrelevant
+            match requested {
+                RunnerCount::Fixed(n) => n,
+                RunnerCount::Auto(pct) => {
+                    u32::try_from((cores * usize::from(pct) / 100).max(1)).unwrap_or(u32::MAX)
+                }
+            }
+        } else {
+            let plan = ResourcePlan::new(
+                &HostResources::detect(),
+                requested,
+                is_ramdisk,
+                gen_args.allow_oversubscribe,
+            )?;
+            plan.print();
+            plan.runners
+        };
+
+        let core_binding = if remote_cores.is_some() {
+            if gen_args.cores.is_some() || gen_args.avoid_smt {
+                println!(
+                    "[!] Core binding is not available for multi-host campaigns, ignoring --cores/--avoid-smt"
+                );
+            }
+            None
+        } else {
+            gen_args
+                .cores
+                .as_deref()
+                .or(gen_args.avoid_smt.then_some("all"))
+                .map(|spec| CoreBinding::new(spec, gen_args.avoid_smt))
+                .transpose()?
+        };
+
         let afl_meta = Bcfg::new(
             gen_args
                 .input_dir
@@ -59,14 +117,29 @@ impl<'a> GenCommand<'a> {
                 .clone()
                 .unwrap_or_else(|| Path::new("/tmp/afl_output").to_path_buf()),
         )
-        .with_dictionary(gen_args.dictionary.clone())
+        .with_dictionary(DictionaryConfig {
+            paths: gen_args.dictionary.clone(),
+            auto_extract: gen_args.auto_dict,
+            tokencap: gen_args.tokencap.clone(),
+            split: gen_args.dict_split,
+        })?
+        .with_foreign_dirs(ForeignDirs {
+            dirs: gen_args.foreign_dirs.clone(),
+            selector: gen_args.foreign_instances.clone(),
+        })?
         .with_raw_afl_flags(raw_afl_flags)
         .with_afl_binary(gen_args.afl_binary.clone())
-        .with_ramdisk(is_ramdisk);
+        .with_core_binding(core_binding)
+        .with_env_settings(arg_aggregator.afl_env_settings()?)
+        .with_instance_overrides(arg_aggregator.instance_overrides()?)
+        .with_phase(phase.cloned())
+        .with_group_size(gen_args.group_size)?
+        .with_ramdisk(is_ramdisk)
+        .with_remote(remote_cores.is_some());
 
         Ok(AFLCmdGenerator::new(
             harness,
-            gen_args.runners.unwrap_or(1),
+            runners,
             &afl_meta,
             gen_args.mode,
             seed,
@@ -77,12 +150,37 @@ impl<'a> GenCommand<'a> {
 impl Command for GenCommand<'_> {
     fn execute(&self) -> Result<()> {
         let (merged_args, raw_afl_flags) = self.arg_aggregator.merge_gen_args(self.args)?;
-        let afl_generator = Self::create_afl_runner(&merged_args, raw_afl_flags.as_ref(), false)
-            .context("Failed to create AFL++ runner")?;
-        afl_generator
+        let mut afl_generator = Self::create_afl_runner(
+            &merged_args,
+            raw_afl_flags.as_ref(),
+            self.arg_aggregator,
+            false,
+            None,
+            None,
+        )
+        .context("Failed to create AFL++ runner")?;
+        // The output directory is left alone, generated dictionaries go to a fresh directory
+        let dictionary = &afl_generator.base_cfg.dictionary;
+        if dictionary.is_enabled() && !dictionary.is_passthrough() {
+            let dict_dir = tempfile::Builder::new()
+                .prefix("aflr_dicts_")
+                .tempdir()
+                .context("Failed to create dictionary directory")?
+                .into_path();
+            afl_generator.base_cfg.dict_dir = Some(dict_dir);
+        }
+        let afl_commands = afl_generator
             .run()
-            .context("Failed to run AFL++ generator")?
-            .print();
+            .context("Failed to run AFL++ generator")?;
+        afl_generator.write_dictionaries()?;
+        afl_commands.print();
+        let output_dir = &afl_generator.base_cfg.output_dir;
+        if !sync_groups::group_dirs(output_dir, &afl_commands).is_empty() {
+            println!(
+                "[*] Run 'aflr group-sync -i {}' alongside to share entries between the sync groups",
+                output_dir.display()
+            );
+        }
         Ok(())
     }
 }
@@ -1,7 +1,12 @@
 use anyhow::{Context, Result};
+#[cfg(unix)]
+use std::fs;
 use std::path::Path;
 
+#[cfg(unix)]
+use crate::utils::system::fuzzer_pids;
 use crate::{
+    afl::sync_groups,
     cli::KillArgs,
     commands::Command,
     runners::{screen::ScreenSession, tmux::TmuxSession},
@@ -15,6 +20,31 @@ impl<'a> KillCommand<'a> {
     pub fn new(args: &'a KillArgs) -> Self {
         Self { args }
     }
+
+    /// Terminates the instances in `output_dir` that still run, including those nested in
+    /// sync groups, and returns their number
+    #[cfg(unix)]
+    fn terminate_instances(output_dir: &Path) -> usize {
+        let is_afl_fuzz = |pid: libc::pid_t| {
+            fs::read(format!("/proc/{pid}/cmdline")).is_ok_and(|cmdline| {
+                cmdline
+                    .split(|&b| b == 0)
+                    .next()
+                    .is_some_and(|bin| bin.ends_with(b"afl-fuzz"))
+            })
+        };
+        fuzzer_pids(output_dir)
+            .into_iter()
+            .filter(|&pid| is_afl_fuzz(pid))
+            .filter(|&pid| unsafe { libc::kill(pid, libc::SIGTERM) } == 0)
+            .count()
+    }
+
+    /// Instances cannot be looked up by PID on this platform
+    #[cfg(not(unix))]
+    fn terminate_instances(_output_dir: &Path) -> usize {
+        0
+    }
 }
 
 impl Command for KillCommand<'_> {
@@ -44,6 +74,21 @@ impl Command for KillCommand<'_> {
             }
         }
 
+        if let Some(output_dir) = &self.args.output_dir {
+            if sync_groups::stop(output_dir) {
+                println!("[+] Stopped the group sync of {}", output_dir.display());
+                terminated = true;
+            }
+            let remaining = Self::terminate_instances(output_dir);
+            if remaining > 0 {
+                println!(
+                    "[+] Terminated {remaining} remaining instances in {}",
+                    output_dir.display()
+                );
+                terminated = true;
+            }
+        }
+
         if !terminated {
             println!("[-] No session found with the name: {session_name}");
         }
@@ -1,8 +1,10 @@
+pub mod adapt;
 pub mod cov;
 pub mod gen;
 pub mod kill;
 pub mod render_tui;
 pub mod run;
+pub mod sync;
 
 use anyhow::Result;
 
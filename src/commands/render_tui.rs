@@ -2,6 +2,7 @@ use anyhow::{bail, Context, Result};
 use std::path::Path;
 
 use crate::{
+    afl::sync_groups::instance_dirs,
     cli::TuiArgs,
     commands::Command,
     tui::{session::CampaignData, Tui},
@@ -17,17 +18,14 @@ impl<'a> RenderCommand<'a> {
     }
 
     fn validate_output_dir(output_dir: &Path) -> Result<()> {
-        for entry in output_dir.read_dir()? {
-            let path = entry?.path();
-            if path.is_dir() {
-                let fuzzer_stats = path.join("fuzzer_stats");
-                if !fuzzer_stats.exists() {
-                    bail!(
-                        "Invalid output directory: {} is missing 'fuzzer_stats' file",
-                        path.display()
-                    );
-                }
-            }
+        let has_stats = instance_dirs(output_dir)
+            .iter()
+            .any(|dir| dir.join("fuzzer_stats").exists());
+        if !has_stats {
+            bail!(
+                "Invalid output directory: no instance with a 'fuzzer_stats' file in {}",
+                output_dir.display()
+            );
         }
         Ok(())
     }
@@ -1,21 +1,51 @@
 use anyhow::{bail, Context, Result};
 use std::{
+    ffi::OsString,
+    fs,
     hash::{DefaultHasher, Hasher},
-    path::Path,
+    io::Write,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
 };
 
 use crate::{
-    afl::cmd::ToStringVec,
+    afl::{
+        calibration::Calibration,
+        cmd::{AFLCmd, ToStringVec},
+        cmd_gen::AFLCmdGenerator,
+        corpus_sync::SYNC_DIR,
+        coverage_snapshot,
+        harness::Harness,
+        phases::{self, find_afl_cmin, Phase},
+        preflight::{ensure_seed_dir, find_afl_tmin, list_seeds, Preflight, PreflightReport},
+        sync_groups::{self, DEFAULT_GROUP_SYNC_INTERVAL, GROUP_SYNC_DIR},
+        version::Capability,
+    },
     argument_aggregator::ArgumentAggregator,
-    cli::{constants, RunArgs, SessionRunner},
+    cli::{constants, CovArgs, RunArgs, SessionRunner},
     commands::{gen::GenCommand, Command},
     runners::{
+        adaptive::{self, AdaptiveState, DEFAULT_RESTART_RATIO},
+        remote::{Host, RemoteCampaign, DEFAULT_SYNC_INTERVAL},
         runner::{Session, SessionManager},
-        screen::ScreenSession,
-        tmux::TmuxSession,
+        screen::Screen,
+        tmux::Tmux,
+        transport::SshTransport,
     },
+    utils::system::{any_fuzzer_alive, get_user_input, mkdir_helper, spawn_detached},
 };
 
+/// Time the instances of a phase get to exit before the next phase starts
+const STOP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Results of the seed checks, written once the output directory is prepared
+#[derive(Default)]
+struct Reports {
+    preflight: Option<PreflightReport>,
+    calibration: Option<Calibration>,
+}
+
 pub struct RunCommand<'a> {
     args: &'a RunArgs,
     arg_aggregator: &'a ArgumentAggregator,
@@ -58,24 +88,609 @@ impl<'a> RunCommand<'a> {
         )
     }
 
-    fn execute_session<T: SessionManager>(
-        session: &Session<T>,
+    fn harness_args(harness: &Harness) -> Vec<String> {
+        harness
+            .target_args
+            .as_deref()
+            .map(|a| a.split_whitespace().map(String::from).collect())
+            .unwrap_or_default()
+    }
+
+    /// Checks the seed corpus and optionally stages a cleaned copy that is used as `-i`
+    fn preflight(
+        afl_generator: &AFLCmdGenerator,
+        afl_commands: &mut [AFLCmd],
+        args: &RunArgs,
+    ) -> Result<PreflightReport> {
+        let preflight = Preflight::new(
+            &afl_generator.harness.target_bin,
+            Self::harness_args(&afl_generator.harness),
+        );
+        let report = preflight
+            .run(&afl_generator.base_cfg.input_dir)
+            .context("Failed to run seed preflight")?;
+        report.print();
+
+        if let Some(stage_dir) = &args.stage_dir {
+            let tmin = args
+                .trim
+                .then(|| {
+                    afl_commands.first().map_or_else(
+                        || bail!("No commands generated"),
+                        |c| find_afl_tmin(&c.afl_binary),
+                    )
+                })
+                .transpose()?;
+            preflight.stage(&report, stage_dir, tmin.as_deref())?;
+            for cmd in afl_commands.iter_mut() {
+                cmd.with_input_dir(stage_dir.clone());
+            }
+        } else if report.needs_ignore_seed_problems() {
+            if afl_generator
+                .capabilities()?
+                .allows(Capability::IgnoreSeedProblems)
+            {
+                println!("[!] Crashing or hanging seeds found, setting AFL_IGNORE_SEED_PROBLEMS=1. Use --stage-dir to drop them instead");
+                for cmd in afl_commands.iter_mut() {
+                    cmd.set_env("AFL_IGNORE_SEED_PROBLEMS=1".to_string());
+                }
+            } else {
+                println!("[!] Warning: Crashing or hanging seeds found but this AFL++ does not support AFL_IGNORE_SEED_PROBLEMS. Use --stage-dir to drop them");
+            }
+        }
+        Ok(report)
+    }
+
+    /// Calibrates `-t`/`-m` on the seed corpus and injects them into the commands
+    fn calibrate(
+        afl_generator: &AFLCmdGenerator,
+        afl_commands: &mut [AFLCmd],
+    ) -> Result<Calibration> {
+        let harness = &afl_generator.harness;
+        let seed_dir = afl_commands
+            .first()
+            .map_or(&afl_generator.base_cfg.input_dir, |c| &c.input_dir);
+        let calibration = Calibration::run(
+            &harness.target_bin,
+            Self::harness_args(&afl_generator.harness),
+            seed_dir,
+        )
+        .context("Failed to calibrate the target")?;
+        calibration.print();
+        calibration.apply(afl_commands, harness.sanitizer_bin.as_deref());
+        Ok(calibration)
+    }
+
+    /// Lets the `-M` instance import the entries `aflr sync-client` fetches from `server`
+    fn read_sync_dir(afl_generator: &AFLCmdGenerator, afl_commands: &mut [AFLCmd], server: &str) {
+        let Some(main) = afl_commands
+            .iter_mut()
+            .find(|c| c.misc_afl_flags.iter().any(|f| f.starts_with("-M ")))
+        else {
+            println!("[!] No -M instance to import the entries of the sync server");
+            return;
+        };
+        let sync_dir = afl_generator.base_cfg.output_dir.join(SYNC_DIR);
+        main.add_flag(format!("-F {}", sync_dir.display()));
+        println!(
+            "[*] The -M instance imports entries from {server}, run 'aflr sync-client -i {}' to sync",
+            afl_generator.base_cfg.output_dir.display()
+        );
+    }
+
+    /// Prepares the output directory once the campaign is confirmed and writes the generated
+    /// dictionaries and the reports of the seed checks into it
+    ///
+    /// `resume` keeps the output directory of the previous phase instead of cleaning it.
+    fn setup_output_dir(
+        &self,
+        afl_generator: &AFLCmdGenerator,
+        afl_commands: &[AFLCmd],
+        reports: &Reports,
+        resume: bool,
+    ) -> Result<()> {
+        let output_dir = &afl_generator.base_cfg.output_dir;
+        if !resume {
+            mkdir_helper(output_dir, true)?;
+        }
+        afl_generator.write_dictionaries()?;
+        for group_dir in sync_groups::group_dirs(output_dir, afl_commands) {
+            mkdir_helper(&group_dir.join(GROUP_SYNC_DIR), false)?;
+        }
+        if self.arg_aggregator.sync_server().is_some() {
+            mkdir_helper(&output_dir.join(SYNC_DIR), false)?;
+        }
+        if let Some(report) = &reports.preflight {
+            let written = report.write(output_dir)?;
+            println!("[+] Preflight results written to {}", written.display());
+        }
+        if let Some(calibration) = &reports.calibration {
+            let written = calibration.write_report(output_dir)?;
+            println!("[+] Calibration results written to {}", written.display());
+        }
+        Ok(())
+    }
+
+    /// Arguments of `aflr cov --watch` for the coverage snapshots, with `cov_path` from the
+    /// config as coverage binary
+    fn coverage_watch_args(
+        &self,
+        afl_generator: &AFLCmdGenerator,
+        args: &RunArgs,
+    ) -> Result<Vec<String>> {
+        let cov_args = self.arg_aggregator.merge_cov_args(&CovArgs::default())?;
+        let Some(cov_target) = cov_args.target else {
+            bail!("--cov-interval requires a coverage binary, set cov_path in the config");
+        };
+        let mut watch_args = vec!["-t".to_string(), cov_target.display().to_string()];
+        if let Some(config) = &args.gen_args.config {
+            watch_args.extend(["--config".to_string(), config.display().to_string()]);
+        }
+        watch_args.push("--".to_string());
+        watch_args.extend(Self::harness_args(&afl_generator.harness));
+        Ok(watch_args)
+    }
+
+    /// Starts the background coverage snapshots with the arguments of `coverage_watch_args`
+    fn start_coverage_snapshots(
+        afl_generator: &AFLCmdGenerator,
+        watch_args: &[String],
+        interval: Duration,
+    ) -> Result<()> {
+        let (pid, log) = coverage_snapshot::spawn_watcher(
+            &afl_generator.base_cfg.output_dir,
+            interval,
+            watch_args,
+        )?;
+        println!(
+            "[+] Taking coverage snapshots every {}s in the background (PID {pid}, log: {})",
+            interval.as_secs(),
+            log.display()
+        );
+        Ok(())
+    }
+
+    /// Starts the background sync between the sync groups of the commands, if any
+    fn start_group_sync(
+        afl_generator: &AFLCmdGenerator,
+        afl_commands: &[AFLCmd],
+        args: &RunArgs,
+    ) -> Result<()> {
+        let output_dir = &afl_generator.base_cfg.output_dir;
+        let groups = sync_groups::group_dirs(output_dir, afl_commands);
+        if groups.is_empty() {
+            return Ok(());
+        }
+        let interval = args
+            .group_sync_interval
+            .unwrap_or(DEFAULT_GROUP_SYNC_INTERVAL);
+        let (pid, log) = sync_groups::spawn(output_dir, interval)?;
+        println!(
+            "[+] Syncing {} groups every {}s in the background (PID {pid}, log: {})",
+            groups.len(),
+            interval.as_secs(),
+            log.display()
+        );
+        Ok(())
+    }
+
+    /// Starts the background strategy reallocation for the session `sname`
+    fn start_adaptive<M: SessionManager>(
+        &self,
+        afl_generator: &AFLCmdGenerator,
+        afl_commands: &[AFLCmd],
+        sname: &str,
+        pid_file: &Path,
+        interval: Duration,
+    ) -> Result<()> {
+        let restart_ratio = self
+            .arg_aggregator
+            .restart_ratio()
+            .unwrap_or(DEFAULT_RESTART_RATIO);
+        let state = AdaptiveState::new::<M>(
+            sname,
+            afl_commands,
+            Some(pid_file.to_path_buf()),
+            restart_ratio,
+            afl_generator.capabilities()?.version(),
+        );
+        let (pid, log) = adaptive::spawn(&state, &afl_generator.base_cfg.output_dir, interval)?;
+        println!(
+            "[+] Reallocating strategies every {}s in the background (PID {pid}, log: {})",
+            interval.as_secs(),
+            log.display()
+        );
+        Ok(())
+    }
+
+    /// Splits the commands across the `[[remote.hosts]]` and synchronizes their corpora
+    /// until the campaign stopped, unless started detached
+    fn run_remote(
+        &self,
+        hosts: Vec<Host>,
+        afl_commands: &[AFLCmd],
+        afl_generator: &AFLCmdGenerator,
+        reports: &Reports,
+        args: &RunArgs,
+        sname: &str,
+    ) -> Result<()> {
+        let campaign = RemoteCampaign::new(
+            SshTransport::new(self.arg_aggregator.ssh_opts()),
+            hosts,
+            afl_commands.to_vec(),
+            sname,
+            afl_generator.base_cfg.output_dir.clone(),
+        )?;
+        campaign.print();
+        if args.dry_run {
+            return Ok(());
+        }
+        if args.tui {
+            println!("[!] The TUI is not available for multi-host campaigns, run 'aflr tui' on a host instead");
+        }
+        if args.cov_interval.is_some() {
+            println!("[!] Coverage snapshots are not available for multi-host campaigns");
+        }
+        if args.adapt_interval.is_some() {
+            println!("[!] The adaptive mode is not available for multi-host campaigns");
+        }
+
+        println!(
+            "Start session '{sname}' on {} hosts. Continue [Y/n]?",
+            campaign.assignments().len()
+        );
+        std::io::stdout().flush()?;
+        if get_user_input() != 'y' {
+            bail!("Aborting");
+        }
+        self.setup_output_dir(afl_generator, afl_commands, reports, false)?;
+
+        let input_dir = &afl_generator.base_cfg.input_dir;
+        let dict_dir = afl_generator.dict_dir();
+        let interval = args.sync_interval.unwrap_or(DEFAULT_SYNC_INTERVAL);
+        match &args.session_runner {
+            SessionRunner::Screen => {
+                campaign.start::<Screen>(input_dir, &dict_dir)?;
+                if !args.detached {
+                    campaign.sync_loop::<Screen>(interval);
+                }
+            }
+            SessionRunner::Tmux => {
+                campaign.start::<Tmux>(input_dir, &dict_dir)?;
+                if !args.detached {
+                    campaign.sync_loop::<Tmux>(interval);
+                }
+            }
+        }
+        if args.detached {
+            println!("[!] Started detached, corpora are not synchronized across hosts");
+        }
+        Ok(())
+    }
+
+    /// Generates the commands of a campaign phase and runs the seed checks
+    ///
+    /// Nothing is written to the output directory yet, see `setup_output_dir`. `resume` lets
+    /// all instances resume from the output directory of the previous phase.
+    fn prepare(
+        &self,
+        args: &RunArgs,
+        raw_afl_flags: Option<&String>,
+        phase: Option<&Phase>,
+        resume: bool,
+    ) -> Result<(AFLCmdGenerator, Vec<AFLCmd>, Reports)> {
+        let mut gen_args = args.gen_args.clone();
+        if let Some(mode) = phase.and_then(|p| p.mode) {
+            gen_args.mode = mode;
+        }
+        let hosts = self.arg_aggregator.remote_hosts()?;
+        let remote_cores = (!hosts.is_empty()).then(|| hosts.iter().map(|h| h.cores).sum());
+        let afl_generator = GenCommand::create_afl_runner(
+            &gen_args,
+            raw_afl_flags,
+            self.arg_aggregator,
+            args.is_ramdisk,
+            phase,
+            remote_cores,
+        )
+        .context("Failed to create AFL++ runner")?;
+
+        let mut afl_commands = afl_generator
+            .run()
+            .context("Failed to run AFL++ generator")?;
+        let input_dir = &afl_generator.base_cfg.input_dir;
+        if !args.dry_run {
+            ensure_seed_dir(input_dir)?;
+        } else if list_seeds(input_dir).map_or(true, |seeds| seeds.is_empty()) {
+            println!(
+                "[!] Warning: Seed directory {} is missing or empty, a placeholder seed is added when the campaign starts",
+                input_dir.display()
+            );
+        }
+
+        let mut reports = Reports::default();
+        if args.preflight || args.stage_dir.is_some() {
+            reports.preflight = Some(Self::preflight(&afl_generator, &mut afl_commands, args)?);
+        }
+
+        if args.calibrate {
+            reports.calibration = Some(Self::calibrate(&afl_generator, &mut afl_commands)?);
+        }
+
+        if let Some(server) = self.arg_aggregator.sync_server() {
+            Self::read_sync_dir(&afl_generator, &mut afl_commands, &server);
+        }
+
+        if resume {
+            for cmd in &mut afl_commands {
+                cmd.set_env("AFL_AUTORESUME=1".to_string());
+            }
+        }
+        Ok((afl_generator, afl_commands, reports))
+    }
+
+    /// Starts `aflr run` as a detached phase controller for the session `sname`
+    ///
+    /// The controller gets the `afl-fuzz` binary resolved here, so it does not have to
+    /// generate the commands of the first phase again.
+    fn start_phase_controller(
         args: &RunArgs,
-        session_type: &str,
+        phases: &[Phase],
+        sname: &str,
+        pid_file: &Path,
+        afl_fuzz: &Path,
     ) -> Result<()> {
+        let mut controller_args: Vec<OsString> = std::env::args_os().skip(1).collect();
+        if args.session_name.is_none() {
+            controller_args.extend(["--session-name".into(), sname.into()]);
+        }
+        controller_args.extend([
+            "--phase-controller".into(),
+            pid_file.into(),
+            "--phase-afl-fuzz".into(),
+            afl_fuzz.into(),
+        ]);
+
+        let log = PathBuf::from(format!("/tmp/aflr_phases_{sname}.log"));
+        let pid = spawn_detached(&controller_args, &log)
+            .context("Failed to start the phase controller")?;
+        println!(
+            "[+] Driving {} phases in the background (PID {pid}, log: {})",
+            phases.len(),
+            log.display()
+        );
+        Ok(())
+    }
+
+    /// Stops all instances of the session `sname`
+    fn stop_session<M: SessionManager>(sname: &str, output_dir: &Path) -> Result<()> {
+        M::build_kill_command(sname)
+            .status()
+            .with_context(|| format!("Failed to stop session '{sname}'"))?;
+        let start = Instant::now();
+        while any_fuzzer_alive(output_dir) {
+            if start.elapsed() > STOP_TIMEOUT {
+                bail!("Instances of session '{sname}' are still alive");
+            }
+            thread::sleep(Duration::from_millis(500));
+        }
+        Ok(())
+    }
+
+    /// Archives the output directory of phase `index` and minimizes the queues of all of
+    /// its instances into the seed corpus of the next phase
+    ///
+    /// Falls back to the merged queues if `afl-cmin` is unavailable or fails.
+    fn minimize(
+        harness: &Harness,
+        output_dir: &Path,
+        afl_fuzz: &Path,
+        index: usize,
+    ) -> Result<PathBuf> {
+        let name = output_dir
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+        // Leftovers of an earlier campaign with the same output directory must not collide
+        let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+        let stem = (0..)
+            .map(|n| match n {
+                0 => format!("{name}_phase{index}_{stamp}"),
+                n => format!("{name}_phase{index}_{stamp}_{n}"),
+            })
+            .find(|stem| {
+                ["", "_queues", "_cmin"].iter().all(|suffix| {
+                    !output_dir
+                        .with_file_name(format!("{stem}{suffix}"))
+                        .exists()
+                })
+            })
+            .unwrap_or_default();
+        let sibling = |suffix: &str| output_dir.with_file_name(format!("{stem}{suffix}"));
+
+        let archive = sibling("");
+        fs::rename(output_dir, &archive).with_context(|| {
+            format!(
+                "Failed to archive {} to {}",
+                output_dir.display(),
+                archive.display()
+            )
+        })?;
+        println!("[*] Archived the previous phase to {}", archive.display());
+
+        let queues = sibling("_queues");
+        let entries = phases::collect_queues(&archive, &queues)?;
+        let minimized = sibling("_cmin");
+        let result = find_afl_cmin(afl_fuzz).and_then(|cmin| {
+            phases::cmin(
+                &cmin,
+                &queues,
+                &minimized,
+                &harness.target_bin,
+                &Self::harness_args(harness),
+            )
+        });
+        match result {
+            Ok(kept) => {
+                println!("[+] Minimized {entries} queue entries to {kept}");
+                Ok(minimized)
+            }
+            Err(e) => {
+                println!("[!] Corpus minimization failed: {e:#}, continuing with all {entries} queue entries");
+                Ok(queues)
+            }
+        }
+    }
+
+    fn run_phase_controller(
+        &self,
+        args: &RunArgs,
+        raw_afl_flags: Option<&String>,
+        phases: &[Phase],
+        pid_file: &Path,
+    ) -> Result<()> {
+        match &args.session_runner {
+            SessionRunner::Screen => {
+                self.drive_phases::<Screen>(args, raw_afl_flags, phases, pid_file)
+            }
+            SessionRunner::Tmux => self.drive_phases::<Tmux>(args, raw_afl_flags, phases, pid_file),
+        }
+    }
+
+    /// Switches the session to the next phase whenever one ended, until the last phase
+    /// ended or the campaign was stopped
+    fn drive_phases<M: SessionManager>(
+        &self,
+        args: &RunArgs,
+        raw_afl_flags: Option<&String>,
+        phases: &[Phase],
+        pid_file: &Path,
+    ) -> Result<()> {
+        let sname = args
+            .session_name
+            .clone()
+            .context("The phase controller needs a session name")?;
+        // Seeds were checked when the campaign was started
+        let mut args = args.clone();
+        args.preflight = false;
+        args.stage_dir = None;
+
+        let mut afl_fuzz = args
+            .phase_afl_fuzz
+            .clone()
+            .context("The phase controller needs the afl-fuzz binary")?;
+        // The commands of the first phase are running already, so they are not generated again
+        let harness = Harness::new(
+            args.gen_args
+                .target
+                .clone()
+                .context("The phase controller needs a target")?,
+            args.gen_args.target_args.clone(),
+        )?;
+        let output_dir = args
+            .gen_args
+            .output_dir
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(constants::AFL_OUTPUT));
+
+        for (index, pair) in phases.windows(2).enumerate() {
+            let (current, next) = (&pair[0], &pair[1]);
+            let duration = current.duration.unwrap_or_default();
+            println!(
+                "[*] Phase '{}' runs for {}s",
+                current.name,
+                duration.as_secs()
+            );
+            if !phases::wait_phase(&output_dir, duration) {
+                println!("[*] No fuzzer alive anymore, stopping the phase transitions");
+                return Ok(());
+            }
+
+            println!(
+                "[*] Phase '{}' ended, switching to '{}'",
+                current.name, next.name
+            );
+            Self::stop_session::<M>(&sname, &output_dir)?;
+            if next.cmin {
+                args.gen_args.input_dir =
+                    Some(Self::minimize(&harness, &output_dir, &afl_fuzz, index)?);
+            }
+            let (next_generator, afl_commands, reports) =
+                self.prepare(&args, raw_afl_flags, Some(next), !next.cmin)?;
+            self.setup_output_dir(&next_generator, &afl_commands, &reports, !next.cmin)?;
+            Session::<M>::new(&sname, &afl_commands.to_string_vec(), pid_file)?
+                .start()
+                .with_context(|| format!("Failed to start phase '{}'", next.name))?;
+            println!(
+                "[+] Started phase '{}' with {} instances",
+                next.name,
+                afl_commands.len()
+            );
+            Self::start_group_sync(&next_generator, &afl_commands, &args)?;
+            afl_fuzz = afl_commands
+                .first()
+                .map_or(afl_fuzz, |c| c.afl_binary.clone());
+        }
+
+        if let Some(last) = phases.last() {
+            if let Some(duration) = last.duration {
+                println!("[*] Phase '{}' runs for {}s", last.name, duration.as_secs());
+                if phases::wait_phase(&output_dir, duration) {
+                    Self::stop_session::<M>(&sname, &output_dir)?;
+                    println!("[+] Last phase '{}' ended, stopped the campaign", last.name);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Asks for confirmation, prepares the output directory and starts the session `sname`
+    /// along with its background jobs
+    fn launch<M: SessionManager>(
+        &self,
+        afl_generator: &AFLCmdGenerator,
+        afl_commands: &[AFLCmd],
+        reports: &Reports,
+        args: &RunArgs,
+        sname: &str,
+        phases: &[Phase],
+    ) -> Result<()> {
+        let manager = M::manager_name();
+        let pid_fn = format!("/tmp/.{}_{}.pids", sname, std::process::id());
+        let pid_file = Path::new(&pid_fn);
+        let session = Session::<M>::new(sname, &afl_commands.to_string_vec(), pid_file)
+            .with_context(|| format!("Failed to create {manager} session"))?;
+        let watch_args = args
+            .cov_interval
+            .map(|_| self.coverage_watch_args(afl_generator, args))
+            .transpose()?;
+        session.confirm()?;
+        self.setup_output_dir(afl_generator, afl_commands, reports, false)?;
+
+        session
+            .start()
+            .with_context(|| format!("Failed to run {manager} session"))?;
+        // Background jobs are only started once the session runs, so an aborted start does
+        // not leave them behind
+        Self::start_group_sync(afl_generator, afl_commands, args)?;
+        if let (Some(interval), Some(watch_args)) = (args.cov_interval, &watch_args) {
+            Self::start_coverage_snapshots(afl_generator, watch_args, interval)?;
+        }
+        if let Some(interval) = args.adapt_interval {
+            self.start_adaptive::<M>(afl_generator, afl_commands, sname, pid_file, interval)?;
+        }
+        if let (false, Some(cmd)) = (phases.is_empty(), afl_commands.first()) {
+            Self::start_phase_controller(args, phases, sname, pid_file, &cmd.afl_binary)?;
+        }
         if args.tui {
             session
-                .run_with_tui(&args.gen_args.output_dir.clone().unwrap())
-                .with_context(|| format!("Failed to run TUI {session_type} session"))?;
-        } else {
+                .tui(&afl_generator.base_cfg.output_dir)
+                .with_context(|| format!("Failed to run TUI {manager} session"))?;
+        } else if !args.detached {
             session
-                .run()
-                .with_context(|| format!("Failed to run {session_type} session"))?;
-            if !args.detached {
-                session
-                    .attach()
-                    .with_context(|| format!("Failed to attach to {session_type} session"))?;
-            }
+                .attach()
+                .with_context(|| format!("Failed to attach to {manager} session"))?;
         }
         Ok(())
     }
@@ -83,50 +698,78 @@ impl<'a> RunCommand<'a> {
 
 impl Command for RunCommand<'_> {
     fn execute(&self) -> Result<()> {
-        let (merged_args, raw_afl_flags) = self.arg_aggregator.merge_run_args(self.args)?;
+        let (mut merged_args, raw_afl_flags) = self.arg_aggregator.merge_run_args(self.args)?;
 
         if merged_args.tui && merged_args.detached {
             bail!("TUI and detached mode cannot be used together");
         }
 
-        let afl_generator = GenCommand::create_afl_runner(
-            &merged_args.gen_args,
-            raw_afl_flags.as_ref(),
-            merged_args.is_ramdisk,
-        )
-        .context("Failed to create AFL++ runner")?;
-
-        let afl_commands = afl_generator
-            .run()
-            .context("Failed to run AFL++ generator")?;
-
-        if merged_args.dry_run {
-            println!("{afl_commands:?}");
-            return Ok(());
+        let phases = self.arg_aggregator.phases()?;
+        if let Some(pid_file) = merged_args.phase_controller.clone() {
+            return self.run_phase_controller(
+                &merged_args,
+                raw_afl_flags.as_ref(),
+                &phases,
+                &pid_file,
+            );
+        }
+        if !phases.is_empty() && merged_args.adapt_interval.take().is_some() {
+            println!("[!] The adaptive mode is not available for phased campaigns, disabling it");
         }
 
+        let (afl_generator, afl_commands, reports) =
+            self.prepare(&merged_args, raw_afl_flags.as_ref(), phases.first(), false)?;
+
         let target_args = merged_args
             .gen_args
             .target_args
             .clone()
             .unwrap_or_default()
             .join(" ");
-
         let sname = Self::generate_session_name(&merged_args, &target_args);
-        let pid_fn = format!("/tmp/.{}_{}.pids", &sname, std::process::id());
-        let pid_fn_path = Path::new(&pid_fn);
 
-        match &merged_args.session_runner {
-            SessionRunner::Screen => {
-                let screen = ScreenSession::new(&sname, &afl_commands.to_string_vec(), pid_fn_path)
-                    .context("Failed to create Screen session")?;
-                Self::execute_session(&screen, &merged_args, "Screen")
+        let hosts = self.arg_aggregator.remote_hosts()?;
+        if !hosts.is_empty() {
+            if !sync_groups::group_dirs(&afl_generator.base_cfg.output_dir, &afl_commands)
+                .is_empty()
+            {
+                bail!("Sync groups are not available for multi-host campaigns, every host already syncs in its own output directory");
             }
-            SessionRunner::Tmux => {
-                let tmux = TmuxSession::new(&sname, &afl_commands.to_string_vec(), pid_fn_path)
-                    .context("Failed to create Tmux session")?;
-                Self::execute_session(&tmux, &merged_args, "Tmux")
+            if !phases.is_empty() {
+                println!("[!] Phases are not available for multi-host campaigns, running the first phase only");
             }
+            return self.run_remote(
+                hosts,
+                &afl_commands,
+                &afl_generator,
+                &reports,
+                &merged_args,
+                &sname,
+            );
+        }
+
+        if merged_args.dry_run {
+            println!("{afl_commands:?}");
+            return Ok(());
+        }
+
+        match &merged_args.session_runner {
+            SessionRunner::Screen => self.launch::<Screen>(
+                &afl_generator,
+                &afl_commands,
+                &reports,
+                &merged_args,
+                &sname,
+                &phases,
+            ),
+            SessionRunner::Tmux => self.launch::<Tmux>(
+                &afl_generator,
+                &afl_commands,
+                &reports,
+                &merged_args,
+                &sname,
+                &phases,
+            ),
         }
     }
 }
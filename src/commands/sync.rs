@@ -0,0 +1,127 @@
+use anyhow::{bail, Context, Result};
+use std::time::Duration;
+
+use crate::{
+    afl::{
+        corpus_sync::{SyncClient, SyncServer, SYNC_LOG},
+        sync_groups::{GroupSync, DEFAULT_GROUP_SYNC_INTERVAL},
+    },
+    argument_aggregator::ArgumentAggregator,
+    cli::{GroupSyncArgs, SyncClientArgs, SyncServerArgs},
+    commands::Command,
+};
+
+/// Interval between sync rounds if none is configured
+const DEFAULT_SYNC_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+pub struct SyncServerCommand<'a> {
+    args: &'a SyncServerArgs,
+    arg_aggregator: &'a ArgumentAggregator,
+}
+
+impl<'a> SyncServerCommand<'a> {
+    pub fn new(args: &'a SyncServerArgs, arg_aggregator: &'a ArgumentAggregator) -> Self {
+        Self {
+            args,
+            arg_aggregator,
+        }
+    }
+}
+
+impl Command for SyncServerCommand<'_> {
+    fn execute(&self) -> Result<()> {
+        let merged_args = self.arg_aggregator.merge_sync_server_args(self.args);
+        let listen = merged_args.listen.unwrap_or_default();
+        let store = merged_args.store.unwrap_or_default();
+
+        let server = SyncServer::bind(&listen, &store).context("Failed to start sync server")?;
+        if !server.local_addr()?.ip().is_loopback() {
+            println!("[!] Warning: The sync server is unauthenticated, every host reaching {listen} can push entries into all connected campaigns");
+        }
+        println!(
+            "[+] Serving {} entries from {} on {} (log: {})",
+            server.entries(),
+            store.display(),
+            server.local_addr()?,
+            store.join(SYNC_LOG).display()
+        );
+        server.serve();
+        Ok(())
+    }
+}
+
+pub struct SyncClientCommand<'a> {
+    args: &'a SyncClientArgs,
+    arg_aggregator: &'a ArgumentAggregator,
+}
+
+impl<'a> SyncClientCommand<'a> {
+    pub fn new(args: &'a SyncClientArgs, arg_aggregator: &'a ArgumentAggregator) -> Self {
+        Self {
+            args,
+            arg_aggregator,
+        }
+    }
+}
+
+impl Command for SyncClientCommand<'_> {
+    fn execute(&self) -> Result<()> {
+        let merged_args = self.arg_aggregator.merge_sync_client_args(self.args);
+        let Some(server) = merged_args.server else {
+            bail!("No sync server given, use --server or set server in the [sync] config");
+        };
+        let output_dir = merged_args.output_dir.unwrap_or_default();
+        if !output_dir.is_dir() {
+            bail!("AFL++ output directory {} not found", output_dir.display());
+        }
+
+        let client = SyncClient::new(&server, &output_dir);
+        println!(
+            "[*] Importing foreign entries into {}, make sure the -M instance reads it via -F",
+            client.foreign_dir().display()
+        );
+        if merged_args.once {
+            let round = client.sync()?;
+            println!(
+                "[+] Pushed {} and imported {} entries",
+                round.pushed, round.pulled
+            );
+            return Ok(());
+        }
+        client.run(merged_args.interval.unwrap_or(DEFAULT_SYNC_INTERVAL));
+        Ok(())
+    }
+}
+
+pub struct GroupSyncCommand<'a> {
+    args: &'a GroupSyncArgs,
+    arg_aggregator: &'a ArgumentAggregator,
+}
+
+impl<'a> GroupSyncCommand<'a> {
+    pub fn new(args: &'a GroupSyncArgs, arg_aggregator: &'a ArgumentAggregator) -> Self {
+        Self {
+            args,
+            arg_aggregator,
+        }
+    }
+}
+
+impl Command for GroupSyncCommand<'_> {
+    fn execute(&self) -> Result<()> {
+        let merged_args = self.arg_aggregator.merge_group_sync_args(self.args);
+        let output_dir = merged_args.output_dir.unwrap_or_default();
+        if !output_dir.is_dir() {
+            bail!("AFL++ output directory {} not found", output_dir.display());
+        }
+
+        let sync = GroupSync::new(&output_dir);
+        if merged_args.once {
+            let shared = sync.sync()?;
+            println!("[+] Shared {shared} new entries across groups");
+            return Ok(());
+        }
+        sync.run(merged_args.interval.unwrap_or(DEFAULT_GROUP_SYNC_INTERVAL));
+        Ok(())
+    }
+}
@@ -12,8 +12,14 @@ pub mod utils;
 use argument_aggregator::ArgumentAggregator;
 use cli::{Cli, Commands};
 use commands::{
-    cov::CovCommand, gen::GenCommand, kill::KillCommand, render_tui::RenderCommand,
-    run::RunCommand, Command,
+    adapt::AdaptCommand,
+    cov::CovCommand,
+    gen::GenCommand,
+    kill::KillCommand,
+    render_tui::RenderCommand,
+    run::RunCommand,
+    sync::{GroupSyncCommand, SyncClientCommand, SyncServerCommand},
+    Command,
 };
 
 fn main() -> Result<()> {
@@ -25,6 +31,10 @@ fn main() -> Result<()> {
         Commands::Gen(args) => arg_aggregator.load(args.config.as_ref()),
         Commands::Run(args) => arg_aggregator.load(args.gen_args.config.as_ref()),
         Commands::Cov(args) => arg_aggregator.load(args.config.as_ref()),
+        Commands::SyncServer(args) => arg_aggregator.load(args.config.as_ref()),
+        Commands::SyncClient(args) => arg_aggregator.load(args.config.as_ref()),
+        Commands::Adapt(args) => arg_aggregator.load(args.config.as_ref()),
+        Commands::GroupSync(args) => arg_aggregator.load(args.config.as_ref()),
         _ => Ok(()),
     }?;
 
@@ -35,5 +45,9 @@ fn main() -> Result<()> {
         Commands::Cov(args) => CovCommand::new(args, &arg_aggregator).execute(),
         Commands::Tui(args) => RenderCommand::new(args).execute(),
         Commands::Kill(args) => KillCommand::new(args).execute(),
+        Commands::SyncServer(args) => SyncServerCommand::new(args, &arg_aggregator).execute(),
+        Commands::SyncClient(args) => SyncClientCommand::new(args, &arg_aggregator).execute(),
+        Commands::Adapt(args) => AdaptCommand::new(args, &arg_aggregator).execute(),
+        Commands::GroupSync(args) => GroupSyncCommand::new(args, &arg_aggregator).execute(),
     }
 }
@@ -0,0 +1,427 @@
+use std::{
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Context, Result};
+use rand::{rngs::StdRng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::afl::adaptive::{Arm, Bandit, Candidate, InstanceStats, Round, ARM_OPTIONS};
+use crate::afl::cmd::AFLCmd;
+use crate::afl::sync_groups::find_instance_dir;
+use crate::afl::version::{AflVersion, Capabilities};
+use crate::runners::runner::SessionManager;
+use crate::utils::system::{any_fuzzer_alive, spawn_detached};
+
+/// State of the adaptive mode in the AFL++ output directory
+pub const ADAPTIVE_STATE: &str = "aflr_adaptive.json";
+/// Log of the background adaptive process
+pub const ADAPTIVE_LOG: &str = "aflr_adaptive.log";
+/// Interval between adaptation rounds if none is configured
+pub const DEFAULT_ADAPT_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// Share of the secondaries restarted per round if none is configured
+pub const DEFAULT_RESTART_RATIO: f64 = 0.25;
+/// Time a stopped instance gets to exit and a restarted one to report its PID
+const RESTART_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// An instance of the campaign along with the command it is restarted with
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdaptiveInstance {
+    /// `-M`/`-S` name of the instance
+    pub name: String,
+    /// Session window the instance runs in
+    pub window: String,
+    /// AFL++ part of the command without the reallocated options
+    pub command: String,
+    /// Target binary and arguments
+    pub target: String,
+    pub arm: String,
+    pub main: bool,
+    pub custom_mutator: bool,
+    pub restarts: u32,
+    /// Stats at the end of the last round
+    pub last: Option<InstanceStats>,
+}
+
+impl AdaptiveInstance {
+    fn new(index: usize, cmd: &AFLCmd) -> Option<Self> {
+        let name = cmd.instance_name()?.to_string();
+        let arm = Arm::from_flags(&cmd.misc_afl_flags);
+        let mut base = cmd.clone();
+        base.remove_flags(&ARM_OPTIONS);
+        let assembled = base.assemble();
+        let (command, target) = assembled.split_once(" -- ")?;
+        let custom_mutator = cmd
+            .env
+            .iter()
+            .map(|e| e.split('=').next().unwrap_or_default().to_string())
+            .chain(std::env::vars().map(|(key, _)| key))
+            .any(|key| key == "AFL_CUSTOM_MUTATOR_LIBRARY" || key == "AFL_PYTHON_MODULE");
+        Some(Self {
+            main: cmd.misc_afl_flags.iter().any(|f| f.starts_with("-M ")),
+            name,
+            window: format!("window-{index}"),
+            command: command.to_string(),
+            target: target.to_string(),
+            arm: arm.to_string(),
+            custom_mutator,
+            restarts: 0,
+            last: None,
+        })
+    }
+
+    /// Command resuming the instance with the configuration `arm`
+    pub fn resume_command(&self, arm: Arm) -> String {
+        format!("AFL_AUTORESUME=1 {} {arm} -- {}", self.command, self.target)
+    }
+}
+
+/// Everything `aflr adapt` needs to restart the instances of a running campaign
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdaptiveState {
+    pub session: String,
+    /// Session manager running the campaign, `tmux` or `screen`
+    pub runner: String,
+    /// PID file of the session, updated with the PIDs of restarted instances
+    pub pid_file: Option<PathBuf>,
+    pub restart_ratio: f64,
+    pub instances: Vec<AdaptiveInstance>,
+    pub bandit: Bandit,
+    /// Version of `afl-fuzz`, restarts only use options it supports
+    #[serde(default)]
+    pub afl_version: Option<AflVersion>,
+}
+
+impl AdaptiveState {
+    pub fn new<M: SessionManager>(
+        session: &str,
+        commands: &[AFLCmd],
+        pid_file: Option<PathBuf>,
+        restart_ratio: f64,
+        afl_version: Option<AflVersion>,
+    ) -> Self {
+        Self {
+            session: session.to_string(),
+            runner: M::manager_name().to_string(),
+            pid_file,
+            restart_ratio,
+            instances: commands
+                .iter()
+                .enumerate()
+                .filter_map(|(i, cmd)| AdaptiveInstance::new(i, cmd))
+                .collect(),
+            bandit: Bandit::default(),
+            afl_version,
+        }
+    }
+
+    /// Loads the state of the campaign in `afl_out`
+    ///
+    /// # Errors
+    /// * If the campaign was not started with the adaptive mode
+    pub fn load(afl_out: &Path) -> Result<Self> {
+        let path = afl_out.join(ADAPTIVE_STATE);
+        if !path.is_file() {
+            bail!(
+                "No adaptive state found in {}, start the campaign with --adapt-interval",
+                afl_out.display()
+            );
+        }
+        serde_json::from_str(&fs::read_to_string(&path)?)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    /// # Errors
+    /// * If the state cannot be written
+    pub fn save(&self, afl_out: &Path) -> Result<()> {
+        let path = afl_out.join(ADAPTIVE_STATE);
+        fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Number of secondaries that may be restarted per round
+    #[allow(clippy::cast_possible_truncation)]
+    #[allow(clippy::cast_precision_loss)]
+    #[allow(clippy::cast_sign_loss)]
+    fn max_restarts(&self) -> usize {
+        let secondaries = self.instances.iter().filter(|i| !i.main).count();
+        (secondaries as f64 * self.restart_ratio.clamp(0.0, 1.0)).ceil() as usize
+    }
+
+    /// Scores all instances since the last round, restarts the worst ones and logs the
+    /// round to the decision log
+    ///
+    /// Instances are only scored once they ran for a whole round with the same PID.
+    ///
+    /// # Errors
+    /// * If the decision log or the state cannot be written
+    pub fn round<M: SessionManager>(&mut self, afl_out: &Path, rng: &mut StdRng) -> Result<Round> {
+        let mut candidates = Vec::new();
+        for instance in &mut self.instances {
+            let Some(stats) = InstanceStats::read(&find_instance_dir(afl_out, &instance.name))
+            else {
+                continue;
+            };
+            if let Some(last) = instance.last.filter(|last| last.pid == stats.pid) {
+                candidates.push(Candidate {
+                    name: instance.name.clone(),
+                    arm: instance.arm.parse()?,
+                    score: stats.score(&last),
+                    restartable: !instance.main,
+                    custom_mutator: instance.custom_mutator,
+                });
+            }
+            instance.last = Some(stats);
+        }
+
+        let max_restarts = self.max_restarts();
+        let capabilities = Capabilities::new(self.afl_version);
+        let mut round = Round::plan(
+            &mut self.bandit,
+            &candidates,
+            max_restarts,
+            &capabilities,
+            rng,
+        );
+        round.restarts.retain(|restart| {
+            let Some(instance) = self
+                .instances
+                .iter_mut()
+                .find(|i| i.name == restart.instance)
+            else {
+                return false;
+            };
+            let arm: Arm = restart.to.parse().unwrap_or_default();
+            match restart_instance::<M>(
+                &self.session,
+                afl_out,
+                instance,
+                arm,
+                self.pid_file.as_deref(),
+            ) {
+                Ok(()) => {
+                    println!(
+                        "[+] Restarted {} with '{}' instead of '{}'",
+                        restart.instance, restart.to, restart.from
+                    );
+                    instance.arm.clone_from(&restart.to);
+                    instance.restarts += 1;
+                    true
+                }
+                Err(e) => {
+                    eprintln!("[-] Failed to restart {}: {e:#}", restart.instance);
+                    false
+                }
+            }
+        });
+        round.append(afl_out)?;
+        self.save(afl_out)?;
+        Ok(round)
+    }
+}
+
+/// Polls `done` until it holds or the restart timeout passed
+fn wait_until(mut done: impl FnMut() -> bool) -> bool {
+    let start = Instant::now();
+    while start.elapsed() < RESTART_TIMEOUT {
+        if done() {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(250));
+    }
+    done()
+}
+
+fn is_alive(pid: u32) -> bool {
+    libc::pid_t::try_from(pid).is_ok_and(|pid| pid > 0 && unsafe { libc::kill(pid, 0) } == 0)
+}
+
+/// Stops an instance and resumes it in its session window with the configuration `arm`
+fn restart_instance<M: SessionManager>(
+    session: &str,
+    afl_out: &Path,
+    instance: &AdaptiveInstance,
+    arm: Arm,
+    pid_file: Option<&Path>,
+) -> Result<()> {
+    let instance_dir = find_instance_dir(afl_out, &instance.name);
+    let old_pid = InstanceStats::read(&instance_dir).map_or(0, |s| s.pid);
+    if is_alive(old_pid) {
+        let pid = libc::pid_t::try_from(old_pid)?;
+        unsafe { libc::kill(pid, libc::SIGTERM) };
+        if !wait_until(|| !is_alive(old_pid)) {
+            unsafe { libc::kill(pid, libc::SIGKILL) };
+        }
+    }
+
+    // The leading space keeps the command out of the shell history
+    let keys = format!(" {}", instance.resume_command(arm));
+    let status = M::build_send_keys_command(session, &instance.window, &keys)
+        .status()
+        .with_context(|| format!("Failed to run {}", M::manager_name()))?;
+    if !status.success() {
+        bail!(
+            "{} could not reach window {}",
+            M::manager_name(),
+            instance.window
+        );
+    }
+
+    let mut new_pid = 0;
+    let started = wait_until(|| {
+        new_pid = InstanceStats::read(&instance_dir).map_or(0, |s| s.pid);
+        new_pid != old_pid && is_alive(new_pid)
+    });
+    if !started {
+        println!("[!] {} did not report a new PID yet", instance.name);
+    } else if let Some(pid_file) = pid_file {
+        if let Ok(pids) = fs::read_to_string(pid_file) {
+            let pids: Vec<String> = pids
+                .trim()
+                .split(':')
+                .map(|pid| {
+                    if pid == old_pid.to_string() {
+                        new_pid.to_string()
+                    } else {
+                        pid.to_string()
+                    }
+                })
+                .collect();
+            fs::write(pid_file, pids.join(":") + "\n")?;
+        }
+    }
+    Ok(())
+}
+
+/// Runs an adaptation round every `interval` until no instance is alive anymore
+///
+/// # Errors
+/// * If the state of the campaign cannot be loaded or written
+pub fn adapt<M: SessionManager>(afl_out: &Path, interval: Duration) -> Result<()> {
+    let mut state = AdaptiveState::load(afl_out)?;
+    let mut rng = StdRng::from_entropy();
+    println!(
+        "[*] Reallocating strategies of {} instances in session '{}' every {}s",
+        state.instances.len(),
+        state.session,
+        interval.as_secs()
+    );
+    loop {
+        thread::sleep(interval);
+        if !any_fuzzer_alive(afl_out) {
+            println!("[*] No fuzzer alive anymore, stopping the adaptive mode");
+            return Ok(());
+        }
+        let round = state.round::<M>(afl_out, &mut rng)?;
+        println!(
+            "[*] Scored {} instances, restarted {}",
+            round.scores.len(),
+            round.restarts.len()
+        );
+    }
+}
+
+/// Writes the adaptive state of a campaign and starts `aflr adapt` as a detached
+/// background process for it. Returns the PID and the log file of the process.
+///
+/// # Errors
+/// * If the state cannot be written or the process cannot be started
+pub fn spawn(state: &AdaptiveState, afl_out: &Path, interval: Duration) -> Result<(u32, PathBuf)> {
+    state.save(afl_out)?;
+    let log_path = afl_out.join(ADAPTIVE_LOG);
+    let args: [OsString; 5] = [
+        "adapt".into(),
+        "-i".into(),
+        afl_out.as_os_str().to_owned(),
+        "--interval".into(),
+        format!("{}s", interval.as_secs()).into(),
+    ];
+    let pid = spawn_detached(&args, &log_path).context("Failed to start the adaptive process")?;
+    Ok((pid, log_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runners::tmux::Tmux;
+
+    fn commands() -> Vec<AFLCmd> {
+        [
+            "-M m_target",
+            "-S s1_target -p rare -P exploit",
+            "-S s2_target -L 0 -Z",
+        ]
+        .iter()
+        .map(|role| {
+            let mut cmd = AFLCmd::new("afl-fuzz".into(), "/bin/target".into());
+            cmd.with_input_dir("/in".into())
+                .with_output_dir("/out".into())
+                .with_misc_flags(crate::afl::cmd::split_flags(role))
+                .with_target_args(Some("@@".into()));
+            cmd
+        })
+        .collect()
+    }
+
+    #[test]
+    fn test_state() {
+        let state = AdaptiveState::new::<Tmux>("sess", &commands(), None, 0.5, None);
+        assert_eq!(state.runner, "tmux");
+        assert_eq!(state.instances.len(), 3);
+        assert!(state.instances[0].main);
+        assert_eq!(state.max_restarts(), 1);
+
+        let s1 = &state.instances[1];
+        assert_eq!(s1.window, "window-1");
+        assert_eq!(s1.arm, "-p rare -P exploit");
+        assert_eq!(
+            s1.resume_command("-p explore".parse().unwrap()),
+            "AFL_AUTORESUME=1 afl-fuzz -i /in -o /out -S s1_target -p explore -- /bin/target @@"
+        );
+        assert_eq!(state.instances[2].arm, "-p fast -L 0");
+
+        let dir = tempfile::tempdir().unwrap();
+        assert!(AdaptiveState::load(dir.path()).is_err());
+        state.save(dir.path()).unwrap();
+        assert_eq!(AdaptiveState::load(dir.path()).unwrap().instances.len(), 3);
+    }
+
+    #[test]
+    fn test_round_scores_stable_instances() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state = AdaptiveState::new::<Tmux>("sess", &commands(), None, 0.0, None);
+        let write_stats = |name: &str, run_time: u64, found: u64| {
+            let instance = dir.path().join(name);
+            fs::create_dir_all(&instance).unwrap();
+            fs::write(
+                instance.join("fuzzer_stats"),
+                format!("fuzzer_pid : 0\nrun_time : {run_time}\ncorpus_found : {found}\n"),
+            )
+            .unwrap();
+        };
+        let mut rng = StdRng::seed_from_u64(0);
+
+        write_stats("m_target", 10, 1);
+        write_stats("s1_target", 10, 1);
+        let round = state.round::<Tmux>(dir.path(), &mut rng).unwrap();
+        assert!(round.scores.is_empty());
+
+        write_stats("m_target", 3610, 11);
+        write_stats("s1_target", 3610, 2);
+        let round = state.round::<Tmux>(dir.path(), &mut rng).unwrap();
+        assert_eq!(round.scores.len(), 2);
+        assert!(round.restarts.is_empty());
+        assert!((round.scores[0].score - 10.0).abs() < 1e-9);
+
+        let log =
+            fs::read_to_string(dir.path().join(crate::afl::adaptive::ADAPTIVE_DECISIONS)).unwrap();
+        assert_eq!(log.lines().count(), 2);
+        assert!(AdaptiveState::load(dir.path()).unwrap().instances[1]
+            .last
+            .is_some());
+    }
+}
@@ -1,3 +1,6 @@
+pub mod adaptive;
+pub mod remote;
 pub mod runner;
 pub mod screen;
 pub mod tmux;
+pub mod transport;
@@ -0,0 +1,501 @@
+use std::{
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+
+use crate::afl::cmd::{AFLCmd, ToStringVec};
+use crate::cli::HostArgs;
+use crate::runners::{
+    runner::{Session, SessionManager},
+    transport::{shell_quote, Transport},
+};
+
+/// Interval between corpus synchronizations if none is configured
+pub const DEFAULT_SYNC_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Machine a part of a multi-host campaign runs on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Host {
+    pub address: String,
+    pub user: Option<String>,
+    pub port: Option<u16>,
+    /// Maximum number of instances on the host
+    pub cores: usize,
+    /// Whether the host runs the `-M` instance
+    pub main: bool,
+    /// Output directory on the host, defaults to the local one
+    pub output_dir: Option<PathBuf>,
+}
+
+impl Host {
+    pub fn new(address: &str, cores: usize) -> Self {
+        Self {
+            address: address.to_string(),
+            user: None,
+            port: None,
+            cores,
+            main: false,
+            output_dir: None,
+        }
+    }
+
+    /// Converts a `[[remote.hosts]]` entry into a host
+    ///
+    /// # Errors
+    /// * If the address is empty or the host has no cores
+    pub fn from_config(args: &HostArgs) -> Result<Self> {
+        if args.address.is_empty() {
+            bail!("[[remote.hosts]] entries need an address");
+        }
+        if args.cores == 0 {
+            bail!("Host {} needs at least one core", args.address);
+        }
+        Ok(Self {
+            address: args.address.clone(),
+            user: args.user.clone().filter(|u| !u.is_empty()),
+            port: args.port,
+            cores: args.cores,
+            main: args.main.unwrap_or(false),
+            output_dir: args
+                .output_dir
+                .clone()
+                .filter(|d| !d.is_empty())
+                .map(PathBuf::from),
+        })
+    }
+
+    /// `user@address` destination for `ssh` and `rsync`
+    pub fn destination(&self) -> String {
+        self.user.as_ref().map_or_else(
+            || self.address.clone(),
+            |user| format!("{user}@{}", self.address),
+        )
+    }
+}
+
+/// Commands assigned to one host
+#[derive(Debug, Clone)]
+pub struct HostAssignment {
+    pub host: Host,
+    pub commands: Vec<AFLCmd>,
+    /// Output directory of the commands on the host
+    pub output_dir: PathBuf,
+}
+
+impl HostAssignment {
+    /// `-M`/`-S` names of the instances on the host
+    pub fn instances(&self) -> impl Iterator<Item = &str> {
+        self.commands.iter().filter_map(AFLCmd::instance_name)
+    }
+}
+
+/// Campaign whose instances are split across several hosts
+///
+/// Every host keeps its own output directory. Corpora are synchronized through a local
+/// staging directory, the local output directory, rather than through the main host: the
+/// queues of all instances are pulled into it and every host then receives the queues of
+/// the instances running elsewhere, which AFL++ picks up like those of local instances.
+/// The main host only differs by running the `-M` instance.
+pub struct RemoteCampaign<T: Transport> {
+    transport: T,
+    assignments: Vec<HostAssignment>,
+    session_name: String,
+    staging: PathBuf,
+}
+
+impl<T: Transport> RemoteCampaign<T> {
+    /// Splits `commands` across `hosts` by their core counts, starting with the main host
+    ///
+    /// # Errors
+    /// * If more than one host is marked as main
+    /// * If there are more commands than cores across all hosts
+    pub fn new(
+        transport: T,
+        mut hosts: Vec<Host>,
+        commands: Vec<AFLCmd>,
+        session_name: &str,
+        staging: PathBuf,
+    ) -> Result<Self> {
+        if hosts.iter().filter(|h| h.main).count() > 1 {
+            bail!("Only one host can be the main node");
+        }
+        let cores: usize = hosts.iter().map(|h| h.cores).sum();
+        if commands.len() > cores {
+            bail!(
+                "{} instances do not fit on {} cores across {} hosts",
+                commands.len(),
+                cores,
+                hosts.len()
+            );
+        }
+        if let Some(main) = hosts.iter().position(|h| h.main) {
+            hosts[..=main].rotate_right(1);
+        } else if let Some(first) = hosts.first_mut() {
+            first.main = true;
+        }
+
+        let mut commands = commands.into_iter();
+        let assignments = hosts
+            .into_iter()
+            .map(|host| {
+                let output_dir = host.output_dir.clone().unwrap_or_else(|| staging.clone());
+                let commands: Vec<_> = commands
+                    .by_ref()
+                    .take(host.cores)
+                    .map(|mut cmd| {
+                        cmd.with_output_dir(output_dir.clone());
+                        cmd
+                    })
+                    .collect();
+                HostAssignment {
+                    host,
+                    commands,
+                    output_dir,
+                }
+            })
+            .filter(|a| !a.commands.is_empty())
+            .collect();
+
+        Ok(Self {
+            transport,
+            assignments,
+            session_name: session_name.to_string(),
+            staging,
+        })
+    }
+
+    pub fn assignments(&self) -> &[HostAssignment] {
+        &self.assignments
+    }
+
+    pub fn print(&self) {
+        for assignment in &self.assignments {
+            println!(
+                "Host {} ({}{}):",
+                assignment.host.destination(),
+                assignment.output_dir.display(),
+                if assignment.host.main { ", main" } else { "" }
+            );
+            for (i, cmd) in assignment.commands.iter().enumerate() {
+                print!("  {i:3}. ");
+                cmd.print();
+            }
+        }
+    }
+
+    /// Copies the seed corpus and the generated dictionaries in `dict_dir` to every host
+    /// and starts its share of the commands in a `M` session
+    ///
+    /// No session is started before every host has the files its commands need.
+    ///
+    /// # Errors
+    /// * If the seeds or dictionaries cannot be copied
+    /// * If a binary or dictionary of the commands is missing on a host
+    /// * If a session cannot be started
+    pub fn start<M: SessionManager>(&self, input_dir: &Path, dict_dir: &Path) -> Result<()> {
+        for assignment in &self.assignments {
+            let host = &assignment.host;
+            self.transport
+                .push(host, input_dir, input_dir)
+                .with_context(|| format!("Failed to copy the seed corpus to {}", host.address))?;
+            if dict_dir.is_dir() {
+                self.transport
+                    .push(host, dict_dir, dict_dir)
+                    .with_context(|| {
+                        format!("Failed to copy the dictionaries to {}", host.address)
+                    })?;
+            }
+            self.transport.exec(
+                host,
+                &format!(
+                    "mkdir -p {}",
+                    shell_quote(&assignment.output_dir.to_string_lossy())
+                ),
+                None,
+            )?;
+            self.check_files(assignment)?;
+        }
+
+        let pid_file = PathBuf::from(format!("/tmp/.{}_remote.pids", self.session_name));
+        for assignment in &self.assignments {
+            let host = &assignment.host;
+            let session = Session::<M>::new(
+                &self.session_name,
+                &assignment.commands.to_string_vec(),
+                &pid_file,
+            )?;
+            let script = session.create_bash_script()?;
+            self.transport
+                .exec(host, "bash -s", Some(script.as_bytes()))
+                .with_context(|| format!("Failed to start the session on {}", host.address))?;
+            println!(
+                "[+] Started {} instances on {}",
+                assignment.commands.len(),
+                host.address
+            );
+        }
+        Ok(())
+    }
+
+    /// Checks that the binaries and dictionaries of the commands exist on their host
+    fn check_files(&self, assignment: &HostAssignment) -> Result<()> {
+        let mut checks: Vec<(&str, &Path)> = Vec::new();
+        for cmd in &assignment.commands {
+            checks.push(("-x", &cmd.afl_binary));
+            checks.push(("-x", &cmd.target_binary));
+            checks.extend(
+                cmd.misc_afl_flags
+                    .iter()
+                    .filter_map(|f| f.strip_prefix("-x "))
+                    .map(|dict| ("-r", Path::new(dict))),
+            );
+        }
+        checks.sort_unstable();
+        checks.dedup();
+
+        for (test, path) in checks {
+            let path = path.to_string_lossy();
+            self.transport
+                .exec(
+                    &assignment.host,
+                    &format!("test {test} {}", shell_quote(&path)),
+                    None,
+                )
+                .with_context(|| {
+                    format!(
+                        "{path} is missing on {}, it has to exist at the same path as locally",
+                        assignment.host.address
+                    )
+                })?;
+        }
+        Ok(())
+    }
+
+    /// Exchanges the instance queues between all hosts and returns the number copied to
+    /// other hosts
+    ///
+    /// Hosts that cannot be reached are skipped with a warning and retried next time.
+    pub fn sync(&self) -> usize {
+        let queue = |instance: &str| Path::new(instance).join("queue");
+
+        for assignment in &self.assignments {
+            for instance in assignment.instances() {
+                if let Err(e) = self.transport.pull(
+                    &assignment.host,
+                    &assignment.output_dir.join(queue(instance)),
+                    &self.staging.join(queue(instance)),
+                ) {
+                    println!(
+                        "[!] Failed to pull {instance} from {}: {e:#}",
+                        assignment.host.address
+                    );
+                }
+            }
+        }
+
+        let mut pushed = 0;
+        for (i, assignment) in self.assignments.iter().enumerate() {
+            let foreign = self
+                .assignments
+                .iter()
+                .enumerate()
+                .filter(|&(j, _)| j != i)
+                .flat_map(|(_, other)| other.instances());
+            for instance in foreign {
+                let staged = self.staging.join(queue(instance));
+                if !staged.is_dir() {
+                    continue;
+                }
+                match self.transport.push(
+                    &assignment.host,
+                    &staged,
+                    &assignment.output_dir.join(queue(instance)),
+                ) {
+                    Ok(()) => pushed += 1,
+                    Err(e) => println!(
+                        "[!] Failed to push {instance} to {}: {e:#}",
+                        assignment.host.address
+                    ),
+                }
+            }
+        }
+        pushed
+    }
+
+    /// Whether the `M` session still runs on any host
+    pub fn is_running<M: SessionManager>(&self) -> bool {
+        let check = M::build_session_check_command(&self.session_name);
+        let check = std::iter::once(check.get_program())
+            .chain(check.get_args())
+            .map(|arg| shell_quote(&arg.to_string_lossy()))
+            .collect::<Vec<_>>()
+            .join(" ");
+        self.assignments
+            .iter()
+            .any(|a| self.transport.exec(&a.host, &check, None).is_ok())
+    }
+
+    /// Synchronizes the corpora every `interval` until the session stopped on all hosts
+    pub fn sync_loop<M: SessionManager>(&self, interval: Duration) {
+        println!(
+            "[*] Synchronizing corpora every {}s, press Ctrl-C to stop",
+            interval.as_secs()
+        );
+        loop {
+            thread::sleep(interval);
+            let pushed = self.sync();
+            println!("[+] Synchronized {pushed} instance queues");
+            if !self.is_running::<M>() {
+                println!("[*] Session stopped on all hosts, stopping synchronization");
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::runners::transport::LocalTransport;
+
+    fn commands(n: usize) -> Vec<AFLCmd> {
+        (0..n)
+            .map(|i| {
+                let mut cmd = AFLCmd::new("afl-fuzz".into(), "/bin/target".into());
+                cmd.add_flag(if i == 0 {
+                    "-M m_target".to_string()
+                } else {
+                    format!("-S s{i}_target")
+                });
+                cmd
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_host_assignment() {
+        let mut main = Host::new("main", 2);
+        main.main = true;
+        let hosts = vec![Host::new("a", 2), Host::new("b", 4), main];
+        let campaign = RemoteCampaign::new(
+            LocalTransport,
+            hosts.clone(),
+            commands(5),
+            "s",
+            "/tmp/out".into(),
+        )
+        .unwrap();
+
+        let assignments = campaign.assignments();
+        assert_eq!(assignments.len(), 3);
+        assert_eq!(assignments[0].host.address, "main");
+        assert_eq!(
+            assignments[0].instances().collect::<Vec<_>>(),
+            vec!["m_target", "s1_target"]
+        );
+        assert_eq!(assignments[2].commands.len(), 1);
+        assert_eq!(assignments[2].output_dir, PathBuf::from("/tmp/out"));
+
+        assert!(
+            RemoteCampaign::new(LocalTransport, hosts, commands(9), "s", "/tmp".into()).is_err()
+        );
+        let two_mains = vec![
+            Host::from_config(&HostArgs {
+                address: "x".into(),
+                cores: 1,
+                main: Some(true),
+                ..HostArgs::default()
+            })
+            .unwrap();
+            2
+        ];
+        assert!(
+            RemoteCampaign::new(LocalTransport, two_mains, commands(1), "s", "/tmp".into())
+                .is_err()
+        );
+        assert!(Host::from_config(&HostArgs::default()).is_err());
+    }
+
+    #[test]
+    fn test_sync() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut first = Host::new("first", 1);
+        first.output_dir = Some(dir.path().join("first"));
+        let mut second = Host::new("second", 1);
+        second.output_dir = Some(dir.path().join("second"));
+        let staging = dir.path().join("staging");
+
+        let campaign = RemoteCampaign::new(
+            LocalTransport,
+            vec![first, second],
+            commands(2),
+            "s",
+            staging.clone(),
+        )
+        .unwrap();
+        fs::create_dir_all(dir.path().join("first/m_target/queue")).unwrap();
+        fs::write(dir.path().join("first/m_target/queue/id:000000"), "a").unwrap();
+        fs::create_dir_all(dir.path().join("second/s1_target/queue")).unwrap();
+        fs::write(dir.path().join("second/s1_target/queue/id:000000"), "b").unwrap();
+
+        assert_eq!(campaign.sync(), 2);
+        assert!(staging.join("m_target/queue/id:000000").is_file());
+        assert!(staging.join("s1_target/queue/id:000000").is_file());
+        assert_eq!(
+            fs::read_to_string(dir.path().join("first/s1_target/queue/id:000000")).unwrap(),
+            "b"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("second/m_target/queue/id:000000")).unwrap(),
+            "a"
+        );
+    }
+
+    #[test]
+    fn test_check_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let dict = dir.path().join("dict.txt");
+        fs::write(&dict, "kw=\"a\"").unwrap();
+        let mut cmds = commands(2);
+        for cmd in &mut cmds {
+            cmd.afl_binary = "/bin/sh".into();
+            cmd.target_binary = "/bin/sh".into();
+        }
+        cmds[1].add_flag(format!("-x {}", dict.display()));
+
+        let campaign = RemoteCampaign::new(
+            LocalTransport,
+            vec![Host::new("a", 2)],
+            cmds.clone(),
+            "s",
+            dir.path().join("out"),
+        )
+        .unwrap();
+        campaign.check_files(&campaign.assignments()[0]).unwrap();
+
+        fs::remove_file(&dict).unwrap();
+        assert!(campaign.check_files(&campaign.assignments()[0]).is_err());
+
+        // Nothing is started if a host misses the target
+        cmds[0].target_binary = dir.path().join("missing");
+        let campaign = RemoteCampaign::new(
+            LocalTransport,
+            vec![Host::new("a", 2)],
+            cmds,
+            "s",
+            dir.path().join("out"),
+        )
+        .unwrap();
+        let seeds = dir.path().join("seeds");
+        fs::create_dir(&seeds).unwrap();
+        let err = campaign
+            .start::<crate::runners::tmux::Tmux>(&seeds, &dir.path().join("aflr_dicts"))
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("missing"));
+    }
+}
@@ -74,6 +74,9 @@ pub trait SessionManager: Sized {
     /// Command to attach to a session
     fn build_attach_command(session_name: &str) -> Command;
 
+    /// Command to type `keys` followed by enter into a window of a session
+    fn build_send_keys_command(session_name: &str, window: &str, keys: &str) -> Command;
+
     /// Optional post-attachment setup (e.g., finding window ID in tmux)
     ///
     /// # Errors
@@ -173,18 +176,20 @@ impl<T: SessionManager> Session<T> {
         Ok(())
     }
 
-    /// Run the session
+    /// Run the session without asking for confirmation, callers confirm via `confirm`
+    /// beforehand unless running in the background
     ///
     /// # Errors
     /// * If the session script could not be created
     /// * If the session could not be started
-    pub fn run(&self) -> Result<()> {
+    pub fn start(&self) -> Result<()> {
         self.setup_directories()?;
-        self.confirm_start()?;
         Self::check_manager_installation()?;
         self.execute_session_script()
     }
 
+    /// Makes sure the directories exist, the seed corpus and output directory are prepared
+    /// by the caller beforehand
     fn setup_directories(&self) -> Result<()> {
         // NOTE: We only need to look at the first command since all commands
         // will use the same directories
@@ -192,14 +197,18 @@ impl<T: SessionManager> Session<T> {
 
         mkdir_helper(&first_cmd.input_dir, false)?;
         if first_cmd.input_dir.read_dir()?.next().is_none() {
-            fs::write(first_cmd.input_dir.join("1"), "fuzz")?;
+            anyhow::bail!("Input directory {} is empty", first_cmd.input_dir.display());
         }
-        mkdir_helper(&first_cmd.output_dir, true)?;
+        mkdir_helper(&first_cmd.output_dir, false)?;
 
         Ok(())
     }
 
-    fn confirm_start(&self) -> Result<()> {
+    /// Asks the user whether to start the session
+    ///
+    /// # Errors
+    /// * If the user declines
+    pub fn confirm(&self) -> Result<()> {
         println!(
             "Generated {} session '{}' for {} commands. Continue [Y/n]?",
             T::manager_name(),
@@ -258,14 +267,12 @@ impl<T: SessionManager> Session<T> {
         Ok(())
     }
 
-    /// Run the session with a TUI
+    /// Shows the TUI for the started session
     ///
     /// # Errors
-    /// * If the session could not be started
-    pub fn run_with_tui(&self, out_dir: &Path) -> Result<()> {
+    /// * If the TUI fails
+    pub fn tui(&self, out_dir: &Path) -> Result<()> {
         let mut cdata = CampaignData::new();
-        self.run()?;
-
         thread::sleep(Duration::from_secs(1));
         Tui::run(out_dir, Some(&self.pid_file), &mut cdata)?;
         Ok(())
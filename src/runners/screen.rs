@@ -35,6 +35,20 @@ impl SessionManager for Screen {
         cmd.args(["-r", session_name]);
         cmd
     }
+
+    fn build_send_keys_command(session_name: &str, window: &str, keys: &str) -> Command {
+        let mut cmd = Command::new(Self::manager_name());
+        cmd.args([
+            "-S",
+            session_name,
+            "-p",
+            window,
+            "-X",
+            "stuff",
+            &format!("{keys}\n"),
+        ]);
+        cmd
+    }
 }
 
 /// Type alias for a Screen session
@@ -60,5 +74,19 @@ mod tests {
             kill_cmd.get_args().collect::<Vec<_>>(),
             vec!["-S", "test_session", "-X", "kill"]
         );
+
+        let send_cmd = Screen::build_send_keys_command(session_name, "window-1", "ls");
+        assert_eq!(
+            send_cmd.get_args().collect::<Vec<_>>(),
+            vec![
+                "-S",
+                "test_session",
+                "-p",
+                "window-1",
+                "-X",
+                "stuff",
+                "ls\n"
+            ]
+        );
     }
 }
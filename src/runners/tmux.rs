@@ -37,6 +37,18 @@ impl SessionManager for Tmux {
         cmd
     }
 
+    fn build_send_keys_command(session_name: &str, window: &str, keys: &str) -> Command {
+        let mut cmd = Command::new(Self::manager_name());
+        cmd.args([
+            "send-keys",
+            "-t",
+            &format!("{session_name}:{window}"),
+            keys,
+            "C-m",
+        ]);
+        cmd
+    }
+
     fn post_attach_setup(session_name: &str) -> Result<()> {
         let output = Command::new(Self::manager_name())
             .args(["list-windows", "-t", session_name])
@@ -80,5 +92,11 @@ mod tests {
             kill_cmd.get_args().collect::<Vec<_>>(),
             vec!["kill-session", "-t", "test_session"]
         );
+
+        let send_cmd = Tmux::build_send_keys_command(session_name, "window-1", "ls");
+        assert_eq!(
+            send_cmd.get_args().collect::<Vec<_>>(),
+            vec!["send-keys", "-t", "test_session:window-1", "ls", "C-m"]
+        );
     }
 }
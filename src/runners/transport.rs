@@ -0,0 +1,224 @@
+use std::{
+    fs,
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use anyhow::{bail, Context, Result};
+
+use crate::runners::remote::Host;
+
+/// Executes commands and copies directories on the hosts of a multi-host campaign
+pub trait Transport {
+    /// Runs a shell command on `host`, feeding `stdin` to it, and returns its stdout
+    ///
+    /// # Errors
+    /// * If the command cannot be started or exits with a non-zero status
+    fn exec(&self, host: &Host, cmd: &str, stdin: Option<&[u8]>) -> Result<String>;
+
+    /// Copies the files of the local directory `src` missing in `dst` on `host`
+    ///
+    /// # Errors
+    /// * If the directory cannot be copied
+    fn push(&self, host: &Host, src: &Path, dst: &Path) -> Result<()>;
+
+    /// Copies the files of `src` on `host` missing in the local directory `dst`
+    ///
+    /// # Errors
+    /// * If the directory cannot be copied
+    fn pull(&self, host: &Host, src: &Path, dst: &Path) -> Result<()>;
+}
+
+/// Quotes `arg` for a POSIX shell
+pub fn shell_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+/// Runs `cmd` to completion and returns its stdout
+fn run(mut cmd: Command, stdin: Option<&[u8]>) -> Result<String> {
+    let program = cmd.get_program().to_string_lossy().into_owned();
+    let mut child = cmd
+        .stdin(if stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to start {program}"))?;
+    if let (Some(data), Some(mut pipe)) = (stdin, child.stdin.take()) {
+        pipe.write_all(data)?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        bail!(
+            "{program} failed with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Transport over `ssh`, copying directories with `rsync`
+#[derive(Debug, Clone, Default)]
+pub struct SshTransport {
+    ssh_opts: Vec<String>,
+}
+
+impl SshTransport {
+    pub fn new(ssh_opts: Vec<String>) -> Self {
+        Self { ssh_opts }
+    }
+
+    fn ssh_args(&self, host: &Host) -> Vec<String> {
+        let mut args = self.ssh_opts.clone();
+        if let Some(port) = host.port {
+            args.extend(["-p".to_string(), port.to_string()]);
+        }
+        args
+    }
+
+    fn rsync(&self, host: &Host, src: String, dst: String) -> Result<()> {
+        let mut cmd = Command::new("rsync");
+        cmd.args(["-a", "--ignore-existing", "-e"])
+            .arg(format!("ssh {}", self.ssh_args(host).join(" ")))
+            .args([src, dst]);
+        run(cmd, None).map(|_| ())
+    }
+}
+
+impl Transport for SshTransport {
+    fn exec(&self, host: &Host, cmd: &str, stdin: Option<&[u8]>) -> Result<String> {
+        let mut ssh = Command::new("ssh");
+        ssh.args(self.ssh_args(host))
+            .arg(host.destination())
+            .arg("--")
+            .arg(cmd);
+        run(ssh, stdin).with_context(|| format!("Failed to run '{cmd}' on {}", host.address))
+    }
+
+    fn push(&self, host: &Host, src: &Path, dst: &Path) -> Result<()> {
+        self.exec(
+            host,
+            &format!("mkdir -p {}", shell_quote(&dst.to_string_lossy())),
+            None,
+        )?;
+        self.rsync(
+            host,
+            format!("{}/", src.display()),
+            format!("{}:{}/", host.destination(), dst.display()),
+        )
+    }
+
+    fn pull(&self, host: &Host, src: &Path, dst: &Path) -> Result<()> {
+        fs::create_dir_all(dst)?;
+        self.rsync(
+            host,
+            format!("{}:{}/", host.destination(), src.display()),
+            format!("{}/", dst.display()),
+        )
+    }
+}
+
+/// Transport that runs everything on the local machine, treating host paths as local ones
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LocalTransport;
+
+impl LocalTransport {
+    /// Recursively copies the files of `src` missing in `dst`
+    fn copy_missing(src: &Path, dst: &Path) -> Result<()> {
+        if src == dst {
+            return Ok(());
+        }
+        fs::create_dir_all(dst)?;
+        for entry in
+            fs::read_dir(src).with_context(|| format!("Failed to read {}", src.display()))?
+        {
+            let entry = entry?;
+            let target = dst.join(entry.file_name());
+            if entry.file_type()?.is_dir() {
+                Self::copy_missing(&entry.path(), &target)?;
+            } else if !target.exists() {
+                fs::copy(entry.path(), &target)
+                    .with_context(|| format!("Failed to copy {}", entry.path().display()))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Transport for LocalTransport {
+    fn exec(&self, _host: &Host, cmd: &str, stdin: Option<&[u8]>) -> Result<String> {
+        let mut bash = Command::new("bash");
+        bash.args(["-c", cmd]);
+        run(bash, stdin)
+    }
+
+    fn push(&self, _host: &Host, src: &Path, dst: &Path) -> Result<()> {
+        Self::copy_missing(src, dst)
+    }
+
+    fn pull(&self, _host: &Host, src: &Path, dst: &Path) -> Result<()> {
+        Self::copy_missing(src, dst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_transport() {
+        let host = Host::new("localhost", 1);
+        let transport = LocalTransport;
+        assert_eq!(transport.exec(&host, "cat", Some(b"seed")).unwrap(), "seed");
+        assert!(transport.exec(&host, "exit 3", None).is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(src.join("queue")).unwrap();
+        fs::write(src.join("queue/id:000000"), "new").unwrap();
+        fs::write(src.join("queue/id:000001"), "new").unwrap();
+        let dst = dir.path().join("dst");
+        fs::create_dir_all(dst.join("queue")).unwrap();
+        fs::write(dst.join("queue/id:000001"), "kept").unwrap();
+
+        transport.push(&host, &src, &dst).unwrap();
+        assert_eq!(
+            fs::read_to_string(dst.join("queue/id:000000")).unwrap(),
+            "new"
+        );
+        assert_eq!(
+            fs::read_to_string(dst.join("queue/id:000001")).unwrap(),
+            "kept"
+        );
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        let host = Host::new("localhost", 1);
+        let arg = "it's $HOME; `id`";
+        assert_eq!(
+            LocalTransport
+                .exec(&host, &format!("printf %s {}", shell_quote(arg)), None)
+                .unwrap(),
+            arg
+        );
+    }
+
+    #[test]
+    fn test_ssh_args() {
+        let transport = SshTransport::new(vec!["-o".into(), "BatchMode=yes".into()]);
+        let mut host = Host::new("fuzz2", 8);
+        host.user = Some("fuzz".into());
+        host.port = Some(2222);
+        assert_eq!(host.destination(), "fuzz@fuzz2");
+        assert_eq!(
+            transport.ssh_args(&host),
+            vec!["-o", "BatchMode=yes", "-p", "2222"]
+        );
+    }
+}
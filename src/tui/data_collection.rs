@@ -8,6 +8,8 @@ use std::{
 
 use sysinfo::{Pid, System};
 
+use crate::afl::coverage_snapshot::CoverageSnapshot;
+use crate::afl::sync_groups::instance_dirs;
 use crate::tui::session::{CampaignData, CrashInfoDetails};
 
 macro_rules! update_stat {
@@ -150,26 +152,19 @@ impl DataFetcher {
                 let mut alive_pids = Vec::new();
                 let mut dead_count = 0;
 
-                if let Ok(entries) = fs::read_dir(output_dir) {
-                    for entry in entries.flatten() {
-                        let path = entry.path();
-                        if !path.is_dir() {
-                            continue;
-                        }
-
-                        let stats_path = path.join("fuzzer_stats");
-                        if !stats_path.exists() {
-                            continue;
-                        }
+                for path in instance_dirs(output_dir) {
+                    let stats_path = path.join("fuzzer_stats");
+                    if !stats_path.exists() {
+                        continue;
+                    }
 
-                        if let Ok(content) = fs::read_to_string(&stats_path) {
-                            let metrics = FuzzerMetrics::parse(&content);
-                            if let Some(pid) = metrics.pid {
-                                if system.process(Pid::from(pid as usize)).is_some() {
-                                    alive_pids.push(pid);
-                                } else {
-                                    dead_count += 1;
-                                }
+                    if let Ok(content) = fs::read_to_string(&stats_path) {
+                        let metrics = FuzzerMetrics::parse(&content);
+                        if let Some(pid) = metrics.pid {
+                            if system.process(Pid::from(pid as usize)).is_some() {
+                                alive_pids.push(pid);
+                            } else {
+                                dead_count += 1;
                             }
                         }
                     }
@@ -209,6 +204,7 @@ impl DataFetcher {
 
         self.campaign_data.clear();
         self.process_fuzzer_directories();
+        self.campaign_data.source_coverage = CoverageSnapshot::latest(&self.output_dir);
         self.update_run_time();
         self.calculate_averages();
 
@@ -220,24 +216,17 @@ impl DataFetcher {
     }
 
     fn process_fuzzer_directories(&mut self) {
-        if let Ok(entries) = fs::read_dir(&self.output_dir) {
-            for entry in entries.flatten() {
-                let path = entry.path();
-                if !path.is_dir() {
-                    continue;
-                }
-
-                let stats_path = path.join("fuzzer_stats");
-                if !stats_path.exists() {
-                    continue;
-                }
+        for path in instance_dirs(&self.output_dir) {
+            let stats_path = path.join("fuzzer_stats");
+            if !stats_path.exists() {
+                continue;
+            }
 
-                if let Ok(content) = fs::read_to_string(&stats_path) {
-                    let metrics = FuzzerMetrics::parse(&content);
-                    if let Some(pid) = metrics.pid {
-                        if self.campaign_data.fuzzers_alive.contains(&(pid as usize)) {
-                            self.process_metrics(&metrics);
-                        }
+            if let Ok(content) = fs::read_to_string(&stats_path) {
+                let metrics = FuzzerMetrics::parse(&content);
+                if let Some(pid) = metrics.pid {
+                    if self.campaign_data.fuzzers_alive.contains(&(pid as usize)) {
+                        self.process_metrics(&metrics);
                     }
                 }
             }
@@ -396,27 +385,20 @@ impl DataFetcher {
         let mut crashes = Vec::with_capacity(num_latest);
         let mut hangs = Vec::with_capacity(num_latest);
 
-        if let Ok(entries) = fs::read_dir(&self.output_dir) {
-            for entry in entries.flatten() {
-                let subdir = entry.path();
-                if !subdir.is_dir() {
-                    continue;
-                }
+        for subdir in instance_dirs(&self.output_dir) {
+            let fuzzer_name = subdir
+                .file_name()
+                .and_then(|n| n.to_str())
+                .map(String::from)
+                .unwrap_or_default();
 
-                let fuzzer_name = subdir
-                    .file_name()
-                    .and_then(|n| n.to_str())
-                    .map(String::from)
-                    .unwrap_or_default();
-
-                Self::collect_solution_files(&subdir, &fuzzer_name, "crashes", &mut crashes);
-                Self::collect_solution_files(&subdir, &fuzzer_name, "hangs", &mut hangs);
-            }
+            Self::collect_solution_files(&subdir, &fuzzer_name, "crashes", &mut crashes);
+            Self::collect_solution_files(&subdir, &fuzzer_name, "hangs", &mut hangs);
         }
 
         // Sort by time and take latest n items
-        crashes.sort_unstable_by(|a, b| b.time.cmp(&a.time));
-        hangs.sort_unstable_by(|a, b| b.time.cmp(&a.time));
+        crashes.sort_unstable_by_key(|c| std::cmp::Reverse(c.time));
+        hangs.sort_unstable_by_key(|h| std::cmp::Reverse(h.time));
 
         (
             crashes.into_iter().take(num_latest).collect(),
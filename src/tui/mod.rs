@@ -298,7 +298,7 @@ impl Tui {
     }
 
     /// Creates the process timings paragraph
-    fn create_process_timings_paragraph(session_data: &CampaignData) -> Paragraph {
+    fn create_process_timings_paragraph(session_data: &CampaignData) -> Paragraph<'_> {
         let last_seen_crash =
             Self::format_last_event(&session_data.last_crashes, &session_data.total_run_time);
         let last_seen_hang =
@@ -349,7 +349,7 @@ impl Tui {
     }
 
     /// Creates the overall results paragraph
-    fn create_overall_results_paragraph(session_data: &CampaignData) -> Paragraph {
+    fn create_overall_results_paragraph(session_data: &CampaignData) -> Paragraph<'_> {
         let stability_style = if session_data.stability.avg >= CAUTION_STABILITY {
             Style::default()
         } else if session_data.stability.avg >= WARN_STABILITY {
@@ -414,7 +414,7 @@ impl Tui {
     }
 
     /// Creates the stage progress paragraph
-    fn create_stage_progress_paragraph(session_data: &CampaignData) -> Paragraph {
+    fn create_stage_progress_paragraph(session_data: &CampaignData) -> Paragraph<'_> {
         let ps_cum_style = if session_data.executions.per_sec.cum < SLOW_EXEC_PS_THRESHOLD {
             Style::default().fg(Color::Red)
         } else {
@@ -471,8 +471,18 @@ impl Tui {
                 Span::raw(")"),
             ]),
             Line::from(format!(
-                "Coverage: {:.2}% ({:.2}%/{:.2}%)",
-                session_data.coverage.avg, session_data.coverage.min, session_data.coverage.max,
+                "Coverage: {:.2}% ({:.2}%/{:.2}%){}",
+                session_data.coverage.avg,
+                session_data.coverage.min,
+                session_data.coverage.max,
+                session_data
+                    .source_coverage
+                    .map(|s| format!(
+                        " | Source: {:.2}% lines, {:.2}% functions",
+                        s.line_percent(),
+                        s.function_percent()
+                    ))
+                    .unwrap_or_default(),
             )),
         ];
 
@@ -488,7 +498,7 @@ impl Tui {
     }
 
     /// Creates the nerd stats paragraph
-    fn create_nerd_stats_paragraph(session_data: &CampaignData) -> Paragraph {
+    fn create_nerd_stats_paragraph(session_data: &CampaignData) -> Paragraph<'_> {
         let content = format!(
             "Levels: {} ({}/{})
 Pending favorites: {} ({}->{}<-{})
@@ -2,6 +2,7 @@ use chrono::{DateTime, Local};
 use std::path::PathBuf;
 use std::time::{Duration, Instant, SystemTime};
 
+use crate::afl::coverage_snapshot::CoverageSnapshot;
 use crate::utils::log_buffer::LogRingBuffer;
 
 #[derive(Default, Debug, Clone)]
@@ -66,6 +67,8 @@ pub struct CampaignData {
     pub pending: ExtendedStats,
     pub corpus: Stats<usize>,
     pub coverage: Stats<f64>,
+    /// Latest background source coverage snapshot, if `--cov-interval` is used
+    pub source_coverage: Option<CoverageSnapshot>,
     pub cycles: CycleStats,
     pub stability: Stats<f64>,
     pub crashes: Stats<usize>,
@@ -90,6 +93,7 @@ impl Default for CampaignData {
             pending: ExtendedStats::default(),
             corpus: Stats::new(),
             coverage: Stats::new(),
+            source_coverage: None,
             cycles: CycleStats::default(),
             stability: Stats::new(),
             crashes: Stats::new(),
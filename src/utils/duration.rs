@@ -0,0 +1,45 @@
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+
+/// Parses a duration like `90s`, `15m`, `1h` or `2d`, a plain number is taken as seconds
+///
+/// # Errors
+/// * If the value is not a positive number with an optional `s`/`m`/`h`/`d` suffix
+pub fn parse_duration(s: &str) -> Result<Duration> {
+    let s = s.trim();
+    let (value, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+        Some(pos) => s.split_at(pos),
+        None => (s, "s"),
+    };
+    let value: u64 = value
+        .parse()
+        .with_context(|| format!("Invalid duration '{s}'"))?;
+    let secs = match unit {
+        "s" => value,
+        "m" => value * 60,
+        "h" => value * 3600,
+        "d" => value * 86400,
+        _ => bail!("Invalid duration unit in '{s}', use s, m, h or d"),
+    };
+    if secs == 0 {
+        bail!("Duration '{s}' must be greater than zero");
+    }
+    Ok(Duration::from_secs(secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("15m").unwrap(), Duration::from_secs(900));
+        assert_eq!(parse_duration(" 1h ").unwrap(), Duration::from_secs(3600));
+        assert_eq!(parse_duration("2d").unwrap(), Duration::from_secs(172_800));
+        for invalid in ["", "0m", "h", "1w", "-1h", "1.5h"] {
+            assert!(parse_duration(invalid).is_err(), "{invalid}");
+        }
+    }
+}
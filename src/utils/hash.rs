@@ -0,0 +1,37 @@
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// Stable 64-bit FNV-1a hash of `data`
+///
+/// Unlike `DefaultHasher`, the result does not change between Rust releases, so it can be
+/// persisted across runs.
+pub fn content_hash(data: &[u8]) -> u64 {
+    data.iter().fold(FNV_OFFSET_BASIS, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// Hex-encoded content hash of the file at `path`
+///
+/// # Errors
+/// * If the file cannot be read
+pub fn hash_file(path: &Path) -> Result<String> {
+    let data = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(format!("{:016x}", content_hash(&data)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_hash() {
+        assert_eq!(content_hash(b""), FNV_OFFSET_BASIS);
+        assert_eq!(content_hash(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_ne!(content_hash(b"ab"), content_hash(b"ba"));
+    }
+}
@@ -1,3 +1,6 @@
+pub mod duration;
+pub mod hash;
 pub mod log_buffer;
 pub mod seed;
 pub mod system;
+pub mod topology;
@@ -1,20 +1,27 @@
 use std::{
+    ffi::OsStr,
     fs,
     io::{self, stdin, Read},
     path::{Path, PathBuf},
-    process::Command,
+    process::{Command, Stdio},
 };
 
 use anyhow::{bail, Context, Result};
 use sysinfo::System;
 use uuid::Uuid;
 
-/// Retrieves the amount of free memory in the system in MB
-/// This function is used to determine the `AFL_TESTCACHE_SIZE` value
+#[cfg(unix)]
+use crate::afl::sync_groups::instance_dirs;
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
+
+/// Retrieves the amount of free memory in the system in MB, counting reclaimable caches as free
+/// This function is used to determine the `AFL_TESTCACHE_SIZE` value and the resource plan
 #[cfg(not(target_os = "windows"))]
 pub fn get_free_mem_in_mb() -> u64 {
-    let s = System::new_all();
-    s.free_memory() / 1024 / 1024
+    let mut s = System::new();
+    s.refresh_memory();
+    s.available_memory() / 1024 / 1024
 }
 
 #[cfg(target_os = "windows")]
@@ -22,6 +29,9 @@ pub fn get_free_mem_in_mb() -> u64 {
     0
 }
 
+/// Size of the `RAMDisk` created by `create_ramdisk` in MB
+pub const RAMDISK_SIZE_MB: u64 = 4096;
+
 /// Creates a `RAMDisk` with 4GB size
 ///
 /// # Returns
@@ -36,7 +46,14 @@ pub fn create_ramdisk() -> Result<String> {
     let folder = format!("/tmp/tmpfs/{uuid}");
     fs::create_dir_all(&folder)?;
     let _ = Command::new("sudo")
-        .args(["mount", "-o", "size=4G", "-t", "tmpfs", "none"])
+        .args([
+            "mount",
+            "-o",
+            &format!("size={RAMDISK_SIZE_MB}M"),
+            "-t",
+            "tmpfs",
+            "none",
+        ])
         .arg(&folder)
         .output()?;
     Ok(folder)
@@ -147,9 +164,82 @@ fn should_clean_directory(dir: &Path) -> io::Result<bool> {
     ))
 }
 
+/// PIDs of the instances in `afl_out` as recorded in their stats, including those nested
+/// in sync groups
+#[cfg(unix)]
+pub fn fuzzer_pids(afl_out: &Path) -> Vec<libc::pid_t> {
+    instance_dirs(afl_out)
+        .iter()
+        .filter_map(|dir| fs::read_to_string(dir.join("fuzzer_stats")).ok())
+        .filter_map(|stats| {
+            stats.lines().find_map(|line| {
+                line.split_once(':')
+                    .filter(|(key, _)| key.trim() == "fuzzer_pid")
+                    .and_then(|(_, pid)| pid.trim().parse::<libc::pid_t>().ok())
+            })
+        })
+        .filter(|&pid| pid > 0)
+        .collect()
+}
+
+/// Whether any instance in `afl_out` still runs, based on the `fuzzer_pid` of its stats
+#[cfg(unix)]
+pub fn any_fuzzer_alive(afl_out: &Path) -> bool {
+    fuzzer_pids(afl_out)
+        .into_iter()
+        .any(|pid| unsafe { libc::kill(pid, 0) } == 0)
+}
+
+/// Liveness of the instances cannot be checked on this platform, so none is reported
+#[cfg(not(unix))]
+pub fn any_fuzzer_alive(_afl_out: &Path) -> bool {
+    false
+}
+
+/// Starts `aflr` with `args` as a background process detached from the terminal, so it
+/// outlives `aflr run`. Returns the PID of the process.
+///
+/// # Errors
+/// * If the log cannot be created or the process cannot be started
+#[cfg(unix)]
+pub fn spawn_detached<I, S>(args: I, log_path: &Path) -> Result<u32>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let log = fs::File::create(log_path)
+        .with_context(|| format!("Failed to create {}", log_path.display()))?;
+    let mut cmd = Command::new(std::env::current_exe()?);
+    cmd.args(args)
+        .stdin(Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log);
+    unsafe {
+        cmd.pre_exec(|| {
+            libc::setsid();
+            Ok(())
+        });
+    }
+    Ok(cmd.spawn()?.id())
+}
+
+/// Background processes need a Unix session of their own
+///
+/// # Errors
+/// * Always, as detaching processes is not supported on this platform
+#[cfg(not(unix))]
+pub fn spawn_detached<I, S>(_args: I, _log_path: &Path) -> Result<u32>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    bail!("Background processes are only supported on Unix")
+}
+
 /// Gets user input from stdin
 pub fn get_user_input() -> char {
     std::io::stdin()
+        .lock()
         .bytes()
         .next()
         .and_then(std::result::Result::ok)
@@ -217,6 +307,32 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_any_fuzzer_alive() {
+        let dir = tempdir().unwrap();
+        assert!(!any_fuzzer_alive(dir.path()));
+
+        let instance = dir.path().join("m_target");
+        fs::create_dir(&instance).unwrap();
+        fs::write(instance.join("fuzzer_stats"), "fuzzer_pid        : 0\n").unwrap();
+        assert!(!any_fuzzer_alive(dir.path()));
+
+        fs::write(
+            instance.join("fuzzer_stats"),
+            format!("fuzzer_pid        : {}\n", std::process::id()),
+        )
+        .unwrap();
+        assert!(any_fuzzer_alive(dir.path()));
+
+        let nested = dir.path().join("group-1").join("m1_target");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(nested.join("fuzzer_stats"), "fuzzer_pid        : 42\n").unwrap();
+        assert_eq!(
+            fuzzer_pids(dir.path()),
+            vec![42, std::process::id() as libc::pid_t]
+        );
+    }
+
     #[test]
     fn test_is_directory_empty() -> io::Result<()> {
         let temp = tempdir()?;
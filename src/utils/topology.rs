@@ -0,0 +1,308 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    path::Path,
+};
+
+use anyhow::{bail, Context, Result};
+
+/// Default location of the Linux CPU/NUMA topology
+const SYSFS_ROOT: &str = "/sys/devices/system";
+
+/// A single logical CPU as exposed by the kernel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogicalCpu {
+    /// Logical CPU id as used by `-b`
+    pub id: usize,
+    /// Physical core id (shared between SMT siblings)
+    pub core_id: usize,
+    /// Physical package (socket) id
+    pub package_id: usize,
+    /// NUMA node the CPU belongs to
+    pub node: usize,
+}
+
+/// CPU topology of the host used for pinning AFL++ instances
+#[derive(Debug, Clone, Default)]
+pub struct CpuTopology {
+    pub cpus: Vec<LogicalCpu>,
+}
+
+/// Which cores AFL++ instances may be bound to
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CoreBinding {
+    /// Allowed logical CPUs, `None` means every online CPU
+    pub allowed: Option<Vec<usize>>,
+    /// Only use one logical CPU per physical core
+    pub avoid_smt: bool,
+}
+
+impl CoreBinding {
+    /// Creates a core binding from a spec like `0-15,32-47` or `all`
+    ///
+    /// # Errors
+    /// * If the spec cannot be parsed
+    pub fn new(spec: &str, avoid_smt: bool) -> Result<Self> {
+        let allowed = match spec.trim() {
+            "all" | "" => None,
+            list => Some(parse_cpu_list(list)?),
+        };
+        Ok(Self { allowed, avoid_smt })
+    }
+}
+
+impl CpuTopology {
+    /// Reads the topology of the running system
+    ///
+    /// # Errors
+    /// * If `/sys/devices/system/cpu` cannot be read
+    pub fn detect() -> Result<Self> {
+        Self::from_sysfs(Path::new(SYSFS_ROOT))
+    }
+
+    /// Reads the topology from a sysfs-like directory tree
+    ///
+    /// # Errors
+    /// * If the list of online CPUs cannot be read or parsed
+    pub fn from_sysfs(root: &Path) -> Result<Self> {
+        let online_path = root.join("cpu").join("online");
+        let online = fs::read_to_string(&online_path)
+            .with_context(|| format!("Failed to read {}", online_path.display()))?;
+        let online = parse_cpu_list(&online)?;
+
+        let node_of = Self::read_numa_nodes(&root.join("node"));
+        let read_id = |cpu: usize, file: &str| -> Option<usize> {
+            fs::read_to_string(
+                root.join("cpu")
+                    .join(format!("cpu{cpu}"))
+                    .join("topology")
+                    .join(file),
+            )
+            .ok()
+            .and_then(|s| s.trim().parse().ok())
+        };
+
+        let cpus = online
+            .into_iter()
+            .map(|id| LogicalCpu {
+                id,
+                core_id: read_id(id, "core_id").unwrap_or(id),
+                package_id: read_id(id, "physical_package_id").unwrap_or(0),
+                node: node_of.get(&id).copied().unwrap_or(0),
+            })
+            .collect();
+
+        Ok(Self { cpus })
+    }
+
+    /// Maps logical CPU ids to their NUMA node, empty on non-NUMA systems
+    fn read_numa_nodes(node_root: &Path) -> BTreeMap<usize, usize> {
+        let mut node_of = BTreeMap::new();
+        for entry in fs::read_dir(node_root).into_iter().flatten().flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let Some(node) = name
+                .strip_prefix("node")
+                .and_then(|n| n.parse::<usize>().ok())
+            else {
+                continue;
+            };
+            let cpus = fs::read_to_string(entry.path().join("cpulist"))
+                .ok()
+                .and_then(|l| parse_cpu_list(&l).ok())
+                .unwrap_or_default();
+            for cpu in cpus {
+                node_of.insert(cpu, node);
+            }
+        }
+        node_of
+    }
+
+    /// Number of distinct physical cores
+    pub fn physical_cores(&self) -> usize {
+        self.cpus
+            .iter()
+            .map(|c| (c.package_id, c.core_id))
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    /// Picks `count` logical CPUs to bind AFL++ instances to
+    ///
+    /// Instances are packed onto as few NUMA nodes as possible, starting with the node that
+    /// offers the most usable CPUs, so that the `-M` instance and its secondaries share memory.
+    /// Within a node, SMT siblings are only used once every physical core is taken.
+    ///
+    /// # Errors
+    /// * If an allowed CPU is not online
+    /// * If there are fewer usable CPUs than instances
+    pub fn place(&self, binding: &CoreBinding, count: usize) -> Result<Vec<usize>> {
+        if let Some(allowed) = &binding.allowed {
+            if let Some(cpu) = allowed
+                .iter()
+                .find(|&&a| !self.cpus.iter().any(|c| c.id == a))
+            {
+                bail!("CPU {cpu} is not online on this host");
+            }
+        }
+
+        let mut candidates: Vec<&LogicalCpu> = self
+            .cpus
+            .iter()
+            .filter(|c| binding.allowed.as_ref().is_none_or(|a| a.contains(&c.id)))
+            .collect();
+        candidates.sort_by_key(|c| c.id);
+
+        // Rank each CPU within its physical core: 0 for the first thread, 1 for its sibling, ...
+        let mut seen_cores: BTreeMap<(usize, usize), usize> = BTreeMap::new();
+        let mut ranked: Vec<(usize, &LogicalCpu)> = candidates
+            .into_iter()
+            .map(|c| {
+                let rank = seen_cores.entry((c.package_id, c.core_id)).or_insert(0);
+                *rank += 1;
+                (*rank - 1, c)
+            })
+            .filter(|(rank, _)| !binding.avoid_smt || *rank == 0)
+            .collect();
+
+        let mut node_sizes: BTreeMap<usize, usize> = BTreeMap::new();
+        for (_, c) in &ranked {
+            *node_sizes.entry(c.node).or_insert(0) += 1;
+        }
+        let mut node_order: Vec<usize> = node_sizes.keys().copied().collect();
+        node_order.sort_by_key(|n| std::cmp::Reverse(node_sizes[n]));
+
+        ranked.sort_by_key(|(rank, c)| {
+            let node_pos = node_order.iter().position(|n| *n == c.node);
+            (node_pos, *rank, c.id)
+        });
+
+        if ranked.len() < count {
+            bail!(
+                "Cannot bind {count} instances to {} available cores{}",
+                ranked.len(),
+                if binding.avoid_smt {
+                    " (SMT siblings excluded)"
+                } else {
+                    ""
+                }
+            );
+        }
+
+        Ok(ranked.into_iter().take(count).map(|(_, c)| c.id).collect())
+    }
+}
+
+/// Parses a kernel-style CPU list such as `0-3,8,10-11`
+///
+/// # Errors
+/// * If a range or id is malformed
+pub fn parse_cpu_list(list: &str) -> Result<Vec<usize>> {
+    let mut cpus = Vec::new();
+    for part in list.trim().split(',').filter(|p| !p.trim().is_empty()) {
+        let part = part.trim();
+        if let Some((start, end)) = part.split_once('-') {
+            let start: usize = start
+                .trim()
+                .parse()
+                .with_context(|| format!("Invalid CPU range: {part}"))?;
+            let end: usize = end
+                .trim()
+                .parse()
+                .with_context(|| format!("Invalid CPU range: {part}"))?;
+            if start > end {
+                bail!("Invalid CPU range: {part}");
+            }
+            cpus.extend(start..=end);
+        } else {
+            cpus.push(
+                part.parse()
+                    .with_context(|| format!("Invalid CPU id: {part}"))?,
+            );
+        }
+    }
+    if cpus.is_empty() {
+        bail!("Empty CPU list");
+    }
+    cpus.sort_unstable();
+    cpus.dedup();
+    Ok(cpus)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    /// Builds a fake sysfs tree with 2 NUMA nodes, 4 physical cores each and 2 threads per core.
+    /// Logical CPUs 0-7 are the first threads, 8-15 their siblings.
+    fn fake_sysfs(root: &Path) {
+        fs::create_dir_all(root.join("cpu")).unwrap();
+        fs::write(root.join("cpu/online"), "0-15\n").unwrap();
+        for cpu in 0..16 {
+            let topo = root.join(format!("cpu/cpu{cpu}/topology"));
+            fs::create_dir_all(&topo).unwrap();
+            fs::write(topo.join("core_id"), format!("{}\n", cpu % 8)).unwrap();
+            fs::write(topo.join("physical_package_id"), "0\n").unwrap();
+        }
+        for (node, list) in [(0, "0-3,8-11"), (1, "4-7,12-15")] {
+            let dir = root.join(format!("node/node{node}"));
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("cpulist"), list).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_parse_cpu_list() {
+        assert_eq!(parse_cpu_list("0-3,8").unwrap(), vec![0, 1, 2, 3, 8]);
+        assert_eq!(parse_cpu_list("5, 1-2\n").unwrap(), vec![1, 2, 5]);
+        assert!(parse_cpu_list("3-1").is_err());
+        assert!(parse_cpu_list("a-b").is_err());
+        assert!(parse_cpu_list("").is_err());
+    }
+
+    #[test]
+    fn test_from_sysfs() {
+        let dir = tempdir().unwrap();
+        fake_sysfs(dir.path());
+        let topo = CpuTopology::from_sysfs(dir.path()).unwrap();
+        assert_eq!(topo.cpus.len(), 16);
+        assert_eq!(topo.physical_cores(), 8);
+        assert_eq!(topo.cpus[12].node, 1);
+        assert_eq!(topo.cpus[12].core_id, 4);
+    }
+
+    #[test]
+    fn test_place_numa_local_and_smt_last() {
+        let dir = tempdir().unwrap();
+        fake_sysfs(dir.path());
+        let topo = CpuTopology::from_sysfs(dir.path()).unwrap();
+
+        let cores = topo.place(&CoreBinding::default(), 6).unwrap();
+        assert_eq!(cores, vec![0, 1, 2, 3, 8, 9]);
+    }
+
+    #[test]
+    fn test_place_avoid_smt() {
+        let dir = tempdir().unwrap();
+        fake_sysfs(dir.path());
+        let topo = CpuTopology::from_sysfs(dir.path()).unwrap();
+
+        let binding = CoreBinding::new("all", true).unwrap();
+        assert_eq!(topo.place(&binding, 6).unwrap(), vec![0, 1, 2, 3, 4, 5]);
+        assert!(topo.place(&binding, 9).is_err());
+    }
+
+    #[test]
+    fn test_place_allowed_list() {
+        let dir = tempdir().unwrap();
+        fake_sysfs(dir.path());
+        let topo = CpuTopology::from_sysfs(dir.path()).unwrap();
+
+        let binding = CoreBinding::new("2-6", false).unwrap();
+        // Node 0 offers 2 CPUs, node 1 offers 3, so node 1 is filled first
+        assert_eq!(topo.place(&binding, 4).unwrap(), vec![4, 5, 6, 2]);
+
+        let offline = CoreBinding::new("31", false).unwrap();
+        assert!(topo.place(&offline, 1).is_err());
+    }
+}